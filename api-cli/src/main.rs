@@ -20,7 +20,7 @@ use audio_manager_api::{
         PlaySelectedParams, RemoveQueueItemParams, SetAudioProgressParams, SetAudioVolumeParams,
     },
     downloader::download_identifier::{AudioKind, ItemUid},
-    state_storage::AppStateRecoveryInfo,
+    state_storage::format::StateFormat,
     streams::{brain_streams::AudioBrainInfoStreamType, node_streams::AudioNodeInfoStreamType},
 };
 use clap::{Parser, Subcommand};
@@ -53,12 +53,15 @@ pub enum Action {
         #[arg(short, long)]
         /// Command to run on received messages. None = print to stdout
         command: Option<String>,
+        #[arg(long)]
+        /// Interval in which the server should send heart beats. None = server default
+        heartbeat_interval_ms: Option<u64>,
         #[command(subcommand)]
         con_type: ListenConnectionType,
     },
     #[command(about = "Log content of saved server state")]
     LogState {
-        /// Path to state file
+        /// Path to state file, files ending with `.json` are read as JSON
         path: Option<PathBuf>,
     },
     #[command(about = "Print the original value the uid was created from")]
@@ -103,6 +106,30 @@ pub enum CliNodeCommand {
         identifier: String,
         #[arg(short, long)]
         local: bool,
+        /// Don't start playing if this is the first item of the queue
+        #[arg(long)]
+        start_paused: bool,
+        /// Subfolder of the audio directory the download is stored in, e.g. 'artist/album'
+        #[arg(long)]
+        folder: Option<String>,
+        /// Index in the queue the item is inserted at, appended if not set
+        #[arg(long)]
+        position: Option<usize>,
+    },
+    /// Adds several tracks with a single queue update
+    AddQueueItems {
+        identifiers: Vec<String>,
+        #[arg(short, long)]
+        local: bool,
+        /// Don't start playing if the first track is the first item of the queue
+        #[arg(long)]
+        start_paused: bool,
+        /// Subfolder of the audio directory the downloads are stored in, e.g. 'artist/album'
+        #[arg(long)]
+        folder: Option<String>,
+        /// Index in the queue the first track is inserted at, appended if not set
+        #[arg(long)]
+        position: Option<usize>,
     },
     RemoveQueueItem {
         index: usize,
@@ -114,6 +141,8 @@ pub enum CliNodeCommand {
         new_pos: usize,
     },
     ShuffleQueue,
+    ShuffleQueueSmart,
+    ClearQueue,
     SetAudioVolume {
         #[arg(short, long)]
         volume: f32,
@@ -124,12 +153,15 @@ pub enum CliNodeCommand {
     },
     PauseQueue,
     UnPauseQueue,
+    Stop,
     PlayNext,
     PlayPrevious,
     PlaySelected {
         #[arg(short, long)]
         index: usize,
     },
+    ReplayCurrent,
+    ReplayPrevious,
 }
 
 impl Display for ListenConnectionType {
@@ -182,6 +214,11 @@ impl Action {
 
     fn get_con_type_endpoint(&self) -> String {
         match self {
+            Self::Listen {
+                con_type,
+                heartbeat_interval_ms: Some(interval),
+                ..
+            } => format!("{con_type}&heartbeat_interval_ms={interval}"),
             Self::Listen { con_type, .. } => format!("{con_type}"),
             Self::Send { con_type } => format!("{con_type}"),
             Self::LogState { .. } => Default::default(),
@@ -193,21 +230,63 @@ impl Action {
 impl From<CliNodeCommand> for AudioNodeCommand {
     fn from(value: CliNodeCommand) -> Self {
         match value {
-            CliNodeCommand::AddQueueItem { identifier, local } => {
+            CliNodeCommand::AddQueueItem {
+                identifier,
+                local,
+                start_paused,
+                folder,
+                position,
+            } => {
                 if local {
                     AudioNodeCommand::AddQueueItem(AddQueueItemParams {
                         identifier: AudioIdentifier::Local {
                             uid: identifier.into(),
                         },
+                        start_paused,
+                        folder: None,
+                        idempotency_key: None,
+                        position,
                     })
                 } else {
                     AudioNodeCommand::AddQueueItem(AddQueueItemParams {
                         identifier: AudioIdentifier::Youtube {
                             url: identifier.into(),
                         },
+                        start_paused,
+                        folder: folder.map(Into::into),
+                        idempotency_key: None,
+                        position,
                     })
                 }
             }
+            CliNodeCommand::AddQueueItems {
+                identifiers,
+                local,
+                start_paused,
+                folder,
+                position,
+            } => AudioNodeCommand::AddQueueItems(
+                identifiers
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, identifier)| AddQueueItemParams {
+                        identifier: if local {
+                            AudioIdentifier::Local {
+                                uid: identifier.into(),
+                            }
+                        } else {
+                            AudioIdentifier::Youtube {
+                                url: identifier.into(),
+                            }
+                        },
+                        start_paused,
+                        folder: folder.as_deref().map(Into::into),
+                        idempotency_key: None,
+                        // keeps the order of the tracks
+                        position: position.map(|index| index + i),
+                    })
+                    .collect(),
+            ),
             CliNodeCommand::RemoveQueueItem { index } => {
                 AudioNodeCommand::RemoveQueueItem(RemoveQueueItemParams { index })
             }
@@ -215,6 +294,8 @@ impl From<CliNodeCommand> for AudioNodeCommand {
                 AudioNodeCommand::MoveQueueItem(MoveQueueItemParams { old_pos, new_pos })
             }
             CliNodeCommand::ShuffleQueue => AudioNodeCommand::ShuffleQueue,
+            CliNodeCommand::ShuffleQueueSmart => AudioNodeCommand::ShuffleQueueSmart,
+            CliNodeCommand::ClearQueue => AudioNodeCommand::ClearQueue,
             CliNodeCommand::SetAudioVolume { volume } => {
                 AudioNodeCommand::SetAudioVolume(SetAudioVolumeParams { volume })
             }
@@ -223,11 +304,14 @@ impl From<CliNodeCommand> for AudioNodeCommand {
             }
             CliNodeCommand::PauseQueue => AudioNodeCommand::PauseQueue,
             CliNodeCommand::UnPauseQueue => AudioNodeCommand::UnPauseQueue,
+            CliNodeCommand::Stop => AudioNodeCommand::Stop,
             CliNodeCommand::PlayNext => AudioNodeCommand::PlayNext,
             CliNodeCommand::PlayPrevious => AudioNodeCommand::PlayPrevious,
             CliNodeCommand::PlaySelected { index } => {
                 AudioNodeCommand::PlaySelected(PlaySelectedParams { index })
             }
+            CliNodeCommand::ReplayCurrent => AudioNodeCommand::ReplayCurrent,
+            CliNodeCommand::ReplayPrevious => AudioNodeCommand::ReplayPrevious,
         }
     }
 }
@@ -259,7 +343,7 @@ async fn send_command(url: &str, body: &AudioNodeCommand) -> Result<String, reqw
     Ok(res.text().await?)
 }
 
-fn listen_on_socket(url: &str, cmd_str: Option<String>) {
+fn listen_on_socket(url: &str, cmd_str: Option<String>, max_ms_without_heart_beat: u64) {
     let client = ClientBuilder::new(url)
         .unwrap()
         .add_protocol("rust-websocket")
@@ -270,7 +354,6 @@ fn listen_on_socket(url: &str, cmd_str: Option<String>) {
     let heart_beat_received = Arc::new(AtomicBool::new(true));
 
     let heart_beat_received_clone = heart_beat_received.clone();
-    let max_ms_without_heart_beat = 600;
 
     thread::spawn(move || loop {
         let received = heart_beat_received_clone.swap(false, Ordering::AcqRel);
@@ -339,14 +422,22 @@ async fn main() -> Result<(), &'static str> {
                 let out = send_command(&url, body.as_ref().unwrap()).await.unwrap();
                 println!("{out}");
             }
-            Action::Listen { command, .. } => {
-                listen_on_socket(&url, command);
+            Action::Listen {
+                command,
+                heartbeat_interval_ms,
+                ..
+            } => {
+                let max_ms_without_heart_beat = heartbeat_interval_ms
+                    .map(|interval| interval * 2)
+                    .unwrap_or(600);
+
+                listen_on_socket(&url, command, max_ms_without_heart_beat);
             }
             Action::LogState { path } => {
                 let path = path.unwrap_or(PathBuf::from("../api/dev/state-recovery-info"));
-                let bytes = fs::read(path).unwrap();
+                let bytes = fs::read(&path).unwrap();
 
-                let state: AppStateRecoveryInfo = bincode::deserialize(&bytes).unwrap();
+                let state = StateFormat::from_path(&path).decode(&bytes).unwrap();
                 let pretty = serde_json::to_string(&state).unwrap();
 
                 println!("{pretty}");
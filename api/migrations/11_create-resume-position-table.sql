@@ -0,0 +1,8 @@
+create table if not exists audio_resume_position (
+    audio_identifier varchar(512) primary key,
+    position_secs double precision not null,
+    constraint fk_audio_metadata
+        foreign key(audio_identifier)
+	    references audio_metadata(identifier)
+        on delete cascade
+);
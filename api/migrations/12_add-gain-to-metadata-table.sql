@@ -0,0 +1 @@
+alter table audio_metadata add column if not exists gain_db real;
@@ -0,0 +1,2 @@
+alter table audio_metadata add column if not exists created_at timestamptz not null default now();
+alter table audio_playlist add column if not exists created_at timestamptz not null default now();
@@ -0,0 +1,12 @@
+create table if not exists play_history (
+    id serial primary key,
+    audio_identifier varchar(512) not null,
+    source_name varchar(255) not null,
+    played_at timestamptz not null default now(),
+    constraint fk_audio_metadata
+        foreign key(audio_identifier)
+	    references audio_metadata(identifier)
+        on delete cascade
+);
+
+create index if not exists play_history_played_at_idx on play_history (played_at desc);
@@ -0,0 +1 @@
+alter table audio_metadata add column if not exists favorite boolean not null default false;
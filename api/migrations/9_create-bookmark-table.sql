@@ -0,0 +1,10 @@
+create table if not exists audio_bookmark (
+    id serial primary key,
+    audio_identifier varchar(512) not null,
+    label varchar(255) not null,
+    position_secs double precision not null,
+    constraint fk_audio_metadata
+        foreign key(audio_identifier)
+	    references audio_metadata(identifier)
+        on delete cascade
+);
@@ -0,0 +1,403 @@
+use std::{fs::File, io::ErrorKind, path::Path, sync::Arc};
+
+use symphonia::core::{
+    audio::{SampleBuffer, SignalSpec},
+    codecs::DecoderOptions,
+    errors::Error,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey, Visual},
+    probe::{Hint, ProbeResult},
+};
+
+use crate::{
+    audio_playback::audio_item::AudioMetadata,
+    error::{AppError, AppErrorKind, IntoAppError},
+};
+
+use super::loudness::MAX_NORMALIZATION_GAIN_DB;
+
+#[derive(Debug, Default)]
+struct AudioTags {
+    title: Option<Arc<str>>,
+    artist: Option<Arc<str>>,
+    replay_gain_db: Option<f32>,
+}
+
+/// Image embedded in an audio file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverArt {
+    pub media_type: Arc<str>,
+    pub data: Vec<u8>,
+}
+
+/// Reads the title, artist, duration and ReplayGain track gain of a local audio file
+///
+/// The file name is used as title if the file has no title tag. Embedded cover art isn't part of
+/// the metadata, it is too large to be stored as url and is read with [`read_cover_art`] instead.
+pub fn read_audio_metadata(path: &Path) -> Result<AudioMetadata, AppError> {
+    let mut probed = probe_audio_file(path)?;
+
+    let mut tags = AudioTags::default();
+
+    // tags of the container take precedence over tags found in front of it (e.g. ID3v2 in mp3)
+    if let Some(revision) = probed.format.metadata().current() {
+        tags.merge(revision);
+    }
+
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|md| md.current()) {
+        tags.merge(revision);
+    }
+
+    let duration = probed.format.default_track().and_then(|track| {
+        let params = &track.codec_params;
+        let time = params.time_base?.calc_time(params.n_frames?);
+
+        Some((time.seconds * 1000) as i64 + (time.frac * 1000.0) as i64)
+    });
+
+    let title = tags.title.or_else(|| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().as_ref().into())
+    });
+
+    Ok(AudioMetadata {
+        name: title.into(),
+        author: tags.artist.into(),
+        duration,
+        cover_art_url: Option::<Arc<str>>::None.into(),
+        gain_db: tags.replay_gain_db,
+    })
+}
+
+/// Reads the cover art embedded in an audio file, e.g. an ID3v2 `APIC` frame
+///
+/// Returns `None` if the file has no embedded images.
+pub fn read_cover_art(path: &Path) -> Result<Option<CoverArt>, AppError> {
+    let mut probed = probe_audio_file(path)?;
+
+    // same precedence as the tags in `read_audio_metadata`
+    if let Some(cover) = probed
+        .format
+        .metadata()
+        .current()
+        .and_then(|revision| front_cover(revision.visuals()))
+    {
+        return Ok(Some(cover.into()));
+    }
+
+    Ok(probed
+        .metadata
+        .get()
+        .as_ref()
+        .and_then(|md| md.current())
+        .and_then(|revision| front_cover(revision.visuals()))
+        .map(CoverArt::from))
+}
+
+/// Checks that the default track of an audio file can be decoded, without decoding it
+pub fn check_audio_file_decodable(path: &Path) -> Result<(), AppError> {
+    let path_info = format!("PATH: {path}", path = path.display());
+    let probed = probe_audio_file(path).map_err(AppError::bad_request)?;
+
+    let track = probed.format.default_track().ok_or_else(|| {
+        AppError::new(
+            AppErrorKind::LocalData,
+            "audio file has no audio track",
+            &[&path_info],
+        )
+        .bad_request()
+    })?;
+
+    symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .into_app_err(
+            "unsupported audio codec",
+            AppErrorKind::LocalData,
+            &[&path_info],
+        )
+        .map_err(AppError::bad_request)?;
+
+    Ok(())
+}
+
+pub(super) fn probe_audio_file(path: &Path) -> Result<ProbeResult, AppError> {
+    let path_info = format!("PATH: {path}", path = path.display());
+
+    let file = File::open(path).into_app_err(
+        "failed to open audio file",
+        AppErrorKind::LocalData,
+        &[&path_info],
+    )?;
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    symphonia::default::get_probe()
+        .format(
+            &hint,
+            MediaSourceStream::new(Box::new(file), Default::default()),
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .into_app_err(
+            "failed to read audio file",
+            AppErrorKind::LocalData,
+            &[&path_info],
+        )
+}
+
+/// Decodes the whole file and passes the interleaved samples of every packet to `on_samples`
+///
+/// Packets that fail to decode are skipped, like during playback.
+pub(super) fn decode_audio_file(
+    path: &Path,
+    mut on_samples: impl FnMut(&[f32], &SignalSpec),
+) -> Result<(), AppError> {
+    let path_info = format!("PATH: {path}", path = path.display());
+    let mut probed = probe_audio_file(path)?;
+
+    let track = probed.format.default_track().ok_or_else(|| {
+        AppError::new(
+            AppErrorKind::LocalData,
+            "audio file has no audio track",
+            &[&path_info],
+        )
+    })?;
+    let track_id = track.id;
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .into_app_err(
+            "failed to create decoder for audio file",
+            AppErrorKind::LocalData,
+            &[&path_info],
+        )?;
+
+    let mut sample_buf: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        let packet = match probed.format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => {
+                return Err(err.into_app_err(
+                    "failed to read audio file",
+                    AppErrorKind::LocalData,
+                    &[&path_info],
+                ))
+            }
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(Error::DecodeError(_)) => continue,
+            Err(err) => {
+                return Err(err.into_app_err(
+                    "failed to decode audio file",
+                    AppErrorKind::LocalData,
+                    &[&path_info],
+                ))
+            }
+        };
+
+        let spec = *decoded.spec();
+        let buf =
+            sample_buf.get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, spec));
+        buf.copy_interleaved_ref(decoded);
+
+        on_samples(buf.samples(), &spec);
+    }
+
+    Ok(())
+}
+
+/// the front cover if the file has one, otherwise the first image
+fn front_cover(visuals: &[Visual]) -> Option<&Visual> {
+    visuals
+        .iter()
+        .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+        .or_else(|| visuals.first())
+}
+
+impl From<&Visual> for CoverArt {
+    fn from(visual: &Visual) -> Self {
+        Self {
+            media_type: visual.media_type.as_str().into(),
+            data: visual.data.to_vec(),
+        }
+    }
+}
+
+impl AudioTags {
+    /// fills in all values that are still missing from the revision
+    fn merge(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            // RIFF INFO values are null terminated
+            let value = tag.value.to_string();
+            let value = value.trim_end_matches('\0').trim();
+            if value.is_empty() {
+                continue;
+            }
+
+            match tag.std_key {
+                Some(StandardTagKey::TrackTitle) if self.title.is_none() => {
+                    self.title = Some(value.into());
+                }
+                Some(StandardTagKey::Artist) if self.artist.is_none() => {
+                    self.artist = Some(value.into());
+                }
+                Some(StandardTagKey::ReplayGainTrackGain) if self.replay_gain_db.is_none() => {
+                    self.replay_gain_db = parse_replay_gain(value);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// parses a ReplayGain tag value, e.g. `-6.48 dB`
+fn parse_replay_gain(value: &str) -> Option<f32> {
+    let gain_db: f32 = value
+        .trim_end_matches(|c: char| c.is_alphabetic() || c.is_whitespace())
+        .parse()
+        .ok()?;
+
+    gain_db
+        .is_finite()
+        .then(|| gain_db.clamp(-MAX_NORMALIZATION_GAIN_DB, MAX_NORMALIZATION_GAIN_DB))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_utils::{test_dir, write_test_wav};
+    use pretty_assertions::assert_eq;
+
+    /// inserts a RIFF `LIST INFO` chunk in front of the data chunk of a wav written by
+    /// `write_test_wav`
+    fn add_info_chunk(path: &Path, tags: &[(&[u8; 4], &str)]) {
+        let mut info = b"INFO".to_vec();
+        for (id, value) in tags {
+            let mut value = value.as_bytes().to_vec();
+            value.push(0);
+            if value.len() % 2 != 0 {
+                value.push(0);
+            }
+
+            info.extend_from_slice(*id);
+            info.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            info.extend_from_slice(&value);
+        }
+
+        let mut list = b"LIST".to_vec();
+        list.extend_from_slice(&(info.len() as u32).to_le_bytes());
+        list.extend_from_slice(&info);
+
+        let mut bytes = std::fs::read(path).unwrap();
+        let riff_len = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) + list.len() as u32;
+        bytes[4..8].copy_from_slice(&riff_len.to_le_bytes());
+        bytes.splice(36..36, list);
+
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    /// prepends an ID3v2.3 tag with a front cover `APIC` frame to a file
+    fn add_id3_cover(path: &Path, media_type: &str, image: &[u8]) {
+        let mut apic = vec![0]; // latin1 text encoding
+        apic.extend_from_slice(media_type.as_bytes());
+        apic.push(0);
+        apic.push(3); // front cover
+        apic.push(0); // empty description
+        apic.extend_from_slice(image);
+
+        let mut frame = b"APIC".to_vec();
+        frame.extend_from_slice(&(apic.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(&apic);
+
+        // the tag size is stored as a synchsafe integer, 7 bits per byte
+        let size = frame.len() as u32;
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend((0..4).rev().map(|i| ((size >> (i * 7)) & 0x7f) as u8));
+        tag.extend_from_slice(&frame);
+
+        let mut bytes = std::fs::read(path).unwrap();
+        bytes.splice(0..0, tag);
+
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_read_cover_art() {
+        let dir = test_dir("read-cover-art");
+        let image = [0x89, b'P', b'N', b'G', 1, 2, 3, 4];
+
+        let with_cover = dir.join("with-cover.wav");
+        write_test_wav(&with_cover, 48_000, 2, 4_800, |_| 0);
+        add_id3_cover(&with_cover, "image/png", &image);
+
+        assert_eq!(
+            read_cover_art(&with_cover).unwrap(),
+            Some(CoverArt {
+                media_type: "image/png".into(),
+                data: image.to_vec(),
+            })
+        );
+
+        let without_cover = dir.join("without-cover.wav");
+        write_test_wav(&without_cover, 48_000, 2, 4_800, |_| 0);
+
+        assert_eq!(read_cover_art(&without_cover).unwrap(), None);
+    }
+
+    #[test]
+    fn test_cover_art_is_not_part_of_metadata() {
+        let dir = test_dir("cover-art-metadata");
+        let image = vec![0xff; 64 * 1024];
+
+        let path = dir.join("with-cover.wav");
+        write_test_wav(&path, 48_000, 2, 4_800, |_| 0);
+        add_id3_cover(&path, "image/jpeg", &image);
+
+        // the url is stored in a `varchar(512)` column, the image is served from the file instead
+        let metadata = read_audio_metadata(&path).unwrap();
+        assert_eq!(metadata.cover_art_url.inner_as_ref(), None);
+        assert_eq!(metadata.name.inner_as_ref(), Some("with-cover"));
+        assert_eq!(read_cover_art(&path).unwrap().unwrap().data, image);
+    }
+
+    #[test]
+    fn test_read_audio_metadata() {
+        let dir = test_dir("read-audio-metadata");
+
+        let tagged = dir.join("tagged.wav");
+        write_test_wav(&tagged, 48_000, 2, 96_000, |_| 0);
+        add_info_chunk(
+            &tagged,
+            &[(b"INAM", "Some Title"), (b"IART", "Some Artist")],
+        );
+
+        let metadata = read_audio_metadata(&tagged).unwrap();
+        assert_eq!(metadata.name.inner_as_ref(), Some("Some Title"));
+        assert_eq!(metadata.author.inner_as_ref(), Some("Some Artist"));
+        assert_eq!(metadata.duration, Some(2000));
+
+        let untagged = dir.join("Untagged Song.wav");
+        write_test_wav(&untagged, 44_100, 1, 22_050, |_| 0);
+
+        let metadata = read_audio_metadata(&untagged).unwrap();
+        assert_eq!(metadata.name.inner_as_ref(), Some("Untagged Song"));
+        assert_eq!(metadata.author.inner_as_ref(), None);
+        assert_eq!(metadata.cover_art_url.inner_as_ref(), None);
+        assert_eq!(metadata.duration, Some(500));
+
+        assert!(read_audio_metadata(&dir.join("missing.wav")).is_err());
+    }
+}
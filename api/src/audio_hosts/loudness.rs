@@ -0,0 +1,243 @@
+use std::{f64::consts::PI, path::Path};
+
+use crate::error::AppError;
+
+use super::local::decode_audio_file;
+
+/// loudness every track is normalized to, the reference level of ReplayGain 2.0
+pub const TARGET_LOUDNESS_LUFS: f64 = -18.0;
+
+/// normalization never boosts or cuts a track by more than this
+pub const MAX_NORMALIZATION_GAIN_DB: f32 = 12.0;
+
+/// blocks quieter than this don't count towards the loudness, e.g. silence between songs
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// blocks this much quieter than the loudness of all blocks above the absolute gate don't count
+/// towards the loudness either
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// loudness is measured over 400ms blocks that overlap by 75%, so every block consists of four
+/// 100ms sub blocks
+const SUB_BLOCKS_PER_BLOCK: usize = 4;
+const SUB_BLOCKS_PER_SEC: u32 = 10;
+
+/// Gain in dB that brings the audio file at `path` to [`TARGET_LOUDNESS_LUFS`], clamped to
+/// [`MAX_NORMALIZATION_GAIN_DB`]
+///
+/// `None` if the file is too short or too quiet to be measured.
+pub fn normalization_gain_db(path: &Path) -> Result<Option<f32>, AppError> {
+    let mut meter: Option<LoudnessMeter> = None;
+
+    decode_audio_file(path, |samples, spec| {
+        meter
+            .get_or_insert_with(|| LoudnessMeter::new(spec.rate, spec.channels.count()))
+            .push(samples);
+    })?;
+
+    Ok(meter
+        .and_then(|meter| meter.integrated_loudness())
+        .map(|loudness| {
+            ((TARGET_LOUDNESS_LUFS - loudness) as f32)
+                .clamp(-MAX_NORMALIZATION_GAIN_DB, MAX_NORMALIZATION_GAIN_DB)
+        }))
+}
+
+/// like [`normalization_gain_db`], but a file that can't be decoded is only logged
+///
+/// Tracks whose loudness can't be measured play without normalization, a failed measurement
+/// shouldn't fail the download or import of the track.
+pub fn normalization_gain_db_or_log(path: &Path) -> Option<f32> {
+    normalization_gain_db(path).unwrap_or_else(|err| {
+        log::warn!(
+            "failed to measure loudness, playing without normalization
+PATH: {path}
+ERROR: {err}",
+            path = path.display()
+        );
+        None
+    })
+}
+
+/// Integrated loudness after ITU-R BS.1770, every channel is weighted equally
+struct LoudnessMeter {
+    channels: usize,
+    /// K-weighting of every channel
+    filters: Vec<[Biquad; 2]>,
+    frames_per_sub_block: usize,
+    frames_in_sub_block: usize,
+    /// sum of the squared samples of all channels in the current sub block
+    sum_of_squares: f64,
+    /// mean square of every finished sub block
+    sub_blocks: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    fn new(sample_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+
+        Self {
+            channels,
+            filters: vec![
+                [
+                    Biquad::high_shelf(sample_rate),
+                    Biquad::high_pass(sample_rate)
+                ];
+                channels
+            ],
+            frames_per_sub_block: (sample_rate / SUB_BLOCKS_PER_SEC).max(1) as usize,
+            frames_in_sub_block: 0,
+            sum_of_squares: 0.0,
+            sub_blocks: vec![],
+        }
+    }
+
+    /// measures interleaved samples
+    fn push(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (sample, [shelf, high_pass]) in frame.iter().zip(self.filters.iter_mut()) {
+                let weighted = high_pass.process(shelf.process(*sample as f64));
+                self.sum_of_squares += weighted * weighted;
+            }
+
+            self.frames_in_sub_block += 1;
+            if self.frames_in_sub_block == self.frames_per_sub_block {
+                self.sub_blocks
+                    .push(self.sum_of_squares / self.frames_per_sub_block as f64);
+                self.frames_in_sub_block = 0;
+                self.sum_of_squares = 0.0;
+            }
+        }
+    }
+
+    /// loudness in LUFS of all gated blocks, `None` if no block passes the gates
+    fn integrated_loudness(&self) -> Option<f64> {
+        let blocks: Vec<f64> = self
+            .sub_blocks
+            .windows(SUB_BLOCKS_PER_BLOCK)
+            .map(|block| block.iter().sum::<f64>() / SUB_BLOCKS_PER_BLOCK as f64)
+            .filter(|mean_square| loudness(*mean_square) > ABSOLUTE_GATE_LUFS)
+            .collect();
+
+        if blocks.is_empty() {
+            return None;
+        }
+
+        let relative_gate = loudness(mean(&blocks)) + RELATIVE_GATE_LU;
+        let gated: Vec<f64> = blocks
+            .into_iter()
+            .filter(|mean_square| loudness(*mean_square) > relative_gate)
+            .collect();
+
+        Some(loudness(mean(&gated)))
+    }
+}
+
+fn loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Filter stage of the K-weighting in transposed direct form II
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    state: [f64; 2],
+}
+
+impl Biquad {
+    /// first stage, models the acoustic effect of the head
+    ///
+    /// The parameters reproduce the coefficients BS.1770 specifies for 48 kHz at any sample rate.
+    fn high_shelf(sample_rate: u32) -> Self {
+        let k = (PI * 1_681.974_450_955_533 / sample_rate as f64).tan();
+        let q = 0.707_175_236_955_419_6;
+        let vh = 10f64.powf(3.999_843_853_973_347 / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+
+        Self::normalized(
+            vh + vb * k / q + k * k,
+            2.0 * (k * k - vh),
+            vh - vb * k / q + k * k,
+            1.0 + k / q + k * k,
+            2.0 * (k * k - 1.0),
+            1.0 - k / q + k * k,
+        )
+    }
+
+    /// second stage, removes frequencies below ~40 Hz
+    fn high_pass(sample_rate: u32) -> Self {
+        let k = (PI * 38.135_470_876_024_44 / sample_rate as f64).tan();
+        let q = 0.500_327_037_323_877_3;
+        let a0 = 1.0 + k / q + k * k;
+
+        // the gain is left unnormalized like in BS.1770
+        Self {
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+            state: Default::default(),
+        }
+    }
+
+    fn normalized(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            state: Default::default(),
+        }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        let [z1, z2] = &mut self.state;
+
+        let out = self.b0 * sample + *z1;
+        *z1 = self.b1 * sample - self.a1 * out + *z2;
+        *z2 = self.b2 * sample - self.a2 * out;
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_utils::{test_dir, write_test_wav};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_normalization_gain() {
+        let dir = test_dir("normalization-gain");
+
+        // a full scale 997 Hz sine on a single channel measures -3.01 LUFS, half of it 6 dB less
+        let sine = dir.join("sine.wav");
+        write_test_wav(&sine, 48_000, 1, 96_000, |frame| {
+            let phase = 2.0 * PI * 997.0 * frame as f64 / 48_000.0;
+            (phase.sin() * 0.5 * i16::MAX as f64) as i16
+        });
+
+        let gain_db = normalization_gain_db(&sine).unwrap().unwrap();
+        assert!((gain_db - (-18.0 + 9.03)).abs() < 0.1, "{gain_db}");
+
+        let silence = dir.join("silence.wav");
+        write_test_wav(&silence, 48_000, 2, 96_000, |_| 0);
+        assert_eq!(normalization_gain_db(&silence).unwrap(), None);
+
+        let not_audio = dir.join("not-audio.wav");
+        std::fs::write(&not_audio, b"not audio").unwrap();
+        assert!(normalization_gain_db(&not_audio).is_err());
+        assert_eq!(normalization_gain_db_or_log(&not_audio), None);
+    }
+}
@@ -1 +1,5 @@
+pub mod local;
+pub mod loudness;
+pub mod soundcloud;
+pub mod waveform;
 pub mod youtube;
@@ -0,0 +1,171 @@
+use std::{process::Command, sync::Arc};
+
+use serde::Deserialize;
+
+use crate::{
+    audio_playback::audio_item::AudioMetadata,
+    downloader::YT_DLP_COMMAND,
+    error::{AppError, AppErrorKind, IntoAppError},
+};
+
+/// Metadata of a track as printed by `yt-dlp --dump-json`
+#[derive(Debug, Deserialize)]
+pub struct SoundCloudTrack {
+    pub title: Option<Arc<str>>,
+    pub uploader: Option<Arc<str>>,
+    /// in seconds
+    pub duration: Option<f64>,
+    pub thumbnail: Option<Arc<str>>,
+}
+
+impl From<SoundCloudTrack> for AudioMetadata {
+    fn from(value: SoundCloudTrack) -> Self {
+        let duration = value.duration.map(|secs| (secs * 1000.0).round() as i64);
+
+        AudioMetadata {
+            name: value.title.into(),
+            author: value.uploader.into(),
+            cover_art_url: value.thumbnail.into(),
+            duration,
+            gain_db: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SoundCloudContentType {
+    Track,
+    /// playlists and albums, called sets by soundcloud
+    Set,
+    Invalid,
+}
+
+pub fn soundcloud_content_type<'a>(value: impl Into<&'a str>) -> SoundCloudContentType {
+    let value = value.into();
+
+    let Some(path) = value
+        .strip_prefix("https://soundcloud.com/")
+        .or_else(|| value.strip_prefix("https://www.soundcloud.com/"))
+        .or_else(|| value.strip_prefix("https://m.soundcloud.com/"))
+    else {
+        return SoundCloudContentType::Invalid;
+    };
+
+    let segments: Vec<_> = clean_soundcloud_url(path)
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    match segments.as_slice() {
+        [_, "sets", _] => SoundCloudContentType::Set,
+        [_, "sets" | "tracks" | "albums" | "reposts" | "likes" | "followers" | "following"] => {
+            SoundCloudContentType::Invalid
+        }
+        [_, _] => SoundCloudContentType::Track,
+        _ => SoundCloudContentType::Invalid,
+    }
+}
+
+/// remove tracking parameters from URL, e.g. `?si=...&utm_source=...`
+pub fn clean_soundcloud_url(url: &str) -> &str {
+    url.split_once('?').map(|(str, _)| str).unwrap_or(url)
+}
+
+/// Asks yt-dlp for the metadata of the track at `url` without downloading it
+pub async fn get_track_metadata(url: &str) -> Result<SoundCloudTrack, AppError> {
+    let url_info = format!("URL: {url}");
+
+    let output = {
+        let url = url.to_owned();
+
+        // yt-dlp blocks until soundcloud has answered
+        actix_rt::task::spawn_blocking(move || {
+            Command::new(YT_DLP_COMMAND)
+                .args(["--dump-json", "--skip-download", "--no-playlist", &url])
+                .output()
+        })
+        .await
+        .into_app_err(
+            "failed to fetch soundcloud track metadata",
+            AppErrorKind::Api,
+            &[&url_info],
+        )?
+        .into_app_err(
+            "failed to fetch soundcloud track metadata",
+            AppErrorKind::Api,
+            &[&url_info],
+        )?
+    };
+
+    if !output.status.success() {
+        return Err(AppError::new(
+            AppErrorKind::Download,
+            "failed to find soundcloud track",
+            &[
+                &url_info,
+                &format!("EXIT STATUS: {status}", status = output.status),
+            ],
+        ));
+    }
+
+    let body = String::from_utf8_lossy(&output.stdout);
+    serde_json::from_str(&body).into_app_err(
+        "failed to parse soundcloud track metadata",
+        AppErrorKind::Api,
+        &[&url_info, &format!("RESPONSE_TEXT: {body}")],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_soundcloud_content_type() {
+        assert_eq!(
+            soundcloud_content_type("https://soundcloud.com/artist-name/track-name"),
+            SoundCloudContentType::Track
+        );
+
+        assert_eq!(
+            soundcloud_content_type(
+                "https://m.soundcloud.com/artist-name/track-name?si=abc&utm_source=clipboard"
+            ),
+            SoundCloudContentType::Track
+        );
+
+        assert_eq!(
+            soundcloud_content_type("https://soundcloud.com/artist-name/sets/album-name"),
+            SoundCloudContentType::Set
+        );
+
+        assert_eq!(
+            soundcloud_content_type("https://soundcloud.com/artist-name"),
+            SoundCloudContentType::Invalid
+        );
+
+        assert_eq!(
+            soundcloud_content_type("https://soundcloud.com/artist-name/tracks"),
+            SoundCloudContentType::Invalid
+        );
+
+        assert_eq!(
+            soundcloud_content_type("https://www.youtube.com/watch?v=HYd9B6YvIHM"),
+            SoundCloudContentType::Invalid
+        );
+    }
+
+    #[test]
+    fn test_soundcloud_track_metadata() {
+        let track: SoundCloudTrack = serde_json::from_str(
+            r#"{"id": "123", "title": "Track", "uploader": "Artist", "duration": 215.4, "thumbnail": "https://i1.sndcdn.com/artworks-t500x500.jpg"}"#,
+        )
+        .unwrap();
+
+        let metadata = AudioMetadata::from(track);
+        assert_eq!(metadata.name.inner_as_ref(), Some("Track"));
+        assert_eq!(metadata.author.inner_as_ref(), Some("Artist"));
+        assert_eq!(metadata.duration, Some(215_400));
+    }
+}
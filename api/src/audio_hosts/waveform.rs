@@ -0,0 +1,218 @@
+use std::{fs, path::Path, sync::Arc};
+
+use crate::{
+    downloader::download_identifier::ItemUid,
+    error::{AppError, AppErrorKind, IntoAppError},
+    path::waveform_cache_dir,
+};
+
+use super::local::decode_audio_file;
+
+/// most peaks that can be requested and the resolution waveforms are cached with, requests for
+/// fewer peaks are downsampled from the cached waveform
+pub const MAX_PEAK_BUCKETS: usize = 4096;
+pub const DEFAULT_PEAK_BUCKETS: usize = 512;
+
+/// frames that are combined into one peak while decoding, keeps the envelope of long tracks small
+/// no matter how many peaks are requested
+const FRAMES_PER_BLOCK: usize = 256;
+
+/// lowest and highest sample of a section of a track, over all channels
+pub type Peak = [f32; 2];
+
+/// Returns `buckets` peaks of the stored audio file of `uid`, evenly spread over the whole track
+///
+/// The waveform is only decoded the first time and cached on disk afterwards, until the audio file
+/// is replaced. Tracks that are too short to have `buckets` peaks return fewer.
+pub fn waveform_peaks(
+    uid: &ItemUid<Arc<str>>,
+    path: &Path,
+    buckets: usize,
+) -> Result<Vec<Peak>, AppError> {
+    let buckets = buckets.clamp(1, MAX_PEAK_BUCKETS);
+    let cache_path = waveform_cache_dir().join(uid.0.as_ref());
+
+    let cached = match read_cached_peaks(&cache_path, path) {
+        Some(peaks) => peaks,
+        None => {
+            let peaks = downsample_peaks(&read_peak_envelope(path)?, MAX_PEAK_BUCKETS);
+
+            // the waveform is still returned if it can't be cached, it is just decoded again
+            let _ = write_cached_peaks(&cache_path, &peaks);
+            peaks
+        }
+    };
+
+    Ok(downsample_peaks(&cached, buckets))
+}
+
+/// Decodes the whole file and returns one peak per [`FRAMES_PER_BLOCK`] frames
+fn read_peak_envelope(path: &Path) -> Result<Vec<Peak>, AppError> {
+    let mut envelope = PeakEnvelope::default();
+    decode_audio_file(path, |samples, spec| {
+        envelope.push(samples, spec.channels.count())
+    })?;
+
+    Ok(envelope.finish())
+}
+
+#[derive(Debug, Default)]
+struct PeakEnvelope {
+    blocks: Vec<Peak>,
+    current: Option<Peak>,
+    frames_in_block: usize,
+}
+
+impl PeakEnvelope {
+    fn push(&mut self, samples: &[f32], channels: usize) {
+        for frame in samples.chunks_exact(channels.max(1)) {
+            let [min, max] = self.current.get_or_insert([f32::MAX, f32::MIN]);
+            for sample in frame {
+                *min = min.min(*sample);
+                *max = max.max(*sample);
+            }
+
+            self.frames_in_block += 1;
+            if self.frames_in_block == FRAMES_PER_BLOCK {
+                self.blocks.extend(self.current.take());
+                self.frames_in_block = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<Peak> {
+        self.blocks.extend(self.current.take());
+        self.blocks
+    }
+}
+
+/// Combines `peaks` into `buckets` peaks, every bucket covers the same share of the track
+///
+/// Returns `peaks` unchanged if there are already at most `buckets` peaks.
+fn downsample_peaks(peaks: &[Peak], buckets: usize) -> Vec<Peak> {
+    if peaks.len() <= buckets {
+        return peaks.to_vec();
+    }
+
+    (0..buckets)
+        .map(|bucket| {
+            let start = bucket * peaks.len() / buckets;
+            let end = (bucket + 1) * peaks.len() / buckets;
+
+            peaks[start..end]
+                .iter()
+                .fold([f32::MAX, f32::MIN], |[min, max], [low, high]| {
+                    [min.min(*low), max.max(*high)]
+                })
+        })
+        .collect()
+}
+
+/// cached peaks of `audio_path`, `None` if nothing is cached or the audio file changed since
+fn read_cached_peaks(cache_path: &Path, audio_path: &Path) -> Option<Vec<Peak>> {
+    let cached_at = fs::metadata(cache_path).ok()?.modified().ok()?;
+    let audio_modified_at = fs::metadata(audio_path).ok()?.modified().ok()?;
+    if cached_at < audio_modified_at {
+        return None;
+    }
+
+    let bytes = fs::read(cache_path).ok()?;
+    if bytes.len() % 8 != 0 {
+        return None;
+    }
+
+    Some(
+        bytes
+            .chunks_exact(8)
+            .map(|peak| {
+                [
+                    f32::from_le_bytes([peak[0], peak[1], peak[2], peak[3]]),
+                    f32::from_le_bytes([peak[4], peak[5], peak[6], peak[7]]),
+                ]
+            })
+            .collect(),
+    )
+}
+
+fn write_cached_peaks(cache_path: &Path, peaks: &[Peak]) -> Result<(), AppError> {
+    let path_info = format!("PATH: {path}", path = cache_path.display());
+
+    if let Some(dir) = cache_path.parent() {
+        fs::create_dir_all(dir).into_app_err(
+            "failed to create waveform cache directory",
+            AppErrorKind::LocalData,
+            &[&path_info],
+        )?;
+    }
+
+    let bytes: Vec<u8> = peaks
+        .iter()
+        .flat_map(|[min, max]| min.to_le_bytes().into_iter().chain(max.to_le_bytes()))
+        .collect();
+
+    fs::write(cache_path, bytes).into_app_err(
+        "failed to cache waveform",
+        AppErrorKind::LocalData,
+        &[&path_info],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_utils::{test_dir, write_test_wav};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_read_peak_envelope() {
+        let dir = test_dir("read-peak-envelope");
+        let path = dir.join("steps.wav");
+
+        // silent first half, full scale square wave in the second half
+        let frames = FRAMES_PER_BLOCK * 8;
+        write_test_wav(&path, 48_000, 2, frames, |frame| {
+            match (frame < frames / 2, frame % 2 == 0) {
+                (true, _) => 0,
+                (false, true) => i16::MAX,
+                (false, false) => i16::MIN,
+            }
+        });
+
+        let envelope = read_peak_envelope(&path).unwrap();
+        assert_eq!(envelope.len(), 8);
+        assert_eq!(&envelope[..4], &[[0.0, 0.0]; 4]);
+        assert!(envelope[4..]
+            .iter()
+            .all(|[min, max]| *min == -1.0 && *max > 0.99));
+
+        assert_eq!(
+            downsample_peaks(&envelope, 2),
+            vec![[0.0, 0.0], envelope[4]]
+        );
+    }
+
+    #[test]
+    fn test_downsample_peaks() {
+        let peaks: Vec<_> = (0..10).map(|i| [-(i as f32), i as f32]).collect();
+
+        assert_eq!(
+            downsample_peaks(&peaks, 3),
+            vec![[-2.0, 2.0], [-5.0, 5.0], [-9.0, 9.0]]
+        );
+        assert_eq!(downsample_peaks(&peaks, 20), peaks);
+    }
+
+    #[test]
+    fn test_cached_peaks_roundtrip() {
+        let dir = test_dir("cached-peaks");
+        let audio_path = dir.join("track.wav");
+        write_test_wav(&audio_path, 48_000, 1, 100, |_| 0);
+
+        let cache_path = dir.join("cache").join("track");
+        let peaks = vec![[-0.5, 0.25], [0.0, 1.0]];
+        write_cached_peaks(&cache_path, &peaks).unwrap();
+
+        assert_eq!(read_cached_peaks(&cache_path, &audio_path), Some(peaks));
+        assert_eq!(read_cached_peaks(&dir.join("missing"), &audio_path), None);
+    }
+}
@@ -15,7 +15,8 @@ pub async fn get_playlist_metadata(url: &str, api_key: &str) -> Result<YoutubeSn
             AppErrorKind::Api,
             "failed to get 'playlist id' from youtube playlist url",
             &[&format!("URL: {url}")],
-        ));
+        )
+        .bad_request());
     };
 
     let api_url = format!("https://youtube.googleapis.com/youtube/v3/playlists?part=snippet&maxResults=1&id={playlist_id}&key={api_key}");
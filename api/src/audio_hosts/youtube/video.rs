@@ -36,6 +36,7 @@ impl From<YoutubeVideo> for AudioMetadata {
             author: Some(value.snippet.channel_title).into(),
             cover_art_url: Some(value.snippet.thumbnails.maxres.url).into(),
             duration,
+            gain_db: None,
         }
     }
 }
@@ -54,7 +55,8 @@ pub async fn get_video_metadata(url: &str, api_key: &str) -> Result<YoutubeVideo
             AppErrorKind::Download,
             "failed to get 'watch id' from youtube video url",
             &[&format!("URL: {url}")],
-        ));
+        )
+        .bad_request());
     };
 
     let api_url =
@@ -14,6 +14,9 @@ pub struct AudioMetadata {
     pub author: OptionArcStr,
     pub duration: Option<i64>,
     pub cover_art_url: OptionArcStr,
+    /// gain that normalizes the loudness of the track, `None` if it couldn't be measured
+    #[serde(default)]
+    pub gain_db: Option<f32>,
 }
 
 pub trait AudioDataLocator: Send {
@@ -31,4 +34,8 @@ pub struct AudioPlayerQueueItem<ADL: AudioDataLocator> {
     pub identifier: ItemUid<Arc<str>>,
     pub metadata: AudioMetadata,
     pub locator: ADL,
+    /// gain trim applied on top of the volume while this item is playing
+    pub gain_db: f32,
+    /// overrides the fade in of the node while the item is queued
+    pub fade_in_ms: Option<u32>,
 }
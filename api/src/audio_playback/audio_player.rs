@@ -1,52 +1,164 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    sync::Arc,
+};
 
-use actix::Addr;
+use actix::{Addr, Recipient};
 use anyhow::anyhow;
 use cpal::{
     traits::{DeviceTrait, StreamTrait},
     Device, Stream, StreamConfig, StreamError,
 };
-use creek::{read::ReadError, ReadDiskStream, SymphoniaDecoder};
-use rand::{seq::SliceRandom, thread_rng};
+use creek::{
+    read::{ReadData, ReadError},
+    ReadDiskStream, SymphoniaDecoder,
+};
+use rand::{seq::SliceRandom, thread_rng, Rng};
 use rtrb::{Consumer, Producer, RingBuffer};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
 use crate::{
-    commands::node_commands::AudioNodeCommand,
+    downloader::download_identifier::ItemUid,
     message_send_handler::{ChangeDetector, MessageSendHandler, RateLimiter},
     node::{
         health::{AudioNodeHealth, AudioNodeHealthMild, AudioNodeHealthPoor},
-        node_server::{AudioNode, SourceName},
+        node_server::{
+            async_actor::AsyncRecordPlay, sync_actor::QueueHeadChanged, AudioNode, SourceName,
+        },
         AudioProcessorToNodeMessage,
     },
     utils::setup_device,
 };
 
-use super::audio_item::{AudioDataLocator, AudioMetadata, AudioPlayerQueueItem};
+use super::{
+    audio_item::{AudioDataLocator, AudioMetadata, AudioPlayerQueueItem},
+    buffer_stats::{BufferCounters, BufferStats, BufferStatsTracker},
+    equalizer::{clamp_eq_bands, Equalizer},
+    smart_shuffle::smart_shuffle,
+    test_tone::{Channel, TestTone},
+};
 
 type InternalQueue<ADL> = Vec<AudioPlayerQueueItem<ADL>>;
 
-pub type SerializableQueue = Arc<[AudioMetadata]>;
+/// Queue as sent to clients, includes the queue head so clients don't have to keep track of it
+/// separately
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SerializableQueue {
+    #[ts(type = "Array<SerializableQueueItem>")]
+    pub items: Arc<[SerializableQueueItem]>,
+    pub current_queue_index: usize,
+}
+
+/// Item of the queue as sent to clients, the uid tells tracks with the same metadata apart and
+/// links the item to the stored track
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SerializableQueueItem {
+    pub uid: Arc<str>,
+    pub metadata: AudioMetadata,
+}
+
+pub const MIN_ITEM_GAIN_DB: f32 = -24.0;
+pub const MAX_ITEM_GAIN_DB: f32 = 12.0;
+/// longest fade in at the start of a track
+pub const MAX_FADE_IN_MS: u32 = 10_000;
+
+/// longest overlap of the end of a track with the start of the next one
+pub const MAX_CROSSFADE_MS: u32 = 12_000;
+
+/// converts a gain in decibels to a linear amplitude factor
+pub fn db_to_gain(gain_db: f32) -> f32 {
+    10f32.powf(gain_db / 20.0)
+}
 
 pub struct AudioPlayer<ADL: AudioDataLocator> {
     source_name: SourceName,
-    device: Device,
+    /// `None` for players without an output, they keep track of their queue but never play anything
+    device: Option<Device>,
     config: StreamConfig,
     current_stream: Option<Stream>,
     queue: InternalQueue<ADL>,
     node_addr: Option<Addr<AudioNode>>,
+    /// receives every change of the queue head, the node unless it is replaced in tests
+    queue_head_addr: Option<Recipient<QueueHeadChanged>>,
     processor_msg_buffer: Option<Producer<AudioProcessorMessage>>,
     queue_head: usize,
     current_volume: f32,
+    max_volume: f32,
+    /// gain of every equalizer band in dB, empty if the equalizer is flat
+    equalizer_bands: Vec<f32>,
+    /// fade in at the start of every track, items can override it
+    fade_in_ms: u32,
+    /// whether the measured loudness of the items is normalized, see [`AudioMetadata::gain_db`]
+    normalization_enabled: bool,
+    repeat_mode: RepeatMode,
+    /// overlap of the end of a track with the start of the next one, `0` plays them back to back
+    crossfade_ms: u32,
+    /// track the processor crossfades into, see [`Self::prepare_crossfade`]
+    crossfade_next: Option<CrossfadeTrack>,
+    current_track_info: Option<TrackInfo>,
+    /// announcements that are played over the current track, the first one is playing
+    announcements: VecDeque<AudioPlayerQueueItem<ADL>>,
+    buffer_stats: BufferStatsTracker,
+    /// the stream was dropped by [`Self::stop`], the queue and its head are kept
+    stopped: bool,
+    /// false while the device is lost, items are only started once it has been recovered
+    device_available: bool,
 }
 
 struct AudioProcessor {
     msg_buffer: Consumer<AudioProcessorMessage>,
     read_disk_stream: Option<ReadDiskStream<SymphoniaDecoder>>,
     had_cache_miss_last_cycle: bool,
+    resumed_this_cycle: bool,
     info: ProcessorInfo,
+    item_gain: f32,
+    max_gain: f32,
+    /// `None` if the equalizer is flat so no filters have to run
+    equalizer: Option<Equalizer>,
+    /// number of interleaved channels of the output device
+    output_channels: usize,
+    buffer_counters: Arc<BufferCounters>,
     node_addr: Option<Addr<AudioNode>>,
+    /// played instead of the disk stream until it has ended
+    test_tone: Option<TestTone>,
+    /// played instead of the disk stream until it has ended, like the test tone
+    announcement: Option<ReadDiskStream<SymphoniaDecoder>>,
+    /// only set at the start of a track, so seeking within the track doesn't fade in again
+    fade_in: Option<FadeIn>,
+    /// frames the end of a track overlaps with the next track
+    crossfade_frames: usize,
+    /// blended in during the crossfade and played on its own once the current track has ended
+    next_track: Option<NextTrack>,
+    /// set once the current track has reached the crossfade
+    crossfade: Option<Crossfade>,
+}
+
+/// Gain that rises from silence to full gain over the first frames of a track
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FadeIn {
+    frames: usize,
+    elapsed: usize,
+}
+
+/// Overlap of the end of the current track with the start of the next one, the gain of the
+/// current track falls to silence while the gain of the next track rises to full gain
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Crossfade {
+    frames: usize,
+    elapsed: usize,
+}
+
+/// track that was sent to the processor to crossfade into
+#[derive(Debug, Clone)]
+struct CrossfadeTrack {
+    uid: Arc<str>,
+    /// `None` if the track couldn't be loaded, it is played without a crossfade instead
+    track_info: Option<TrackInfo>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, TS)]
@@ -57,6 +169,18 @@ pub struct AudioInfo {
     pub current_queue_index: usize,
     pub audio_progress: f64,
     pub audio_volume: f32,
+    /// exact length of the current track in seconds, read from the decoded stream as soon as the
+    /// track starts playing, `None` if no track is loaded
+    #[serde(default)]
+    pub duration_secs: Option<f64>,
+    /// position in the current track in seconds, `None` if no track is loaded
+    #[serde(default)]
+    pub elapsed_secs: Option<f64>,
+    #[serde(default)]
+    pub repeat_mode: RepeatMode,
+    /// overlap of the end of a track with the start of the next one, `0` plays them back to back
+    #[serde(default)]
+    pub crossfade_ms: u32,
 }
 
 impl Default for AudioInfo {
@@ -66,10 +190,45 @@ impl Default for AudioInfo {
             audio_progress: Default::default(),
             current_queue_index: Default::default(),
             playback_state: Default::default(),
+            duration_secs: None,
+            elapsed_secs: None,
+            repeat_mode: Default::default(),
+            crossfade_ms: 0,
         }
     }
 }
 
+/// What happens once a track has been played to its end, skipping tracks manually always moves
+/// through the queue
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "kebab-case")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub enum RepeatMode {
+    /// playback stops after the last track of the queue
+    Off,
+    /// the queue starts over after its last track
+    #[default]
+    All,
+    /// the current track is played again
+    One,
+}
+
+/// Layout negotiated with the output device of a player
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct OutputConfig {
+    pub channels: u16,
+    pub sample_rate: u32,
+}
+
+/// Length information of the track that is currently loaded into the processor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackInfo {
+    pub num_frames: usize,
+    pub sample_rate: u32,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct PlaybackInfo {
     pub current_queue_index: usize,
@@ -80,6 +239,12 @@ pub struct ProcessorInfo {
     pub playback_state: PlaybackState,
     pub audio_progress: f64,
     pub audio_volume: f32,
+    /// length of the track, `None` if the processor has no track or its sample rate is unknown
+    pub duration_secs: Option<f64>,
+    pub elapsed_secs: f64,
+    /// false once the track has been played to its end or if the processor was started without
+    /// a track
+    pub track_loaded: bool,
 }
 
 #[derive(Debug)]
@@ -96,13 +261,60 @@ pub enum PlaybackState {
     #[default]
     Playing,
     Paused,
+    /// the stream was dropped, playback restarts at the start of the current item
+    Stopped,
 }
 
-#[derive(Debug, Clone)]
+/// Stream of an announcement that is sent to the processor
+pub struct AnnouncementStream(ReadDiskStream<SymphoniaDecoder>);
+
+impl Debug for AnnouncementStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("AnnouncementStream")
+            .field(&self.0.info().num_frames)
+            .finish()
+    }
+}
+
+/// Track that is sent to the processor to crossfade into once the current track ends
+pub struct NextTrack {
+    stream: ReadDiskStream<SymphoniaDecoder>,
+    item_gain: f32,
+}
+
+impl Debug for NextTrack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NextTrack")
+            .field("num_frames", &self.stream.info().num_frames)
+            .field("item_gain", &self.item_gain)
+            .finish()
+    }
+}
+
+// boxing the equalizer would free the previous one in the audio callback
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
 pub enum AudioProcessorMessage {
     SetVolume(f32),
+    /// linear gain of the current item, applied on top of the volume
+    SetItemGain(f32),
+    /// upper limit of the combined volume and item gain
+    SetMaxGain(f32),
     SetState(PlaybackState),
     SetProgress(f64),
+    /// seeks relative to the current position, the delta is a fraction of the track length
+    SeekProgressDelta(f64),
+    /// seeks to an exact frame, clamped to the length of the track
+    SeekFrames(usize),
+    PlayTestTone(TestTone),
+    PlayAnnouncement(AnnouncementStream),
+    SetEqualizer(Option<Equalizer>),
+    /// frames the end of a track overlaps with the next track, `0` disables crossfading
+    SetCrossfade(usize),
+    /// track to crossfade into, `None` if no track follows the current one
+    SetNextTrack(Option<NextTrack>),
+    /// counters of the track the processor crossfaded into
+    SetBufferCounters(Arc<BufferCounters>),
     Addr(Option<Addr<AudioNode>>),
 }
 
@@ -112,7 +324,55 @@ impl ProcessorInfo {
             audio_volume: volume,
             audio_progress: Default::default(),
             playback_state: Default::default(),
+            duration_secs: None,
+            elapsed_secs: 0.0,
+            track_loaded: false,
+        }
+    }
+
+    /// converts with the sample rate of the track, like [`TrackInfo`], so the reported seconds
+    /// match the seconds that are seeked to
+    fn set_track_secs(&mut self, playhead: usize, num_frames: usize, sample_rate: Option<u32>) {
+        let Some(sample_rate) = sample_rate.filter(|sample_rate| *sample_rate > 0) else {
+            self.duration_secs = None;
+            self.elapsed_secs = 0.0;
+            return;
+        };
+
+        self.duration_secs = Some(num_frames as f64 / sample_rate as f64);
+        self.elapsed_secs = playhead as f64 / sample_rate as f64;
+    }
+}
+
+impl TrackInfo {
+    fn from_stream(stream: &ReadDiskStream<SymphoniaDecoder>) -> Option<Self> {
+        let info = stream.info();
+
+        Some(Self {
+            num_frames: info.num_frames,
+            sample_rate: info.sample_rate?,
+        })
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        self.num_frames as f64 / self.sample_rate as f64
+    }
+
+    pub fn progress_to_secs(&self, progress: f64) -> f64 {
+        self.duration_secs() * progress.clamp(0.0, 1.0)
+    }
+
+    pub fn secs_to_progress(&self, seconds: f64) -> f64 {
+        if self.num_frames == 0 {
+            return 0.0;
         }
+
+        (seconds * self.sample_rate as f64 / self.num_frames as f64).clamp(0.0, 1.0)
+    }
+
+    /// frame of the track at `seconds`, clamped to the length of the track
+    pub fn secs_to_frame(&self, seconds: f64) -> usize {
+        ((seconds.max(0.0) * self.sample_rate as f64).round() as usize).min(self.num_frames)
     }
 }
 
@@ -122,9 +382,52 @@ impl<ADL: AudioDataLocator + Clone> AudioPlayer<ADL> {
         node_addr: Option<Addr<AudioNode>>,
         restored_state: AudioInfo,
         restored_queue: Vec<AudioPlayerQueueItem<ADL>>,
+        max_volume: f32,
     ) -> anyhow::Result<Self> {
         let (device, config) = setup_device(&source_name)?;
 
+        Ok(Self::new(
+            source_name,
+            Some(device),
+            config,
+            node_addr,
+            restored_state,
+            restored_queue,
+            max_volume,
+        ))
+    }
+
+    /// player without an output device, the queue works like on a device but nothing is played
+    #[cfg(test)]
+    pub fn without_output(source_name: SourceName) -> Self {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: cpal::SampleRate(48000),
+            buffer_size: cpal::BufferSize::Default,
+        };
+
+        Self::new(
+            source_name,
+            None,
+            config,
+            None,
+            AudioInfo::default(),
+            vec![],
+            1.0,
+        )
+    }
+
+    fn new(
+        source_name: SourceName,
+        device: Option<Device>,
+        config: StreamConfig,
+        node_addr: Option<Addr<AudioNode>>,
+        restored_state: AudioInfo,
+        restored_queue: Vec<AudioPlayerQueueItem<ADL>>,
+        max_volume: f32,
+    ) -> Self {
+        let max_volume = max_volume.clamp(0.0, 1.0);
+
         let mut player = Self {
             source_name,
             device,
@@ -132,22 +435,47 @@ impl<ADL: AudioDataLocator + Clone> AudioPlayer<ADL> {
             queue: restored_queue,
             current_stream: None,
             processor_msg_buffer: None,
+            queue_head_addr: node_addr.clone().map(Addr::recipient),
             node_addr,
-            current_volume: restored_state.audio_volume,
-            queue_head: restored_state.current_queue_index,
+            current_volume: restored_state.audio_volume.clamp(0.0, max_volume),
+            max_volume,
+            equalizer_bands: vec![],
+            fade_in_ms: 0,
+            normalization_enabled: true,
+            repeat_mode: restored_state.repeat_mode,
+            crossfade_ms: restored_state.crossfade_ms.min(MAX_CROSSFADE_MS),
+            crossfade_next: None,
+            announcements: VecDeque::default(),
+            // validated against the restored queue by `restore_state`
+            queue_head: 0,
+            current_track_info: None,
+            buffer_stats: Default::default(),
+            stopped: false,
+            device_available: true,
         };
 
         player.restore_state(restored_state);
-
-        Ok(player)
+        player
     }
 
     pub fn try_recover_device(&mut self, current_progress: f64) -> anyhow::Result<()> {
         let (device, config) = setup_device(&self.source_name)?;
-        self.device = device;
+        self.device = Some(device);
         self.config = config;
+        self.device_available = true;
+
+        // a stopped player doesn't use the device until playback is restarted
+        if self.stopped {
+            return Ok(());
+        }
 
-        self.play_selected(self.queue_head, true)?;
+        // the current track continues, it isn't a new play
+        match self.current_item() {
+            Some(item) => {
+                self.start_playback(&item)?;
+            }
+            None => self.play_selected(self.queue_head, true)?,
+        }
         self.set_stream_progress(current_progress);
 
         Ok(())
@@ -156,25 +484,138 @@ impl<ADL: AudioDataLocator + Clone> AudioPlayer<ADL> {
     pub fn play_next(&mut self) -> anyhow::Result<()> {
         if self.queue.is_empty() {
             self.current_stream = None;
+            self.current_track_info = None;
+            self.buffer_stats.finish_current_track();
             return Ok(());
         }
 
-        self.update_queue_head(self.queue_head + 1);
+        self.update_queue_head((self.queue_head + 1) % self.queue.len());
+
+        if let Some(item) = self.current_item() {
+            self.play(&item)?;
+        }
+
+        Ok(())
+    }
+
+    /// continues after the current track has been played to its end, depending on the repeat mode
+    ///
+    /// Returns false if nothing is played because the end of the queue was reached.
+    pub fn play_after_finish(&mut self) -> anyhow::Result<bool> {
+        let Some(index) =
+            next_head_after_finish(self.repeat_mode, self.queue_head, self.queue.len())
+        else {
+            return Ok(false);
+        };
+
+        self.play_selected(index, true)?;
+        Ok(true)
+    }
+
+    /// loads the track that follows the current one into the processor, so the current track can
+    /// crossfade into it
+    ///
+    /// Nothing is loaded if crossfading is disabled or playback doesn't continue on its own, the
+    /// track is only loaded again if a different item follows.
+    pub fn prepare_crossfade(&mut self, auto_advance: bool) {
+        let next = if self.crossfade_ms > 0 && auto_advance && self.current_track_info.is_some() {
+            next_head_after_finish(self.repeat_mode, self.queue_head, self.queue.len())
+                .and_then(|index| self.queue.get(index))
+                .cloned()
+        } else {
+            None
+        };
 
-        if self.queue_head >= self.queue.len() {
-            self.update_queue_head(0);
+        let next_uid = next.as_ref().map(|item| &item.identifier.0);
+        if self.crossfade_next.as_ref().map(|track| &track.uid) == next_uid {
+            return;
         }
 
-        if let Some(locator) = self.get_locator() {
-            self.play(&locator)?;
+        if self.processor_msg_buffer.is_none() {
+            return;
         }
 
-        Ok(())
+        let (next_track, prepared) = match next {
+            Some(item) => {
+                let uid = Arc::clone(&item.identifier.0);
+
+                match item.locator.load_audio_data() {
+                    Ok(mut stream) => {
+                        // a fresh stream doesn't request any blocks until it is read from or
+                        // seeked, so the start is cached before the crossfade begins
+                        let _ = stream.seek(0, creek::SeekMode::Auto);
+                        let track_info = TrackInfo::from_stream(&stream);
+
+                        (
+                            Some(NextTrack {
+                                stream,
+                                item_gain: db_to_gain(self.item_gain_db(&item)),
+                            }),
+                            Some(CrossfadeTrack { uid, track_info }),
+                        )
+                    }
+                    Err(err) => {
+                        log::error!("failed to load next track for crossfade, ERROR: {err}");
+                        (
+                            None,
+                            Some(CrossfadeTrack {
+                                uid,
+                                track_info: None,
+                            }),
+                        )
+                    }
+                }
+            }
+            None => (None, None),
+        };
+
+        if let Some(buffer) = self.processor_msg_buffer.as_mut() {
+            if buffer
+                .push(AudioProcessorMessage::SetNextTrack(next_track))
+                .is_ok()
+            {
+                self.crossfade_next = prepared;
+            }
+        }
+    }
+
+    /// moves the queue head to the track the processor has crossfaded into, which keeps playing
+    ///
+    /// If the queue has changed since the track was prepared, the item that follows now is played
+    /// from its start instead.
+    pub fn finish_crossfade(&mut self) -> anyhow::Result<()> {
+        let prepared = self.crossfade_next.take();
+        let index = next_head_after_finish(self.repeat_mode, self.queue_head, self.queue.len());
+
+        match (index, prepared) {
+            (Some(index), Some(prepared)) if self.queue[index].identifier.0 == prepared.uid => {
+                self.update_queue_head(index);
+                self.current_track_info = prepared.track_info;
+
+                self.record_play(&self.queue[index].identifier);
+
+                let buffer_counters = self.buffer_stats.start_track(prepared.uid);
+                if let Some(buffer) = self.processor_msg_buffer.as_mut() {
+                    let _ = buffer.push(AudioProcessorMessage::SetBufferCounters(buffer_counters));
+                }
+
+                Ok(())
+            }
+            (Some(index), _) => self.play_selected(index, true),
+            (None, _) => {
+                self.current_stream = None;
+                self.current_track_info = None;
+                self.buffer_stats.finish_current_track();
+                Ok(())
+            }
+        }
     }
 
     pub fn play_prev(&mut self) -> anyhow::Result<()> {
         if self.queue.is_empty() {
             self.current_stream = None;
+            self.current_track_info = None;
+            self.buffer_stats.finish_current_track();
             return Ok(());
         }
 
@@ -184,8 +625,8 @@ impl<ADL: AudioDataLocator + Clone> AudioPlayer<ADL> {
             .unwrap_or(self.queue.len() - 1);
         self.update_queue_head(prev_head);
 
-        if let Some(locator) = self.get_locator() {
-            self.play(&locator)?;
+        if let Some(item) = self.current_item() {
+            self.play(&item)?;
         }
 
         Ok(())
@@ -194,24 +635,32 @@ impl<ADL: AudioDataLocator + Clone> AudioPlayer<ADL> {
     pub fn play_selected(&mut self, index: usize, allow_self_select: bool) -> anyhow::Result<()> {
         if self.queue.is_empty() {
             self.current_stream = None;
+            self.current_track_info = None;
+            self.buffer_stats.finish_current_track();
             return Ok(());
         }
 
-        if index == self.queue_head && !allow_self_select {
+        // a stopped player restarts the current item when it is selected again
+        if index == self.queue_head && !allow_self_select && !self.stopped {
             return Ok(());
         }
 
         let new_head_pos = index.clamp(0, self.queue.len() - 1);
         self.update_queue_head(new_head_pos);
 
-        if let Some(locator) = self.get_locator() {
-            self.play(&locator)?;
+        if let Some(item) = self.current_item() {
+            self.play(&item)?;
         }
 
         Ok(())
     }
 
     pub fn set_stream_playback_state(&mut self, state: PlaybackState) {
+        if state == PlaybackState::Stopped {
+            self.stop();
+            return;
+        }
+
         if let Some(buffer) = self.processor_msg_buffer.as_mut() {
             let _ = buffer.push(AudioProcessorMessage::SetState(state));
         }
@@ -225,8 +674,137 @@ impl<ADL: AudioDataLocator + Clone> AudioPlayer<ADL> {
         }
     }
 
+    /// plays a sine wave on `channel` instead of the current track, the track continues at the same
+    /// position once the tone has ended
+    ///
+    /// A silent stream is started for the tone if no track is loaded.
+    pub fn play_test_tone(&mut self, channel: Channel, seconds: u64) -> anyhow::Result<()> {
+        if self.current_stream.is_none() {
+            self.start_stream(None, 1.0, 0, Default::default())?;
+        }
+
+        let tone = TestTone::new(channel, seconds, self.config.sample_rate.0);
+        let Some(buffer) = self.processor_msg_buffer.as_mut() else {
+            return Err(anyhow!("no audio stream, can not play test tone"));
+        };
+
+        buffer
+            .push(AudioProcessorMessage::PlayTestTone(tone))
+            .map_err(|_| anyhow!("audio processor message buffer is full"))
+    }
+
+    /// plays `item` over the current track, the track continues at the same position once the
+    /// announcement has ended
+    ///
+    /// Announcements that are added while another one is playing are played after it. A silent
+    /// stream is started for the announcement if no track is loaded.
+    pub fn announce(&mut self, item: AudioPlayerQueueItem<ADL>) -> anyhow::Result<()> {
+        if !self.announcements.is_empty() {
+            self.announcements.push_back(item);
+            return Ok(());
+        }
+
+        let stream = item.locator.load_audio_data()?;
+        self.announcements.push_back(item);
+
+        if let Err(err) = self.play_announcement(stream) {
+            self.announcements.clear();
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// plays the next announcement once the current one has ended
+    ///
+    /// Announcements that can't be played are skipped, the error of the last one is returned.
+    /// The stream is released again after the last announcement if no track is loaded.
+    pub fn finish_announcement(&mut self) -> anyhow::Result<()> {
+        self.announcements.pop_front();
+        let mut result = Ok(());
+
+        while let Some(item) = self.announcements.front() {
+            let played = item
+                .locator
+                .load_audio_data()
+                .map_err(anyhow::Error::from)
+                .and_then(|stream| self.play_announcement(stream));
+
+            match played {
+                Ok(()) => return result,
+                Err(err) => {
+                    self.announcements.pop_front();
+                    result = Err(err);
+                }
+            }
+        }
+
+        if self.stopped || self.queue.is_empty() {
+            self.current_stream = None;
+            self.processor_msg_buffer = None;
+        }
+
+        result
+    }
+
+    fn play_announcement(
+        &mut self,
+        stream: ReadDiskStream<SymphoniaDecoder>,
+    ) -> anyhow::Result<()> {
+        let stream = ready_announcement(stream)?;
+
+        if self.current_stream.is_none() {
+            self.start_stream(None, 1.0, 0, Default::default())?;
+        }
+
+        let Some(buffer) = self.processor_msg_buffer.as_mut() else {
+            return Err(anyhow!("no audio stream, can not play announcement"));
+        };
+
+        buffer
+            .push(AudioProcessorMessage::PlayAnnouncement(AnnouncementStream(
+                stream,
+            )))
+            .map_err(|_| anyhow!("audio processor message buffer is full"))
+    }
+
+    /// seeks relative to the current position by a fraction of the track length, the new position
+    /// is clamped between `0.0` and `1.0`
+    ///
+    /// Returns false without seeking if the length of the current track is unknown.
+    pub fn seek_stream_progress_delta(&mut self, delta: f64) -> bool {
+        if self
+            .current_track_info
+            .is_none_or(|track_info| track_info.num_frames == 0)
+        {
+            return false;
+        }
+
+        if let Some(buffer) = self.processor_msg_buffer.as_mut() {
+            let _ = buffer.push(AudioProcessorMessage::SeekProgressDelta(delta));
+        }
+
+        true
+    }
+
+    /// seeks to an absolute position in the current track, clamped to the length of the track
+    pub fn set_stream_progress_secs(&mut self, seconds: f64) -> anyhow::Result<()> {
+        let Some(track_info) = self.current_track_info else {
+            return Err(anyhow!("no track loaded, can not seek"));
+        };
+
+        if let Some(buffer) = self.processor_msg_buffer.as_mut() {
+            let _ = buffer.push(AudioProcessorMessage::SeekFrames(
+                track_info.secs_to_frame(seconds),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// sets the volume, clamped to the max volume of the player
     pub fn set_volume(&mut self, volume: f32) {
-        let volume = volume.clamp(0.0, 1.0);
+        let volume = volume.clamp(0.0, self.max_volume);
         self.current_volume = volume;
 
         if let Some(buffer) = self.processor_msg_buffer.as_mut() {
@@ -234,135 +812,496 @@ impl<ADL: AudioDataLocator + Clone> AudioPlayer<ADL> {
         }
     }
 
-    /// if this is the first song to be added to the queue starts playing immediately
-    pub fn push_to_queue(&mut self, item: AudioPlayerQueueItem<ADL>) -> anyhow::Result<()> {
-        if self.queue.is_empty() {
-            self.play(&item.locator)?;
+    /// sets the max volume of the player, lowers the current volume if it exceeds the new cap
+    pub fn set_max_volume(&mut self, max_volume: f32) {
+        self.max_volume = max_volume.clamp(0.0, 1.0);
+
+        if let Some(buffer) = self.processor_msg_buffer.as_mut() {
+            let _ = buffer.push(AudioProcessorMessage::SetMaxGain(self.max_volume));
         }
 
-        self.queue.push(item);
-        Ok(())
+        if self.current_volume > self.max_volume {
+            self.set_volume(self.max_volume);
+        }
     }
 
-    pub fn remove_from_queue(&mut self, idx: usize) -> anyhow::Result<()> {
-        if idx >= self.queue.len() {
-            return Err(anyhow!("index out of bounds, can not remove item"));
+    /// sets the gain trim of a queue item, clamped between [`MIN_ITEM_GAIN_DB`] and
+    /// [`MAX_ITEM_GAIN_DB`]
+    ///
+    /// Returns the clamped gain.
+    pub fn set_item_gain(&mut self, index: usize, gain_db: f32) -> anyhow::Result<f32> {
+        let gain_db = gain_db.clamp(MIN_ITEM_GAIN_DB, MAX_ITEM_GAIN_DB);
+
+        let Some(item) = self.queue.get_mut(index) else {
+            return Err(anyhow!("index out of bounds, can not set gain of item"));
+        };
+        item.gain_db = gain_db;
+
+        if index == self.queue_head {
+            self.update_item_gain();
         }
 
-        self.queue.remove(idx);
+        Ok(gain_db)
+    }
 
-        if self.queue.is_empty() {
-            self.play_next() // play nothing
-        } else if idx == self.queue_head {
-            if self.queue_head > 0 {
-                self.update_queue_head(self.queue_head - 1);
-            } else {
-                self.update_queue_head(self.queue.len() - 1);
-            }
+    /// enables normalizing the loudness of items whose loudness was measured, applies to the
+    /// current item right away
+    pub fn set_normalization_enabled(&mut self, enabled: bool) {
+        self.normalization_enabled = enabled;
+        self.update_item_gain();
 
-            self.play_next()
-        } else if idx < self.queue_head {
-            // keep playing current
-            self.update_queue_head(self.queue_head - 1);
-            Ok(())
-        } else {
-            Ok(()) // keep playing current
-        }
+        // loaded again with the new gain
+        self.crossfade_next = None;
     }
 
-    pub fn shuffle_queue(&mut self) -> anyhow::Result<()> {
-        self.queue.shuffle(&mut thread_rng());
-        self.update_queue_head(0);
-        self.play_selected(0, true)
+    pub fn normalization_enabled(&self) -> bool {
+        self.normalization_enabled
     }
 
-    // holy shit this should be unit tested
-    pub fn move_queue_item(&mut self, old: usize, new: usize) {
-        if old == new {
-            return;
+    /// gain trim of `item` plus its normalization gain if normalization is enabled, items whose
+    /// loudness wasn't measured aren't normalized
+    fn item_gain_db(&self, item: &AudioPlayerQueueItem<ADL>) -> f32 {
+        match item.metadata.gain_db {
+            Some(normalization_db) if self.normalization_enabled => item.gain_db + normalization_db,
+            _ => item.gain_db,
         }
+    }
 
-        if old > new {
-            for i in (new + 1..=old).rev() {
-                if self.queue_head == i - 1 {
-                    self.update_queue_head(i);
-                } else if self.queue_head == i {
-                    self.update_queue_head(i - 1);
-                }
+    /// sends the gain of the current item to the processor
+    fn update_item_gain(&mut self) {
+        let Some(gain_db) = self
+            .queue
+            .get(self.queue_head)
+            .map(|item| self.item_gain_db(item))
+        else {
+            return;
+        };
 
-                self.queue.swap(i - 1, i);
-            }
-        } else {
-            for i in old..new {
-                if self.queue_head == i {
-                    self.update_queue_head(i + 1);
-                } else if self.queue_head == i + 1 {
-                    self.update_queue_head(i);
-                }
+        if let Some(buffer) = self.processor_msg_buffer.as_mut() {
+            let _ = buffer.push(AudioProcessorMessage::SetItemGain(db_to_gain(gain_db)));
+        }
+    }
 
-                self.queue.swap(i, i + 1);
+    /// applies stored gain overrides to all queue items with a matching uid
+    pub fn apply_gain_overrides(&mut self, overrides: &HashMap<Arc<str>, f32>) {
+        for index in 0..self.queue.len() {
+            if let Some(gain_db) = overrides.get(&self.queue[index].identifier.0) {
+                if self.queue[index].gain_db != *gain_db {
+                    let _ = self.set_item_gain(index, *gain_db);
+                }
             }
         }
     }
 
-    pub fn queue(&self) -> &[AudioPlayerQueueItem<ADL>] {
-        &self.queue
+    pub fn max_volume(&self) -> f32 {
+        self.max_volume
     }
 
-    pub fn queue_head(&self) -> usize {
-        self.queue_head
+    /// sets the fade in at the start of every track, clamped to [`MAX_FADE_IN_MS`]
+    ///
+    /// Applies from the next track on. Returns the clamped duration.
+    pub fn set_fade_in_ms(&mut self, fade_in_ms: u32) -> u32 {
+        self.fade_in_ms = fade_in_ms.min(MAX_FADE_IN_MS);
+        self.fade_in_ms
     }
 
-    pub fn set_addr(&mut self, node_addr: Option<Addr<AudioNode>>) {
-        self.node_addr = node_addr.clone();
+    pub fn set_repeat_mode(&mut self, repeat_mode: RepeatMode) {
+        self.repeat_mode = repeat_mode;
+    }
+
+    pub fn repeat_mode(&self) -> RepeatMode {
+        self.repeat_mode
+    }
+
+    /// sets the overlap of the end of a track with the start of the next one, clamped to
+    /// [`MAX_CROSSFADE_MS`]
+    ///
+    /// Returns the clamped duration.
+    pub fn set_crossfade_ms(&mut self, crossfade_ms: u32) -> u32 {
+        self.crossfade_ms = crossfade_ms.min(MAX_CROSSFADE_MS);
 
+        let frames = self.crossfade_frames();
         if let Some(buffer) = self.processor_msg_buffer.as_mut() {
-            let _ = buffer.push(AudioProcessorMessage::Addr(node_addr));
+            let _ = buffer.push(AudioProcessorMessage::SetCrossfade(frames));
         }
+
+        self.crossfade_ms
     }
 
-    fn get_locator(&self) -> Option<ADL> {
-        self.queue
-            .get(self.queue_head)
-            .map(|audio| audio.locator.clone())
+    pub fn crossfade_ms(&self) -> u32 {
+        self.crossfade_ms
     }
 
-    fn update_queue_head(&mut self, value: usize) {
-        self.queue_head = value;
+    fn crossfade_frames(&self) -> usize {
+        (self.crossfade_ms as u64 * self.config.sample_rate.0 as u64 / 1000) as usize
     }
 
-    fn restore_state(&mut self, info: AudioInfo) {
-        self.queue_head = info.current_queue_index;
+    pub fn fade_in_ms(&self) -> u32 {
+        self.fade_in_ms
+    }
 
-        if let Some(locator) = self.get_locator() {
-            if let Err(err) = self.play(&locator) {
-                log::error!("failed to play audio after restore\nERROR: {err}")
-            }
+    /// overrides the fade in of a queue item, `None` uses the fade in of the player
+    ///
+    /// Applies the next time the item is played. Returns the clamped duration.
+    pub fn set_item_fade_in_ms(
+        &mut self,
+        index: usize,
+        fade_in_ms: Option<u32>,
+    ) -> anyhow::Result<Option<u32>> {
+        let fade_in_ms = fade_in_ms.map(|ms| ms.min(MAX_FADE_IN_MS));
 
-            self.set_volume(info.audio_volume);
-            self.set_stream_progress(info.audio_progress);
-            self.set_stream_playback_state(info.playback_state);
-        } else {
-            self.queue_head = 0
+        let Some(item) = self.queue.get_mut(index) else {
+            return Err(anyhow!("index out of bounds, can not set fade in of item"));
+        };
+        item.fade_in_ms = fade_in_ms;
+
+        Ok(fade_in_ms)
+    }
+
+    /// sets the gain of every equalizer band in dB, see [`clamp_eq_bands`]
+    ///
+    /// Returns the clamped gains.
+    pub fn set_equalizer(&mut self, bands_db: &[f32]) -> &[f32] {
+        self.equalizer_bands = clamp_eq_bands(bands_db);
+
+        if let Some(buffer) = self.processor_msg_buffer.as_mut() {
+            let _ = buffer.push(AudioProcessorMessage::SetEqualizer(Equalizer::new(
+                &self.equalizer_bands,
+                self.config.sample_rate.0,
+            )));
         }
+
+        &self.equalizer_bands
     }
 
-    fn play(&mut self, locator: &ADL) -> anyhow::Result<()> {
-        // prevent bluez-alsa from throwing error 'device busy' by removing the stream accessing
-        // the bluetooth device before creating a new stream
-        self.current_stream = None;
+    pub fn equalizer_bands(&self) -> &[f32] {
+        &self.equalizer_bands
+    }
 
-        let read_disk_stream = locator.load_audio_data()?;
+    pub fn source_name(&self) -> &SourceName {
+        &self.source_name
+    }
 
-        let (producer, consumer) = RingBuffer::<AudioProcessorMessage>::new(16);
-        self.processor_msg_buffer = Some(producer);
+    pub fn output_config(&self) -> OutputConfig {
+        OutputConfig {
+            channels: self.config.channels,
+            sample_rate: self.config.sample_rate.0,
+        }
+    }
 
-        let mut processor = AudioProcessor::new(
-            consumer,
+    pub fn buffer_stats(&self) -> BufferStats {
+        self.buffer_stats.stats()
+    }
+
+    /// drops the stream to release the output device, the queue and its head are kept so playback
+    /// can be restarted from the start of the current item
+    pub fn stop(&mut self) {
+        self.current_stream = None;
+        self.processor_msg_buffer = None;
+        self.current_track_info = None;
+        self.buffer_stats.finish_current_track();
+        self.announcements.clear();
+        self.stopped = true;
+    }
+
+    /// removes every item and stops playback, the next item that is added is played right away
+    pub fn clear_queue(&mut self) {
+        self.stop();
+        self.queue.clear();
+        self.update_queue_head(0);
+    }
+
+    /// while the device is unavailable the queue can still be edited, the current item is started
+    /// by [`Self::try_recover_device`]
+    pub fn set_device_available(&mut self, available: bool) {
+        self.device_available = available;
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub fn reset_buffer_stats(&mut self) {
+        self.buffer_stats.reset();
+    }
+
+    /// if this is the first song to be added to the queue starts playing immediately
+    ///
+    /// The first song is only added if it can be played, so the next song that is added after a
+    /// failure starts playing instead.
+    pub fn push_to_queue(&mut self, item: AudioPlayerQueueItem<ADL>) -> anyhow::Result<()> {
+        if self.queue.is_empty() {
+            self.play(&item)?;
+        }
+
+        push_item(&mut self.queue, &mut self.queue_head, item);
+        Ok(())
+    }
+
+    /// inserts an item at `index` without interrupting playback, an index past the end of the queue
+    /// appends the item
+    ///
+    /// The current item keeps playing, inserting at the head places the new item before it. The
+    /// item is played right away if the queue is empty, like with [`Self::push_to_queue`].
+    pub fn insert_into_queue(
+        &mut self,
+        index: usize,
+        item: AudioPlayerQueueItem<ADL>,
+    ) -> anyhow::Result<()> {
+        if self.queue.is_empty() {
+            return self.push_to_queue(item);
+        }
+
+        let head = insert_item(&mut self.queue, self.queue_head, index, item);
+        self.update_queue_head(head);
+
+        Ok(())
+    }
+
+    /// like [`Self::push_to_queue`] but the first song is only loaded and stays paused
+    pub fn push_to_queue_paused(&mut self, item: AudioPlayerQueueItem<ADL>) -> anyhow::Result<()> {
+        let is_first = self.queue.is_empty();
+        self.push_to_queue(item)?;
+
+        if is_first {
+            self.set_stream_playback_state(PlaybackState::Paused);
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_from_queue(&mut self, idx: usize) -> anyhow::Result<()> {
+        if idx >= self.queue.len() {
+            return Err(anyhow!("index out of bounds, can not remove item"));
+        }
+
+        self.queue.remove(idx);
+
+        if self.queue.is_empty() {
+            self.play_next() // play nothing
+        } else if idx == self.queue_head {
+            // the next item takes the place of the removed one, it is only started if the removed
+            // one was playing
+            if self.queue_head >= self.queue.len() {
+                self.update_queue_head(0);
+            }
+
+            match self.current_item() {
+                Some(item) if !self.stopped => self.play(&item),
+                _ => Ok(()),
+            }
+        } else if idx < self.queue_head {
+            // keep playing current
+            self.update_queue_head(self.queue_head - 1);
+            Ok(())
+        } else {
+            Ok(()) // keep playing current
+        }
+    }
+
+    /// shuffles the queue without interrupting playback, the current item is moved to the start of
+    /// the queue
+    pub fn shuffle_queue(&mut self) {
+        shuffle_after_head(&mut self.queue, self.queue_head, &mut thread_rng());
+        self.update_queue_head(0);
+    }
+
+    /// shuffles the queue with [`smart_shuffle`] without interrupting playback, the current item is
+    /// moved to the start of the queue
+    pub fn smart_shuffle_queue(&mut self, seed: u64) {
+        smart_shuffle(&mut self.queue, self.queue_head, seed, |item| {
+            item.metadata.author.inner_as_ref()
+        });
+        self.update_queue_head(0);
+    }
+
+    /// moves a queue item without interrupting playback, the queue head keeps pointing at the item
+    /// that is currently playing
+    ///
+    /// Returns the new queue head, `None` without moving anything if a position is out of bounds.
+    pub fn move_queue_item(&mut self, old: usize, new: usize) -> Option<usize> {
+        let head = move_item(&mut self.queue, self.queue_head, old, new)?;
+        self.update_queue_head(head);
+
+        Some(head)
+    }
+
+    /// replaces the queue and playback state, as if the player had been created with them
+    pub fn restore(&mut self, queue: Vec<AudioPlayerQueueItem<ADL>>, info: AudioInfo) {
+        self.current_stream = None;
+        self.current_track_info = None;
+        self.buffer_stats.finish_current_track();
+        self.stopped = false;
+
+        self.queue = queue;
+        self.set_volume(info.audio_volume);
+        self.restore_state(info);
+    }
+
+    pub fn queue(&self) -> &[AudioPlayerQueueItem<ADL>] {
+        &self.queue
+    }
+
+    /// whether an output stream is running, there is none if the queue is empty
+    pub fn has_stream(&self) -> bool {
+        self.current_stream.is_some()
+    }
+
+    pub fn queue_head(&self) -> usize {
+        self.queue_head
+    }
+
+    pub fn current_track_info(&self) -> Option<TrackInfo> {
+        self.current_track_info
+    }
+
+    pub fn set_addr(&mut self, node_addr: Option<Addr<AudioNode>>) {
+        self.node_addr = node_addr.clone();
+        self.queue_head_addr = node_addr.clone().map(Addr::recipient);
+
+        if let Some(buffer) = self.processor_msg_buffer.as_mut() {
+            let _ = buffer.push(AudioProcessorMessage::Addr(node_addr));
+        }
+    }
+
+    fn current_item(&self) -> Option<AudioPlayerQueueItem<ADL>> {
+        self.queue.get(self.queue_head).cloned()
+    }
+
+    fn update_queue_head(&mut self, value: usize) {
+        if self.queue_head == value {
+            return;
+        }
+
+        self.queue_head = value;
+        if let Some(addr) = self.queue_head_addr.as_ref() {
+            addr.do_send(QueueHeadChanged(value));
+        }
+    }
+
+    fn restore_state(&mut self, info: AudioInfo) {
+        self.repeat_mode = info.repeat_mode;
+        self.crossfade_ms = info.crossfade_ms.min(MAX_CROSSFADE_MS);
+        self.queue_head = clamp_queue_head(info.current_queue_index, self.queue.len());
+        if self.queue_head != info.current_queue_index {
+            log::warn!(
+                "restored queue index is out of range, continuing at {head} instead\nNODE_NAME: {name}\nINDEX: {index}\nQUEUE_LEN: {len}",
+                head = self.queue_head,
+                name = self.source_name,
+                index = info.current_queue_index,
+                len = self.queue.len()
+            );
+        }
+
+        if info.playback_state == PlaybackState::Stopped {
+            self.stopped = true;
+        } else if let Some(item) = self.current_item() {
+            if let Err(err) = self.start_playback(&item) {
+                log::error!("failed to play audio after restore\nERROR: {err}")
+            }
+
+            self.set_volume(info.audio_volume);
+            self.set_stream_progress(info.audio_progress);
+            self.set_stream_playback_state(info.playback_state);
+        } else {
+            self.queue_head = 0
+        }
+    }
+
+    fn play(&mut self, item: &AudioPlayerQueueItem<ADL>) -> anyhow::Result<()> {
+        if self.start_playback(item)? {
+            self.record_play(&item.identifier);
+        }
+
+        Ok(())
+    }
+
+    /// sends the started track to the node which stores it in the play history, playback never
+    /// waits for the database
+    fn record_play(&self, uid: &ItemUid<Arc<str>>) {
+        if let Some(addr) = self.node_addr.as_ref() {
+            addr.do_send(AsyncRecordPlay(uid.clone()));
+        }
+    }
+
+    /// plays `item` without recording it in the play history, returns false if nothing is played
+    /// because the device is unavailable
+    fn start_playback(&mut self, item: &AudioPlayerQueueItem<ADL>) -> anyhow::Result<bool> {
+        // prevent bluez-alsa from throwing error 'device busy' by removing the stream accessing
+        // the bluetooth device before creating a new stream
+        self.current_stream = None;
+        self.current_track_info = None;
+        self.stopped = false;
+
+        if !self.device_available || self.device.is_none() {
+            return Ok(false);
+        }
+
+        let read_disk_stream = item.locator.load_audio_data()?;
+        let track_info = TrackInfo::from_stream(&read_disk_stream);
+        let buffer_counters = self
+            .buffer_stats
+            .start_track(Arc::clone(&item.identifier.0));
+        let fade_in_ms = item.fade_in_ms.unwrap_or(self.fade_in_ms);
+        let fade_in_frames = fade_in_ms as u64 * self.config.sample_rate.0 as u64 / 1000;
+
+        self.start_stream(
             Some(read_disk_stream),
+            db_to_gain(self.item_gain_db(item)),
+            fade_in_frames as usize,
+            buffer_counters,
+        )?;
+        self.current_track_info = track_info;
+
+        // the announcement was dropped with the previous stream, it starts over on the new one
+        if let Some(item) = self.announcements.front() {
+            let restarted = item
+                .locator
+                .load_audio_data()
+                .map_err(anyhow::Error::from)
+                .and_then(|stream| self.play_announcement(stream));
+
+            if let Err(err) = restarted {
+                log::error!("failed to restart announcement, ERROR: {err}");
+                self.announcements.clear();
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// builds and starts a new output stream with its own processor
+    fn start_stream(
+        &mut self,
+        read_disk_stream: Option<ReadDiskStream<SymphoniaDecoder>>,
+        item_gain: f32,
+        fade_in_frames: usize,
+        buffer_counters: Arc<BufferCounters>,
+    ) -> anyhow::Result<()> {
+        let Some(device) = self.device.as_ref() else {
+            return Err(anyhow!(
+                "player has no output device, can not start audio stream"
+            ));
+        };
+
+        let (producer, consumer) = RingBuffer::<AudioProcessorMessage>::new(16);
+        self.processor_msg_buffer = Some(producer);
+        self.crossfade_next = None;
+
+        let mut processor = AudioProcessor::new(
+            consumer,
+            read_disk_stream,
             self.node_addr.clone(),
             self.current_volume,
+            item_gain,
+            self.max_volume,
+            buffer_counters,
         );
+        processor.equalizer = Equalizer::new(&self.equalizer_bands, self.config.sample_rate.0);
+        processor.output_channels = self.config.channels.max(1) as usize;
+        processor.report_track_secs();
+        processor.fade_in = FadeIn::new(fade_in_frames);
+        processor.crossfade_frames = self.crossfade_frames();
 
         let mut msg_handler = MessageSendHandler::with_limiters(vec![
             Box::new(ChangeDetector::<AudioProcessorToNodeMessage>::new(Some(
@@ -380,16 +1319,18 @@ impl<ADL: AudioDataLocator + Clone> AudioPlayer<ADL> {
 
         let addr_for_err = self.node_addr.clone();
 
-        let new_stream = self.device.build_output_stream(
+        let new_stream = device.build_output_stream(
             &self.config,
             move |data: &mut [f32], _| match processor.try_process(data) {
                 Ok(state) => match state {
                     AudioStreamState::Finished => {
                         processor.read_disk_stream = None;
+                        processor.info.track_loaded = false;
 
                         if let Some(addr) = processor.node_addr.as_ref() {
-                            if let Err(err) = addr.try_send(AudioNodeCommand::PlayNext) {
-                                log::error!("failed to play next audio in queue, ERROR: {err}");
+                            if let Err(err) = addr.try_send(AudioProcessorToNodeMessage::TrackEnded)
+                            {
+                                log::error!("failed to notify node of track end, ERROR: {err}");
                             }
                         }
                     }
@@ -456,45 +1397,209 @@ impl AudioProcessor {
         read_disk_stream: Option<ReadDiskStream<SymphoniaDecoder>>,
         node_addr: Option<Addr<AudioNode>>,
         volume: f32,
+        item_gain: f32,
+        max_gain: f32,
+        buffer_counters: Arc<BufferCounters>,
     ) -> Self {
+        let info = ProcessorInfo {
+            track_loaded: read_disk_stream.is_some(),
+            ..ProcessorInfo::new(volume)
+        };
+
         Self {
             msg_buffer,
             read_disk_stream,
             node_addr,
             had_cache_miss_last_cycle: false,
-            info: ProcessorInfo::new(volume),
+            resumed_this_cycle: false,
+            info,
+            item_gain,
+            max_gain,
+            equalizer: None,
+            output_channels: 2,
+            buffer_counters,
+            test_tone: None,
+            announcement: None,
+            fade_in: None,
+            crossfade_frames: 0,
+            next_track: None,
+            crossfade: None,
+        }
+    }
+
+    /// reports the length of the track that was loaded with the processor
+    fn report_track_secs(&mut self) {
+        if let Some(stream) = self.read_disk_stream.as_ref() {
+            let info = stream.info();
+            self.info
+                .set_track_secs(stream.playhead(), info.num_frames, info.sample_rate);
+        }
+    }
+
+    /// returns whether the new position was cached, `None` if seeking failed
+    fn seek(&mut self, percentage: f64) -> Option<bool> {
+        let num_frames = self.read_disk_stream.as_ref()?.info().num_frames;
+
+        self.seek_to((num_frames as f64 * percentage) as usize, percentage)
+    }
+
+    /// like [`Self::seek`], `frame` is clamped to the length of the track
+    fn seek_frame(&mut self, frame: usize) -> Option<bool> {
+        let num_frames = self.read_disk_stream.as_ref()?.info().num_frames;
+        let frame = frame.min(num_frames);
+
+        self.seek_to(frame, frame as f64 / num_frames.max(1) as f64)
+    }
+
+    /// seeks to `seek_frame`, which is at `percentage` of the track
+    fn seek_to(&mut self, seek_frame: usize, percentage: f64) -> Option<bool> {
+        let read_disk_stream = self.read_disk_stream.as_mut()?;
+        let num_frames = read_disk_stream.info().num_frames;
+        let sample_rate = read_disk_stream.info().sample_rate;
+        let cache_found = read_disk_stream
+            .seek(seek_frame, creek::SeekMode::Auto)
+            .ok()?;
+
+        // the crossfade starts over once the new position reaches it
+        if self.crossfade.take().is_some() {
+            if let Some(next_track) = self.next_track.as_mut() {
+                let _ = next_track.stream.seek(0, creek::SeekMode::Auto);
+            }
+        }
+
+        // report the new position right away, even while paused the playhead won't move until
+        // playback resumes
+        self.info.audio_progress = percentage;
+        self.info
+            .set_track_secs(seek_frame, num_frames, sample_rate);
+
+        Some(cache_found)
+    }
+
+    /// replaces the track that has ended during the crossfade with the next track, which is
+    /// already playing
+    fn continue_with_next_track(&mut self) {
+        self.crossfade = None;
+
+        let Some(NextTrack { stream, item_gain }) = self.next_track.take() else {
+            return;
+        };
+
+        let (playhead, num_frames) = (stream.playhead(), stream.info().num_frames);
+        self.info.audio_progress = playhead as f64 / num_frames.max(1) as f64;
+        self.info
+            .set_track_secs(playhead, num_frames, stream.info().sample_rate);
+        self.read_disk_stream = Some(stream);
+        self.item_gain = item_gain;
+        self.fade_in = None;
+
+        if let Some(addr) = self.node_addr.as_ref() {
+            if let Err(err) = addr.try_send(AudioProcessorToNodeMessage::TrackCrossfaded) {
+                log::error!("failed to notify node of crossfade, ERROR: {err}");
+            }
         }
     }
 
     fn try_process(
         &mut self,
-        mut data: &mut [f32],
+        output: &mut [f32],
     ) -> Result<AudioStreamState, ReadError<symphonia_core::errors::Error>> {
         let mut cache_missed_this_cycle = false;
+        let mut crossfaded = false;
         let mut stream_state = AudioStreamState::Playing;
 
         while let Ok(msg) = self.msg_buffer.pop() {
             match msg {
                 AudioProcessorMessage::Addr(addr) => self.node_addr = addr,
                 AudioProcessorMessage::SetVolume(volume) => self.info.audio_volume = volume,
-                AudioProcessorMessage::SetState(state) => self.info.playback_state = state,
+                AudioProcessorMessage::SetItemGain(gain) => self.item_gain = gain,
+                AudioProcessorMessage::SetMaxGain(gain) => self.max_gain = gain,
+                AudioProcessorMessage::SetState(state) => {
+                    if self.info.playback_state == PlaybackState::Paused
+                        && state == PlaybackState::Playing
+                    {
+                        self.resumed_this_cycle = true;
+                    }
+
+                    self.info.playback_state = state
+                }
                 AudioProcessorMessage::SetProgress(percentage) => {
-                    if let Some(read_disk_stream) = &mut self.read_disk_stream {
-                        let num_frames = read_disk_stream.info().num_frames;
-                        let seek_frame = (num_frames as f64 * percentage) as usize;
-                        if let Ok(cache_found) =
-                            read_disk_stream.seek(seek_frame, creek::SeekMode::Auto)
-                        {
-                            if !cache_found {
-                                stream_state = AudioStreamState::Buffering;
-                            }
-                        }
+                    if let Some(false) = self.seek(percentage) {
+                        stream_state = AudioStreamState::Buffering;
+                    }
+                }
+                AudioProcessorMessage::SeekProgressDelta(delta) => {
+                    let percentage = (self.info.audio_progress + delta).clamp(0.0, 1.0);
+                    if let Some(false) = self.seek(percentage) {
+                        stream_state = AudioStreamState::Buffering;
                     }
                 }
+                AudioProcessorMessage::SeekFrames(frame) => {
+                    if let Some(false) = self.seek_frame(frame) {
+                        stream_state = AudioStreamState::Buffering;
+                    }
+                }
+                AudioProcessorMessage::PlayTestTone(tone) => self.test_tone = Some(tone),
+                AudioProcessorMessage::PlayAnnouncement(AnnouncementStream(stream)) => {
+                    self.announcement = Some(stream)
+                }
+                AudioProcessorMessage::SetEqualizer(equalizer) => self.equalizer = equalizer,
+                AudioProcessorMessage::SetCrossfade(frames) => self.crossfade_frames = frames,
+                AudioProcessorMessage::SetNextTrack(next_track) => {
+                    // a running crossfade starts over with the new track
+                    self.crossfade = None;
+                    self.next_track = next_track;
+                }
+                AudioProcessorMessage::SetBufferCounters(counters) => {
+                    self.buffer_counters = counters
+                }
             }
         }
 
-        if let Some(read_disk_stream) = &mut self.read_disk_stream {
+        if let Some(test_tone) = self.test_tone.as_mut() {
+            // the disk stream isn't read while the tone is playing, so the track continues at the
+            // same position once the tone has ended
+            if !test_tone.fill(
+                output,
+                self.output_channels,
+                self.info.audio_volume.min(self.max_gain),
+            ) {
+                self.test_tone = None;
+            }
+
+            return Ok(stream_state);
+        }
+
+        let mut data = &mut *output;
+        let announcing = self.announcement.is_some();
+
+        if let Some(announcement) = self.announcement.as_mut() {
+            // played even if the track is paused, the track isn't read until it has ended
+            if announcement.is_ready().unwrap_or(false) {
+                let vol = self.info.audio_volume.min(self.max_gain);
+                let ended = fill_from_stream(announcement, data, vol, self.output_channels)
+                    .unwrap_or_else(|err| {
+                        log::error!("failed to read announcement, ERROR: {err}");
+                        true
+                    });
+
+                if ended {
+                    self.announcement = None;
+
+                    if let Some(addr) = self.node_addr.as_ref() {
+                        if let Err(err) =
+                            addr.try_send(AudioProcessorToNodeMessage::AnnouncementEnded)
+                        {
+                            log::error!("failed to notify node of announcement end, ERROR: {err}");
+                        }
+                    }
+                }
+            } else {
+                // the announcement continues where it stopped once it is buffered again
+                silence(data);
+                cache_missed_this_cycle = true;
+            }
+        } else if let Some(read_disk_stream) = &mut self.read_disk_stream {
             if self.info.playback_state == PlaybackState::Paused {
                 silence(data);
                 return Ok(AudioStreamState::Playing);
@@ -503,90 +1608,1350 @@ impl AudioProcessor {
             if !read_disk_stream.is_ready().unwrap_or(false) {
                 stream_state = AudioStreamState::Buffering;
                 cache_missed_this_cycle = true;
+
+                if !self.had_cache_miss_last_cycle {
+                    self.buffer_counters.add_cache_miss();
+                }
+
+                let sample_rate = read_disk_stream.info().sample_rate.unwrap_or(48000);
+                let frames = (data.len() / self.output_channels) as u64;
+                self.buffer_counters
+                    .add_buffering_time(frames * 1_000_000_000 / sample_rate as u64);
             }
 
             let num_frames = read_disk_stream.info().num_frames;
-            let num_channels = usize::from(read_disk_stream.info().num_channels);
+            let track_sample_rate = read_disk_stream.info().sample_rate;
+
+            let vol = (self.info.audio_volume * self.item_gain).min(self.max_gain);
+            let next_vol = self.next_track.as_ref().map(|next_track| {
+                (self.info.audio_volume * next_track.item_gain).min(self.max_gain)
+            });
+
+            // shortened if the next track is shorter than the crossfade
+            let crossfade_frames = self.next_track.as_ref().map_or(0, |next_track| {
+                self.crossfade_frames
+                    .min(next_track.stream.info().num_frames)
+            });
+
+            // tracks are always played as stereo, independent of the number of channels of the
+            // track and the output device
+            while data.len() >= self.output_channels {
+                let mut read_frames = data.len() / self.output_channels;
+                let mut playhead = read_disk_stream.playhead();
 
-            let vol = self.info.audio_volume;
+                if crossfade_frames > 0 && self.crossfade.is_none() {
+                    let crossfade_start = num_frames.saturating_sub(crossfade_frames);
 
-            while data.len() >= num_channels {
-                let read_frames = data.len() / 2;
-                let mut playhead = read_disk_stream.playhead();
+                    if playhead >= crossfade_start {
+                        // shortened if the crossfade is longer than the rest of the track
+                        self.crossfade = Crossfade::new(num_frames.saturating_sub(playhead));
+                    } else {
+                        read_frames = read_frames.min(crossfade_start - playhead);
+                    }
+                }
 
                 let read_data = read_disk_stream.read(read_frames)?;
                 playhead += read_data.num_frames();
 
-                if playhead >= num_frames {
-                    let to_end_of_loop = read_data.num_frames() - (playhead - num_frames);
+                let ended = playhead >= num_frames;
+                let frames = if ended {
+                    read_data.num_frames() - (playhead - num_frames)
+                } else {
+                    read_data.num_frames()
+                };
 
-                    if read_data.num_channels() == 1 {
-                        let ch = read_data.read_channel(0);
+                write_stereo(&read_data, data, frames, vol, self.output_channels);
+
+                if let (Some(crossfade), Some(next_track), Some(next_vol)) =
+                    (self.crossfade.as_mut(), self.next_track.as_mut(), next_vol)
+                {
+                    let (from, to) = crossfade.advance(frames);
+                    let overlap = &mut data[..frames * self.output_channels];
+
+                    apply_ramp(overlap, self.output_channels, 1.0 - from, 1.0 - to);
+                    mix_from_stream(
+                        &mut next_track.stream,
+                        overlap,
+                        next_vol,
+                        self.output_channels,
+                        from,
+                        to,
+                    )?;
+                }
 
-                        for i in 0..to_end_of_loop {
-                            data[i * 2] = ch[i] * vol;
-                            data[(i * 2) + 1] = ch[i] * vol;
+                if ended {
+                    let rest = &mut data[frames * self.output_channels..];
+
+                    match (self.crossfade.is_some(), self.next_track.as_mut(), next_vol) {
+                        // the rest of the buffer already plays the next track
+                        (true, Some(next_track), Some(next_vol)) => {
+                            fill_from_stream(
+                                &mut next_track.stream,
+                                rest,
+                                next_vol,
+                                self.output_channels,
+                            )?;
+                            crossfaded = true;
                         }
-                    } else if read_data.num_channels() == 2 {
-                        let ch1 = read_data.read_channel(0);
-                        let ch2 = read_data.read_channel(1);
-
-                        for i in 0..to_end_of_loop {
-                            data[i * 2] = ch1[i] * vol;
-                            data[(i * 2) + 1] = ch2[i] * vol;
+                        _ => {
+                            silence(rest);
+                            stream_state = AudioStreamState::Finished;
                         }
                     }
 
-                    data = &mut data[to_end_of_loop * 2..];
-
-                    stream_state = AudioStreamState::Finished;
                     break;
                 } else {
-                    if read_data.num_channels() == 1 {
-                        let ch = read_data.read_channel(0);
-
-                        for i in 0..read_data.num_frames() {
-                            data[i * 2] = ch[i] * vol;
-                            data[(i * 2) + 1] = ch[i] * vol;
-                        }
-                    } else if read_data.num_channels() == 2 {
-                        let ch1 = read_data.read_channel(0);
-                        let ch2 = read_data.read_channel(1);
-
-                        for i in 0..read_data.num_frames() {
-                            data[i * 2] = ch1[i] * vol;
-                            data[(i * 2) + 1] = ch2[i] * vol;
-                        }
-                    }
-
-                    data = &mut data[read_data.num_frames() * 2..];
+                    data = &mut data[frames * self.output_channels..];
 
                     stream_state = AudioStreamState::Playing;
                 }
 
                 self.info.audio_progress = playhead as f64 / num_frames as f64;
+                self.info
+                    .set_track_secs(playhead, num_frames, track_sample_rate);
             }
         } else {
             silence(data);
         }
 
-        // When the cache misses, the buffer is filled with silence. So the next
-        // buffer after the cache miss is starting from silence. To avoid an audible
-        // pop, apply a ramping gain from 0 up to unity.
-        if self.had_cache_miss_last_cycle {
-            let buffer_size = data.len() as f32;
-            for (i, sample) in data.iter_mut().enumerate() {
-                *sample *= i as f32 / buffer_size;
+        if crossfaded {
+            self.continue_with_next_track();
+        }
+
+        if let Some(equalizer) = self.equalizer.as_mut() {
+            equalizer.process(output, self.output_channels);
+        }
+
+        // When the cache misses or playback resumes after a pause, the buffer is
+        // filled with silence. So the next buffer is starting from silence. To avoid
+        // an audible pop, apply a ramping gain from 0 up to unity.
+        if self.had_cache_miss_last_cycle || self.resumed_this_cycle {
+            apply_ramp(output, self.output_channels, 0.0, 1.0);
+        }
+
+        // the fade in belongs to the track, which isn't read while an announcement is playing
+        if self.read_disk_stream.is_some() && !announcing {
+            if let Some(fade_in) = self.fade_in.as_mut() {
+                if !fade_in.apply(output, self.output_channels) {
+                    self.fade_in = None;
+                }
             }
         }
 
         self.had_cache_miss_last_cycle = cache_missed_this_cycle;
+        self.resumed_this_cycle = false;
         Ok(stream_state)
     }
 }
 
+impl Crossfade {
+    /// `None` if there is nothing to fade
+    fn new(frames: usize) -> Option<Self> {
+        (frames > 0).then_some(Self { frames, elapsed: 0 })
+    }
+
+    /// advances the crossfade by `frames` frames
+    ///
+    /// Returns the gain of the next track at the first frame and right after the last frame, the
+    /// gain of the current track is the rest to full gain.
+    fn advance(&mut self, frames: usize) -> (f32, f32) {
+        let from = self.elapsed as f32 / self.frames as f32;
+        self.elapsed = (self.elapsed + frames).min(self.frames);
+
+        (from, self.elapsed as f32 / self.frames as f32)
+    }
+}
+
+impl FadeIn {
+    /// `None` if there is nothing to fade
+    fn new(frames: usize) -> Option<Self> {
+        (frames > 0).then_some(Self { frames, elapsed: 0 })
+    }
+
+    /// fades the next frames of `data`, which is interleaved with `channels` channels
+    ///
+    /// Returns false once the fade has ended.
+    fn apply(&mut self, data: &mut [f32], channels: usize) -> bool {
+        let remaining = self.frames - self.elapsed;
+        let frames = (data.len() / channels).min(remaining);
+
+        apply_ramp(
+            &mut data[..frames * channels],
+            channels,
+            self.elapsed as f32 / self.frames as f32,
+            (self.elapsed + frames) as f32 / self.frames as f32,
+        );
+        self.elapsed += frames;
+
+        self.elapsed < self.frames
+    }
+}
+
+/// multiplies `data`, which is interleaved with `channels` channels, with a gain that rises
+/// linearly from `from` at the first frame towards `to`, which is reached right after the last
+/// frame
+fn apply_ramp(data: &mut [f32], channels: usize, from: f32, to: f32) {
+    let num_frames = (data.len() / channels) as f32;
+
+    for (i, frame) in data.chunks_exact_mut(channels).enumerate() {
+        let gain = from + (to - from) * i as f32 / num_frames;
+
+        for sample in frame {
+            *sample *= gain;
+        }
+    }
+}
+
+/// writes the first `frames` frames of `read_data` to `data`, which is interleaved with
+/// `output_channels` channels, see [`write_stereo_frame`]
+///
+/// Mono tracks are played on both channels. Tracks with more than two channels, e.g. 5.1 opus or
+/// vorbis, only play their first two channels which symphonia always orders as front left and
+/// front right.
+fn write_stereo(
+    read_data: &ReadData<f32>,
+    data: &mut [f32],
+    frames: usize,
+    vol: f32,
+    output_channels: usize,
+) {
+    let left = read_data.read_channel(0);
+    let right = if read_data.num_channels() == 1 {
+        left
+    } else {
+        read_data.read_channel(1)
+    };
+
+    for (i, frame) in data
+        .chunks_exact_mut(output_channels)
+        .take(frames)
+        .enumerate()
+    {
+        write_stereo_frame(frame, left[i] * vol, right[i] * vol);
+    }
+}
+
+/// writes a stereo sample to a frame of the output device
+///
+/// Mono devices play the mix of both channels, devices with more than two channels play the
+/// sample on their first two channels which cpal orders as front left and front right.
+pub(super) fn write_stereo_frame(frame: &mut [f32], left: f32, right: f32) {
+    match frame {
+        [] => {}
+        [mono] => *mono = (left + right) * 0.5,
+        [l, r, rest @ ..] => {
+            *l = left;
+            *r = right;
+            silence(rest);
+        }
+    }
+}
+
+/// adds a stereo sample to a frame of the output device, see [`write_stereo_frame`]
+fn mix_stereo_frame(frame: &mut [f32], left: f32, right: f32) {
+    match frame {
+        [] => {}
+        [mono] => *mono += (left + right) * 0.5,
+        [l, r, ..] => {
+            *l += left;
+            *r += right;
+        }
+    }
+}
+
+/// adds the next frames of `stream` to `data`, which is interleaved with `output_channels`
+/// channels, with a gain that rises linearly from `from` towards `to` like [`apply_ramp`]
+fn mix_from_stream(
+    stream: &mut ReadDiskStream<SymphoniaDecoder>,
+    data: &mut [f32],
+    vol: f32,
+    output_channels: usize,
+    from: f32,
+    to: f32,
+) -> Result<(), ReadError<symphonia_core::errors::Error>> {
+    let num_frames = data.len() / output_channels;
+    let mut mixed = 0;
+
+    while mixed < num_frames {
+        let read_data = stream.read(num_frames - mixed)?;
+        if read_data.num_frames() == 0 {
+            break;
+        }
+
+        let left = read_data.read_channel(0);
+        let right = if read_data.num_channels() == 1 {
+            left
+        } else {
+            read_data.read_channel(1)
+        };
+
+        for (i, frame) in data[mixed * output_channels..]
+            .chunks_exact_mut(output_channels)
+            .take(read_data.num_frames())
+            .enumerate()
+        {
+            let gain = vol * (from + (to - from) * (mixed + i) as f32 / num_frames as f32);
+            mix_stereo_frame(frame, left[i] * gain, right[i] * gain);
+        }
+
+        mixed += read_data.num_frames();
+    }
+
+    Ok(())
+}
+
+/// fills `data`, which is interleaved with `output_channels` channels, from `stream`, frames after
+/// the end of the stream are silent
+///
+/// Returns whether the stream has ended.
+fn fill_from_stream(
+    stream: &mut ReadDiskStream<SymphoniaDecoder>,
+    mut data: &mut [f32],
+    vol: f32,
+    output_channels: usize,
+) -> Result<bool, ReadError<symphonia_core::errors::Error>> {
+    let num_frames = stream.info().num_frames;
+
+    while data.len() >= output_channels {
+        let read_frames = data.len() / output_channels;
+        let playhead = stream.playhead();
+
+        let read_data = stream.read(read_frames)?;
+        let playhead = playhead + read_data.num_frames();
+
+        if playhead >= num_frames {
+            let to_end = read_data.num_frames() - (playhead - num_frames);
+            write_stereo(&read_data, data, to_end, vol, output_channels);
+            silence(&mut data[to_end * output_channels..]);
+
+            return Ok(true);
+        }
+
+        write_stereo(
+            &read_data,
+            data,
+            read_data.num_frames(),
+            vol,
+            output_channels,
+        );
+        data = &mut data[read_data.num_frames() * output_channels..];
+    }
+
+    Ok(false)
+}
+
+/// requests the start of an announcement and waits until it is cached, like a track that is
+/// started
+///
+/// A fresh stream doesn't request any blocks until it is read from or seeked, the start of the
+/// announcement would be skipped otherwise.
+fn ready_announcement(
+    mut stream: ReadDiskStream<SymphoniaDecoder>,
+) -> anyhow::Result<ReadDiskStream<SymphoniaDecoder>> {
+    stream.seek(0, creek::SeekMode::Auto)?;
+    stream.block_until_ready()?;
+
+    Ok(stream)
+}
+
 fn silence(data: &mut [f32]) {
     for sample in data.iter_mut() {
         *sample = 0.0;
     }
 }
+
+/// adds an item to the end of the queue, returns true if it is the first item which has to start
+/// playing
+///
+/// Items are only added once they are ready to be played, e.g. a failed download never occupies a
+/// queue slot. The head is reset for the first item because it can still point past the end of a
+/// queue whose items were all removed.
+fn push_item<T>(items: &mut Vec<T>, head: &mut usize, item: T) -> bool {
+    let is_first = items.is_empty();
+    if is_first {
+        *head = 0;
+    }
+
+    items.push(item);
+    is_first
+}
+
+/// inserts `item` at `index`, clamped to the end of `items`, and returns the index `head` has to be
+/// changed to so it still points at the same item
+fn insert_item<T>(items: &mut Vec<T>, head: usize, index: usize, item: T) -> usize {
+    let index = index.min(items.len());
+    items.insert(index, item);
+
+    if index <= head {
+        head + 1
+    } else {
+        head
+    }
+}
+
+/// index of the track that plays once the track at `head` of a queue with `len` items has ended,
+/// `None` if playback stops
+pub fn next_head_after_finish(repeat_mode: RepeatMode, head: usize, len: usize) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+
+    match repeat_mode {
+        RepeatMode::Off => (head + 1 < len).then_some(head + 1),
+        RepeatMode::All => Some((head + 1) % len),
+        RepeatMode::One => Some(head.min(len - 1)),
+    }
+}
+
+/// moves the item at `head` to the front and shuffles all other items behind it
+///
+/// Everything is shuffled if `head` is out of bounds.
+fn shuffle_after_head<T>(items: &mut [T], head: usize, rng: &mut impl Rng) {
+    if head >= items.len() {
+        items.shuffle(rng);
+        return;
+    }
+
+    items.swap(0, head);
+    items[1..].shuffle(rng);
+}
+
+/// head of a restored queue with `len` items, an index past the end of the queue continues at the
+/// last item since the queue might have been edited after the state was stored
+fn clamp_queue_head(head: usize, len: usize) -> usize {
+    head.min(len.saturating_sub(1))
+}
+
+/// moves the item at `old` to `new` and returns the index `head` has to be changed to so it still
+/// points at the same item
+///
+/// Nothing is moved if either index is out of bounds.
+fn move_item<T>(items: &mut [T], head: usize, old: usize, new: usize) -> Option<usize> {
+    if old >= items.len() || new >= items.len() {
+        return None;
+    }
+
+    if old < new {
+        items[old..=new].rotate_left(1);
+    } else {
+        items[new..=old].rotate_right(1);
+    }
+
+    let head = if head == old {
+        new
+    } else if old < head && head <= new {
+        head - 1
+    } else if new <= head && head < old {
+        head + 1
+    } else {
+        head
+    };
+
+    Some(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+    use crate::tests_utils::{test_dir, write_test_wav};
+    use actix::{Actor, Context, Handler, Message, MessageResult};
+    use pretty_assertions::assert_eq;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    fn test_processor(
+        name: &str,
+        num_frames: usize,
+    ) -> (AudioProcessor, Producer<AudioProcessorMessage>) {
+        test_processor_with_channels(name, 2, num_frames)
+    }
+
+    fn test_processor_with_channels(
+        name: &str,
+        num_channels: u16,
+        num_frames: usize,
+    ) -> (AudioProcessor, Producer<AudioProcessorMessage>) {
+        let path = test_dir(name).join("test.wav");
+        write_test_wav(&path, SAMPLE_RATE, num_channels, num_frames, |_| {
+            i16::MAX / 2
+        });
+
+        // a fresh stream doesn't request any blocks until it is read from or seeked
+        let mut stream = path.load_audio_data().unwrap();
+        stream.seek(0, creek::SeekMode::Auto).unwrap();
+        stream.block_until_ready().unwrap();
+
+        let (producer, consumer) = RingBuffer::<AudioProcessorMessage>::new(16);
+        (
+            AudioProcessor::new(
+                consumer,
+                Some(stream),
+                None,
+                1.0,
+                1.0,
+                1.0,
+                Default::default(),
+            ),
+            producer,
+        )
+    }
+
+    #[test]
+    fn test_first_pushed_item_resets_head() {
+        // head of a queue whose items were all removed
+        let mut items: Vec<char> = vec![];
+        let mut head = 2;
+
+        // the first item plays immediately
+        assert!(push_item(&mut items, &mut head, 'a'));
+        assert_eq!(head, 0);
+
+        // add -> download succeeds, the item is queued behind the playing one
+        assert!(!push_item(&mut items, &mut head, 'b'));
+        assert_eq!(items, vec!['a', 'b']);
+        assert_eq!(items[head], 'a');
+    }
+
+    #[test]
+    fn test_repeat_mode_after_finish() {
+        // the finished track is replayed instead of advancing
+        assert_eq!(next_head_after_finish(RepeatMode::One, 1, 3), Some(1));
+        assert_eq!(next_head_after_finish(RepeatMode::All, 1, 3), Some(2));
+        assert_eq!(next_head_after_finish(RepeatMode::Off, 1, 3), Some(2));
+
+        // end of the queue
+        assert_eq!(next_head_after_finish(RepeatMode::One, 2, 3), Some(2));
+        assert_eq!(next_head_after_finish(RepeatMode::All, 2, 3), Some(0));
+        assert_eq!(next_head_after_finish(RepeatMode::Off, 2, 3), None);
+
+        for mode in [RepeatMode::Off, RepeatMode::All, RepeatMode::One] {
+            assert_eq!(next_head_after_finish(mode, 0, 0), None);
+        }
+    }
+
+    #[test]
+    fn test_restore_out_of_range_queue_head() {
+        assert_eq!(clamp_queue_head(2, 5), 2);
+        assert_eq!(clamp_queue_head(4, 5), 4);
+
+        // the saved index points past the end of a queue that was shortened out-of-band
+        assert_eq!(clamp_queue_head(7, 5), 4);
+        assert_eq!(clamp_queue_head(usize::MAX, 1), 0);
+
+        assert_eq!(clamp_queue_head(0, 0), 0);
+        assert_eq!(clamp_queue_head(3, 0), 0);
+    }
+
+    #[test]
+    fn test_move_head_item() {
+        let mut items = vec!['a', 'b', 'c', 'd'];
+
+        // forward
+        let head = move_item(&mut items, 1, 1, 3).unwrap();
+        assert_eq!(items, vec!['a', 'c', 'd', 'b']);
+        assert_eq!(head, 3);
+
+        // backward
+        let head = move_item(&mut items, head, 3, 0).unwrap();
+        assert_eq!(items, vec!['b', 'a', 'c', 'd']);
+        assert_eq!(head, 0);
+    }
+
+    #[test]
+    fn test_move_item_across_head() {
+        let mut items = vec!['a', 'b', 'c', 'd'];
+
+        let head = move_item(&mut items, 2, 0, 3).unwrap();
+        assert_eq!(items, vec!['b', 'c', 'd', 'a']);
+        assert_eq!(items[head], 'c');
+
+        let head = move_item(&mut items, head, 3, 0).unwrap();
+        assert_eq!(items, vec!['a', 'b', 'c', 'd']);
+        assert_eq!(items[head], 'c');
+
+        let head = move_item(&mut items, head, 3, 2).unwrap();
+        assert_eq!(items, vec!['a', 'b', 'd', 'c']);
+        assert_eq!(items[head], 'c');
+    }
+
+    #[test]
+    fn test_move_head_item_by_one_and_to_the_ends() {
+        let mut items = vec!['a', 'b', 'c', 'd'];
+
+        // up and down by one
+        let head = move_item(&mut items, 1, 1, 2).unwrap();
+        assert_eq!(items, vec!['a', 'c', 'b', 'd']);
+        assert_eq!(head, 2);
+
+        let head = move_item(&mut items, head, 2, 1).unwrap();
+        assert_eq!(items, vec!['a', 'b', 'c', 'd']);
+        assert_eq!(head, 1);
+
+        // to the ends
+        let head = move_item(&mut items, head, 1, 3).unwrap();
+        assert_eq!(items, vec!['a', 'c', 'd', 'b']);
+        assert_eq!(head, 3);
+
+        let head = move_item(&mut items, head, 3, 0).unwrap();
+        assert_eq!(items, vec!['b', 'a', 'c', 'd']);
+        assert_eq!(head, 0);
+
+        // onto itself
+        let head = move_item(&mut items, head, 0, 0).unwrap();
+        assert_eq!(items, vec!['b', 'a', 'c', 'd']);
+        assert_eq!(head, 0);
+    }
+
+    #[test]
+    fn test_move_item_follows_head_for_every_move() {
+        for len in 1..=5 {
+            for head in 0..len {
+                for old in 0..len {
+                    for new in 0..len {
+                        let mut items: Vec<usize> = (0..len).collect();
+
+                        let new_head = move_item(&mut items, head, old, new).unwrap();
+
+                        assert_eq!(items[new], old, "LEN: {len}, OLD: {old}, NEW: {new}");
+                        assert_eq!(
+                            items[new_head], head,
+                            "LEN: {len}, HEAD: {head}, OLD: {old}, NEW: {new}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_insert_item_before_head() {
+        let mut items = vec!["a", "b", "c"];
+        let head = insert_item(&mut items, 2, 0, "new");
+
+        assert_eq!(items, vec!["new", "a", "b", "c"]);
+        assert_eq!(items[head], "c");
+    }
+
+    #[test]
+    fn test_insert_item_at_head() {
+        let mut items = vec!["a", "b", "c"];
+        let head = insert_item(&mut items, 1, 1, "new");
+
+        assert_eq!(items, vec!["a", "new", "b", "c"]);
+        assert_eq!(items[head], "b");
+
+        // inserting after the head plays the item next
+        let head = insert_item(&mut items, head, head + 1, "next");
+        assert_eq!(items, vec!["a", "new", "b", "next", "c"]);
+        assert_eq!(items[head], "b");
+    }
+
+    #[test]
+    fn test_insert_item_past_end() {
+        let mut items = vec!["a", "b", "c"];
+        let head = insert_item(&mut items, 1, 10, "new");
+
+        assert_eq!(items, vec!["a", "b", "c", "new"]);
+        assert_eq!(items[head], "b");
+    }
+
+    #[test]
+    fn test_shuffle_keeps_current_item_first() {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for head in 0..5 {
+            let mut items: Vec<usize> = (0..5).collect();
+            shuffle_after_head(&mut items, head, &mut rng);
+
+            assert_eq!(items[0], head);
+
+            let mut sorted = items.clone();
+            sorted.sort();
+            assert_eq!(sorted, vec![0, 1, 2, 3, 4]);
+        }
+
+        let mut empty: Vec<usize> = vec![];
+        shuffle_after_head(&mut empty, 0, &mut rng);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_move_item_out_of_bounds() {
+        let mut items = vec!['a', 'b', 'c'];
+
+        assert_eq!(move_item(&mut items, 0, 0, 3), None);
+        assert_eq!(move_item(&mut items, 0, 2, usize::MAX), None);
+        assert_eq!(move_item(&mut items, 0, 3, 0), None);
+        assert_eq!(items, vec!['a', 'b', 'c']);
+
+        let mut empty: Vec<char> = vec![];
+        assert_eq!(move_item(&mut empty, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn test_track_info_secs_conversion() {
+        let info = TrackInfo {
+            num_frames: 48000 * 10,
+            sample_rate: 48000,
+        };
+
+        assert_eq!(info.duration_secs(), 10.0);
+        assert_eq!(info.secs_to_progress(2.5), 0.25);
+        assert_eq!(info.secs_to_progress(20.0), 1.0);
+        assert_eq!(info.secs_to_progress(-1.0), 0.0);
+        assert_eq!(info.progress_to_secs(0.5), 5.0);
+
+        assert_eq!(info.secs_to_frame(2.5), 120_000);
+        assert_eq!(info.secs_to_frame(20.0), info.num_frames);
+        assert_eq!(info.secs_to_frame(-1.0), 0);
+    }
+
+    #[test]
+    fn test_item_gain_is_limited_by_max_gain() {
+        let (mut processor, mut producer) = test_processor("item-gain", SAMPLE_RATE as usize);
+        let sample = (i16::MAX / 2) as f32 / i16::MAX as f32;
+        let mut data = [0.0; 512];
+
+        producer
+            .push(AudioProcessorMessage::SetVolume(0.5))
+            .unwrap();
+        producer
+            .push(AudioProcessorMessage::SetItemGain(db_to_gain(6.0)))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        let expected = sample * 0.5 * db_to_gain(6.0);
+        assert!(data.iter().all(|v| (v - expected).abs() < 1e-3));
+
+        producer
+            .push(AudioProcessorMessage::SetMaxGain(0.25))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        let expected = sample * 0.25;
+        assert!(data.iter().all(|v| (v - expected).abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_seek_progress_delta() {
+        let num_frames = SAMPLE_RATE as usize;
+        let (mut processor, mut producer) = test_processor("seek-progress-delta", num_frames);
+        let mut data = [0.0; 512];
+
+        producer
+            .push(AudioProcessorMessage::SetState(PlaybackState::Paused))
+            .unwrap();
+        producer
+            .push(AudioProcessorMessage::SetProgress(0.5))
+            .unwrap();
+        producer
+            .push(AudioProcessorMessage::SeekProgressDelta(0.25))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        assert_eq!(processor.info.audio_progress, 0.75);
+
+        producer
+            .push(AudioProcessorMessage::SeekProgressDelta(0.5))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        assert_eq!(processor.info.audio_progress, 1.0);
+
+        producer
+            .push(AudioProcessorMessage::SeekProgressDelta(-2.0))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        assert_eq!(processor.info.audio_progress, 0.0);
+    }
+
+    #[test]
+    fn test_seek_frames() {
+        let num_frames = SAMPLE_RATE as usize;
+        let (mut processor, mut producer) = test_processor("seek-frames", num_frames);
+        let mut data = [0.0; 512];
+
+        producer
+            .push(AudioProcessorMessage::SetState(PlaybackState::Paused))
+            .unwrap();
+        producer
+            .push(AudioProcessorMessage::SeekFrames(num_frames / 4))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        assert_eq!(
+            processor.read_disk_stream.as_ref().unwrap().playhead(),
+            num_frames / 4
+        );
+        assert_eq!(processor.info.audio_progress, 0.25);
+        assert_eq!(processor.info.elapsed_secs, 0.25);
+        assert_eq!(processor.info.duration_secs, Some(1.0));
+
+        producer
+            .push(AudioProcessorMessage::SeekFrames(num_frames * 2))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        assert_eq!(processor.info.audio_progress, 1.0);
+        assert_eq!(processor.info.elapsed_secs, 1.0);
+    }
+
+    #[test]
+    fn test_track_secs_use_track_sample_rate() {
+        // the track has a different sample rate than the output device
+        let sample_rate = SAMPLE_RATE / 2;
+        let path = test_dir("track-secs-sample-rate").join("test.wav");
+        write_test_wav(&path, sample_rate, 2, sample_rate as usize, |_| 0);
+
+        let mut stream = path.load_audio_data().unwrap();
+        stream.seek(0, creek::SeekMode::Auto).unwrap();
+        stream.block_until_ready().unwrap();
+
+        let (mut producer, consumer) = RingBuffer::<AudioProcessorMessage>::new(16);
+        let mut processor = AudioProcessor::new(
+            consumer,
+            Some(stream),
+            None,
+            1.0,
+            1.0,
+            1.0,
+            Default::default(),
+        );
+        processor.report_track_secs();
+        assert_eq!(processor.info.duration_secs, Some(1.0));
+
+        let track_info = TrackInfo {
+            num_frames: sample_rate as usize,
+            sample_rate,
+        };
+        producer
+            .push(AudioProcessorMessage::SetState(PlaybackState::Paused))
+            .unwrap();
+        producer
+            .push(AudioProcessorMessage::SeekFrames(
+                track_info.secs_to_frame(0.5),
+            ))
+            .unwrap();
+        processor.try_process(&mut [0.0; 512]).unwrap();
+
+        assert_eq!(processor.info.elapsed_secs, 0.5);
+        assert_eq!(
+            processor.info.duration_secs,
+            Some(track_info.duration_secs())
+        );
+    }
+
+    #[test]
+    fn test_test_tone_keeps_track_position() {
+        let (mut processor, mut producer) = test_processor("test-tone", SAMPLE_RATE as usize);
+        let mut data = [0.0; 512];
+
+        processor.try_process(&mut data).unwrap();
+        let progress_before_tone = processor.info.audio_progress;
+
+        producer
+            .push(AudioProcessorMessage::PlayTestTone(TestTone::new(
+                Channel::Right,
+                1,
+                SAMPLE_RATE,
+            )))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        assert_eq!(processor.info.audio_progress, progress_before_tone);
+        assert!(data.iter().step_by(2).all(|sample| *sample == 0.0));
+        assert!(data.iter().skip(1).step_by(2).any(|sample| *sample != 0.0));
+
+        processor.test_tone = None;
+        processor.try_process(&mut data).unwrap();
+
+        assert_eq!(processor.info.audio_progress, 2.0 * progress_before_tone);
+    }
+
+    #[test]
+    fn test_announcement_keeps_track_position() {
+        let (mut processor, mut producer) = test_processor("announcement", SAMPLE_RATE as usize);
+        let mut data = [0.0; 512];
+
+        processor.try_process(&mut data).unwrap();
+        let progress_before_announcement = processor.info.audio_progress;
+
+        // 300 frames, 256 frames are played per cycle
+        let path = test_dir("announcement-audio").join("announcement.wav");
+        write_test_wav(&path, SAMPLE_RATE, 1, 300, |_| i16::MAX / 4);
+        let mut announcement = path.load_audio_data().unwrap();
+        announcement.seek(0, creek::SeekMode::Auto).unwrap();
+        announcement.block_until_ready().unwrap();
+
+        // announcements are played even if the track is paused
+        producer
+            .push(AudioProcessorMessage::SetState(PlaybackState::Paused))
+            .unwrap();
+        producer
+            .push(AudioProcessorMessage::PlayAnnouncement(AnnouncementStream(
+                announcement,
+            )))
+            .unwrap();
+        let announcement_sample = (i16::MAX / 4) as f32 / i16::MAX as f32;
+
+        processor.try_process(&mut data).unwrap();
+        assert!(data
+            .iter()
+            .all(|sample| (sample - announcement_sample).abs() < 1e-3));
+        assert_eq!(processor.info.audio_progress, progress_before_announcement);
+
+        processor.try_process(&mut data).unwrap();
+        assert!(processor.announcement.is_none());
+        assert!((data[86] - announcement_sample).abs() < 1e-3);
+        assert_eq!(&data[88..], &[0.0; 424]);
+        assert_eq!(processor.info.audio_progress, progress_before_announcement);
+
+        producer
+            .push(AudioProcessorMessage::SetState(PlaybackState::Playing))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        assert_eq!(
+            processor.info.audio_progress,
+            2.0 * progress_before_announcement
+        );
+    }
+
+    #[test]
+    fn test_announcement_starts_buffered_and_fades_in() {
+        let (mut processor, mut producer) =
+            test_processor("announcement-fade-in", SAMPLE_RATE as usize);
+        let mut data = [0.0; 512];
+
+        let path = test_dir("announcement-fade-in-audio").join("announcement.wav");
+        write_test_wav(&path, SAMPLE_RATE, 1, 1000, |_| i16::MAX / 4);
+        let announcement = ready_announcement(path.load_audio_data().unwrap()).unwrap();
+        let announcement_sample = (i16::MAX / 4) as f32 / i16::MAX as f32;
+
+        // the output starts from silence, e.g. after the track missed the cache
+        processor.had_cache_miss_last_cycle = true;
+        producer
+            .push(AudioProcessorMessage::PlayAnnouncement(AnnouncementStream(
+                announcement,
+            )))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        // the start of the announcement is played right away and ramped up like a track
+        assert_eq!(processor.announcement.as_mut().unwrap().playhead(), 256);
+        assert_eq!(data[0], 0.0);
+        assert!((data[2] - announcement_sample / 256.0).abs() < 1e-3);
+        assert!((data[510] - announcement_sample * 255.0 / 256.0).abs() < 1e-3);
+
+        processor.try_process(&mut data).unwrap();
+        assert!(data
+            .iter()
+            .all(|sample| (sample - announcement_sample).abs() < 1e-3));
+    }
+
+    fn test_next_track(name: &str, num_frames: usize) -> NextTrack {
+        let path = test_dir(name).join("next.wav");
+        write_test_wav(&path, SAMPLE_RATE, 2, num_frames, |_| i16::MAX / 4);
+
+        let mut stream = path.load_audio_data().unwrap();
+        stream.seek(0, creek::SeekMode::Auto).unwrap();
+        stream.block_until_ready().unwrap();
+
+        NextTrack {
+            stream,
+            item_gain: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_crossfade_into_next_track() {
+        let sample = (i16::MAX / 2) as f32 / i16::MAX as f32;
+        let next_sample = (i16::MAX / 4) as f32 / i16::MAX as f32;
+
+        // 4 cycles of 256 frames, the last one overlaps with the next track
+        let (mut processor, mut producer) = test_processor("crossfade", 1024);
+        let mut data = [0.0; 512];
+
+        producer
+            .push(AudioProcessorMessage::SetCrossfade(256))
+            .unwrap();
+        producer
+            .push(AudioProcessorMessage::SetNextTrack(Some(test_next_track(
+                "crossfade-next",
+                1024,
+            ))))
+            .unwrap();
+
+        for _ in 0..3 {
+            processor.try_process(&mut data).unwrap();
+        }
+        assert!(data.iter().all(|v| (v - sample).abs() < 1e-3));
+        assert_eq!(processor.crossfade, None);
+
+        let state = processor.try_process(&mut data).unwrap();
+        assert!(matches!(state, AudioStreamState::Playing));
+        for frame in [0, 128, 255] {
+            let gain = frame as f32 / 256.0;
+            let expected = sample * (1.0 - gain) + next_sample * gain;
+
+            assert!((data[frame * 2] - expected).abs() < 1e-3, "FRAME: {frame}");
+        }
+
+        // the next track keeps playing where the crossfade left off
+        assert!(processor.next_track.is_none());
+        assert_eq!(processor.crossfade, None);
+        assert_eq!(processor.info.audio_progress, 0.25);
+
+        processor.try_process(&mut data).unwrap();
+        assert!(data.iter().all(|v| (v - next_sample).abs() < 1e-3));
+        assert_eq!(processor.info.audio_progress, 0.5);
+    }
+
+    #[test]
+    fn test_crossfade_is_clamped_and_paused() {
+        let (mut processor, mut producer) = test_processor("crossfade-clamped", 1024);
+        let mut data = [0.0; 512];
+
+        producer
+            .push(AudioProcessorMessage::SetCrossfade(
+                SAMPLE_RATE as usize * 12,
+            ))
+            .unwrap();
+        producer
+            .push(AudioProcessorMessage::SetNextTrack(Some(test_next_track(
+                "crossfade-clamped-next",
+                2048,
+            ))))
+            .unwrap();
+
+        // longer than the track, the crossfade covers the whole track
+        processor.try_process(&mut data).unwrap();
+        assert_eq!(
+            processor.crossfade,
+            Some(Crossfade {
+                frames: 1024,
+                elapsed: 256
+            })
+        );
+
+        // both tracks are frozen while paused
+        let next_playhead = processor.next_track.as_ref().unwrap().stream.playhead();
+        producer
+            .push(AudioProcessorMessage::SetState(PlaybackState::Paused))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        assert_eq!(&data, &[0.0; 512]);
+        assert_eq!(processor.info.audio_progress, 0.25);
+        assert_eq!(
+            processor.crossfade,
+            Some(Crossfade {
+                frames: 1024,
+                elapsed: 256
+            })
+        );
+        assert_eq!(
+            processor.next_track.as_ref().unwrap().stream.playhead(),
+            next_playhead
+        );
+    }
+
+    #[test]
+    fn test_channel_layouts_fill_stereo_output() {
+        let sample = (i16::MAX / 2) as f32 / i16::MAX as f32;
+
+        // mono, stereo and 5.1
+        for num_channels in [1, 2, 6] {
+            let (mut processor, _producer) = test_processor_with_channels(
+                &format!("channel-layout-{num_channels}"),
+                num_channels,
+                SAMPLE_RATE as usize,
+            );
+            let mut data = [f32::NAN; 512];
+
+            processor.try_process(&mut data).unwrap();
+
+            assert!(
+                data.iter().all(|v| (v - sample).abs() < 1e-3),
+                "CHANNELS: {num_channels}"
+            );
+            assert_eq!(processor.info.audio_progress, 256.0 / SAMPLE_RATE as f64);
+        }
+    }
+
+    #[test]
+    fn test_track_duration() {
+        // 2.5 seconds
+        let num_frames = SAMPLE_RATE as usize * 5 / 2;
+        let (processor, _producer) = test_processor("track-duration", num_frames);
+
+        let track_info =
+            TrackInfo::from_stream(processor.read_disk_stream.as_ref().unwrap()).unwrap();
+
+        assert_eq!(track_info.num_frames, num_frames);
+        assert_eq!(track_info.sample_rate, SAMPLE_RATE);
+        assert_eq!(track_info.duration_secs(), 2.5);
+    }
+
+    #[test]
+    fn test_output_device_channels() {
+        let sample = (i16::MAX / 2) as f32 / i16::MAX as f32;
+
+        // mono device
+        let (mut processor, _producer) = test_processor("mono-output", SAMPLE_RATE as usize);
+        processor.output_channels = 1;
+        let mut data = [f32::NAN; 256];
+
+        processor.try_process(&mut data).unwrap();
+
+        assert!(data.iter().all(|v| (v - sample).abs() < 1e-3));
+        assert_eq!(processor.info.audio_progress, 256.0 / SAMPLE_RATE as f64);
+
+        // 5.1 device, the track is only played on the front left and right channels
+        let (mut processor, _producer) = test_processor("surround-output", SAMPLE_RATE as usize);
+        processor.output_channels = 6;
+        let mut data = [f32::NAN; 6 * 256];
+
+        processor.try_process(&mut data).unwrap();
+
+        for frame in data.chunks_exact(6) {
+            assert!(frame[..2].iter().all(|v| (v - sample).abs() < 1e-3));
+            assert_eq!(&frame[2..], &[0.0; 4]);
+        }
+        assert_eq!(processor.info.audio_progress, 256.0 / SAMPLE_RATE as f64);
+    }
+
+    #[test]
+    fn test_pause_seek_resume() {
+        let num_frames = SAMPLE_RATE as usize;
+        let (mut processor, mut producer) = test_processor("pause-seek-resume", num_frames);
+        let mut data = [0.0; 512];
+
+        processor.try_process(&mut data).unwrap();
+        let progress_before_pause = processor.info.audio_progress;
+        assert_eq!(progress_before_pause, 256.0 / num_frames as f64);
+
+        producer
+            .push(AudioProcessorMessage::SetState(PlaybackState::Paused))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        assert_eq!(processor.info.audio_progress, progress_before_pause);
+        assert!(data.iter().all(|sample| *sample == 0.0));
+
+        producer
+            .push(AudioProcessorMessage::SetProgress(0.5))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        // seeking while paused is reported immediately without playing anything
+        assert_eq!(processor.info.audio_progress, 0.5);
+        assert!(data.iter().all(|sample| *sample == 0.0));
+
+        processor
+            .read_disk_stream
+            .as_mut()
+            .unwrap()
+            .block_until_ready()
+            .unwrap();
+
+        producer
+            .push(AudioProcessorMessage::SetState(PlaybackState::Playing))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+
+        // playback continues from the seeked position and fades in from silence
+        assert_eq!(
+            processor.info.audio_progress,
+            (num_frames / 2 + 256) as f64 / num_frames as f64
+        );
+        assert_eq!(data[0], 0.0);
+        assert!(data[1] < data[511]);
+        assert!(data[511] > 0.0);
+    }
+
+    #[test]
+    fn test_fade_in_at_track_start() {
+        let num_frames = SAMPLE_RATE as usize;
+        let (mut processor, mut producer) = test_processor("fade-in", num_frames);
+        let sample = (i16::MAX / 2) as f32 / i16::MAX as f32;
+        // 256 frames per cycle, the fade spans two cycles
+        processor.fade_in = FadeIn::new(512);
+        let mut data = [0.0; 512];
+
+        processor.try_process(&mut data).unwrap();
+        assert_eq!(&data[..2], &[0.0, 0.0]);
+        assert!((data[510] - sample * 255.0 / 512.0).abs() < 1e-3);
+
+        processor.try_process(&mut data).unwrap();
+        assert!((data[0] - sample * 256.0 / 512.0).abs() < 1e-3);
+        assert!((data[510] - sample * 511.0 / 512.0).abs() < 1e-3);
+
+        processor.try_process(&mut data).unwrap();
+        assert_eq!(processor.fade_in, None);
+        assert!(data.iter().all(|v| (v - sample).abs() < 1e-3));
+
+        // seeking within the track doesn't fade in again
+        producer
+            .push(AudioProcessorMessage::SetProgress(0.0))
+            .unwrap();
+        processor.try_process(&mut data).unwrap();
+        processor
+            .read_disk_stream
+            .as_mut()
+            .unwrap()
+            .block_until_ready()
+            .unwrap();
+
+        // the cycle after a cache miss ramps up on its own
+        processor.try_process(&mut data).unwrap();
+        processor.try_process(&mut data).unwrap();
+        assert_eq!(processor.fade_in, None);
+        assert!(data.iter().all(|v| (v - sample).abs() < 1e-3));
+    }
+
+    /// collects the queue heads the player sends to its node
+    #[derive(Default)]
+    struct QueueHeadCollector {
+        heads: Vec<usize>,
+    }
+
+    #[derive(Message)]
+    #[rtype(result = "Vec<usize>")]
+    struct GetQueueHeads;
+
+    impl Actor for QueueHeadCollector {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<QueueHeadChanged> for QueueHeadCollector {
+        type Result = ();
+
+        fn handle(&mut self, msg: QueueHeadChanged, _ctx: &mut Self::Context) -> Self::Result {
+            self.heads.push(msg.0);
+        }
+    }
+
+    impl Handler<GetQueueHeads> for QueueHeadCollector {
+        type Result = MessageResult<GetQueueHeads>;
+
+        fn handle(&mut self, _msg: GetQueueHeads, _ctx: &mut Self::Context) -> Self::Result {
+            MessageResult(self.heads.clone())
+        }
+    }
+
+    fn test_player() -> AudioPlayer<PathBuf> {
+        AudioPlayer::without_output("test".into())
+    }
+
+    fn test_item(dir: &Path, name: &str) -> AudioPlayerQueueItem<PathBuf> {
+        let path = dir.join(format!("{name}.wav"));
+        write_test_wav(&path, SAMPLE_RATE, 2, SAMPLE_RATE as usize, |_| 0);
+
+        AudioPlayerQueueItem {
+            identifier: ItemUid(name.into()),
+            metadata: AudioMetadata {
+                name: Some(name.to_owned()).into(),
+                author: Option::<String>::None.into(),
+                duration: Some(1),
+                cover_art_url: Option::<String>::None.into(),
+                gain_db: None,
+            },
+            locator: path,
+            gain_db: 0.0,
+            fade_in_ms: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_queue_head_is_sent_once_per_change() {
+        let dir = test_dir("queue-head-changes");
+        let collector = QueueHeadCollector::default().start();
+
+        let mut player = test_player();
+        player.queue_head_addr = Some(collector.clone().recipient());
+
+        for name in ["a", "b", "c"] {
+            player.push_to_queue(test_item(&dir, name)).unwrap();
+        }
+
+        // the last item wraps around to the first one
+        player.play_selected(2, false).unwrap();
+        player.play_next().unwrap();
+        assert_eq!(player.queue_head(), 0);
+
+        // the removed last item is replaced by the first one
+        player.play_selected(2, false).unwrap();
+        player.remove_from_queue(2).unwrap();
+        assert_eq!(player.queue_head(), 0);
+
+        // the removed first item is replaced by the next one at the same index
+        player.remove_from_queue(0).unwrap();
+        assert_eq!(player.queue_head(), 0);
+        assert_eq!(&*player.queue()[0].identifier.0, "b");
+        assert!(!player.is_stopped());
+
+        // a stopped player isn't restarted by removing its current item
+        player.push_to_queue(test_item(&dir, "d")).unwrap();
+        player.stop();
+        player.remove_from_queue(0).unwrap();
+        assert_eq!(&*player.queue()[0].identifier.0, "d");
+        assert!(player.is_stopped());
+
+        let heads = collector.send(GetQueueHeads).await.unwrap();
+        assert_eq!(heads, vec![2, 0, 2, 0]);
+    }
+
+    #[test]
+    fn test_manual_skip_in_repeat_one() {
+        let dir = test_dir("play-next-repeat-one");
+
+        let mut player = test_player();
+        for name in ["a", "b"] {
+            player.push_to_queue(test_item(&dir, name)).unwrap();
+        }
+        player.set_repeat_mode(RepeatMode::One);
+
+        // only a finished track is repeated, skipping it moves on to the next one
+        player.play_next().unwrap();
+        assert_eq!(player.queue_head(), 1);
+        assert_eq!(&*player.queue()[player.queue_head()].identifier.0, "b");
+
+        player.play_after_finish().unwrap();
+        assert_eq!(player.queue_head(), 1);
+
+        player.play_prev().unwrap();
+        assert_eq!(player.queue_head(), 0);
+
+        player.play_next().unwrap();
+        assert_eq!(player.queue_head(), 1);
+        assert_eq!(player.repeat_mode(), RepeatMode::One);
+    }
+
+    #[test]
+    fn test_clear_queue() {
+        let dir = test_dir("clear-queue");
+
+        let mut player = test_player();
+        for name in ["a", "b", "c"] {
+            player.push_to_queue(test_item(&dir, name)).unwrap();
+        }
+        player.play_selected(1, false).unwrap();
+
+        player.clear_queue();
+        assert!(player.queue().is_empty());
+        assert_eq!(player.queue_head(), 0);
+        assert!(player.is_stopped());
+
+        // the next item starts playing right away
+        player.push_to_queue(test_item(&dir, "d")).unwrap();
+        assert_eq!(player.queue().len(), 1);
+        assert_eq!(player.queue_head(), 0);
+        assert!(!player.is_stopped());
+    }
+}
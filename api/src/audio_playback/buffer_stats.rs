@@ -0,0 +1,188 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use serde::Serialize;
+use ts_rs::TS;
+
+/// Buffering counters of the track that is currently playing
+///
+/// Shared between the player and the audio processor, only uses atomics so updating it is
+/// realtime-safe.
+#[derive(Debug, Default)]
+pub struct BufferCounters {
+    cache_misses: AtomicU32,
+    buffering_nanos: AtomicU64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct TrackBufferStats {
+    pub uid: Arc<str>,
+    pub cache_misses: u32,
+    pub buffering_secs: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct BufferStats {
+    pub tracks: Vec<TrackBufferStats>,
+    pub total_cache_misses: u32,
+    pub total_buffering_secs: f64,
+}
+
+/// Buffering statistics of all tracks played since the last reset
+#[derive(Debug, Default)]
+pub struct BufferStatsTracker {
+    finished_tracks: HashMap<Arc<str>, TrackBufferStats>,
+    current: Option<(Arc<str>, Arc<BufferCounters>)>,
+}
+
+impl BufferCounters {
+    /// counts a new buffering event, call this once when the stream starts buffering
+    pub fn add_cache_miss(&self) {
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_buffering_time(&self, nanos: u64) {
+        self.buffering_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.cache_misses.store(0, Ordering::Relaxed);
+        self.buffering_nanos.store(0, Ordering::Relaxed);
+    }
+
+    fn to_stats(&self, uid: Arc<str>) -> TrackBufferStats {
+        TrackBufferStats {
+            uid,
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            buffering_secs: self.buffering_nanos.load(Ordering::Relaxed) as f64 / 1e9,
+        }
+    }
+}
+
+impl BufferStatsTracker {
+    /// starts tracking a new track, the counters of the previous track are kept
+    pub fn start_track(&mut self, uid: Arc<str>) -> Arc<BufferCounters> {
+        self.finish_current_track();
+
+        let counters = Arc::new(BufferCounters::default());
+        self.current = Some((uid, Arc::clone(&counters)));
+
+        counters
+    }
+
+    pub fn finish_current_track(&mut self) {
+        if let Some((uid, counters)) = self.current.take() {
+            let stats = counters.to_stats(Arc::clone(&uid));
+            let entry =
+                self.finished_tracks
+                    .entry(uid)
+                    .or_insert_with_key(|uid| TrackBufferStats {
+                        uid: Arc::clone(uid),
+                        ..Default::default()
+                    });
+
+            entry.cache_misses += stats.cache_misses;
+            entry.buffering_secs += stats.buffering_secs;
+        }
+    }
+
+    /// clears all counters, the counters of the current track are cleared in place since the
+    /// processor keeps writing to them
+    pub fn reset(&mut self) {
+        self.finished_tracks.clear();
+
+        if let Some((_, counters)) = self.current.as_ref() {
+            counters.clear();
+        }
+    }
+
+    pub fn stats(&self) -> BufferStats {
+        let mut tracks = self.finished_tracks.clone();
+
+        if let Some((uid, counters)) = self.current.as_ref() {
+            let current = counters.to_stats(Arc::clone(uid));
+            let entry = tracks
+                .entry(Arc::clone(uid))
+                .or_insert_with_key(|uid| TrackBufferStats {
+                    uid: Arc::clone(uid),
+                    ..Default::default()
+                });
+
+            entry.cache_misses += current.cache_misses;
+            entry.buffering_secs += current.buffering_secs;
+        }
+
+        let mut tracks: Vec<TrackBufferStats> = tracks.into_values().collect();
+        tracks.sort_by(|a, b| a.uid.cmp(&b.uid));
+
+        BufferStats {
+            total_cache_misses: tracks.iter().map(|track| track.cache_misses).sum(),
+            total_buffering_secs: tracks.iter().map(|track| track.buffering_secs).sum(),
+            tracks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_buffer_stats_per_track() {
+        let mut tracker = BufferStatsTracker::default();
+
+        let counters = tracker.start_track("a".into());
+        counters.add_cache_miss();
+        counters.add_buffering_time(500_000_000);
+
+        let counters = tracker.start_track("b".into());
+        counters.add_cache_miss();
+
+        let counters = tracker.start_track("a".into());
+        counters.add_cache_miss();
+        counters.add_buffering_time(250_000_000);
+
+        let stats = tracker.stats();
+        assert_eq!(
+            stats.tracks,
+            vec![
+                TrackBufferStats {
+                    uid: "a".into(),
+                    cache_misses: 2,
+                    buffering_secs: 0.75,
+                },
+                TrackBufferStats {
+                    uid: "b".into(),
+                    cache_misses: 1,
+                    buffering_secs: 0.0,
+                },
+            ]
+        );
+        assert_eq!(stats.total_cache_misses, 3);
+
+        tracker.reset();
+        assert_eq!(tracker.stats().total_cache_misses, 0);
+
+        // the processor keeps counting for the current track after the reset
+        counters.add_cache_miss();
+        counters.add_buffering_time(100_000_000);
+        assert_eq!(
+            tracker.stats().tracks,
+            vec![TrackBufferStats {
+                uid: "a".into(),
+                cache_misses: 1,
+                buffering_secs: 0.1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use std::f32::consts::TAU;
+
+/// most bands an equalizer can have, keeps the equalizer on the stack so it can be sent to the
+/// audio processor without allocating
+pub const MAX_EQ_BANDS: usize = 10;
+pub const MAX_EQ_GAIN_DB: f32 = 12.0;
+
+const MIN_EQ_FREQUENCY: f32 = 31.25;
+const MAX_EQ_FREQUENCY: f32 = 16_000.0;
+
+/// Graphic equalizer with peaking filters at logarithmically spaced frequencies between
+/// `MIN_EQ_FREQUENCY` and `MAX_EQ_FREQUENCY`
+///
+/// Only uses preallocated state so it can run in the audio callback.
+#[derive(Debug, Clone)]
+pub struct Equalizer {
+    bands: [Biquad; MAX_EQ_BANDS],
+    num_bands: usize,
+}
+
+/// Peaking filter in transposed direct form II, keeps separate state for the left and right
+/// channel
+#[derive(Debug, Clone, Copy, Default)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    state: [[f32; 2]; 2],
+}
+
+/// limits the number of bands and the gain of every band, invalid gains are treated as flat
+pub fn clamp_eq_bands(bands_db: &[f32]) -> Vec<f32> {
+    bands_db
+        .iter()
+        .take(MAX_EQ_BANDS)
+        .map(|gain| {
+            if gain.is_finite() {
+                gain.clamp(-MAX_EQ_GAIN_DB, MAX_EQ_GAIN_DB)
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// center frequency of `band` if the equalizer has `num_bands` bands
+pub fn eq_band_frequency(band: usize, num_bands: usize) -> f32 {
+    if num_bands <= 1 {
+        return 1000.0;
+    }
+
+    let position = band as f32 / (num_bands - 1) as f32;
+    MIN_EQ_FREQUENCY * (MAX_EQ_FREQUENCY / MIN_EQ_FREQUENCY).powf(position)
+}
+
+impl Equalizer {
+    /// builds an equalizer from the gain of every band in dB, returns `None` if all bands are flat
+    /// so no filters have to run at all
+    pub fn new(bands_db: &[f32], sample_rate: u32) -> Option<Self> {
+        let bands_db = clamp_eq_bands(bands_db);
+        let num_bands = bands_db.len();
+
+        // neighbouring bands overlap at their half gain points
+        let q = if num_bands > 1 {
+            let octaves = (MAX_EQ_FREQUENCY / MIN_EQ_FREQUENCY).log2() / (num_bands - 1) as f32;
+            2f32.powf(octaves / 2.0) / (2f32.powf(octaves) - 1.0)
+        } else {
+            std::f32::consts::FRAC_1_SQRT_2
+        };
+
+        let mut equalizer = Self {
+            bands: [Biquad::default(); MAX_EQ_BANDS],
+            num_bands: 0,
+        };
+
+        for (band, gain_db) in bands_db.iter().enumerate() {
+            if *gain_db == 0.0 {
+                continue;
+            }
+
+            let frequency = eq_band_frequency(band, num_bands).min(sample_rate as f32 * 0.45);
+            equalizer.bands[equalizer.num_bands] =
+                Biquad::peaking(frequency, q, *gain_db, sample_rate);
+            equalizer.num_bands += 1;
+        }
+
+        (equalizer.num_bands > 0).then_some(equalizer)
+    }
+
+    /// filters `data`, which is interleaved with `channels` channels, in place
+    ///
+    /// Only the first two channels are filtered, tracks are never played on any other channels.
+    pub fn process(&mut self, data: &mut [f32], channels: usize) {
+        let bands = &mut self.bands[..self.num_bands];
+
+        for frame in data.chunks_exact_mut(channels) {
+            for (channel, sample) in frame.iter_mut().take(2).enumerate() {
+                for band in bands.iter_mut() {
+                    *sample = band.process(*sample, channel);
+                }
+            }
+        }
+    }
+}
+
+impl Biquad {
+    /// coefficients from the 'Audio EQ Cookbook' by Robert Bristow-Johnson
+    fn peaking(frequency: f32, q: f32, gain_db: f32, sample_rate: u32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = TAU * frequency / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos_w0 = w0.cos();
+
+        let a0 = 1.0 + alpha / a;
+
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: (-2.0 * cos_w0) / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: (-2.0 * cos_w0) / a0,
+            a2: (1.0 - alpha / a) / a0,
+            state: Default::default(),
+        }
+    }
+
+    fn process(&mut self, sample: f32, channel: usize) -> f32 {
+        let [z1, z2] = &mut self.state[channel];
+
+        let out = self.b0 * sample + *z1;
+        *z1 = self.b1 * sample - self.a1 * out + *z2;
+        *z2 = self.b2 * sample - self.a2 * out;
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    /// peak amplitude of a sine with amplitude 0.1 after the filters have settled
+    fn filtered_amplitude(equalizer: &mut Equalizer, frequency: f32) -> f32 {
+        let mut data: Vec<f32> = (0..SAMPLE_RATE as usize)
+            .flat_map(|i| {
+                let sample = (TAU * frequency * i as f32 / SAMPLE_RATE as f32).sin() * 0.1;
+                [sample, sample]
+            })
+            .collect();
+
+        equalizer.process(&mut data, 2);
+
+        data[data.len() / 2..]
+            .iter()
+            .fold(0.0, |max, sample| sample.abs().max(max))
+    }
+
+    #[test]
+    fn test_flat_equalizer_is_skipped() {
+        assert!(Equalizer::new(&[], SAMPLE_RATE).is_none());
+        assert!(Equalizer::new(&[0.0; 5], SAMPLE_RATE).is_none());
+        assert!(Equalizer::new(&[f32::NAN], SAMPLE_RATE).is_none());
+    }
+
+    #[test]
+    fn test_equalizer_band_gain() {
+        let mut bands = [0.0; 10];
+        bands[5] = 6.0;
+        let frequency = eq_band_frequency(5, bands.len());
+
+        let mut equalizer = Equalizer::new(&bands, SAMPLE_RATE).unwrap();
+        assert_eq!(equalizer.num_bands, 1);
+
+        // the band is boosted by its gain while frequencies far away from it are not affected
+        let boosted = filtered_amplitude(&mut equalizer, frequency);
+        assert!((boosted - 0.1 * db_to_gain(6.0)).abs() < 0.005, "{boosted}");
+
+        let mut equalizer = Equalizer::new(&bands, SAMPLE_RATE).unwrap();
+        let unaffected = filtered_amplitude(&mut equalizer, eq_band_frequency(0, bands.len()));
+        assert!((unaffected - 0.1).abs() < 0.005, "{unaffected}");
+    }
+
+    #[test]
+    fn test_clamp_eq_bands() {
+        assert_eq!(
+            clamp_eq_bands(&[-30.0, 3.0, 30.0, f32::INFINITY]),
+            vec![-MAX_EQ_GAIN_DB, 3.0, MAX_EQ_GAIN_DB, 0.0]
+        );
+        assert_eq!(clamp_eq_bands(&[1.0; 20]).len(), MAX_EQ_BANDS);
+    }
+
+    fn db_to_gain(gain_db: f32) -> f32 {
+        10f32.powf(gain_db / 20.0)
+    }
+}
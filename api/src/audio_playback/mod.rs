@@ -1,2 +1,6 @@
 pub mod audio_item;
 pub mod audio_player;
+pub mod buffer_stats;
+pub mod equalizer;
+pub mod smart_shuffle;
+pub mod test_tone;
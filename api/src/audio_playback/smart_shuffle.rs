@@ -0,0 +1,130 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+/// Shuffles `items` while avoiding tracks of the same author playing back to back
+///
+/// The item at `head` is moved to the front and keeps playing. The rest is shuffled uniformly,
+/// afterwards the next item is always the first one in the shuffled order whose author differs
+/// from the previous one. An author that would otherwise run out of other authors to be placed
+/// between is picked first. Items without an author never count as the same author.
+///
+/// The order only depends on `seed`, the items and their authors.
+pub fn smart_shuffle<T>(
+    items: &mut Vec<T>,
+    head: usize,
+    seed: u64,
+    author: impl Fn(&T) -> Option<&str>,
+) {
+    if head >= items.len() {
+        return;
+    }
+
+    let current = items.remove(head);
+    let mut remaining = std::mem::take(items);
+    remaining.shuffle(&mut StdRng::seed_from_u64(seed));
+
+    items.reserve(remaining.len() + 1);
+    items.push(current);
+
+    while !remaining.is_empty() {
+        let previous = items.last().and_then(&author);
+        let same_as_previous = |item: &T| previous.is_some() && author(item) == previous;
+
+        let index = most_frequent_author(&remaining, &author)
+            // placing any other item first would leave too few items to separate this author
+            .filter(|(_, count)| count * 2 > remaining.len())
+            .and_then(|(frequent, _)| {
+                remaining.iter().position(|item| {
+                    author(item) == Some(frequent.as_str()) && !same_as_previous(item)
+                })
+            })
+            .or_else(|| remaining.iter().position(|item| !same_as_previous(item)))
+            .unwrap_or(0);
+
+        items.push(remaining.remove(index));
+    }
+}
+
+fn most_frequent_author<T>(
+    items: &[T],
+    author: impl Fn(&T) -> Option<&str>,
+) -> Option<(String, usize)> {
+    let mut counts: Vec<(&str, usize)> = vec![];
+
+    for author in items.iter().filter_map(&author) {
+        match counts.iter_mut().find(|(name, _)| *name == author) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((author, 1)),
+        }
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(name, count)| (name.to_owned(), count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn author<'a>(item: &'a (usize, Option<&'static str>)) -> Option<&'a str> {
+        item.1
+    }
+
+    fn back_to_back(items: &[(usize, Option<&'static str>)]) -> usize {
+        items
+            .windows(2)
+            .filter(|pair| pair[0].1.is_some() && pair[0].1 == pair[1].1)
+            .count()
+    }
+
+    #[test]
+    fn test_smart_shuffle() {
+        let queue: Vec<_> = [
+            Some("a"),
+            Some("a"),
+            Some("a"),
+            Some("a"),
+            Some("b"),
+            Some("b"),
+            Some("c"),
+            None,
+            None,
+        ]
+        .into_iter()
+        .enumerate()
+        .collect();
+
+        for seed in 0..50 {
+            let mut items = queue.clone();
+            smart_shuffle(&mut items, 5, seed, author);
+
+            assert_eq!(items[0], queue[5], "SEED: {seed}");
+            assert_eq!(back_to_back(&items), 0, "SEED: {seed}");
+
+            let mut sorted = items.clone();
+            sorted.sort();
+            assert_eq!(sorted, queue, "SEED: {seed}");
+
+            let mut again = queue.clone();
+            smart_shuffle(&mut again, 5, seed, author);
+            assert_eq!(again, items, "SEED: {seed}");
+        }
+    }
+
+    #[test]
+    fn test_smart_shuffle_single_author() {
+        // back to back tracks can't be avoided
+        let queue: Vec<_> = (0..4).map(|i| (i, Some("a"))).collect();
+
+        let mut items = queue.clone();
+        smart_shuffle(&mut items, 2, 7, author);
+        assert_eq!(items[0], queue[2]);
+        assert_eq!(items.len(), queue.len());
+
+        let mut empty: Vec<(usize, Option<&str>)> = vec![];
+        smart_shuffle(&mut empty, 0, 7, author);
+        assert_eq!(empty, vec![]);
+    }
+}
@@ -0,0 +1,115 @@
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::audio_player::write_stereo_frame;
+
+const TEST_TONE_FREQUENCY: f32 = 440.0;
+const TEST_TONE_AMPLITUDE: f32 = 0.25;
+
+/// longest test tone that can be requested, prevents a typo from blocking a node for hours
+pub const MAX_TEST_TONE_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "kebab-case")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub enum Channel {
+    Left,
+    Right,
+    Both,
+}
+
+/// Sine wave that is played instead of the current track, used to check which speaker is
+/// connected to which channel
+///
+/// Only uses preallocated state so it can be generated in the audio callback.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestTone {
+    channel: Channel,
+    remaining_frames: usize,
+    phase: f32,
+    phase_step: f32,
+}
+
+impl TestTone {
+    pub fn new(channel: Channel, seconds: u64, sample_rate: u32) -> Self {
+        let seconds = seconds.min(MAX_TEST_TONE_SECS);
+
+        Self {
+            channel,
+            remaining_frames: (seconds * sample_rate as u64) as usize,
+            phase: 0.0,
+            phase_step: TAU * TEST_TONE_FREQUENCY / sample_rate as f32,
+        }
+    }
+
+    /// fills `data`, which is interleaved with `channels` channels, with the tone, frames after the
+    /// end of the tone are silent
+    ///
+    /// Returns false once the whole tone has been played.
+    pub fn fill(&mut self, data: &mut [f32], channels: usize, volume: f32) -> bool {
+        for frame in data.chunks_exact_mut(channels) {
+            let sample = if self.remaining_frames > 0 {
+                self.remaining_frames -= 1;
+                self.phase = (self.phase + self.phase_step) % TAU;
+
+                self.phase.sin() * TEST_TONE_AMPLITUDE * volume
+            } else {
+                0.0
+            };
+
+            let left = match self.channel {
+                Channel::Left | Channel::Both => sample,
+                Channel::Right => 0.0,
+            };
+            let right = match self.channel {
+                Channel::Right | Channel::Both => sample,
+                Channel::Left => 0.0,
+            };
+
+            write_stereo_frame(frame, left, right);
+        }
+
+        self.remaining_frames > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_tone_channels() {
+        let mut data = [1.0; 512];
+
+        let mut tone = TestTone::new(Channel::Left, 1, 48_000);
+        assert!(tone.fill(&mut data, 2, 1.0));
+        assert!(data.iter().step_by(2).any(|sample| *sample != 0.0));
+        assert!(data.iter().skip(1).step_by(2).all(|sample| *sample == 0.0));
+
+        let mut tone = TestTone::new(Channel::Right, 1, 48_000);
+        assert!(tone.fill(&mut data, 2, 1.0));
+        assert!(data.iter().step_by(2).all(|sample| *sample == 0.0));
+        assert!(data.iter().skip(1).step_by(2).any(|sample| *sample != 0.0));
+
+        assert!(data
+            .iter()
+            .all(|sample| sample.abs() <= TEST_TONE_AMPLITUDE));
+    }
+
+    #[test]
+    fn test_tone_ends() {
+        // 100 frames
+        let mut tone = TestTone::new(Channel::Both, 1, 100);
+        let mut data = [1.0; 300];
+
+        assert!(!tone.fill(&mut data, 2, 1.0));
+        assert!(data[..200].iter().any(|sample| *sample != 0.0));
+        assert_eq!(&data[200..], &[0.0; 100]);
+
+        let tone = TestTone::new(Channel::Both, MAX_TEST_TONE_SECS * 10, 100);
+        assert_eq!(tone.remaining_frames, MAX_TEST_TONE_SECS as usize * 100);
+    }
+}
@@ -0,0 +1,25 @@
+use actix_web::{http::header::AUTHORIZATION, HttpRequest};
+
+use crate::ADMIN_TOKEN;
+
+/// Whether admin operations are enabled, this is the case when `ADMIN_TOKEN` was set on server
+/// start
+pub fn auth_enabled() -> bool {
+    ADMIN_TOKEN.get().is_some()
+}
+
+/// Checks the `Authorization: Bearer <token>` header of a request against the admin token
+///
+/// Always returns false if no admin token is configured.
+pub fn is_admin_request(req: &HttpRequest) -> bool {
+    let Some(admin_token) = ADMIN_TOKEN.get() else {
+        return false;
+    };
+
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim() == admin_token)
+        .unwrap_or(false)
+}
@@ -1,19 +1,45 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
 
-use actix::{Actor, Addr, AsyncContext, Context, Handler, Message, MessageResponse};
+use actix::{
+    Actor, Addr, AsyncContext, Context, Handler, Message, MessageResponse, MessageResult,
+    ResponseFuture,
+};
 
 use crate::{
-    audio_playback::audio_player::{AudioInfo, AudioPlayer},
-    downloader::actor::AudioDownloader,
+    audio_playback::audio_player::{AudioInfo, AudioPlayer, OutputConfig},
+    downloader::{
+        actor::{
+            AudioDownloader, CancelDownloads, UpdateDownloadRateLimit, UpdatePlaybackActivity,
+        },
+        rate_limit::validate_rate_limit,
+    },
+    error::{AppError, AppErrorKind, IntoAppError},
     node::{
         health::AudioNodeHealth,
-        node_server::{AudioNode, AudioNodeInfo, SourceName},
+        multi_node_session::MultiNodeSession,
+        node_server::{
+            connections::{NodeConnectMessage, NodeConnectResponse, NodeSessionAddr},
+            sync_actor::StopAudioNode,
+            AudioNode, AudioNodeInfo, NodeOptions, SourceName,
+        },
+        recovery::DEFAULT_MAX_RECOVERY_ATTEMPTS,
     },
     state_storage::{
-        restore_state_actor::{RestoreDownloadQueue, RestoreStateActor},
-        AppStateRecoveryInfo, AudioStateInfo,
+        restore_state_actor::{
+            GlobalSettingsUpdateMessage, RestoreDownloadQueue, RestoreStateActor,
+        },
+        AppStateRecoveryInfo, AudioStateInfo, GlobalSettings,
+    },
+    streams::{
+        brain_streams::{
+            ActiveSourceChange, AudioBrainInfoStreamMessage, AudioBrainInfoStreamType,
+        },
+        node_streams::AudioNodeInfoStreamType,
     },
-    streams::brain_streams::{AudioBrainInfoStreamMessage, AudioBrainInfoStreamType},
     utils::{get_audio_sources, log_msg_received},
 };
 
@@ -23,8 +49,12 @@ pub struct AudioBrain {
     downloader_addr: Addr<AudioDownloader>,
     restore_state_addr: Addr<RestoreStateActor>,
     restored_state: AppStateRecoveryInfo,
+    settings: GlobalSettings,
     nodes: HashMap<SourceName, (Addr<AudioNode>, AudioNodeInfo)>,
     sessions: HashMap<usize, Addr<AudioBrainSession>>,
+    active_sources: HashMap<usize, SourceName>,
+    /// nodes that are currently playing, downloads can wait until this is empty
+    playing_nodes: HashSet<SourceName>,
 }
 
 #[derive(Debug, Clone, Message)]
@@ -33,12 +63,61 @@ pub struct GetAudioNodeMessage {
     pub source_name: SourceName,
 }
 
+/// every node regardless of its health, e.g. to remove deleted audio from all queues
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Vec<(SourceName, Addr<AudioNode>)>")]
+pub struct GetAllAudioNodes;
+
+/// Connects a multi node session to the requested nodes
+///
+/// Nodes are connected regardless of their health so their health can be followed.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Vec<NodeSubscriptionResult>")]
+pub struct SubscribeNodes {
+    pub addr: Addr<MultiNodeSession>,
+    pub sources: Vec<(SourceName, Arc<[AudioNodeInfoStreamType]>)>,
+}
+
+pub type NodeSubscriptionResult = (
+    SourceName,
+    Arc<[AudioNodeInfoStreamType]>,
+    Result<(Addr<AudioNode>, NodeConnectResponse), AppError>,
+);
+
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "()")]
 pub enum AudioNodeToBrainMessage {
     NodeHealthUpdate((SourceName, AudioNodeHealth)),
+    NodeMaxVolumeUpdate((SourceName, f32)),
+    NodeAutoAdvanceUpdate((SourceName, bool)),
+    NodeResumePlaybackUpdate((SourceName, bool)),
+    NodeEqualizerUpdate((SourceName, Vec<f32>)),
+    NodePauseOnDeviceLossUpdate((SourceName, bool)),
+    NodeFadeInUpdate((SourceName, u32)),
+    NodeNormalizationUpdate((SourceName, bool)),
+    NodeQueueLabelUpdate((SourceName, Option<Arc<str>>)),
+    /// sent when a node starts or stops playing
+    NodePlaybackActivityUpdate((SourceName, bool)),
 }
 
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "f32")]
+pub struct GetDefaultVolume;
+
+/// Sets the volume new nodes start with, responds with the clamped volume that was stored
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "f32")]
+pub struct SetDefaultVolume(pub f32);
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Option<Arc<str>>")]
+pub struct GetDownloadRateLimit;
+
+/// Sets the max download rate, `None` removes the limit, responds with the limit that was stored
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Result<Option<Arc<str>>, AppError>")]
+pub struct SetDownloadRateLimit(pub Option<Arc<str>>);
+
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "BrainConnectResponse")]
 pub struct BrainConnectMessage {
@@ -52,6 +131,50 @@ pub struct BrainConnectResponse {
     pub connection_response: BrainSessionWsResponse,
 }
 
+/// Sets the source a brain session is currently controlling, responds with the source that was
+/// active before
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Result<Option<SourceName>, AppError>")]
+pub struct SetSessionActiveSource {
+    pub id: usize,
+    pub source_name: SourceName,
+}
+
+/// Responds with the source a brain session is currently controlling, `None` if the session
+/// hasn't selected a source yet
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Option<SourceName>")]
+pub struct GetSessionActiveSource {
+    pub id: usize,
+}
+
+/// Stops a node at runtime and releases its audio device, running and queued downloads of the node
+/// are cancelled
+///
+/// The node is created again on the next start if its source is still configured.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Result<(), AppError>")]
+pub struct RemoveAudioNodeMessage {
+    pub source_name: SourceName,
+}
+
+/// Starts a node on the output device with the name `device_name` at runtime, the name of the
+/// device is used as the source name of the node
+///
+/// Responds with the output config the device is played with. Nodes added at runtime start with
+/// an empty queue and are not added to the sources file.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Result<OutputConfig, AppError>")]
+pub struct AddAudioNodeMessage {
+    pub human_readable_name: String,
+    pub device_name: SourceName,
+}
+
+/// number of connected brain sessions
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "usize")]
+pub struct GetBrainSessionCount;
+
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "()")]
 pub struct BrainDisconnect {
@@ -67,12 +190,62 @@ impl AudioBrain {
         Self {
             downloader_addr,
             restore_state_addr,
+            settings: restored_state.settings.clone(),
             restored_state,
             nodes: HashMap::default(),
             sessions: HashMap::default(),
+            active_sources: HashMap::default(),
+            playing_nodes: HashSet::default(),
         }
     }
 
+    /// applies the options to the player and starts the node playing on it
+    fn start_node(
+        &mut self,
+        ctx: &mut Context<Self>,
+        mut player: AudioPlayer<PathBuf>,
+        human_readable_name: String,
+        options: NodeOptions,
+        equalizer_bands: &[f32],
+        queue_label: Option<Arc<str>>,
+    ) {
+        let source_name = player.source_name().clone();
+        let max_volume = player.max_volume();
+        let equalizer_bands = player.set_equalizer(equalizer_bands).to_vec();
+        let fade_in_ms = player.set_fade_in_ms(options.fade_in_ms);
+        player.set_normalization_enabled(options.normalization_enabled);
+        let node = AudioNode::new(
+            source_name.clone(),
+            player,
+            ctx.address(),
+            self.downloader_addr.clone(),
+            self.restore_state_addr.clone(),
+            options,
+            queue_label.clone(),
+        );
+        let node_addr = node.start();
+
+        self.nodes.insert(
+            source_name.clone(),
+            (
+                node_addr,
+                AudioNodeInfo {
+                    source_name,
+                    human_readable_name,
+                    health: AudioNodeHealth::Good,
+                    max_volume,
+                    auto_advance: options.auto_advance,
+                    resume_playback: options.resume_playback,
+                    pause_on_device_loss: options.pause_on_device_loss,
+                    equalizer_bands,
+                    fade_in_ms,
+                    normalization_enabled: options.normalization_enabled,
+                    queue_label,
+                },
+            ),
+        );
+    }
+
     fn multicast<M>(&self, msg: M)
     where
         M: Message + Send + Clone + 'static,
@@ -94,13 +267,23 @@ impl Actor for AudioBrain {
         log::info!("stared new 'AudioBrain', CONTEXT: {ctx:?}");
 
         for (source_name, info) in get_audio_sources().into_iter() {
-            let (restored_state, restored_queue) =
+            let (restored_state, restored_queue, max_volume, options, equalizer_bands, queue_label) =
                 match self.restored_state.audio_info.get(&source_name).cloned() {
                     Some(AudioStateInfo {
                         playback_state,
                         current_queue_index,
                         audio_progress,
                         audio_volume,
+                        max_volume,
+                        auto_advance,
+                        resume_playback,
+                        pause_on_device_loss,
+                        queue_label,
+                        equalizer_bands,
+                        fade_in_ms,
+                        normalization_enabled,
+                        repeat_mode,
+                        crossfade_ms,
                         restored_queue,
                         ..
                     }) => (
@@ -109,34 +292,58 @@ impl Actor for AudioBrain {
                             current_queue_index,
                             audio_progress,
                             audio_volume,
+                            duration_secs: None,
+                            elapsed_secs: None,
+                            repeat_mode,
+                            crossfade_ms,
                         },
                         restored_queue,
+                        max_volume,
+                        NodeOptions {
+                            auto_advance,
+                            resume_playback,
+                            pause_on_device_loss,
+                            fade_in_ms,
+                            normalization_enabled,
+                            ..Default::default()
+                        },
+                        equalizer_bands,
+                        queue_label,
+                    ),
+                    None => (
+                        AudioInfo {
+                            audio_volume: self.settings.default_volume,
+                            ..Default::default()
+                        },
+                        Default::default(),
+                        1.0,
+                        NodeOptions::default(),
+                        vec![],
+                        None,
                     ),
-                    None => Default::default(),
                 };
 
-            if let Ok(player) =
-                AudioPlayer::try_new(source_name.to_owned(), None, restored_state, restored_queue)
-            {
-                let node = AudioNode::new(
-                    source_name.to_owned(),
+            let options = NodeOptions {
+                max_recovery_attempts: info
+                    .max_recovery_attempts
+                    .unwrap_or(DEFAULT_MAX_RECOVERY_ATTEMPTS),
+                ..options
+            };
+
+            if let Ok(player) = AudioPlayer::try_new(
+                source_name.to_owned(),
+                None,
+                restored_state,
+                restored_queue,
+                max_volume,
+            ) {
+                self.start_node(
+                    ctx,
                     player,
-                    ctx.address(),
-                    self.downloader_addr.clone(),
-                    self.restore_state_addr.clone(),
-                );
-                let node_addr = node.start();
-
-                self.nodes.insert(
-                    source_name.to_owned(),
-                    (
-                        node_addr,
-                        AudioNodeInfo {
-                            source_name,
-                            human_readable_name: info.human_readable_name.clone(),
-                            health: AudioNodeHealth::Good,
-                        },
-                    ),
+                    info.human_readable_name.clone(),
+                    options,
+                    &equalizer_bands,
+                    queue_label,
                 );
             }
         }
@@ -186,6 +393,155 @@ impl Handler<BrainDisconnect> for AudioBrain {
 
         let BrainDisconnect { id } = msg;
         self.sessions.remove(&id);
+        self.active_sources.remove(&id);
+    }
+}
+
+impl Handler<SetSessionActiveSource> for AudioBrain {
+    type Result = Result<Option<SourceName>, AppError>;
+
+    fn handle(&mut self, msg: SetSessionActiveSource, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let SetSessionActiveSource { id, source_name } = msg;
+
+        if !self.nodes.contains_key(&source_name) {
+            return Err(AppError::new(
+                AppErrorKind::Api,
+                "unknown audio source",
+                &[&format!("SOURCE_NAME: {source_name}")],
+            )
+            .not_found());
+        }
+
+        let previous_source_name = self.active_sources.insert(id, Arc::clone(&source_name));
+
+        self.multicast(AudioBrainInfoStreamMessage::ActiveSourceChanged(
+            ActiveSourceChange {
+                source_name,
+                previous_source_name: previous_source_name.clone(),
+            },
+        ));
+
+        Ok(previous_source_name)
+    }
+}
+
+impl Handler<AddAudioNodeMessage> for AudioBrain {
+    type Result = Result<OutputConfig, AppError>;
+
+    fn handle(&mut self, msg: AddAudioNodeMessage, ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let AddAudioNodeMessage {
+            human_readable_name,
+            device_name,
+        } = msg;
+
+        if self.nodes.contains_key(&device_name) {
+            return Err(AppError::new(
+                AppErrorKind::Api,
+                "audio source already exists",
+                &[&format!("SOURCE_NAME: {device_name}")],
+            )
+            .bad_request());
+        }
+
+        let player = AudioPlayer::try_new(
+            device_name.clone(),
+            None,
+            AudioInfo {
+                audio_volume: self.settings.default_volume,
+                ..Default::default()
+            },
+            vec![],
+            1.0,
+        )
+        .into_app_err(
+            "failed to set up output device",
+            AppErrorKind::Api,
+            &[&format!("DEVICE_NAME: {device_name}")],
+        )?;
+        let output_config = player.output_config();
+
+        self.start_node(
+            ctx,
+            player,
+            human_readable_name,
+            NodeOptions::default(),
+            &[],
+            None,
+        );
+
+        self.multicast(AudioBrainInfoStreamMessage::NodeInfo(
+            self.nodes
+                .values()
+                .map(|(_, info)| info.to_owned())
+                .collect(),
+        ));
+
+        Ok(output_config)
+    }
+}
+
+impl Handler<RemoveAudioNodeMessage> for AudioBrain {
+    type Result = Result<(), AppError>;
+
+    fn handle(&mut self, msg: RemoveAudioNodeMessage, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let RemoveAudioNodeMessage { source_name } = msg;
+
+        let Some((node_addr, _)) = self.nodes.remove(&source_name) else {
+            return Err(AppError::new(
+                AppErrorKind::Api,
+                "unknown audio source",
+                &[&format!("SOURCE_NAME: {source_name}")],
+            )
+            .not_found());
+        };
+
+        // finished downloads would be added to the queue of a node that no longer exists
+        self.downloader_addr.do_send(CancelDownloads {
+            source_name: source_name.clone(),
+        });
+        node_addr.do_send(StopAudioNode);
+
+        self.active_sources
+            .retain(|_, active_source| *active_source != source_name);
+
+        if self.playing_nodes.remove(&source_name) && self.playing_nodes.is_empty() {
+            self.downloader_addr.do_send(UpdatePlaybackActivity(false));
+        }
+
+        self.multicast(AudioBrainInfoStreamMessage::NodeInfo(
+            self.nodes
+                .values()
+                .map(|(_, info)| info.to_owned())
+                .collect(),
+        ));
+
+        Ok(())
+    }
+}
+
+impl Handler<GetBrainSessionCount> for AudioBrain {
+    type Result = usize;
+
+    fn handle(&mut self, msg: GetBrainSessionCount, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.sessions.len()
+    }
+}
+
+impl Handler<GetSessionActiveSource> for AudioBrain {
+    type Result = Option<SourceName>;
+
+    fn handle(&mut self, msg: GetSessionActiveSource, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.active_sources.get(&msg.id).cloned()
     }
 }
 
@@ -212,10 +568,217 @@ impl Handler<AudioNodeToBrainMessage> for AudioBrain {
                     self.multicast(msg)
                 }
             }
+            AudioNodeToBrainMessage::NodeMaxVolumeUpdate(params) => {
+                let (source_name, max_volume) = params;
+
+                if let Some((_, node_info)) = self.nodes.get_mut(source_name) {
+                    node_info.max_volume = *max_volume;
+
+                    let msg = AudioBrainInfoStreamMessage::NodeInfo(
+                        self.nodes
+                            .values()
+                            .map(|(_, info)| info.to_owned())
+                            .collect(),
+                    );
+
+                    self.multicast(msg)
+                }
+            }
+            AudioNodeToBrainMessage::NodeAutoAdvanceUpdate(params) => {
+                let (source_name, auto_advance) = params;
+
+                if let Some((_, node_info)) = self.nodes.get_mut(source_name) {
+                    node_info.auto_advance = *auto_advance;
+
+                    let msg = AudioBrainInfoStreamMessage::NodeInfo(
+                        self.nodes
+                            .values()
+                            .map(|(_, info)| info.to_owned())
+                            .collect(),
+                    );
+
+                    self.multicast(msg)
+                }
+            }
+            AudioNodeToBrainMessage::NodeResumePlaybackUpdate(params) => {
+                let (source_name, resume_playback) = params;
+
+                if let Some((_, node_info)) = self.nodes.get_mut(source_name) {
+                    node_info.resume_playback = *resume_playback;
+
+                    let msg = AudioBrainInfoStreamMessage::NodeInfo(
+                        self.nodes
+                            .values()
+                            .map(|(_, info)| info.to_owned())
+                            .collect(),
+                    );
+
+                    self.multicast(msg)
+                }
+            }
+            AudioNodeToBrainMessage::NodeEqualizerUpdate(params) => {
+                let (source_name, equalizer_bands) = params;
+
+                if let Some((_, node_info)) = self.nodes.get_mut(source_name) {
+                    node_info.equalizer_bands = equalizer_bands.clone();
+
+                    let msg = AudioBrainInfoStreamMessage::NodeInfo(
+                        self.nodes
+                            .values()
+                            .map(|(_, info)| info.to_owned())
+                            .collect(),
+                    );
+
+                    self.multicast(msg)
+                }
+            }
+            AudioNodeToBrainMessage::NodePauseOnDeviceLossUpdate(params) => {
+                let (source_name, pause_on_device_loss) = params;
+
+                if let Some((_, node_info)) = self.nodes.get_mut(source_name) {
+                    node_info.pause_on_device_loss = *pause_on_device_loss;
+
+                    let msg = AudioBrainInfoStreamMessage::NodeInfo(
+                        self.nodes
+                            .values()
+                            .map(|(_, info)| info.to_owned())
+                            .collect(),
+                    );
+
+                    self.multicast(msg)
+                }
+            }
+            AudioNodeToBrainMessage::NodeFadeInUpdate(params) => {
+                let (source_name, fade_in_ms) = params;
+
+                if let Some((_, node_info)) = self.nodes.get_mut(source_name) {
+                    node_info.fade_in_ms = *fade_in_ms;
+
+                    let msg = AudioBrainInfoStreamMessage::NodeInfo(
+                        self.nodes
+                            .values()
+                            .map(|(_, info)| info.to_owned())
+                            .collect(),
+                    );
+
+                    self.multicast(msg)
+                }
+            }
+            AudioNodeToBrainMessage::NodeNormalizationUpdate(params) => {
+                let (source_name, normalization_enabled) = params;
+
+                if let Some((_, node_info)) = self.nodes.get_mut(source_name) {
+                    node_info.normalization_enabled = *normalization_enabled;
+
+                    let msg = AudioBrainInfoStreamMessage::NodeInfo(
+                        self.nodes
+                            .values()
+                            .map(|(_, info)| info.to_owned())
+                            .collect(),
+                    );
+
+                    self.multicast(msg)
+                }
+            }
+            AudioNodeToBrainMessage::NodeQueueLabelUpdate(params) => {
+                let (source_name, queue_label) = params;
+
+                if let Some((_, node_info)) = self.nodes.get_mut(source_name) {
+                    node_info.queue_label = queue_label.clone();
+
+                    let msg = AudioBrainInfoStreamMessage::NodeInfo(
+                        self.nodes
+                            .values()
+                            .map(|(_, info)| info.to_owned())
+                            .collect(),
+                    );
+
+                    self.multicast(msg)
+                }
+            }
+            AudioNodeToBrainMessage::NodePlaybackActivityUpdate(params) => {
+                let (source_name, is_playing) = params;
+
+                let was_any_playing = !self.playing_nodes.is_empty();
+                if *is_playing {
+                    self.playing_nodes.insert(source_name.clone());
+                } else {
+                    self.playing_nodes.remove(source_name);
+                }
+
+                let is_any_playing = !self.playing_nodes.is_empty();
+                if is_any_playing != was_any_playing {
+                    self.downloader_addr
+                        .do_send(UpdatePlaybackActivity(is_any_playing));
+                }
+            }
         }
     }
 }
 
+impl Handler<SubscribeNodes> for AudioBrain {
+    type Result = ResponseFuture<Vec<NodeSubscriptionResult>>;
+
+    fn handle(&mut self, msg: SubscribeNodes, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let SubscribeNodes { addr, sources } = msg;
+        let sources: Vec<_> = sources
+            .into_iter()
+            .map(|(source_name, wanted_info)| {
+                let node_addr = self.nodes.get(&source_name).map(|(addr, _)| addr.clone());
+                (source_name, wanted_info, node_addr)
+            })
+            .collect();
+
+        Box::pin(async move {
+            let mut results = Vec::with_capacity(sources.len());
+
+            for (source_name, wanted_info, node_addr) in sources {
+                let res = match node_addr {
+                    Some(node_addr) => node_addr
+                        .send(NodeConnectMessage {
+                            addr: NodeSessionAddr::Multiplexed(addr.clone()),
+                            wanted_info: Arc::clone(&wanted_info),
+                        })
+                        .await
+                        .into_app_err(
+                            "failed to connect to audio node",
+                            AppErrorKind::Api,
+                            &[&format!("NODE_NAME: {source_name}")],
+                        )
+                        .map(|res| (node_addr, res)),
+                    None => Err(AppError::new(
+                        AppErrorKind::Api,
+                        "no node with this source name exists",
+                        &[&format!("NODE_NAME: {source_name}")],
+                    )
+                    .not_found()),
+                };
+
+                results.push((source_name, wanted_info, res));
+            }
+
+            results
+        })
+    }
+}
+
+impl Handler<GetAllAudioNodes> for AudioBrain {
+    type Result = MessageResult<GetAllAudioNodes>;
+
+    fn handle(&mut self, msg: GetAllAudioNodes, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        MessageResult(
+            self.nodes
+                .iter()
+                .map(|(source_name, (addr, _))| (source_name.clone(), addr.clone()))
+                .collect(),
+        )
+    }
+}
+
 impl Handler<GetAudioNodeMessage> for AudioBrain {
     type Result = Option<Addr<AudioNode>>;
 
@@ -230,3 +793,58 @@ impl Handler<GetAudioNodeMessage> for AudioBrain {
             })
     }
 }
+
+impl Handler<GetDefaultVolume> for AudioBrain {
+    type Result = f32;
+
+    fn handle(&mut self, msg: GetDefaultVolume, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.settings.default_volume
+    }
+}
+
+impl Handler<SetDefaultVolume> for AudioBrain {
+    type Result = f32;
+
+    fn handle(&mut self, msg: SetDefaultVolume, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.settings.default_volume = msg.0.clamp(0.0, 1.0);
+        self.restore_state_addr
+            .do_send(GlobalSettingsUpdateMessage(self.settings.clone()));
+
+        self.settings.default_volume
+    }
+}
+
+impl Handler<GetDownloadRateLimit> for AudioBrain {
+    type Result = Option<Arc<str>>;
+
+    fn handle(&mut self, msg: GetDownloadRateLimit, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.settings.download_rate_limit.clone()
+    }
+}
+
+impl Handler<SetDownloadRateLimit> for AudioBrain {
+    type Result = Result<Option<Arc<str>>, AppError>;
+
+    fn handle(&mut self, msg: SetDownloadRateLimit, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let rate_limit = msg
+            .0
+            .map(|rate_limit| validate_rate_limit(&rate_limit))
+            .transpose()?;
+
+        self.settings.download_rate_limit = rate_limit.clone();
+        self.restore_state_addr
+            .do_send(GlobalSettingsUpdateMessage(self.settings.clone()));
+        self.downloader_addr
+            .do_send(UpdateDownloadRateLimit(rate_limit.clone()));
+
+        Ok(rate_limit)
+    }
+}
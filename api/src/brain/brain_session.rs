@@ -1,32 +1,43 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use actix::{
     Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, ContextFutureSpawner, Handler,
-    ResponseActFuture, Running, StreamHandler, WrapFuture,
+    Message, ResponseActFuture, Running, StreamHandler, WrapFuture,
 };
 
 use actix_web_actors::ws;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
 use crate::{
-    brain::brain_server::{BrainConnectMessage, BrainDisconnect},
-    node::node_server::AudioNodeInfo,
+    brain::brain_server::{
+        BrainConnectMessage, BrainDisconnect, GetSessionActiveSource, SetSessionActiveSource,
+    },
+    error::{AppErrorKind, IntoAppError},
+    node::node_server::{AudioNodeInfo, SourceName},
     streams::{
         brain_streams::{
             get_type_of_stream_data, AudioBrainInfoStreamMessage, AudioBrainInfoStreamType,
         },
-        HeartBeat,
+        ClientActivity, HeartBeat, CLIENT_TIMEOUT_CHECK_INTERVAL,
     },
 };
 
-use super::brain_server::AudioBrain;
+use super::{
+    brain_server::AudioBrain,
+    queue_clone::{clone_queue, CloneQueueParams, ClonedQueue},
+};
 
 #[derive(Debug, Clone)]
 pub struct AudioBrainSession {
     id: usize,
     server_addr: Addr<AudioBrain>,
     wanted_info: Arc<[AudioBrainInfoStreamType]>,
+    heartbeat_interval: Duration,
+    activity: ClientActivity,
 }
 
 #[derive(Debug, Clone, Serialize, TS)]
@@ -37,17 +48,57 @@ pub enum BrainSessionWsResponse {
         #[ts(type = "Array<AudioNodeInfo>")]
         node_info: Option<Arc<[AudioNodeInfo]>>,
     },
+    #[serde(rename_all = "camelCase")]
+    SetActiveSourceResponse {
+        source_name: SourceName,
+        previous_source_name: Option<SourceName>,
+    },
+    #[serde(rename_all = "camelCase")]
+    GetActiveSourceResponse {
+        source_name: Option<SourceName>,
+    },
+    CloneQueueResponse(ClonedQueue),
+}
+
+/// Commands a client can send over the brain websocket
+///
+/// Example:
+///
+/// {"SET_ACTIVE_SOURCE": {"sourceName": "living_room"}}
+///
+/// "GET_ACTIVE_SOURCE"
+///
+/// {"CLONE_QUEUE": {"fromSource": "living_room", "toSource": "office", "copyPosition": true}}
+#[derive(Debug, Clone, Deserialize, TS, Message)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[ts(export, export_to = "../app/src/api-types/")]
+#[rtype(result = "()")]
+pub enum BrainSessionWsCommand {
+    SetActiveSource(SetActiveSourceParams),
+    GetActiveSource,
+    /// copies the queue of one node to another, the response lists items that couldn't be copied
+    CloneQueue(CloneQueueParams),
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetActiveSourceParams {
+    pub source_name: SourceName,
 }
 
 impl AudioBrainSession {
     pub fn new(
         server_addr: Addr<AudioBrain>,
         wanted_info: Arc<[AudioBrainInfoStreamType]>,
+        heartbeat_interval: Duration,
     ) -> Self {
         Self {
             id: usize::MAX,
             server_addr,
             wanted_info,
+            heartbeat_interval,
+            activity: ClientActivity::new(heartbeat_interval),
         }
     }
 }
@@ -90,6 +141,13 @@ impl Actor for AudioBrainSession {
                 actix::fut::ready(())
             })
             .wait(ctx);
+
+        ctx.run_interval(CLIENT_TIMEOUT_CHECK_INTERVAL, |act, ctx| {
+            if act.activity.timed_out(Instant::now()) {
+                log::info!("'AudioBrainSession' timed out, ID: {}", act.id);
+                ctx.stop();
+            }
+        });
     }
 
     fn stopping(&mut self, _: &mut Self::Context) -> Running {
@@ -105,9 +163,11 @@ impl Handler<HeartBeat> for AudioBrainSession {
 
     fn handle(&mut self, _msg: HeartBeat, ctx: &mut Self::Context) -> Self::Result {
         ctx.ping(b"heart-beat");
+
+        let interval = self.heartbeat_interval;
         Box::pin(
-            async {
-                actix_rt::time::sleep(std::time::Duration::from_millis(333)).await;
+            async move {
+                actix_rt::time::sleep(interval).await;
             }
             .into_actor(self)
             .map(|_res, _act, ctx| ctx.notify(HeartBeat)),
@@ -135,14 +195,103 @@ impl Handler<AudioBrainInfoStreamMessage> for AudioBrainSession {
     }
 }
 
+impl Handler<BrainSessionWsCommand> for AudioBrainSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: BrainSessionWsCommand, ctx: &mut Self::Context) -> Self::Result {
+        match msg {
+            BrainSessionWsCommand::SetActiveSource(SetActiveSourceParams { source_name }) => {
+                self.server_addr
+                    .send(SetSessionActiveSource {
+                        id: self.id,
+                        source_name: Arc::clone(&source_name),
+                    })
+                    .into_actor(self)
+                    .map(move |res, _act, ctx| {
+                        let res = res
+                            .into_app_err("failed to set active source", AppErrorKind::Api, &[])
+                            .and_then(|res| res);
+
+                        let text = match res {
+                            Ok(previous_source_name) => serde_json::to_string(
+                                &BrainSessionWsResponse::SetActiveSourceResponse {
+                                    source_name,
+                                    previous_source_name,
+                                },
+                            ),
+                            Err(err) => serde_json::to_string(&err),
+                        };
+
+                        ctx.text(text.unwrap_or("failed to serialize on server".to_owned()));
+                    })
+                    .spawn(ctx);
+            }
+            BrainSessionWsCommand::GetActiveSource => {
+                self.server_addr
+                    .send(GetSessionActiveSource { id: self.id })
+                    .into_actor(self)
+                    .map(|res, _act, ctx| {
+                        let res =
+                            res.into_app_err("failed to get active source", AppErrorKind::Api, &[]);
+
+                        let text = match res {
+                            Ok(source_name) => serde_json::to_string(
+                                &BrainSessionWsResponse::GetActiveSourceResponse { source_name },
+                            ),
+                            Err(err) => serde_json::to_string(&err),
+                        };
+
+                        ctx.text(text.unwrap_or("failed to serialize on server".to_owned()));
+                    })
+                    .spawn(ctx);
+            }
+            BrainSessionWsCommand::CloneQueue(params) => {
+                clone_queue(params)
+                    .into_actor(self)
+                    .map(|res, _act, ctx| {
+                        let text = match res {
+                            Ok(report) => serde_json::to_string(
+                                &BrainSessionWsResponse::CloneQueueResponse(report),
+                            ),
+                            Err(err) => serde_json::to_string(&err),
+                        };
+
+                        ctx.text(text.unwrap_or("failed to serialize on server".to_owned()));
+                    })
+                    .spawn(ctx);
+            }
+        }
+    }
+}
+
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for AudioBrainSession {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        if msg.is_ok() {
+            self.activity.seen(Instant::now());
+        }
+
         match &msg {
-            Ok(ws::Message::Text(_text)) => {}
+            Ok(ws::Message::Text(text)) => match serde_json::from_str(text) {
+                Ok(cmd) => ctx.notify::<BrainSessionWsCommand>(cmd),
+                Err(err) => {
+                    let err = err.into_app_err(
+                        "invalid brain session command",
+                        AppErrorKind::Api,
+                        &[&format!("COMMAND: {text}")],
+                    );
+
+                    ctx.text(
+                        serde_json::to_string(&err)
+                            .unwrap_or("failed to serialize on server".to_owned()),
+                    );
+                }
+            },
+            Ok(ws::Message::Ping(bytes)) => ctx.pong(bytes),
             Ok(ws::Message::Close(reason)) => {
                 ctx.close(reason.clone());
                 ctx.stop();
             }
+            Err(_) => ctx.stop(),
             _ => {}
         }
     }
@@ -1,2 +1,3 @@
 pub mod brain_server;
 pub mod brain_session;
+pub mod queue_clone;
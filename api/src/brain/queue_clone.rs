@@ -0,0 +1,187 @@
+use std::sync::Arc;
+
+use actix::Addr;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    brain_addr,
+    error::{AppError, AppErrorKind, IntoAppError},
+    node::node_server::{
+        sync_actor::{GetQueueItems, QueuePosition, ReplaceQueue},
+        AudioNode, SourceName,
+    },
+};
+
+use super::brain_server::GetAudioNodeMessage;
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct CloneQueueParams {
+    pub from_source: SourceName,
+    pub to_source: SourceName,
+    /// continue at the current track and progress of the source node instead of at the start of
+    /// the queue
+    pub copy_position: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct ClonedQueue {
+    pub from_source: SourceName,
+    pub to_source: SourceName,
+    pub copied: usize,
+    /// uids of the items that were skipped because their audio file is missing
+    pub skipped: Vec<Arc<str>>,
+}
+
+/// Replaces the queue of `to_source` with the queue of `from_source`
+///
+/// The stored audio files are reused, items whose file is missing are skipped. If the current
+/// item of the source is skipped the target continues at the start of the next item.
+pub async fn clone_queue(params: CloneQueueParams) -> Result<ClonedQueue, AppError> {
+    let CloneQueueParams {
+        from_source,
+        to_source,
+        copy_position,
+    } = params;
+
+    if from_source == to_source {
+        return Err(AppError::new(
+            AppErrorKind::Queue,
+            "can not clone the queue of a node to itself",
+            &[&format!("NODE_NAME: {from_source}")],
+        )
+        .bad_request());
+    }
+
+    let from_addr = get_node(&from_source).await?;
+    let to_addr = get_node(&to_source).await?;
+
+    let (items, info) = from_addr.send(GetQueueItems).await.into_app_err(
+        "failed to get queue of node",
+        AppErrorKind::Api,
+        &[&format!("NODE_NAME: {from_source}")],
+    )?;
+
+    let (queue, missing, head, head_copied) =
+        retain_existing(items, info.current_queue_index, |item| {
+            item.locator.is_file()
+        });
+
+    let copied = queue.len();
+    let position = copy_position.then_some(QueuePosition {
+        current_queue_index: head,
+        audio_progress: if head_copied {
+            info.audio_progress
+        } else {
+            0.0
+        },
+        playback_state: info.playback_state,
+    });
+
+    to_addr
+        .send(ReplaceQueue { queue, position })
+        .await
+        .into_app_err(
+            "failed to replace queue of node",
+            AppErrorKind::Api,
+            &[&format!("NODE_NAME: {to_source}")],
+        )?;
+
+    let report = ClonedQueue {
+        from_source,
+        to_source,
+        copied,
+        skipped: missing
+            .into_iter()
+            .map(|item| Arc::clone(&item.identifier.0))
+            .collect(),
+    };
+
+    log::info!("cloned queue, REPORT: {report:?}");
+
+    Ok(report)
+}
+
+async fn get_node(source_name: &SourceName) -> Result<Addr<AudioNode>, AppError> {
+    brain_addr()
+        .send(GetAudioNodeMessage {
+            source_name: source_name.clone(),
+        })
+        .await
+        .into_app_err("failed to get audio node", AppErrorKind::Api, &[])?
+        .ok_or_else(|| {
+            AppError::new(
+                AppErrorKind::Api,
+                "no node with this source name exists",
+                &[&format!("NODE_NAME: {source_name}")],
+            )
+            .not_found()
+        })
+}
+
+/// Splits `items` into the items that exist and the ones that don't
+///
+/// Also returns where `head` ends up in the existing items and whether the item at `head` exists,
+/// if it doesn't the next existing item takes its place.
+pub(crate) fn retain_existing<T>(
+    items: Vec<T>,
+    head: usize,
+    exists: impl Fn(&T) -> bool,
+) -> (Vec<T>, Vec<T>, usize, bool) {
+    let mut existing = vec![];
+    let mut missing = vec![];
+    let mut new_head = None;
+    let mut head_exists = false;
+
+    for (i, item) in items.into_iter().enumerate() {
+        if exists(&item) {
+            if i >= head && new_head.is_none() {
+                new_head = Some(existing.len());
+                head_exists = i == head;
+            }
+
+            existing.push(item);
+        } else {
+            missing.push(item);
+        }
+    }
+
+    (existing, missing, new_head.unwrap_or(0), head_exists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_retain_existing() {
+        let is_even = |n: &usize| n.is_multiple_of(2);
+
+        assert_eq!(
+            retain_existing(vec![0, 1, 2, 3, 4], 2, is_even),
+            (vec![0, 2, 4], vec![1, 3], 1, true)
+        );
+
+        // a missing head continues at the next existing item
+        assert_eq!(
+            retain_existing(vec![0, 1, 2, 3, 4], 3, is_even),
+            (vec![0, 2, 4], vec![1, 3], 2, false)
+        );
+
+        // wraps to the start if nothing after the head exists
+        assert_eq!(
+            retain_existing(vec![0, 1, 2, 3], 3, is_even),
+            (vec![0, 2], vec![1, 3], 0, false)
+        );
+
+        assert_eq!(
+            retain_existing(vec![1, 3], 0, is_even),
+            (vec![], vec![1, 3], 0, false)
+        );
+    }
+}
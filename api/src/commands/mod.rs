@@ -1 +1,2 @@
 pub mod node_commands;
+pub mod rate_limit;
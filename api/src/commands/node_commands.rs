@@ -1,14 +1,31 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc, time::Instant};
 
-use actix::Message;
-use actix_web::{http::StatusCode, post, web, HttpResponse};
+use actix::{Actor, Addr, Context, Handler, Message};
+use actix_web::{delete, http::StatusCode, post, web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
 use crate::{
-    brain_addr, error::AppError, node::node_server::SourceName, utils::get_node_by_source_name,
+    audio_playback::{audio_player::RepeatMode, test_tone::Channel},
+    auth::is_admin_request,
+    brain::brain_server::{
+        AddAudioNodeMessage, GetAllAudioNodes, GetAudioNodeMessage, RemoveAudioNodeMessage,
+    },
+    brain_addr,
+    error::{AppError, AppErrorKind, IntoAppError},
+    node::{
+        health::AudioNodeHealth,
+        node_server::{AudioNode, SourceName},
+    },
+    rest_data_access::{error_response, json_response},
+    utils::{get_node_by_source_name, get_node_by_source_name_any_health},
+    COMMAND_RATE_LIMITER,
 };
 
+/// most items that can be added with a single `ADD_QUEUE_ITEMS` command, a batch counts as a
+/// single command for the rate limit
+pub const MAX_QUEUE_ITEMS_PER_BATCH: usize = 100;
+
 /// Commands a client can send to an audio node
 ///
 /// # Example commands
@@ -23,24 +40,240 @@ use crate::{
 #[rtype(result = "Result<(), AppError>")]
 pub enum AudioNodeCommand {
     AddQueueItem(AddQueueItemParams),
+    /// adds several tracks with a single queue update, tracks that have to be downloaded are added
+    /// once their download has finished
+    ///
+    /// Playlists can't be part of a batch, add them with `ADD_QUEUE_ITEM`. A batch has at most
+    /// [`MAX_QUEUE_ITEMS_PER_BATCH`] items.
+    AddQueueItems(Vec<AddQueueItemParams>),
     RemoveQueueItem(RemoveQueueItemParams),
     MoveQueueItem(MoveQueueItemParams),
+    /// shuffles the queue, the current track keeps playing and is moved to the start of the queue
     ShuffleQueue,
+    /// shuffles the queue while avoiding tracks of the same author back to back, the current track
+    /// keeps playing
+    ShuffleQueueSmart,
+    /// removes every item from the queue and stops playback
+    ClearQueue,
     SetAudioVolume(SetAudioVolumeParams),
     SetAudioProgress(SetAudioProgressParams),
+    /// seeks relative to the current position, e.g. `0.05` skips ahead by 5% of the track
+    SeekPercentDelta(SeekPercentDeltaParams),
+    /// seeks to a position in seconds, clamped to the length of the track
+    SeekToSeconds(SeekSecondsParams),
     PauseQueue,
     UnPauseQueue,
+    /// stops playback and releases the audio device, the queue is kept and playback restarts at
+    /// the start of the current item
+    Stop,
     PlayNext,
     PlayPrevious,
     PlaySelected(PlaySelectedParams),
+    /// restarts the current track from the beginning
+    ReplayCurrent,
+    /// plays the track that finished last again, e.g. after the queue already advanced past it
+    ReplayPrevious,
+    AddBookmark(AddBookmarkParams),
+    SeekBookmark(SeekBookmarkParams),
+    SetItemGain(SetItemGainParams),
+    ResetBufferStats,
+    /// rebuilds the audio device immediately instead of waiting for the next recovery attempt,
+    /// e.g. after reconnecting a USB DAC
+    ReconnectDevice,
+    SetAutoAdvance(SetAutoAdvanceParams),
+    /// continue tracks at the position they were paused or skipped at, e.g. for audiobooks
+    SetResumePlayback(SetResumePlaybackParams),
+    /// plays a sine wave on a single channel to check the speaker setup, the current track
+    /// continues afterwards
+    PlayTestTone(PlayTestToneParams),
+    /// sets the gain in dB of every band of the equalizer, an empty list disables it
+    SetEqualizer(SetEqualizerParams),
+    /// pause playback when the audio device is lost instead of playing into the void, playback
+    /// continues where it stopped once the device is back
+    SetPauseOnDeviceLoss(SetPauseOnDeviceLossParams),
+    /// labels the current queue for display, e.g. 'Sunday Jazz', an empty label clears it
+    SetQueueLabel(SetQueueLabelParams),
+    /// fades every track in from silence over its first milliseconds, applies from the next track
+    SetFadeIn(SetFadeInParams),
+    /// overrides the fade in of a single queue item
+    SetItemFadeIn(SetItemFadeInParams),
+    /// plays stored audio over the current track, e.g. a TTS announcement, the track continues at
+    /// the same position afterwards and announcements that arrive in the meantime are played in
+    /// order
+    Announce(AnnounceParams),
+    /// what happens once a track has ended, `one` plays the current track again
+    SetRepeatMode(SetRepeatModeParams),
+    /// overlap of the end of a track with the start of the next one, only used while the node
+    /// advances on its own
+    SetCrossfade(SetCrossfadeParams),
+    /// normalizes the loudness of tracks whose loudness was measured when they were downloaded
+    SetNormalizationEnabled(SetNormalizationEnabledParams),
+}
+
+impl AudioNodeCommand {
+    /// whether the command starts or changes playback, which fails while the audio device is
+    /// unavailable
+    ///
+    /// Editing the queue and changing settings works without the device, the queue is played once
+    /// the device is back.
+    pub fn needs_audio_device(&self) -> bool {
+        match self {
+            Self::SetAudioProgress(_)
+            | Self::SeekPercentDelta(_)
+            | Self::SeekToSeconds(_)
+            | Self::PauseQueue
+            | Self::UnPauseQueue
+            | Self::PlayNext
+            | Self::PlayPrevious
+            | Self::PlaySelected(_)
+            | Self::ReplayCurrent
+            | Self::ReplayPrevious
+            | Self::SeekBookmark(_)
+            | Self::PlayTestTone(_)
+            | Self::Announce(_) => true,
+            Self::AddQueueItem(_)
+            | Self::AddQueueItems(_)
+            | Self::RemoveQueueItem(_)
+            | Self::MoveQueueItem(_)
+            | Self::ShuffleQueue
+            | Self::ShuffleQueueSmart
+            | Self::ClearQueue
+            | Self::SetAudioVolume(_)
+            | Self::Stop
+            | Self::AddBookmark(_)
+            | Self::SetItemGain(_)
+            | Self::ResetBufferStats
+            | Self::ReconnectDevice
+            | Self::SetAutoAdvance(_)
+            | Self::SetResumePlayback(_)
+            | Self::SetEqualizer(_)
+            | Self::SetPauseOnDeviceLoss(_)
+            | Self::SetQueueLabel(_)
+            | Self::SetFadeIn(_)
+            | Self::SetItemFadeIn(_)
+            | Self::SetRepeatMode(_)
+            | Self::SetCrossfade(_)
+            | Self::SetNormalizationEnabled(_) => false,
+        }
+    }
+
+    /// whether the command changes the queue, writes to the database or rebuilds the audio
+    /// device
+    ///
+    /// Heavy commands are rate limited tighter than commands that only control playback, like
+    /// seeking or changing the volume.
+    pub fn is_heavy(&self) -> bool {
+        match self {
+            Self::AddQueueItem(_)
+            | Self::AddQueueItems(_)
+            | Self::RemoveQueueItem(_)
+            | Self::MoveQueueItem(_)
+            | Self::ShuffleQueue
+            | Self::ShuffleQueueSmart
+            | Self::ClearQueue
+            | Self::AddBookmark(_)
+            | Self::SetItemGain(_)
+            | Self::ReconnectDevice
+            | Self::PlayTestTone(_)
+            | Self::Announce(_) => true,
+            Self::SetAudioVolume(_)
+            | Self::SetAudioProgress(_)
+            | Self::SeekPercentDelta(_)
+            | Self::SeekToSeconds(_)
+            | Self::PauseQueue
+            | Self::UnPauseQueue
+            | Self::Stop
+            | Self::PlayNext
+            | Self::PlayPrevious
+            | Self::PlaySelected(_)
+            | Self::ReplayCurrent
+            | Self::ReplayPrevious
+            | Self::SeekBookmark(_)
+            | Self::ResetBufferStats
+            | Self::SetAutoAdvance(_)
+            | Self::SetResumePlayback(_)
+            | Self::SetEqualizer(_)
+            | Self::SetPauseOnDeviceLoss(_)
+            | Self::SetQueueLabel(_)
+            | Self::SetFadeIn(_)
+            | Self::SetItemFadeIn(_)
+            | Self::SetRepeatMode(_)
+            | Self::SetCrossfade(_)
+            | Self::SetNormalizationEnabled(_) => false,
+        }
+    }
+
+    /// whether the command is accepted by a node with poor health, so the queue can be edited and
+    /// the device reconnected until the node is healthy again
+    pub fn allowed_with_poor_health(&self) -> bool {
+        matches!(
+            self,
+            Self::AddQueueItem(_)
+                | Self::AddQueueItems(_)
+                | Self::RemoveQueueItem(_)
+                | Self::MoveQueueItem(_)
+                | Self::ShuffleQueue
+                | Self::ShuffleQueueSmart
+                | Self::ClearQueue
+                | Self::ReconnectDevice
+        )
+    }
+
+    /// rejects commands that need the audio device while it is unavailable, instead of failing
+    /// with an error of the audio backend
+    pub fn ensure_device_available(
+        &self,
+        health: &AudioNodeHealth,
+        source_name: &str,
+    ) -> Result<(), AppError> {
+        if !self.needs_audio_device() || !health.device_unavailable() {
+            return Ok(());
+        }
+
+        Err(AppError::new(
+            AppErrorKind::Queue,
+            "audio device unavailable, reconnect it first",
+            &[
+                &format!("NODE_NAME: {source_name}"),
+                &format!("HEALTH: {health:?}"),
+            ],
+        ))
+    }
+}
+
+/// Commands that change how a node may be used, only accepted with the admin token
+///
+/// # Example commands
+///
+/// { "SET_MAX_VOLUME": { "maxVolume": 0.4 } }
+///
+#[derive(Debug, Clone, Serialize, TS, Deserialize, Message)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[ts(export, export_to = "../app/src/api-types/")]
+#[rtype(result = "Result<(), AppError>")]
+pub enum AudioNodeAdminCommand {
+    SetMaxVolume(SetMaxVolumeParams),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[serde(rename_all = "kebab-case")]
 #[ts(export, export_to = "../app/src/api-types/")]
 pub enum AudioIdentifier {
-    Local { uid: Arc<str> },
-    Youtube { url: Arc<str> },
+    Local {
+        uid: Arc<str>,
+    },
+    Youtube {
+        url: Arc<str>,
+    },
+    #[serde(rename = "soundcloud")]
+    SoundCloud {
+        url: Arc<str>,
+    },
+    /// audio file in the local import directory that isn't stored yet, it is copied into the audio
+    /// directory
+    LocalFile {
+        path: PathBuf,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, TS, Deserialize)]
@@ -48,6 +281,25 @@ pub enum AudioIdentifier {
 #[ts(export, export_to = "../app/src/api-types/")]
 pub struct AddQueueItemParams {
     pub identifier: AudioIdentifier,
+    /// load the item without playing it if it is the first item of the queue, also applies if the
+    /// item has to be downloaded first
+    #[serde(default)]
+    pub start_paused: bool,
+    /// subfolder of the audio directory new downloads are stored in, e.g. `artist/album`, audio
+    /// that is already stored keeps its location
+    #[serde(default)]
+    pub folder: Option<Arc<str>>,
+    /// chosen by the client, repeating a request with the same key within a minute doesn't add the
+    /// item again, e.g. when retrying after a timeout
+    #[serde(default)]
+    pub idempotency_key: Option<Arc<str>>,
+    /// index in the queue the item is inserted at, e.g. the index after the current item to play it
+    /// next, the item is appended if not set or past the end of the queue
+    ///
+    /// Tracks of a playlist are inserted one after another, tracks of a playlist that have to be
+    /// downloaded first are appended.
+    #[serde(default)]
+    pub position: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, TS, Deserialize)]
@@ -79,6 +331,62 @@ pub struct SetAudioVolumeParams {
     pub volume: f32,
 }
 
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetAutoAdvanceParams {
+    pub auto_advance: bool,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetResumePlaybackParams {
+    pub resume_playback: bool,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetPauseOnDeviceLossParams {
+    pub pause_on_device_loss: bool,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetQueueLabelParams {
+    /// at most [`MAX_QUEUE_LABEL_LEN`](crate::node::node_server::MAX_QUEUE_LABEL_LEN) characters
+    #[serde(default)]
+    pub label: Option<Arc<str>>,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct PlayTestToneParams {
+    pub channel: Channel,
+    /// limited to [`MAX_TEST_TONE_SECS`](crate::audio_playback::test_tone::MAX_TEST_TONE_SECS)
+    pub seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetEqualizerParams {
+    /// gains from the lowest to the highest band, at most
+    /// [`MAX_EQ_BANDS`](crate::audio_playback::equalizer::MAX_EQ_BANDS) bands limited to
+    /// ±[`MAX_EQ_GAIN_DB`](crate::audio_playback::equalizer::MAX_EQ_GAIN_DB)
+    pub bands: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetMaxVolumeParams {
+    pub max_volume: f32,
+}
+
 #[derive(Debug, Clone, Serialize, TS, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[ts(export, export_to = "../app/src/api-types/")]
@@ -86,11 +394,171 @@ pub struct SetAudioProgressParams {
     pub progress: f64,
 }
 
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SeekPercentDeltaParams {
+    pub delta: f64,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SeekSecondsParams {
+    pub seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct AddBookmarkParams {
+    pub label: Arc<str>,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SeekBookmarkParams {
+    pub id: i32,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetItemGainParams {
+    pub index: usize,
+    pub gain_db: f32,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetFadeInParams {
+    /// at most [`MAX_FADE_IN_MS`](crate::audio_playback::audio_player::MAX_FADE_IN_MS), `0`
+    /// disables the fade
+    pub fade_in_ms: u32,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetItemFadeInParams {
+    pub index: usize,
+    /// `None` uses the fade in of the node again
+    #[serde(default)]
+    pub fade_in_ms: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetRepeatModeParams {
+    pub repeat_mode: RepeatMode,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetNormalizationEnabledParams {
+    pub normalization_enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SetCrossfadeParams {
+    /// at most [`MAX_CROSSFADE_MS`](crate::audio_playback::audio_player::MAX_CROSSFADE_MS), `0`
+    /// plays tracks back to back
+    pub crossfade_ms: u32,
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct AnnounceParams {
+    /// has to be stored already or be a local file that can be imported, announcements are never
+    /// downloaded
+    pub identifier: AudioIdentifier,
+}
+
 #[post("/commands/node/{source_name}")]
 pub async fn receive_node_cmd(
+    req: HttpRequest,
     source_name: web::Path<SourceName>,
     cmd: web::Json<AudioNodeCommand>,
 ) -> HttpResponse {
+    let source_name = source_name.into_inner();
+
+    if let Err(err) = check_command_rate_limit(&req, &cmd) {
+        return error_response(&err);
+    }
+
+    let Some(node_addr) = find_command_node(&cmd, source_name, brain_addr()).await else {
+        return HttpResponse::new(StatusCode::NOT_FOUND);
+    };
+
+    match node_addr.send(cmd.into_inner()).await {
+        Ok(res) => match res {
+            Ok(()) => HttpResponse::new(StatusCode::OK),
+            Err(err) => error_response(&err),
+        },
+        Err(err) => error_response(&err.into_app_err(
+            "failed to send command to node",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+/// Node the command is sent to, nodes with poor health are only found for the commands of
+/// [`AudioNodeCommand::allowed_with_poor_health`]
+async fn find_command_node<B>(
+    cmd: &AudioNodeCommand,
+    source_name: SourceName,
+    brain: &Addr<B>,
+) -> Option<Addr<AudioNode>>
+where
+    B: Actor<Context = Context<B>> + Handler<GetAudioNodeMessage> + Handler<GetAllAudioNodes>,
+{
+    if cmd.allowed_with_poor_health() {
+        get_node_by_source_name_any_health(source_name, brain).await
+    } else {
+        get_node_by_source_name(source_name, brain).await
+    }
+}
+
+/// commands of clients without a known address are never limited
+fn check_command_rate_limit(req: &HttpRequest, cmd: &AudioNodeCommand) -> Result<(), AppError> {
+    let (Some(limiter), Some(addr)) = (COMMAND_RATE_LIMITER.get(), req.peer_addr()) else {
+        return Ok(());
+    };
+
+    let mut limiter = limiter.lock().unwrap_or_else(|err| err.into_inner());
+    if limiter.try_acquire(addr.ip(), cmd, Instant::now()) {
+        return Ok(());
+    }
+
+    Err(AppError::new(
+        AppErrorKind::RateLimited,
+        "too many commands, slow down",
+        &[
+            &format!("CLIENT: {ip}", ip = addr.ip()),
+            &format!("HEAVY: {heavy}", heavy = cmd.is_heavy()),
+        ],
+    )
+    .too_many_requests())
+}
+
+#[post("/commands/node/{source_name}/admin")]
+pub async fn receive_node_admin_cmd(
+    req: HttpRequest,
+    source_name: web::Path<SourceName>,
+    cmd: web::Json<AudioNodeAdminCommand>,
+) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
     let node_addr = match get_node_by_source_name(source_name.into_inner(), brain_addr()).await {
         Some(addr) => addr,
         None => {
@@ -101,10 +569,200 @@ pub async fn receive_node_cmd(
     match node_addr.send(cmd.into_inner()).await {
         Ok(res) => match res {
             Ok(()) => HttpResponse::new(StatusCode::OK),
-            Err(err) => HttpResponse::InternalServerError().body(
-                serde_json::to_string(&err).unwrap_or("oops something went wrong".to_owned()),
-            ),
+            Err(err) => error_response(&err),
         },
-        Err(_) => HttpResponse::new(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to send command to node",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct AddNodeParams {
+    pub human_readable_name: String,
+    /// name of the output device, also used as the source name of the node
+    pub device_name: SourceName,
+}
+
+/// Starts a node on an output device until the next start, only accepted with the admin token
+///
+/// Responds with the output config of the device.
+#[post("/commands/nodes")]
+pub async fn add_node(req: HttpRequest, params: web::Json<AddNodeParams>) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
+    let AddNodeParams {
+        human_readable_name,
+        device_name,
+    } = params.into_inner();
+
+    match brain_addr()
+        .send(AddAudioNodeMessage {
+            human_readable_name,
+            device_name,
+        })
+        .await
+    {
+        Ok(Ok(output_config)) => json_response(StatusCode::OK, &output_config),
+        Ok(Err(err)) => error_response(&err),
+        Err(err) => error_response(&err.into_app_err("failed to add node", AppErrorKind::Api, &[])),
+    }
+}
+
+/// Stops the node and releases its audio device until the next start, only accepted with the admin
+/// token
+#[delete("/commands/node/{source_name}")]
+pub async fn remove_node(req: HttpRequest, source_name: web::Path<SourceName>) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
+    match brain_addr()
+        .send(RemoveAudioNodeMessage {
+            source_name: source_name.into_inner(),
+        })
+        .await
+    {
+        Ok(Ok(())) => HttpResponse::new(StatusCode::OK),
+        Ok(Err(err)) => error_response(&err),
+        Err(err) => {
+            error_response(&err.into_app_err("failed to remove node", AppErrorKind::Api, &[]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::health::AudioNodeHealthPoor;
+    use actix::MessageResult;
+
+    /// answers node lookups like the brain, nodes with poor health are only part of
+    /// `GetAllAudioNodes`
+    struct TestBrain {
+        nodes: Vec<(SourceName, Addr<AudioNode>, AudioNodeHealth)>,
+    }
+
+    impl Actor for TestBrain {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<GetAudioNodeMessage> for TestBrain {
+        type Result = Option<Addr<AudioNode>>;
+
+        fn handle(&mut self, msg: GetAudioNodeMessage, _ctx: &mut Self::Context) -> Self::Result {
+            self.nodes
+                .iter()
+                .find(|(name, _, health)| {
+                    *name == msg.source_name && !matches!(health, AudioNodeHealth::Poor(_))
+                })
+                .map(|(_, addr, _)| addr.clone())
+        }
+    }
+
+    impl Handler<GetAllAudioNodes> for TestBrain {
+        type Result = MessageResult<GetAllAudioNodes>;
+
+        fn handle(&mut self, _msg: GetAllAudioNodes, _ctx: &mut Self::Context) -> Self::Result {
+            MessageResult(
+                self.nodes
+                    .iter()
+                    .map(|(name, addr, _)| (name.clone(), addr.clone()))
+                    .collect(),
+            )
+        }
+    }
+
+    fn add_queue_item_params() -> AddQueueItemParams {
+        AddQueueItemParams {
+            identifier: AudioIdentifier::Local { uid: "uid".into() },
+            start_paused: false,
+            folder: None,
+            idempotency_key: None,
+            position: None,
+        }
+    }
+
+    #[test]
+    fn test_playback_commands_need_device() {
+        let lost = AudioNodeHealth::Poor(AudioNodeHealthPoor::DeviceNotAvailable);
+        let given_up = AudioNodeHealth::Poor(AudioNodeHealthPoor::PermanentlyUnavailable);
+
+        for cmd in [
+            AudioNodeCommand::PlayNext,
+            AudioNodeCommand::UnPauseQueue,
+            AudioNodeCommand::PlaySelected(PlaySelectedParams { index: 0 }),
+        ] {
+            assert!(cmd.ensure_device_available(&lost, "node").is_err());
+            assert!(cmd.ensure_device_available(&given_up, "node").is_err());
+            assert!(cmd
+                .ensure_device_available(&AudioNodeHealth::Good, "node")
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn test_queue_commands_work_without_device() {
+        let lost = AudioNodeHealth::Poor(AudioNodeHealthPoor::DeviceNotAvailable);
+
+        for cmd in [
+            AudioNodeCommand::AddQueueItem(add_queue_item_params()),
+            AudioNodeCommand::AddQueueItems(vec![add_queue_item_params(); 2]),
+            AudioNodeCommand::RemoveQueueItem(RemoveQueueItemParams { index: 0 }),
+            AudioNodeCommand::MoveQueueItem(MoveQueueItemParams {
+                old_pos: 0,
+                new_pos: 1,
+            }),
+            AudioNodeCommand::ClearQueue,
+            AudioNodeCommand::ReconnectDevice,
+        ] {
+            assert!(cmd.ensure_device_available(&lost, "node").is_ok());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_poor_nodes_only_receive_queue_commands() {
+        let node = Context::<AudioNode>::new().address();
+        let brain = TestBrain {
+            nodes: vec![
+                ("good".into(), node.clone(), AudioNodeHealth::Good),
+                (
+                    "read-failed".into(),
+                    node.clone(),
+                    AudioNodeHealth::Poor(AudioNodeHealthPoor::AudioStreamReadFailed),
+                ),
+                (
+                    "lost".into(),
+                    node,
+                    AudioNodeHealth::Poor(AudioNodeHealthPoor::DeviceNotAvailable),
+                ),
+            ],
+        }
+        .start();
+
+        let play = AudioNodeCommand::PlayNext;
+        let clear = AudioNodeCommand::ClearQueue;
+
+        for name in ["read-failed", "lost"] {
+            assert!(find_command_node(&play, name.into(), &brain)
+                .await
+                .is_none());
+            assert!(find_command_node(&clear, name.into(), &brain)
+                .await
+                .is_some());
+        }
+
+        assert!(find_command_node(&play, "good".into(), &brain)
+            .await
+            .is_some());
+        assert!(find_command_node(&clear, "unknown".into(), &brain)
+            .await
+            .is_none());
     }
 }
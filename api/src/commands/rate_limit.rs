@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use super::node_commands::AudioNodeCommand;
+
+/// commands per second a client can send to nodes, set through `NODE_COMMAND_RATE_LIMIT`
+pub const DEFAULT_COMMAND_RATE_LIMIT: f64 = 20.0;
+
+/// heavy commands per second a client can send to nodes, see [`AudioNodeCommand::is_heavy`], set
+/// through `NODE_HEAVY_COMMAND_RATE_LIMIT`
+pub const DEFAULT_HEAVY_COMMAND_RATE_LIMIT: f64 = 5.0;
+
+/// how long a client is remembered after its last command
+const CLIENT_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandRateLimits {
+    pub commands_per_sec: f64,
+    pub heavy_commands_per_sec: f64,
+}
+
+impl Default for CommandRateLimits {
+    fn default() -> Self {
+        Self {
+            commands_per_sec: DEFAULT_COMMAND_RATE_LIMIT,
+            heavy_commands_per_sec: DEFAULT_HEAVY_COMMAND_RATE_LIMIT,
+        }
+    }
+}
+
+/// Refills at `rate` tokens per second up to a burst of one second worth of tokens
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    rate: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn full(rate: f64, now: Instant) -> Self {
+        Self {
+            tokens: Self::capacity(rate),
+            rate,
+            updated_at: now,
+        }
+    }
+
+    fn capacity(rate: f64) -> f64 {
+        rate.max(1.0)
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * self.rate).min(Self::capacity(self.rate));
+        self.updated_at = now;
+    }
+
+    fn has_token(&self) -> bool {
+        self.tokens >= 1.0
+    }
+}
+
+#[derive(Debug)]
+struct ClientBuckets {
+    commands: TokenBucket,
+    heavy_commands: TokenBucket,
+}
+
+/// Limits the commands every client can send to nodes, so a misbehaving client can't fill the
+/// mailboxes of the nodes
+///
+/// Inbound counterpart of the [`RateLimiter`](crate::message_send_handler::RateLimiter) of
+/// messages sent to clients. Heavy commands count towards both limits.
+#[derive(Debug)]
+pub struct CommandRateLimiter {
+    limits: CommandRateLimits,
+    clients: HashMap<IpAddr, ClientBuckets>,
+}
+
+impl CommandRateLimiter {
+    pub fn new(limits: CommandRateLimits) -> Self {
+        Self {
+            limits,
+            clients: HashMap::default(),
+        }
+    }
+
+    /// takes a token from the buckets of `client`, returns false if the client sent too many
+    /// commands
+    pub fn try_acquire(&mut self, client: IpAddr, cmd: &AudioNodeCommand, now: Instant) -> bool {
+        self.clients.retain(|_, buckets| {
+            now.saturating_duration_since(buckets.commands.updated_at) < CLIENT_TTL
+        });
+
+        let limits = self.limits;
+        let buckets = self.clients.entry(client).or_insert_with(|| ClientBuckets {
+            commands: TokenBucket::full(limits.commands_per_sec, now),
+            heavy_commands: TokenBucket::full(limits.heavy_commands_per_sec, now),
+        });
+
+        buckets.commands.refill(now);
+        buckets.heavy_commands.refill(now);
+
+        let is_heavy = cmd.is_heavy();
+        if !buckets.commands.has_token() || (is_heavy && !buckets.heavy_commands.has_token()) {
+            return false;
+        }
+
+        buckets.commands.tokens -= 1.0;
+        if is_heavy {
+            buckets.heavy_commands.tokens -= 1.0;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use crate::commands::node_commands::SetAudioProgressParams;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 10));
+    const OTHER_CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 11));
+
+    fn limiter() -> CommandRateLimiter {
+        CommandRateLimiter::new(CommandRateLimits {
+            commands_per_sec: 4.0,
+            heavy_commands_per_sec: 1.0,
+        })
+    }
+
+    fn seek() -> AudioNodeCommand {
+        AudioNodeCommand::SetAudioProgress(SetAudioProgressParams { progress: 0.5 })
+    }
+
+    #[test]
+    fn test_commands_are_limited_per_client() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+
+        let accepted = (0..6)
+            .filter(|_| limiter.try_acquire(CLIENT, &seek(), start))
+            .count();
+        assert_eq!(accepted, 4);
+
+        assert!(limiter.try_acquire(OTHER_CLIENT, &seek(), start));
+
+        // the bucket refills over time
+        let later = start + Duration::from_millis(500);
+        let accepted = (0..6)
+            .filter(|_| limiter.try_acquire(CLIENT, &seek(), later))
+            .count();
+        assert_eq!(accepted, 2);
+    }
+
+    #[test]
+    fn test_heavy_commands_are_limited_tighter() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire(CLIENT, &AudioNodeCommand::ShuffleQueue, start));
+        assert!(!limiter.try_acquire(CLIENT, &AudioNodeCommand::ShuffleQueue, start));
+
+        // light commands are not affected by the heavy limit
+        assert!(limiter.try_acquire(CLIENT, &seek(), start));
+        assert!(limiter.try_acquire(CLIENT, &AudioNodeCommand::PauseQueue, start));
+
+        assert!(limiter.try_acquire(
+            CLIENT,
+            &AudioNodeCommand::ShuffleQueue,
+            start + Duration::from_secs(1)
+        ));
+    }
+
+    #[test]
+    fn test_idle_clients_are_forgotten() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+
+        limiter.try_acquire(CLIENT, &seek(), start);
+        limiter.try_acquire(OTHER_CLIENT, &seek(), start + CLIENT_TTL);
+
+        assert_eq!(limiter.clients.len(), 1);
+    }
+}
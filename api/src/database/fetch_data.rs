@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     audio_playback::audio_item::AudioMetadata,
@@ -8,7 +8,7 @@ use crate::{
     opt_arc::OptionArcStr,
 };
 
-use super::PlaylistMetadata;
+use super::{AudioBookmark, LibraryTotals, PlayHistoryEntry, PlaylistMetadata, SortBy, SortOrder};
 
 struct AudioQueryResult {
     identifier: Arc<str>,
@@ -16,6 +16,17 @@ struct AudioQueryResult {
     author: OptionArcStr,
     duration: Option<i64>,
     cover_art_url: OptionArcStr,
+    gain_db: Option<f32>,
+}
+
+struct LibraryAudioQueryResult {
+    identifier: Arc<str>,
+    name: OptionArcStr,
+    author: OptionArcStr,
+    duration: Option<i64>,
+    cover_art_url: OptionArcStr,
+    gain_db: Option<f32>,
+    favorite: bool,
 }
 
 struct PlaylistQueryResult {
@@ -25,6 +36,17 @@ struct PlaylistQueryResult {
     cover_art_url: OptionArcStr,
 }
 
+struct PlayHistoryQueryResult {
+    identifier: Arc<str>,
+    source_name: Arc<str>,
+    played_at_ms: i64,
+    name: OptionArcStr,
+    author: OptionArcStr,
+    duration: Option<i64>,
+    cover_art_url: OptionArcStr,
+    gain_db: Option<f32>,
+}
+
 impl From<AudioQueryResult> for (ItemUid<Arc<str>>, AudioMetadata) {
     fn from(value: AudioQueryResult) -> Self {
         (
@@ -34,11 +56,45 @@ impl From<AudioQueryResult> for (ItemUid<Arc<str>>, AudioMetadata) {
                 author: value.author,
                 duration: value.duration,
                 cover_art_url: value.cover_art_url,
+                gain_db: value.gain_db,
             },
         )
     }
 }
 
+impl From<LibraryAudioQueryResult> for (ItemUid<Arc<str>>, AudioMetadata, bool) {
+    fn from(value: LibraryAudioQueryResult) -> Self {
+        (
+            ItemUid(value.identifier),
+            AudioMetadata {
+                name: value.name,
+                author: value.author,
+                duration: value.duration,
+                cover_art_url: value.cover_art_url,
+                gain_db: value.gain_db,
+            },
+            value.favorite,
+        )
+    }
+}
+
+impl From<PlayHistoryQueryResult> for PlayHistoryEntry {
+    fn from(value: PlayHistoryQueryResult) -> Self {
+        PlayHistoryEntry {
+            uid: value.identifier,
+            source_name: value.source_name,
+            played_at_ms: value.played_at_ms,
+            metadata: AudioMetadata {
+                name: value.name,
+                author: value.author,
+                duration: value.duration,
+                cover_art_url: value.cover_art_url,
+                gain_db: value.gain_db,
+            },
+        }
+    }
+}
+
 impl From<PlaylistQueryResult> for (ItemUid<Arc<str>>, PlaylistMetadata) {
     fn from(value: PlaylistQueryResult) -> Self {
         (
@@ -60,7 +116,7 @@ pub async fn get_audio_metadata_from_db<T: AsRef<str> + std::fmt::Debug>(
     async fn inner(uid: &str) -> Result<Option<AudioMetadata>, AppError> {
         sqlx::query_as!(
         AudioMetadata,
-        "SELECT name, author, duration, cover_art_url FROM audio_metadata where identifier = $1",
+        "SELECT name, author, duration, cover_art_url, gain_db FROM audio_metadata where identifier = $1",
         uid
     )
         .fetch_optional(db_pool())
@@ -75,19 +131,69 @@ pub async fn get_audio_metadata_from_db<T: AsRef<str> + std::fmt::Debug>(
     inner(uid).await
 }
 
+/// metadata of all `uids` that are stored in a single query, uids without stored metadata are
+/// missing from the result
+pub async fn get_many_audio_metadata_from_db(
+    uids: &[ItemUid<Arc<str>>],
+) -> Result<HashMap<Arc<str>, AudioMetadata>, AppError> {
+    let uids: Vec<String> = uids.iter().map(|uid| uid.0.to_string()).collect();
+
+    sqlx::query_as!(
+        AudioQueryResult,
+        "SELECT identifier, name, author, duration, cover_art_url, gain_db FROM audio_metadata
+        WHERE identifier = ANY($1)",
+        &uids
+    )
+    .fetch_all(db_pool())
+    .await
+    .map(|rows| {
+        rows.into_iter()
+            .map(|row| {
+                let (uid, metadata): (ItemUid<Arc<str>>, AudioMetadata) = row.into();
+                (uid.0, metadata)
+            })
+            .collect()
+    })
+    .into_app_err(
+        "failed to get audio metadata",
+        AppErrorKind::Database,
+        &[&format!("UIDS: {uids:?}")],
+    )
+}
+
+/// Items without a value for the sorted column come last, ties are ordered by uid, every item
+/// includes whether it is marked as favorite
 pub async fn get_all_audio_metadata_from_db(
     limit: Option<i64>,
     offset: Option<i64>,
-) -> Result<Arc<[(ItemUid<Arc<str>>, AudioMetadata)]>, AppError> {
+    sort_by: SortBy,
+    order: SortOrder,
+    favorites_only: bool,
+) -> Result<Arc<[(ItemUid<Arc<str>>, AudioMetadata, bool)]>, AppError> {
     let limit = limit.unwrap_or(50);
     let offset = offset.unwrap_or(0);
 
     sqlx::query_as!(
-        AudioQueryResult,
-        "SELECT identifier, name, author, duration, cover_art_url FROM audio_metadata
+        LibraryAudioQueryResult,
+        "SELECT identifier, name, author, duration, cover_art_url, gain_db, favorite
+        FROM audio_metadata
+        WHERE favorite OR NOT $5
+        ORDER BY
+            CASE WHEN $3 = 'name' AND NOT $4 THEN name END ASC NULLS LAST,
+            CASE WHEN $3 = 'name' AND $4 THEN name END DESC NULLS LAST,
+            CASE WHEN $3 = 'author' AND NOT $4 THEN author END ASC NULLS LAST,
+            CASE WHEN $3 = 'author' AND $4 THEN author END DESC NULLS LAST,
+            CASE WHEN $3 = 'duration' AND NOT $4 THEN duration END ASC NULLS LAST,
+            CASE WHEN $3 = 'duration' AND $4 THEN duration END DESC NULLS LAST,
+            CASE WHEN $3 = 'created_at' AND NOT $4 THEN created_at END ASC,
+            CASE WHEN $3 = 'created_at' AND $4 THEN created_at END DESC,
+            identifier
         LIMIT $1 OFFSET $2",
         limit,
-        offset
+        offset,
+        sort_by.as_str(),
+        order.is_desc(),
+        favorites_only,
     )
     .fetch_all(db_pool())
     .await
@@ -95,23 +201,112 @@ pub async fn get_all_audio_metadata_from_db(
     .into_app_err(
         "failed to get all audio metdata from db",
         AppErrorKind::Database,
-        &[&format!("LIMIT: {limit}"), &format!("OFFSET: {offset}")],
+        &[
+            &format!("LIMIT: {limit}"),
+            &format!("OFFSET: {offset}"),
+            &format!("SORT_BY: {sort_by:?}"),
+            &format!("ORDER: {order:?}"),
+            &format!("FAVORITES_ONLY: {favorites_only}"),
+        ],
+    )
+}
+
+/// number of stored tracks, the total of [`get_all_audio_metadata_from_db`]
+pub async fn count_audio_metadata_in_db(favorites_only: bool) -> Result<i64, AppError> {
+    sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM audio_metadata WHERE favorite OR NOT $1"#,
+        favorites_only,
+    )
+    .fetch_one(db_pool())
+    .await
+    .into_app_err(
+        "failed to count audio metadata",
+        AppErrorKind::Database,
+        &[&format!("FAVORITES_ONLY: {favorites_only}")],
     )
 }
 
+pub async fn get_all_audio_uids_from_db() -> Result<Arc<[ItemUid<Arc<str>>]>, AppError> {
+    sqlx::query!("SELECT identifier FROM audio_metadata")
+        .fetch_all(db_pool())
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| ItemUid(row.identifier.into()))
+                .collect()
+        })
+        .into_app_err(
+            "failed to get all audio uids from db",
+            AppErrorKind::Database,
+            &[],
+        )
+}
+
+/// the uids of `uids` that have stored metadata
+pub async fn get_existing_audio_uids_from_db(
+    uids: &[ItemUid<Arc<str>>],
+) -> Result<Vec<ItemUid<Arc<str>>>, AppError> {
+    let uids: Vec<String> = uids.iter().map(|uid| uid.0.to_string()).collect();
+
+    sqlx::query!(
+        "SELECT identifier FROM audio_metadata WHERE identifier = ANY($1)",
+        &uids
+    )
+    .fetch_all(db_pool())
+    .await
+    .map(|rows| {
+        rows.into_iter()
+            .map(|row| ItemUid(row.identifier.into()))
+            .collect()
+    })
+    .into_app_err(
+        "failed to get existing audio uids from db",
+        AppErrorKind::Database,
+        &[&format!("UIDS: {uids:?}")],
+    )
+}
+
+/// number of stored playlists, the total of [`get_all_playlist_metadata_from_db`]
+pub async fn count_playlists_in_db() -> Result<i64, AppError> {
+    sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM audio_playlist"#)
+        .fetch_one(db_pool())
+        .await
+        .into_app_err("failed to count playlists", AppErrorKind::Database, &[])
+}
+
+/// Playlists without a value for the sorted column come last, ties are ordered by uid
 pub async fn get_all_playlist_metadata_from_db(
     limit: Option<i64>,
     offset: Option<i64>,
+    sort_by: SortBy,
+    order: SortOrder,
 ) -> Result<Arc<[(ItemUid<Arc<str>>, PlaylistMetadata)]>, AppError> {
     let limit = limit.unwrap_or(50);
     let offset = offset.unwrap_or(0);
 
     sqlx::query_as!(
         PlaylistQueryResult,
-        "SELECT identifier, name, author, cover_art_url FROM audio_playlist
+        "SELECT identifier, name, author, cover_art_url FROM audio_playlist playlist
+        ORDER BY
+            CASE WHEN $3 = 'name' AND NOT $4 THEN name END ASC NULLS LAST,
+            CASE WHEN $3 = 'name' AND $4 THEN name END DESC NULLS LAST,
+            CASE WHEN $3 = 'author' AND NOT $4 THEN author END ASC NULLS LAST,
+            CASE WHEN $3 = 'author' AND $4 THEN author END DESC NULLS LAST,
+            CASE WHEN $3 = 'duration' THEN (
+                SELECT COALESCE(SUM(audio.duration), 0) * CASE WHEN $4 THEN -1 ELSE 1 END
+                FROM audio_playlist_item items
+                    INNER JOIN audio_metadata audio
+                    ON audio.identifier = items.item_identifier
+                WHERE items.playlist_identifier = playlist.identifier
+            ) END ASC,
+            CASE WHEN $3 = 'created_at' AND NOT $4 THEN created_at END ASC,
+            CASE WHEN $3 = 'created_at' AND $4 THEN created_at END DESC,
+            identifier
         LIMIT $1 OFFSET $2",
         limit,
         offset,
+        sort_by.as_str(),
+        order.is_desc(),
     )
     .fetch_all(db_pool())
     .await
@@ -119,14 +314,51 @@ pub async fn get_all_playlist_metadata_from_db(
     .into_app_err(
         "failed to get all playlist metdata",
         AppErrorKind::Database,
-        &[&format!("LIMIT: {limit}"), &format!("OFFSET: {offset}")],
+        &[
+            &format!("LIMIT: {limit}"),
+            &format!("OFFSET: {offset}"),
+            &format!("SORT_BY: {sort_by:?}"),
+            &format!("ORDER: {order:?}"),
+        ],
+    )
+}
+
+pub async fn playlist_exists_in_db(playlist_uid: &str) -> Result<bool, AppError> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM audio_playlist WHERE identifier = $1) AS "exists!""#,
+        playlist_uid
+    )
+    .fetch_one(db_pool())
+    .await
+    .into_app_err(
+        "failed to check if playlist exists",
+        AppErrorKind::Database,
+        &[&format!("PLAYLIST_UID: {playlist_uid}")],
     )
 }
 
+/// number of items in a playlist, the total of [`get_playlist_items_from_db`]
+pub async fn count_playlist_items_in_db(playlist_uid: &str) -> Result<i64, AppError> {
+    sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM audio_playlist_item WHERE playlist_identifier = $1"#,
+        playlist_uid
+    )
+    .fetch_one(db_pool())
+    .await
+    .into_app_err(
+        "failed to count playlist items",
+        AppErrorKind::Database,
+        &[&format!("PLAYLIST_UID: {playlist_uid}")],
+    )
+}
+
+/// Items are in playlist order unless `sort_by` is set
 pub async fn get_playlist_items_from_db<T: AsRef<str> + std::fmt::Debug>(
     playlist_uid: &ItemUid<T>,
     limit: Option<i64>,
     offset: Option<i64>,
+    sort_by: Option<SortBy>,
+    order: SortOrder,
 ) -> Result<Arc<[(ItemUid<Arc<str>>, AudioMetadata)]>, AppError> {
     let playlist_uid = playlist_uid.0.as_ref();
 
@@ -134,22 +366,36 @@ pub async fn get_playlist_items_from_db<T: AsRef<str> + std::fmt::Debug>(
         playlist_uid: &str,
         limit: Option<i64>,
         offset: Option<i64>,
+        sort_by: Option<SortBy>,
+        order: SortOrder,
     ) -> Result<Arc<[(ItemUid<Arc<str>>, AudioMetadata)]>, AppError> {
         let limit = limit.unwrap_or(50);
         let offset = offset.unwrap_or(0);
 
         sqlx::query_as!(
             AudioQueryResult,
-            "SELECT audio.identifier, audio.name, audio.author, audio.duration, audio.cover_art_url
+            "SELECT audio.identifier, audio.name, audio.author, audio.duration, audio.cover_art_url,
+                audio.gain_db
              FROM audio_metadata audio
                  INNER JOIN audio_playlist_item items 
                  ON audio.identifier = items.item_identifier
              WHERE items.playlist_identifier = $1
-             ORDER BY position
+             ORDER BY
+                CASE WHEN $4 = 'name' AND NOT $5 THEN audio.name END ASC NULLS LAST,
+                CASE WHEN $4 = 'name' AND $5 THEN audio.name END DESC NULLS LAST,
+                CASE WHEN $4 = 'author' AND NOT $5 THEN audio.author END ASC NULLS LAST,
+                CASE WHEN $4 = 'author' AND $5 THEN audio.author END DESC NULLS LAST,
+                CASE WHEN $4 = 'duration' AND NOT $5 THEN audio.duration END ASC NULLS LAST,
+                CASE WHEN $4 = 'duration' AND $5 THEN audio.duration END DESC NULLS LAST,
+                CASE WHEN $4 = 'created_at' AND NOT $5 THEN audio.created_at END ASC,
+                CASE WHEN $4 = 'created_at' AND $5 THEN audio.created_at END DESC,
+                position
              LIMIT $2 OFFSET $3",
             playlist_uid,
             limit,
             offset,
+            sort_by.map(SortBy::as_str),
+            order.is_desc(),
         )
         .fetch_all(db_pool())
         .await
@@ -161,11 +407,13 @@ pub async fn get_playlist_items_from_db<T: AsRef<str> + std::fmt::Debug>(
                 &format!("PLAYLIST_UID: {playlist_uid}"),
                 &format!("LIMIT: {limit}"),
                 &format!("OFFSET: {offset}"),
+                &format!("SORT_BY: {sort_by:?}"),
+                &format!("ORDER: {order:?}"),
             ],
         )
     }
 
-    inner(playlist_uid, limit, offset).await
+    inner(playlist_uid, limit, offset, sort_by, order).await
 }
 
 pub async fn get_next_position_item_for_playlist<T: AsRef<str> + std::fmt::Debug>(
@@ -196,3 +444,308 @@ pub async fn get_next_position_item_for_playlist<T: AsRef<str> + std::fmt::Debug
 
     inner(playlist_uid).await
 }
+
+pub async fn get_bookmarks_from_db<T: AsRef<str> + std::fmt::Debug>(
+    uid: &ItemUid<T>,
+) -> Result<Arc<[AudioBookmark]>, AppError> {
+    let uid = uid.0.as_ref();
+
+    async fn inner(uid: &str) -> Result<Arc<[AudioBookmark]>, AppError> {
+        sqlx::query_as!(
+            AudioBookmark,
+            "SELECT id, audio_identifier, label, position_secs FROM audio_bookmark
+            WHERE audio_identifier = $1
+            ORDER BY position_secs",
+            uid
+        )
+        .fetch_all(db_pool())
+        .await
+        .map(Into::into)
+        .into_app_err(
+            "failed to get bookmarks",
+            AppErrorKind::Database,
+            &[&format!("UID: {uid}")],
+        )
+    }
+
+    inner(uid).await
+}
+
+pub async fn get_bookmark_from_db(id: i32) -> Result<Option<AudioBookmark>, AppError> {
+    sqlx::query_as!(
+        AudioBookmark,
+        "SELECT id, audio_identifier, label, position_secs FROM audio_bookmark WHERE id = $1",
+        id
+    )
+    .fetch_optional(db_pool())
+    .await
+    .into_app_err(
+        "failed to get bookmark",
+        AppErrorKind::Database,
+        &[&format!("ID: {id}")],
+    )
+}
+
+pub async fn get_gain_overrides_from_db(
+    uids: &[ItemUid<Arc<str>>],
+) -> Result<HashMap<Arc<str>, f32>, AppError> {
+    let uids: Vec<String> = uids.iter().map(|uid| uid.0.to_string()).collect();
+
+    sqlx::query!(
+        "SELECT audio_identifier, gain_db FROM audio_gain_override
+        WHERE audio_identifier = ANY($1)",
+        &uids
+    )
+    .fetch_all(db_pool())
+    .await
+    .map(|rows| {
+        rows.into_iter()
+            .map(|row| (row.audio_identifier.into(), row.gain_db))
+            .collect()
+    })
+    .into_app_err(
+        "failed to get gain overrides",
+        AppErrorKind::Database,
+        &[&format!("UIDS: {uids:?}")],
+    )
+}
+
+pub async fn get_resume_position_from_db<T: AsRef<str> + std::fmt::Debug>(
+    uid: &ItemUid<T>,
+) -> Result<Option<f64>, AppError> {
+    let uid = uid.0.as_ref();
+
+    async fn inner(uid: &str) -> Result<Option<f64>, AppError> {
+        sqlx::query!(
+            "SELECT position_secs FROM audio_resume_position WHERE audio_identifier = $1",
+            uid
+        )
+        .fetch_optional(db_pool())
+        .await
+        .map(|row| row.map(|row| row.position_secs))
+        .into_app_err(
+            "failed to get resume position",
+            AppErrorKind::Database,
+            &[&format!("UID: {uid}")],
+        )
+    }
+
+    inner(uid).await
+}
+
+pub async fn get_library_totals_from_db() -> Result<LibraryTotals, AppError> {
+    sqlx::query_as!(
+        LibraryTotals,
+        r#"SELECT
+            (SELECT COUNT(*) FROM audio_metadata) AS "total_tracks!",
+            (SELECT COUNT(*) FROM audio_playlist) AS "total_playlists!",
+            (SELECT COALESCE(SUM(duration), 0)::bigint FROM audio_metadata) AS "total_duration!",
+            (SELECT author FROM audio_metadata
+                WHERE author IS NOT NULL
+                GROUP BY author
+                ORDER BY COUNT(*) DESC, author
+                LIMIT 1) AS most_common_author"#
+    )
+    .fetch_one(db_pool())
+    .await
+    .into_app_err("failed to get library totals", AppErrorKind::Database, &[])
+}
+
+/// Searches stored audio by name and author, ordered by relevance
+///
+/// Exact name matches come first, followed by name prefix matches, other name matches and finally
+/// author matches.
+pub async fn search_audio_metadata_from_db(
+    query: &str,
+    limit: Option<i64>,
+) -> Result<Arc<[(ItemUid<Arc<str>>, AudioMetadata)]>, AppError> {
+    let limit = limit.unwrap_or(50);
+    let pattern = escape_like_pattern(query.trim());
+
+    sqlx::query_as!(
+        AudioQueryResult,
+        r#"SELECT identifier, name, author, duration, cover_art_url, gain_db FROM audio_metadata
+        WHERE name ILIKE '%' || $1 || '%' OR author ILIKE '%' || $1 || '%'
+        ORDER BY
+            CASE
+                WHEN name ILIKE $1 THEN 0
+                WHEN name ILIKE $1 || '%' THEN 1
+                WHEN name ILIKE '%' || $1 || '%' THEN 2
+                ELSE 3
+            END,
+            name
+        LIMIT $2"#,
+        pattern,
+        limit,
+    )
+    .fetch_all(db_pool())
+    .await
+    .map(|vec| vec.into_iter().map(Into::into).collect())
+    .into_app_err(
+        "failed to search audio metadata",
+        AppErrorKind::Database,
+        &[&format!("QUERY: {query}"), &format!("LIMIT: {limit}")],
+    )
+}
+
+/// most plays that are returned at once
+pub const MAX_PLAY_HISTORY_LIMIT: i64 = 500;
+
+/// Most recent plays first, only the plays of `source_name` if it is set
+pub async fn get_play_history_from_db(
+    limit: Option<i64>,
+    offset: Option<i64>,
+    source_name: Option<&str>,
+) -> Result<Arc<[PlayHistoryEntry]>, AppError> {
+    let (limit, offset) = play_history_page(limit, offset);
+
+    sqlx::query_as!(
+        PlayHistoryQueryResult,
+        r#"SELECT
+            play_history.audio_identifier AS identifier,
+            play_history.source_name,
+            (EXTRACT(EPOCH FROM play_history.played_at) * 1000)::BIGINT AS "played_at_ms!",
+            name, author, duration, cover_art_url, gain_db
+        FROM play_history
+        JOIN audio_metadata ON audio_metadata.identifier = play_history.audio_identifier
+        WHERE $3::VARCHAR IS NULL OR play_history.source_name = $3
+        ORDER BY play_history.played_at DESC, play_history.id DESC
+        LIMIT $1 OFFSET $2"#,
+        limit,
+        offset,
+        source_name,
+    )
+    .fetch_all(db_pool())
+    .await
+    .map(|vec| vec.into_iter().map(Into::into).collect())
+    .into_app_err(
+        "failed to get play history from db",
+        AppErrorKind::Database,
+        &[
+            &format!("LIMIT: {limit}"),
+            &format!("OFFSET: {offset}"),
+            &format!("SOURCE_NAME: {source_name:?}"),
+        ],
+    )
+}
+
+/// number of recorded plays, the total of [`get_play_history_from_db`]
+pub async fn count_play_history_in_db(source_name: Option<&str>) -> Result<i64, AppError> {
+    sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM play_history
+        WHERE $1::VARCHAR IS NULL OR source_name = $1"#,
+        source_name,
+    )
+    .fetch_one(db_pool())
+    .await
+    .into_app_err(
+        "failed to count play history",
+        AppErrorKind::Database,
+        &[&format!("SOURCE_NAME: {source_name:?}")],
+    )
+}
+
+/// limit and offset of a page of the play history, the limit is capped at
+/// [`MAX_PLAY_HISTORY_LIMIT`] since the history grows with every play
+fn play_history_page(limit: Option<i64>, offset: Option<i64>) -> (i64, i64) {
+    (
+        limit.unwrap_or(50).clamp(0, MAX_PLAY_HISTORY_LIMIT),
+        offset.unwrap_or(0).max(0),
+    )
+}
+
+/// escapes the wildcard characters of a `LIKE` pattern so user input is matched literally
+fn escape_like_pattern(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::store_data::{
+            delete_audio_metadata, store_audio_metadata_if_not_exists, store_play,
+        },
+        tests_utils::test_db_pool,
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_escape_like_pattern() {
+        assert_eq!(escape_like_pattern("lo-fi"), "lo-fi");
+        assert_eq!(escape_like_pattern("100%_\\"), "100\\%\\_\\\\");
+    }
+
+    #[test]
+    fn test_play_history_page_is_capped() {
+        assert_eq!(play_history_page(None, None), (50, 0));
+        assert_eq!(play_history_page(Some(20), Some(40)), (20, 40));
+        assert_eq!(
+            play_history_page(Some(i64::MAX), None),
+            (MAX_PLAY_HISTORY_LIMIT, 0)
+        );
+        assert_eq!(play_history_page(Some(-1), Some(-1)), (0, 0));
+    }
+
+    #[actix_web::test]
+    async fn test_plays_are_read_back_most_recent_first() {
+        test_db_pool().await;
+
+        let uids = [
+            ItemUid(Arc::from("test_play_history_a")),
+            ItemUid(Arc::from("test_play_history_b")),
+        ];
+        // removes the plays of previous runs as well
+        delete_audio_metadata(&uids).await.unwrap();
+
+        for uid in &uids {
+            let metadata = AudioMetadata {
+                name: Some(uid.0.to_string()).into(),
+                author: Option::<String>::None.into(),
+                duration: Some(60),
+                cover_art_url: Option::<String>::None.into(),
+                gain_db: None,
+            };
+            store_audio_metadata_if_not_exists(uid, &metadata)
+                .await
+                .unwrap();
+        }
+
+        store_play(&uids[0], "test-history-node").await.unwrap();
+        store_play(&uids[1], "test-history-node").await.unwrap();
+        store_play(&uids[0], "test-history-other").await.unwrap();
+
+        let plays = get_play_history_from_db(None, None, Some("test-history-node"))
+            .await
+            .unwrap();
+        let played: Vec<_> = plays.iter().map(|play| &*play.uid).collect();
+        assert_eq!(played, vec!["test_play_history_b", "test_play_history_a"]);
+        assert_eq!(&*plays[0].source_name, "test-history-node");
+        assert_eq!(plays[0].metadata.duration, Some(60));
+
+        let plays = get_play_history_from_db(Some(1), Some(1), Some("test-history-node"))
+            .await
+            .unwrap();
+        assert_eq!(plays.len(), 1);
+        assert_eq!(&*plays[0].uid, "test_play_history_a");
+
+        assert_eq!(
+            count_play_history_in_db(Some("test-history-node"))
+                .await
+                .unwrap(),
+            2
+        );
+
+        delete_audio_metadata(&uids).await.unwrap();
+    }
+}
@@ -1,8 +1,10 @@
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use ts_rs::TS;
 
-use crate::opt_arc::OptionArcStr;
+use crate::{audio_playback::audio_item::AudioMetadata, opt_arc::OptionArcStr};
 
 pub mod fetch_data;
 pub mod store_data;
@@ -14,3 +16,96 @@ pub struct PlaylistMetadata {
     pub author: OptionArcStr,
     pub cover_art_url: OptionArcStr,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct AudioBookmark {
+    pub id: i32,
+    pub audio_identifier: Arc<str>,
+    pub label: Arc<str>,
+    pub position_secs: f64,
+}
+
+/// A track that was started on a node, see [`store_play`](store_data::store_play)
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct PlayHistoryEntry {
+    pub uid: Arc<str>,
+    pub source_name: Arc<str>,
+    /// unix timestamp in milliseconds
+    #[ts(type = "number")]
+    pub played_at_ms: i64,
+    pub metadata: AudioMetadata,
+}
+
+/// aggregates over the whole library
+#[derive(Debug, Clone, FromRow)]
+pub struct LibraryTotals {
+    pub total_tracks: i64,
+    pub total_playlists: i64,
+    /// sum of the duration of all tracks whose duration is known
+    pub total_duration: i64,
+    /// author with the most tracks, ties are broken alphabetically
+    pub most_common_author: OptionArcStr,
+}
+
+/// Columns the lists of stored audio and playlists can be sorted by, the duration of a playlist is
+/// the total duration of its items
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub enum SortBy {
+    #[default]
+    Name,
+    Author,
+    Duration,
+    CreatedAt,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortBy {
+    /// compared against in the `ORDER BY` clause of the list queries, the column itself is never
+    /// part of the query string
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Author => "author",
+            Self::Duration => "duration",
+            Self::CreatedAt => "created_at",
+        }
+    }
+}
+
+impl SortOrder {
+    pub fn is_desc(self) -> bool {
+        self == Self::Desc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_sort_params_allow_list() {
+        let sort_by: SortBy = serde_json::from_str(r#""created_at""#).unwrap();
+        assert_eq!(sort_by.as_str(), "created_at");
+
+        let order: SortOrder = serde_json::from_str(r#""desc""#).unwrap();
+        assert!(order.is_desc());
+
+        assert!(serde_json::from_str::<SortBy>(r#""name; DROP TABLE audio_metadata""#).is_err());
+        assert!(serde_json::from_str::<SortBy>(r#""gain_db""#).is_err());
+    }
+}
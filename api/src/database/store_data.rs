@@ -1,4 +1,7 @@
+use std::sync::Arc;
+
 use crate::{
+    audio_playback::audio_item::AudioMetadata,
     db_pool,
     downloader::download_identifier::ItemUid,
     error::{AppError, AppErrorKind, IntoAppError},
@@ -81,3 +84,211 @@ pub async fn store_playlist_item_relation_if_not_exists<T: AsRef<str> + std::fmt
 
     inner(position, playlist_uid, audio_uid).await
 }
+
+pub async fn store_bookmark<T: AsRef<str> + std::fmt::Debug>(
+    uid: &ItemUid<T>,
+    label: &str,
+    position_secs: f64,
+) -> Result<i32, AppError> {
+    let uid = uid.0.as_ref();
+
+    async fn inner(uid: &str, label: &str, position_secs: f64) -> Result<i32, AppError> {
+        struct Id {
+            id: i32,
+        }
+
+        sqlx::query_as!(
+            Id,
+            "INSERT INTO audio_bookmark
+        (audio_identifier, label, position_secs) VALUES ($1, $2, $3)
+        RETURNING id",
+            uid,
+            label,
+            position_secs,
+        )
+        .fetch_one(db_pool())
+        .await
+        .map(|id| id.id)
+        .into_app_err(
+            "failed to store bookmark",
+            AppErrorKind::Database,
+            &[&format!("UID: {uid}"), &format!("LABEL: {label}")],
+        )
+    }
+
+    inner(uid, label, position_secs).await
+}
+
+pub async fn store_gain_override<T: AsRef<str> + std::fmt::Debug>(
+    uid: &ItemUid<T>,
+    gain_db: f32,
+) -> Result<(), AppError> {
+    let uid = uid.0.as_ref();
+
+    async fn inner(uid: &str, gain_db: f32) -> Result<(), AppError> {
+        sqlx::query!(
+            "INSERT INTO audio_gain_override (audio_identifier, gain_db) VALUES ($1, $2)
+            ON CONFLICT (audio_identifier) DO UPDATE SET gain_db = EXCLUDED.gain_db",
+            uid,
+            gain_db,
+        )
+        .execute(db_pool())
+        .await
+        .map(|_| ())
+        .into_app_err(
+            "failed to store gain override",
+            AppErrorKind::Database,
+            &[&format!("UID: {uid}"), &format!("GAIN_DB: {gain_db}")],
+        )
+    }
+
+    inner(uid, gain_db).await
+}
+
+pub async fn store_resume_position<T: AsRef<str> + std::fmt::Debug>(
+    uid: &ItemUid<T>,
+    position_secs: f64,
+) -> Result<(), AppError> {
+    let uid = uid.0.as_ref();
+
+    async fn inner(uid: &str, position_secs: f64) -> Result<(), AppError> {
+        sqlx::query!(
+            "INSERT INTO audio_resume_position (audio_identifier, position_secs) VALUES ($1, $2)
+            ON CONFLICT (audio_identifier) DO UPDATE SET position_secs = EXCLUDED.position_secs",
+            uid,
+            position_secs,
+        )
+        .execute(db_pool())
+        .await
+        .map(|_| ())
+        .into_app_err(
+            "failed to store resume position",
+            AppErrorKind::Database,
+            &[
+                &format!("UID: {uid}"),
+                &format!("POSITION_SECS: {position_secs}"),
+            ],
+        )
+    }
+
+    inner(uid, position_secs).await
+}
+
+pub async fn delete_resume_position<T: AsRef<str> + std::fmt::Debug>(
+    uid: &ItemUid<T>,
+) -> Result<(), AppError> {
+    let uid = uid.0.as_ref();
+
+    async fn inner(uid: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            "DELETE FROM audio_resume_position WHERE audio_identifier = $1",
+            uid
+        )
+        .execute(db_pool())
+        .await
+        .map(|_| ())
+        .into_app_err(
+            "failed to delete resume position",
+            AppErrorKind::Database,
+            &[&format!("UID: {uid}")],
+        )
+    }
+
+    inner(uid).await
+}
+
+/// Marks the track of `uid` as a favorite or removes the mark, setting the current value again
+/// changes nothing
+///
+/// Returns false if no track with `uid` is stored.
+pub async fn set_audio_favorite<T: AsRef<str> + std::fmt::Debug>(
+    uid: &ItemUid<T>,
+    favorite: bool,
+) -> Result<bool, AppError> {
+    let uid = uid.0.as_ref();
+
+    sqlx::query!(
+        "UPDATE audio_metadata SET favorite = $2 WHERE identifier = $1",
+        uid,
+        favorite,
+    )
+    .execute(db_pool())
+    .await
+    .map(|res| res.rows_affected() > 0)
+    .into_app_err(
+        "failed to update favorite",
+        AppErrorKind::Database,
+        &[&format!("UID: {uid}"), &format!("FAVORITE: {favorite}")],
+    )
+}
+
+/// records that the track of `uid` was started on the node `source_name`
+pub async fn store_play<T: AsRef<str> + std::fmt::Debug>(
+    uid: &ItemUid<T>,
+    source_name: &str,
+) -> Result<(), AppError> {
+    let uid = uid.0.as_ref();
+
+    sqlx::query!(
+        "INSERT INTO play_history (audio_identifier, source_name) VALUES ($1, $2)",
+        uid,
+        source_name,
+    )
+    .execute(db_pool())
+    .await
+    .map(|_| ())
+    .into_app_err(
+        "failed to store play history",
+        AppErrorKind::Database,
+        &[
+            &format!("UID: {uid}"),
+            &format!("SOURCE_NAME: {source_name}"),
+        ],
+    )
+}
+
+/// metadata that is already stored for `uid` is kept
+pub async fn store_audio_metadata_if_not_exists<T: AsRef<str> + std::fmt::Debug>(
+    uid: &ItemUid<T>,
+    metadata: &AudioMetadata,
+) -> Result<(), AppError> {
+    let uid = uid.0.as_ref();
+
+    sqlx::query!(
+        "INSERT INTO audio_metadata
+        (identifier, name, author, duration, cover_art_url, gain_db)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT DO NOTHING",
+        uid,
+        metadata.name.inner_as_ref(),
+        metadata.author.inner_as_ref(),
+        metadata.duration,
+        metadata.cover_art_url.inner_as_ref(),
+        metadata.gain_db
+    )
+    .execute(db_pool())
+    .await
+    .map(|_| ())
+    .into_app_err(
+        "failed to store audio metadata",
+        AppErrorKind::Database,
+        &[&format!("UID: {uid}")],
+    )
+}
+
+pub async fn delete_audio_metadata(uids: &[ItemUid<Arc<str>>]) -> Result<(), AppError> {
+    let uids: Vec<String> = uids.iter().map(|uid| uid.0.to_string()).collect();
+
+    sqlx::query!(
+        "DELETE FROM audio_metadata WHERE identifier = ANY($1)",
+        &uids
+    )
+    .execute(db_pool())
+    .await
+    .map(|_| ())
+    .into_app_err(
+        "failed to delete audio metadata",
+        AppErrorKind::Database,
+        &[&format!("UIDS: {uids:?}")],
+    )
+}
@@ -6,7 +6,8 @@ use crate::{
     db_pool,
     downloader::{
         download_identifier::Identifier,
-        info::DownloadInfo,
+        info::{DownloadInfo, DownloadProgress},
+        soundcloud::process_soundcloud_track,
         youtube::{download_and_store_youtube_audio_with_metadata, process_single_youtube_video},
         DownloadRequiredInformation, YoutubePlaylistDownloadInfo,
     },
@@ -17,15 +18,19 @@ use crate::{
 };
 use std::{collections::VecDeque, sync::Arc, time::Duration};
 
-use actix::{Actor, Addr, Context, Handler, Message, Recipient};
+use actix::{Actor, Addr, Context, Handler, Message, Recipient, ResponseActFuture, WrapFuture};
 use actix_rt::Arbiter;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use tokio::sync::Mutex;
+use ts_rs::TS;
 
 use super::{
     download_identifier::{ItemUid, YoutubeVideoUrl},
     info::OptionalDownloadInfo,
+    playback_activity::PlaybackActivity,
+    rate_limit::DownloadRateLimit,
+    workers::{DownloadJob, DownloadWorkers},
 };
 
 const MAX_CONSECUTIVE_BATCHES: usize = 10;
@@ -33,6 +38,9 @@ const MAX_CONSECUTIVE_BATCHES: usize = 10;
 pub struct AudioDownloader {
     download_thread: Arbiter,
     queue: Arc<Mutex<VecDeque<DownloadAudioRequest>>>,
+    workers: DownloadWorkers,
+    rate_limit: DownloadRateLimit,
+    playback_activity: PlaybackActivity,
     restore_state_addr: Addr<RestoreStateActor>,
 }
 
@@ -42,12 +50,16 @@ pub struct DownloadAudioRequest {
     pub source_name: Option<SourceName>,
     pub addr: Recipient<NotifyDownloadUpdate>,
     pub required_info: DownloadRequiredInformation,
+    /// subfolder of the audio directory the audio is stored in, validated with
+    /// [`validate_audio_folder`](super::download_identifier::validate_audio_folder)
+    pub folder: Option<Arc<str>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SerializableDownloadAudioRequest {
     pub source_name: Option<SourceName>,
     pub required_info: DownloadRequiredInformation,
+    pub folder: Option<Arc<str>>,
 }
 
 type SingleDownloadFinished =
@@ -57,22 +69,88 @@ type SingleDownloadFinished =
 #[rtype(result = "()")]
 pub enum NotifyDownloadUpdate {
     Queued(DownloadInfo),
+    /// sent once the actual download process is launched, items that are already stored
+    /// locally are never started
+    Started(DownloadInfo),
+    /// sent while the download process is running, at most every [`DOWNLOAD_PROGRESS_INTERVAL`]
+    ///
+    /// [`DOWNLOAD_PROGRESS_INTERVAL`]: super::youtube::DOWNLOAD_PROGRESS_INTERVAL
+    Progress(DownloadProgress),
     FailedToQueue((DownloadInfo, AppError)),
     SingleFinished(SingleDownloadFinished),
-    BatchUpdated { batch: DownloadInfo },
+    BatchUpdated {
+        batch: DownloadInfo,
+    },
     BatchDownloadFailedToStart((DownloadInfo, AppError)),
+    /// the download is held back until no node is playing anymore
+    WaitingForIdle(DownloadInfo),
 }
 
 #[derive(Debug, Message)]
 #[rtype(result = "()")]
 pub struct RestoreQueue(pub Vec<DownloadAudioRequest>);
 
+/// Cancels the running download and removes all queued downloads of a source
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct CancelDownloads {
+    pub source_name: SourceName,
+}
+
+/// A download that hasn't been started yet
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct PendingDownload {
+    pub source_name: Option<SourceName>,
+    /// `None` for items that are already stored locally and will be skipped
+    pub info: Option<DownloadInfo>,
+}
+
+/// Gets the pending downloads in the order they will be started in
+#[derive(Debug, Message)]
+#[rtype(result = "Vec<PendingDownload>")]
+pub struct GetDownloadQueue;
+
+/// Moves a pending download to a new position in the download queue
+#[derive(Debug, Message)]
+#[rtype(result = "Result<Vec<PendingDownload>, AppError>")]
+pub struct MoveDownloadQueueItem {
+    pub old_pos: usize,
+    pub new_pos: usize,
+}
+
+/// Sets the max download rate, applies to all downloads started afterwards
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct UpdateDownloadRateLimit(pub Option<Arc<str>>);
+
+/// Sets how many downloads run at the same time, at least one
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct SetDownloadConcurrency(pub usize);
+
+/// Informs the downloader whether any node is playing, new downloads wait until no node is playing
+/// if this was enabled in the config
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct UpdatePlaybackActivity(pub bool);
+
 impl AudioDownloader {
-    pub fn new(download_thread: Arbiter, restore_state_addr: Addr<RestoreStateActor>) -> Self {
+    pub fn new(
+        download_thread: Arbiter,
+        restore_state_addr: Addr<RestoreStateActor>,
+        rate_limit: Option<Arc<str>>,
+        wait_for_idle: bool,
+        concurrency: usize,
+    ) -> Self {
         Self {
             download_thread,
             restore_state_addr,
             queue: Default::default(),
+            workers: DownloadWorkers::new(concurrency),
+            rate_limit: DownloadRateLimit::new(rate_limit),
+            playback_activity: PlaybackActivity::new(wait_for_idle),
         }
     }
 }
@@ -84,11 +162,34 @@ impl Actor for AudioDownloader {
         log::info!("stared new 'AudioDownloader', CONTEXT: {ctx:?}");
 
         let queue = self.queue.clone();
+        let workers = self.workers.clone();
+        let rate_limit = self.rate_limit.clone();
+        let playback_activity = self.playback_activity.clone();
         let restore_state_addr = self.restore_state_addr.clone().recipient();
 
         self.download_thread.spawn(async move {
+            let mut waiting_for_idle = false;
+            let db_writes = Arc::default();
+
             loop {
-                process_queue(queue.clone(), db_pool(), &restore_state_addr).await;
+                if playback_activity.should_wait() {
+                    if !waiting_for_idle {
+                        waiting_for_idle = notify_waiting_for_idle(&queue).await;
+                    }
+
+                    actix_rt::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+
+                waiting_for_idle = false;
+                start_downloads(
+                    &queue,
+                    &workers,
+                    &rate_limit,
+                    &db_writes,
+                    &restore_state_addr,
+                )
+                .await;
                 actix_rt::time::sleep(Duration::from_secs(1)).await;
             }
         });
@@ -154,125 +255,324 @@ impl Handler<RestoreQueue> for AudioDownloader {
     }
 }
 
-async fn process_queue(
-    queue: Arc<Mutex<VecDeque<DownloadAudioRequest>>>,
-    pool: &PgPool,
-    restore_state_addr: &Recipient<DownloadQueueStateUpdateMessage>,
-) {
-    let mut queue = queue.lock().await;
-
-    restore_state_addr.do_send(DownloadQueueStateUpdateMessage(
-        queue
-            .iter()
-            .map(|item| SerializableDownloadAudioRequest {
-                source_name: item.source_name.clone(),
-                required_info: item.required_info.clone(),
-            })
-            .collect(),
-    ));
-
-    if let Some(req) = queue.pop_front() {
-        let DownloadAudioRequest {
-            source_name,
-            addr,
-            required_info,
-        } = req;
-        log::info!("download for {required_info:?} has started");
-
-        match required_info {
-            DownloadRequiredInformation::StoredLocally { uid } => {
-                log::warn!("downloader received request for locally stored item with uid '{uid}'");
-            }
-            DownloadRequiredInformation::YoutubeVideo { url } => {
-                process_single_youtube_video(&url, pool, &addr).await;
+impl Handler<CancelDownloads> for AudioDownloader {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: CancelDownloads, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let CancelDownloads { source_name } = msg;
+
+        let cancelled = self.workers.cancel(&source_name);
+        if cancelled > 0 {
+            log::info!("cancelled {cancelled} running downloads for 'source name' {source_name}");
+        }
+
+        let queue = self.queue.clone();
+        Box::pin(
+            async move {
+                queue
+                    .lock()
+                    .await
+                    .retain(|req| req.source_name.as_ref() != Some(&source_name));
             }
-            DownloadRequiredInformation::YoutubePlaylist(YoutubePlaylistDownloadInfo {
-                ref playlist_url,
-                video_urls,
-            }) => {
-                let playlist_uid = playlist_url.uid();
-                match store_playlist_if_not_exists(&playlist_uid).await {
-                    Ok(_) => {}
-                    Err(err) => {
-                        addr.do_send(NotifyDownloadUpdate::BatchDownloadFailedToStart((
-                            DownloadInfo::yt_playlist_from_arc(&playlist_url.0, &video_urls),
-                            err,
-                        )));
-                        return;
-                    }
-                }
+            .into_actor(self),
+        )
+    }
+}
 
-                let (videos_to_process, videos_for_next_batch) =
-                    if MAX_CONSECUTIVE_BATCHES > video_urls.len() {
-                        (video_urls.as_ref(), Default::default())
-                    } else {
-                        video_urls.split_at(MAX_CONSECUTIVE_BATCHES)
-                    };
-
-                for url in videos_to_process {
-                    let info = DownloadInfo::yt_video_from_arc(url);
-
-                    let tx = match pool.begin().await.into_app_err(
-                        "failed to start transaction",
-                        AppErrorKind::Database,
-                        &[],
-                    ) {
-                        Ok(tx) => tx,
-                        Err(err) => {
-                            addr.do_send(NotifyDownloadUpdate::FailedToQueue((info, err)));
-                            return;
-                        }
-                    };
+impl Handler<UpdateDownloadRateLimit> for AudioDownloader {
+    type Result = ();
+
+    fn handle(&mut self, msg: UpdateDownloadRateLimit, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.rate_limit.set(msg.0);
+    }
+}
+
+impl Handler<SetDownloadConcurrency> for AudioDownloader {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetDownloadConcurrency, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.workers.set_concurrency(msg.0);
+    }
+}
+
+impl Handler<UpdatePlaybackActivity> for AudioDownloader {
+    type Result = ();
+
+    fn handle(&mut self, msg: UpdatePlaybackActivity, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.playback_activity.set_playing(msg.0);
+    }
+}
+
+impl Handler<GetDownloadQueue> for AudioDownloader {
+    type Result = ResponseActFuture<Self, Vec<PendingDownload>>;
 
-                    let video_url = YoutubeVideoUrl(&url);
+    fn handle(&mut self, msg: GetDownloadQueue, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let queue = self.queue.clone();
+        Box::pin(async move { pending_downloads(&*queue.lock().await) }.into_actor(self))
+    }
+}
+
+impl Handler<MoveDownloadQueueItem> for AudioDownloader {
+    type Result = ResponseActFuture<Self, Result<Vec<PendingDownload>, AppError>>;
+
+    fn handle(&mut self, msg: MoveDownloadQueueItem, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let MoveDownloadQueueItem { old_pos, new_pos } = msg;
 
-                    let result = match download_and_store_youtube_audio_with_metadata(
-                        &video_url, tx,
+        let queue = self.queue.clone();
+        let workers = self.workers.clone();
+        let restore_state_addr = self.restore_state_addr.clone().recipient();
+        Box::pin(
+            async move {
+                let mut queue = queue.lock().await;
+
+                if old_pos >= queue.len() || new_pos >= queue.len() {
+                    return Err(AppError::new(
+                        AppErrorKind::Download,
+                        "position out of bounds, can not move pending download",
+                        &[
+                            &format!("OLD_POS: {old_pos}"),
+                            &format!("NEW_POS: {new_pos}"),
+                            &format!("QUEUE_LEN: {len}", len = queue.len()),
+                        ],
                     )
-                    .await
-                    {
-                        Ok(metadata) => {
-                            match store_playlist_item_relation_if_not_exists(
-                                &playlist_url.uid(),
-                                &video_url.uid(),
-                            )
-                            .await
-                            {
-                                Ok(()) => Ok((info, metadata, video_url.uid())),
-                                Err(err) => Err((info, err)),
-                            }
-                        }
-                        Err(err) => Err((info, err)),
-                    };
+                    .bad_request());
+                }
 
-                    addr.do_send(NotifyDownloadUpdate::SingleFinished(result));
+                if let Some(item) = queue.remove(old_pos) {
+                    queue.insert(new_pos, item);
                 }
 
-                if videos_for_next_batch.is_empty() {
-                    addr.do_send(NotifyDownloadUpdate::BatchUpdated {
-                        batch: DownloadInfo::yt_playlist_from_arc(
-                            &playlist_url.0,
-                            videos_for_next_batch,
-                        ),
-                    });
+                persist_queue(&queue, &workers, &restore_state_addr);
+
+                Ok(pending_downloads(&queue))
+            }
+            .into_actor(self),
+        )
+    }
+}
+
+/// tells everyone waiting for a pending download that it is held back because a node is playing,
+/// returns false if nothing is pending
+async fn notify_waiting_for_idle(queue: &Mutex<VecDeque<DownloadAudioRequest>>) -> bool {
+    let queue = queue.lock().await;
+
+    for item in queue.iter() {
+        let info: OptionalDownloadInfo = (&item.required_info).into();
+        if let Some(info) = info.into() {
+            item.addr
+                .do_send(NotifyDownloadUpdate::WaitingForIdle(info));
+        }
+    }
+
+    if !queue.is_empty() {
+        log::info!(
+            "holding back {len} downloads until playback has stopped",
+            len = queue.len()
+        );
+    }
+
+    !queue.is_empty()
+}
+
+fn pending_downloads(queue: &VecDeque<DownloadAudioRequest>) -> Vec<PendingDownload> {
+    queue
+        .iter()
+        .map(|item| PendingDownload {
+            source_name: item.source_name.clone(),
+            info: OptionalDownloadInfo::from(&item.required_info).into(),
+        })
+        .collect()
+}
+
+/// stores the running downloads followed by the pending ones, running downloads are started again
+/// first if the server is restarted before they finish
+fn persist_queue(
+    queue: &VecDeque<DownloadAudioRequest>,
+    workers: &DownloadWorkers,
+    restore_state_addr: &Recipient<DownloadQueueStateUpdateMessage>,
+) {
+    let requests = workers
+        .requests()
+        .into_iter()
+        .chain(queue.iter().cloned().map(Into::into))
+        .collect();
+
+    restore_state_addr.do_send(DownloadQueueStateUpdateMessage(requests));
+}
+
+/// Takes requests from the front of the queue until every worker is busy, every request is
+/// downloaded on its own task
+async fn start_downloads(
+    queue: &Arc<Mutex<VecDeque<DownloadAudioRequest>>>,
+    workers: &DownloadWorkers,
+    rate_limit: &DownloadRateLimit,
+    db_writes: &Arc<Mutex<()>>,
+    restore_state_addr: &Recipient<DownloadQueueStateUpdateMessage>,
+) {
+    // the queue is only locked while taking the next requests so pending downloads can be
+    // reordered while downloads are running
+    let mut pending = queue.lock().await;
+
+    while workers.has_free_worker() {
+        let Some(req) = pending.pop_front() else {
+            break;
+        };
+
+        let (id, running) = workers.start(req.clone().into());
+        let job = DownloadJob {
+            running,
+            rate_limit: rate_limit.clone(),
+            db_writes: Arc::clone(db_writes),
+        };
+
+        let queue = Arc::clone(queue);
+        let workers = workers.clone();
+        let restore_state_addr = restore_state_addr.clone();
+        actix_rt::spawn(async move {
+            process_request(req, &queue, db_pool(), &job).await;
+
+            workers.finish(id);
+            persist_queue(&*queue.lock().await, &workers, &restore_state_addr);
+        });
+    }
+
+    persist_queue(&pending, workers, restore_state_addr);
+}
+
+async fn process_request(
+    req: DownloadAudioRequest,
+    queue: &Mutex<VecDeque<DownloadAudioRequest>>,
+    pool: &PgPool,
+    job: &DownloadJob,
+) {
+    let DownloadAudioRequest {
+        source_name,
+        addr,
+        required_info,
+        folder,
+    } = req;
+    log::info!("download for {required_info:?} has started");
+
+    match required_info {
+        DownloadRequiredInformation::StoredLocally { uid } => {
+            log::warn!("downloader received request for locally stored item with uid '{uid}'");
+        }
+        DownloadRequiredInformation::YoutubeVideo { url } => {
+            process_single_youtube_video(&url, folder.as_deref(), pool, &addr, job).await;
+        }
+        DownloadRequiredInformation::SoundCloudTrack { url } => {
+            process_soundcloud_track(&url, folder.as_deref(), &addr, job).await;
+        }
+        DownloadRequiredInformation::YoutubePlaylist(YoutubePlaylistDownloadInfo {
+            ref playlist_url,
+            video_urls,
+        }) => {
+            let playlist_uid = playlist_url.uid();
+            let stored = {
+                let _db_write = job.db_writes.lock().await;
+                store_playlist_if_not_exists(&playlist_uid).await
+            };
+
+            match stored {
+                Ok(_) => {}
+                Err(err) => {
+                    addr.do_send(NotifyDownloadUpdate::BatchDownloadFailedToStart((
+                        DownloadInfo::yt_playlist_from_arc(&playlist_url.0, &video_urls),
+                        err,
+                    )));
+                    return;
+                }
+            }
+
+            let (videos_to_process, videos_for_next_batch) =
+                if MAX_CONSECUTIVE_BATCHES > video_urls.len() {
+                    (video_urls.as_ref(), Default::default())
                 } else {
-                    let next_batch =
-                        DownloadRequiredInformation::YoutubePlaylist(YoutubePlaylistDownloadInfo {
-                            playlist_url: playlist_url.clone(),
-                            video_urls: videos_for_next_batch.into(),
-                        });
-
-                    addr.do_send(NotifyDownloadUpdate::BatchUpdated {
-                        batch: DownloadInfo::yt_playlist_from_arc(
-                            &playlist_url.0,
-                            videos_for_next_batch,
-                        ),
+                    video_urls.split_at(MAX_CONSECUTIVE_BATCHES)
+                };
+
+            for url in videos_to_process {
+                if job.running.is_cancelled() {
+                    return;
+                }
+
+                let info = DownloadInfo::yt_video_from_arc(url);
+                let video_url = YoutubeVideoUrl(&url);
+
+                let result = match download_and_store_youtube_audio_with_metadata(
+                    &video_url,
+                    folder.as_deref(),
+                    pool,
+                    &info,
+                    &addr,
+                    job,
+                )
+                .await
+                {
+                    Ok(metadata) => {
+                        let _db_write = job.db_writes.lock().await;
+                        match store_playlist_item_relation_if_not_exists(
+                            &playlist_url.uid(),
+                            &video_url.uid(),
+                        )
+                        .await
+                        {
+                            Ok(()) => Ok((info, metadata, video_url.uid())),
+                            Err(err) => Err((info, err)),
+                        }
+                    }
+                    Err(err) => Err((info, err)),
+                };
+
+                addr.do_send(NotifyDownloadUpdate::SingleFinished(result));
+            }
+
+            if job.running.is_cancelled() {
+                return;
+            }
+
+            if videos_for_next_batch.is_empty() {
+                addr.do_send(NotifyDownloadUpdate::BatchUpdated {
+                    batch: DownloadInfo::yt_playlist_from_arc(
+                        &playlist_url.0,
+                        videos_for_next_batch,
+                    ),
+                });
+            } else {
+                let next_batch =
+                    DownloadRequiredInformation::YoutubePlaylist(YoutubePlaylistDownloadInfo {
+                        playlist_url: playlist_url.clone(),
+                        video_urls: videos_for_next_batch.into(),
                     });
 
+                addr.do_send(NotifyDownloadUpdate::BatchUpdated {
+                    batch: DownloadInfo::yt_playlist_from_arc(
+                        &playlist_url.0,
+                        videos_for_next_batch,
+                    ),
+                });
+
+                // checked again while holding the lock, otherwise the batch could be pushed
+                // right after `CancelDownloads` cleared the queue
+                let mut queue = queue.lock().await;
+                if !job.running.is_cancelled() {
                     queue.push_back(DownloadAudioRequest {
                         source_name,
                         addr,
                         required_info: next_batch,
+                        folder,
                     });
                 }
             }
@@ -285,6 +585,7 @@ impl From<DownloadAudioRequest> for SerializableDownloadAudioRequest {
         Self {
             source_name: value.source_name,
             required_info: value.required_info,
+            folder: value.folder,
         }
     }
 }
@@ -1,24 +1,182 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::path::audio_data_dir;
+use crate::{
+    error::{AppError, AppErrorKind},
+    path::audio_data_dir,
+};
+
+/// subfolder of the audio directory every audio file that isn't stored in the audio directory
+/// itself is stored in, keyed by uid
+///
+/// Built on startup by [`index_audio_folders`] and updated whenever a download into a subfolder
+/// finishes.
+static AUDIO_FOLDERS: RwLock<BTreeMap<Arc<str>, Arc<str>>> = RwLock::new(BTreeMap::new());
 
 pub trait Identifier {
     fn uid(&self) -> ItemUid<Arc<str>>;
     fn to_path(&self) -> PathBuf {
-        audio_data_dir().join(self.uid().0.as_ref())
+        let uid = self.uid();
+        let folder = AUDIO_FOLDERS
+            .read()
+            .ok()
+            .and_then(|folders| folders.get(uid.0.as_ref()).cloned());
+
+        self.to_path_in_folder(folder.as_deref())
+    }
+
+    /// path the audio would be stored at in `folder`, a subfolder of the audio directory
+    fn to_path_in_folder(&self, folder: Option<&str>) -> PathBuf {
+        let dir = match folder {
+            Some(folder) => audio_data_dir().join(folder),
+            None => audio_data_dir(),
+        };
+
+        dir.join(self.uid().0.as_ref())
     }
 
+    /// path of the stored audio file, including the extension the file was actually stored with
+    ///
+    /// Falls back to [`DEFAULT_AUDIO_EXTENSION`] if no file is stored yet, which is where new
+    /// downloads are written to.
     fn to_path_with_ext(&self) -> PathBuf {
-        self.to_path().with_extension("wav")
+        resolve_audio_file_path(&self.to_path())
+    }
+}
+
+/// extension new downloads are stored with
+///
+/// Youtube mostly serves opus in webm as best audio, which symphonia can't decode, so downloads
+/// are still converted instead of keeping their native format.
+pub const DEFAULT_AUDIO_EXTENSION: &str = "wav";
+
+/// extensions a stored audio file can have, checked in order
+///
+/// `ogg` and `webm` files are only playable if they contain vorbis, not opus.
+pub const AUDIO_FILE_EXTENSIONS: [&str; 5] = ["wav", "mp3", "flac", "ogg", "webm"];
+
+pub fn resolve_audio_file_path(path: &Path) -> PathBuf {
+    AUDIO_FILE_EXTENSIONS
+        .iter()
+        .map(|ext| path.with_extension(ext))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| path.with_extension(DEFAULT_AUDIO_EXTENSION))
+}
+
+/// Checks that `folder` is a relative path that stays within the audio directory, e.g.
+/// `artist/album`
+///
+/// Returns the normalized folder, without surrounding whitespace and duplicate slashes.
+pub fn validate_audio_folder(folder: &str) -> Result<Arc<str>, AppError> {
+    let folder = folder.trim();
+    let path = Path::new(folder);
+
+    let is_valid = path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+
+    if !is_valid {
+        return Err(AppError::new(
+            AppErrorKind::Download,
+            "invalid download folder, expected a relative path without '..' like 'artist/album'",
+            &[&format!("FOLDER: {folder}")],
+        )
+        .bad_request());
+    }
+
+    let components: Vec<_> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+
+    Ok(components.join("/").into())
+}
+
+/// remembers that the audio of `uid` is stored in `folder`
+pub fn register_audio_folder(uid: &ItemUid<Arc<str>>, folder: &str) {
+    if let Ok(mut folders) = AUDIO_FOLDERS.write() {
+        folders.insert(Arc::clone(&uid.0), folder.into());
+    }
+}
+
+/// forgets the folder of `uid` once its audio was deleted
+pub fn unregister_audio_folder(uid: &ItemUid<Arc<str>>) {
+    if let Ok(mut folders) = AUDIO_FOLDERS.write() {
+        folders.remove(uid.0.as_ref());
     }
 }
 
+/// Indexes the audio files in subfolders of `dir`, returns the number of indexed files
+///
+/// Only needs to run once on startup, later downloads are registered by the downloader.
+pub fn index_audio_folders(dir: &Path) -> io::Result<usize> {
+    let found = find_audio_folders(dir)?;
+    let count = found.len();
+
+    if let Ok(mut folders) = AUDIO_FOLDERS.write() {
+        folders.extend(found);
+    }
+
+    Ok(count)
+}
+
+/// uid and folder relative to `dir` of all files in subfolders of `dir`
+fn find_audio_folders(dir: &Path) -> io::Result<Vec<(Arc<str>, Arc<str>)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+
+    let mut subfolders = vec![];
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            subfolders.push(entry.path());
+        }
+    }
+
+    let mut found = vec![];
+    while let Some(folder) = subfolders.pop() {
+        let relative = folder
+            .strip_prefix(dir)
+            .unwrap_or(&folder)
+            .to_string_lossy()
+            .to_string();
+        let relative: Arc<str> = relative.into();
+
+        for entry in fs::read_dir(&folder)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+
+            if file_type.is_dir() {
+                subfolders.push(entry.path());
+            } else if file_type.is_file() {
+                // everything in front of the first `.` of a file name is its uid
+                let name = entry.file_name().to_string_lossy().to_string();
+                let uid = name.split('.').next().unwrap_or_default();
+                found.push((uid.into(), Arc::clone(&relative)));
+            }
+        }
+    }
+
+    found.sort();
+    Ok(found)
+}
+
 #[derive(Debug)]
 pub enum AudioKind {
     YoutubeVideo,
     YoutubePlaylist,
+    LocalFile,
+    SoundCloud,
 }
 
 impl AudioKind {
@@ -34,6 +192,12 @@ impl AudioKind {
             {
                 Some(AudioKind::YoutubePlaylist)
             }
+            s if s.0.as_ref().starts_with(AudioKind::LocalFile.prefix()) => {
+                Some(AudioKind::LocalFile)
+            }
+            s if s.0.as_ref().starts_with(AudioKind::SoundCloud.prefix()) => {
+                Some(AudioKind::SoundCloud)
+            }
             _ => None,
         }
     }
@@ -42,6 +206,8 @@ impl AudioKind {
         match self {
             Self::YoutubeVideo => "youtube_audio_",
             Self::YoutubePlaylist => "youtube_playlist_audio_",
+            Self::LocalFile => "local_file_audio_",
+            Self::SoundCloud => "soundcloud_audio_",
         }
     }
 }
@@ -85,6 +251,9 @@ pub struct YoutubeVideoUrl<T: AsRef<str> + std::fmt::Debug>(pub T);
 #[derive(Debug, PartialEq)]
 pub struct YoutubePlaylistUrl<T: AsRef<str> + std::fmt::Debug>(pub T);
 
+#[derive(Debug, PartialEq)]
+pub struct SoundCloudTrackUrl<T: AsRef<str> + std::fmt::Debug>(pub T);
+
 impl<T: AsRef<str> + std::fmt::Debug> Identifier for YoutubeVideoUrl<T> {
     fn uid(&self) -> ItemUid<Arc<str>> {
         let prefix = AudioKind::YoutubeVideo.prefix();
@@ -103,6 +272,15 @@ impl<T: AsRef<str> + std::fmt::Debug> Identifier for YoutubePlaylistUrl<T> {
     }
 }
 
+impl<T: AsRef<str> + std::fmt::Debug> Identifier for SoundCloudTrackUrl<T> {
+    fn uid(&self) -> ItemUid<Arc<str>> {
+        let prefix = AudioKind::SoundCloud.prefix();
+        let hex_url = hex::encode(self.0.as_ref());
+
+        ItemUid(format!("{prefix}{hex_url}").into())
+    }
+}
+
 impl Clone for YoutubeVideoUrl<Arc<str>> {
     fn clone(&self) -> Self {
         YoutubeVideoUrl(Arc::clone(&self.0))
@@ -115,6 +293,12 @@ impl Clone for YoutubePlaylistUrl<Arc<str>> {
     }
 }
 
+impl Clone for SoundCloudTrackUrl<Arc<str>> {
+    fn clone(&self) -> Self {
+        SoundCloudTrackUrl(Arc::clone(&self.0))
+    }
+}
+
 impl Serialize for YoutubeVideoUrl<Arc<str>> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -133,6 +317,15 @@ impl Serialize for YoutubePlaylistUrl<Arc<str>> {
     }
 }
 
+impl Serialize for SoundCloudTrackUrl<Arc<str>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
 impl<'de> Deserialize<'de> for YoutubeVideoUrl<Arc<str>> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -150,3 +343,74 @@ impl<'de> Deserialize<'de> for YoutubePlaylistUrl<Arc<str>> {
         Ok(Self(Arc::<str>::deserialize(deserializer)?))
     }
 }
+
+impl<'de> Deserialize<'de> for SoundCloudTrackUrl<Arc<str>> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Self(Arc::<str>::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_utils::test_dir;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_resolve_audio_file_path() {
+        let dir = test_dir("resolve-audio-file-path");
+
+        let flac_path = dir.join("youtube_audio_flac.flac");
+        std::fs::write(&flac_path, []).unwrap();
+
+        assert_eq!(
+            resolve_audio_file_path(&dir.join("youtube_audio_flac")),
+            flac_path
+        );
+        assert_eq!(
+            resolve_audio_file_path(&dir.join("youtube_audio_missing")),
+            dir.join("youtube_audio_missing.wav")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_validate_audio_folder() {
+        assert_eq!(
+            validate_audio_folder(" artist//album/ ").unwrap().as_ref(),
+            "artist/album"
+        );
+        assert_eq!(
+            validate_audio_folder("podcasts").unwrap().as_ref(),
+            "podcasts"
+        );
+
+        for invalid in ["", " ", "/", "/artist", "..", "artist/../..", "./artist"] {
+            assert!(validate_audio_folder(invalid).is_err(), "FOLDER: {invalid}");
+        }
+    }
+
+    #[test]
+    fn test_find_audio_folders() {
+        let dir = test_dir("find-audio-folders");
+
+        std::fs::create_dir_all(dir.join("artist/album")).unwrap();
+        std::fs::create_dir_all(dir.join("podcasts")).unwrap();
+        std::fs::write(dir.join("flat.wav"), []).unwrap();
+        std::fs::write(dir.join("artist/album/track.wav"), []).unwrap();
+        std::fs::write(dir.join("podcasts/episode.mp3"), []).unwrap();
+
+        assert_eq!(
+            find_audio_folders(&dir).unwrap(),
+            vec![
+                (Arc::<str>::from("episode"), Arc::<str>::from("podcasts")),
+                (Arc::<str>::from("track"), Arc::<str>::from("artist/album")),
+            ]
+        );
+        assert!(find_audio_folders(&dir.join("missing")).unwrap().is_empty());
+    }
+}
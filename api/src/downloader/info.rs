@@ -17,6 +17,22 @@ pub enum DownloadInfo {
         #[ts(type = "Array<string>")]
         video_urls: Vec<Arc<str>>,
     },
+    #[serde(rename = "soundcloud-track")]
+    SoundCloudTrack {
+        url: Arc<str>,
+    },
+}
+
+/// progress of a running download as reported by the download process
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct DownloadProgress {
+    pub info: DownloadInfo,
+    pub progress_percent: f32,
+    /// estimated seconds until the download is done, `None` if the download process doesn't know
+    /// yet
+    pub eta_secs: Option<u32>,
 }
 
 impl std::hash::Hash for DownloadInfo {
@@ -24,6 +40,7 @@ impl std::hash::Hash for DownloadInfo {
         match self {
             Self::YoutubeVideo { url } => url.hash(state),
             Self::YoutubePlaylist { playlist_url, .. } => playlist_url.hash(state),
+            Self::SoundCloudTrack { url } => url.hash(state),
         };
     }
 }
@@ -41,6 +58,10 @@ impl PartialEq for DownloadInfo {
                     ..
                 },
             ) => playlist_url.eq(playlist_url_other),
+            (
+                DownloadInfo::SoundCloudTrack { url },
+                DownloadInfo::SoundCloudTrack { url: url_other },
+            ) => url.eq(url_other),
             _ => false,
         }
     }
@@ -72,6 +93,12 @@ impl DownloadInfo {
             video_urls: video_urls.iter().map(|str| str.as_ref().into()).collect(),
         }
     }
+
+    pub fn soundcloud_track(track_url: impl AsRef<str>) -> Self {
+        DownloadInfo::SoundCloudTrack {
+            url: track_url.as_ref().into(),
+        }
+    }
 }
 
 pub struct OptionalDownloadInfo {
@@ -104,6 +131,11 @@ impl<T: Borrow<DownloadRequiredInformation>> From<T> for OptionalDownloadInfo {
                     video_urls: video_urls.iter().map(Arc::clone).collect(),
                 }),
             },
+            DownloadRequiredInformation::SoundCloudTrack { url } => OptionalDownloadInfo {
+                inner: Some(DownloadInfo::SoundCloudTrack {
+                    url: Arc::clone(&url.0),
+                }),
+            },
         }
     }
 }
@@ -131,9 +163,9 @@ mod tests {
 
         let mut set: HashSet<DownloadInfo> = Default::default();
 
-        assert_eq!(set.insert(info_1), true);
-        assert_eq!(set.insert(info_2), false);
-        assert_eq!(set.insert(info_3), true);
+        assert!(set.insert(info_1));
+        assert!(!set.insert(info_2));
+        assert!(set.insert(info_3));
         assert_eq!(set.len(), 2)
     }
 }
@@ -0,0 +1,262 @@
+use std::{collections::HashMap, sync::Arc};
+
+use actix::{
+    Actor, ActorFutureExt, Addr, AsyncContext, Context, Handler, Message, ResponseActFuture,
+    WrapFuture,
+};
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::{
+    audio_hosts::youtube::{
+        playlist::get_playlist_video_urls, youtube_content_type, YoutubeContentType,
+    },
+    database::{
+        fetch_data::get_audio_metadata_from_db,
+        store_data::{store_playlist_if_not_exists, store_playlist_item_relation_if_not_exists},
+    },
+    downloader::{
+        actor::{AudioDownloader, DownloadAudioRequest, NotifyDownloadUpdate},
+        download_identifier::{Identifier, YoutubePlaylistUrl, YoutubeVideoUrl},
+        info::DownloadInfo,
+        DownloadRequiredInformation, YoutubePlaylistDownloadInfo,
+    },
+    error::{AppError, AppErrorKind},
+    node::node_server::clean_url,
+    utils::log_msg_received,
+    yt_api_key,
+};
+
+/// Downloads playlists into the library without adding them to the queue of any node
+///
+/// Progress is tracked per playlist, the uid of the playlist is used as the batch id. Batches are
+/// only kept in memory and are not restored after a restart.
+pub struct LibraryDownloader {
+    downloader_addr: Addr<AudioDownloader>,
+    batches: HashMap<Arc<str>, LibraryDownloadBatch>,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct LibraryDownloadBatch {
+    pub batch_id: Arc<str>,
+    pub playlist_url: Arc<str>,
+    pub total: usize,
+    pub finished: usize,
+    #[ts(type = "Array<string>")]
+    pub pending: Vec<Arc<str>>,
+    #[ts(type = "Array<[string, AppError]>")]
+    pub failed: Vec<(Arc<str>, AppError)>,
+}
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Result<LibraryDownloadBatch, AppError>")]
+pub struct DownloadPlaylistToLibrary {
+    pub playlist_url: Arc<str>,
+}
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Vec<LibraryDownloadBatch>")]
+pub struct GetLibraryDownloads;
+
+impl LibraryDownloader {
+    pub fn new(downloader_addr: Addr<AudioDownloader>) -> Self {
+        Self {
+            downloader_addr,
+            batches: HashMap::default(),
+        }
+    }
+}
+
+impl Actor for LibraryDownloader {
+    type Context = Context<Self>;
+}
+
+impl LibraryDownloadBatch {
+    /// marks a video of the batch as done, returns false if the video isn't part of the batch
+    fn finish(&mut self, url: &Arc<str>, err: Option<AppError>) -> bool {
+        let Some(index) = self.pending.iter().position(|pending| pending == url) else {
+            return false;
+        };
+
+        let url = self.pending.remove(index);
+        match err {
+            Some(err) => self.failed.push((url, err)),
+            None => self.finished += 1,
+        }
+
+        true
+    }
+
+    fn fail_pending(&mut self, err: AppError) {
+        let pending = std::mem::take(&mut self.pending);
+        self.failed
+            .extend(pending.into_iter().map(|url| (url, err.clone())));
+    }
+}
+
+impl Handler<DownloadPlaylistToLibrary> for LibraryDownloader {
+    type Result = ResponseActFuture<Self, Result<LibraryDownloadBatch, AppError>>;
+
+    fn handle(&mut self, msg: DownloadPlaylistToLibrary, ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let receiver_addr = ctx.address().recipient();
+
+        Box::pin(
+            async move {
+                let url = clean_url(&msg.playlist_url);
+                if !matches!(youtube_content_type(url), YoutubeContentType::Playlist) {
+                    return Err(AppError::new(
+                        AppErrorKind::Download,
+                        "invalid youtube playlist url",
+                        &[&format!("URL: {url}")],
+                    )
+                    .bad_request());
+                }
+
+                let playlist_url = YoutubePlaylistUrl(Arc::<str>::from(url));
+                let video_urls = get_playlist_video_urls(url, yt_api_key()).await?;
+
+                let playlist_uid = playlist_url.uid();
+                store_playlist_if_not_exists(&playlist_uid).await?;
+
+                let mut batch = LibraryDownloadBatch {
+                    batch_id: Arc::clone(&playlist_uid.0),
+                    playlist_url: Arc::clone(&playlist_url.0),
+                    total: video_urls.len(),
+                    finished: 0,
+                    pending: Vec::with_capacity(video_urls.len()),
+                    failed: vec![],
+                };
+
+                for url in video_urls.iter() {
+                    let audio_uid = YoutubeVideoUrl(url).uid();
+
+                    if get_audio_metadata_from_db(&audio_uid).await?.is_some() {
+                        store_playlist_item_relation_if_not_exists(&playlist_uid, &audio_uid)
+                            .await?;
+                        batch.finished += 1;
+                    } else {
+                        batch.pending.push(Arc::clone(url));
+                    }
+                }
+
+                Ok((playlist_url, batch))
+            }
+            .into_actor(self)
+            .map(move |res, act, _ctx| {
+                let (playlist_url, batch) = res?;
+
+                if !batch.pending.is_empty() {
+                    act.downloader_addr.do_send(DownloadAudioRequest {
+                        source_name: None,
+                        addr: receiver_addr,
+                        folder: None,
+                        required_info: DownloadRequiredInformation::YoutubePlaylist(
+                            YoutubePlaylistDownloadInfo {
+                                playlist_url,
+                                video_urls: batch.pending.clone().into(),
+                            },
+                        ),
+                    });
+                }
+
+                act.batches
+                    .insert(Arc::clone(&batch.batch_id), batch.clone());
+
+                Ok(batch)
+            }),
+        )
+    }
+}
+
+impl Handler<GetLibraryDownloads> for LibraryDownloader {
+    type Result = Vec<LibraryDownloadBatch>;
+
+    fn handle(&mut self, msg: GetLibraryDownloads, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let mut batches: Vec<_> = self.batches.values().cloned().collect();
+        batches.sort_by(|a, b| a.batch_id.cmp(&b.batch_id));
+
+        batches
+    }
+}
+
+impl Handler<NotifyDownloadUpdate> for LibraryDownloader {
+    type Result = ();
+
+    fn handle(&mut self, msg: NotifyDownloadUpdate, _ctx: &mut Self::Context) -> Self::Result {
+        let (url, err) = match msg {
+            NotifyDownloadUpdate::SingleFinished(Ok((DownloadInfo::YoutubeVideo { url }, ..))) => {
+                (url, None)
+            }
+            NotifyDownloadUpdate::SingleFinished(Err((
+                DownloadInfo::YoutubeVideo { url },
+                err,
+            )))
+            | NotifyDownloadUpdate::FailedToQueue((DownloadInfo::YoutubeVideo { url }, err)) => {
+                (url, Some(err))
+            }
+            NotifyDownloadUpdate::FailedToQueue((
+                DownloadInfo::YoutubePlaylist { playlist_url, .. },
+                err,
+            ))
+            | NotifyDownloadUpdate::BatchDownloadFailedToStart((
+                DownloadInfo::YoutubePlaylist { playlist_url, .. },
+                err,
+            )) => {
+                let batch_id = YoutubePlaylistUrl(&playlist_url).uid().0;
+                if let Some(batch) = self.batches.get_mut(&batch_id) {
+                    batch.fail_pending(err);
+                }
+
+                return;
+            }
+            _ => return,
+        };
+
+        if !self
+            .batches
+            .values_mut()
+            .any(|batch| batch.finish(&url, err.clone()))
+        {
+            log::warn!("received library download update for unknown video, URL: {url}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_library_download_batch_progress() {
+        let mut batch = LibraryDownloadBatch {
+            batch_id: "batch".into(),
+            playlist_url: "playlist".into(),
+            total: 4,
+            finished: 1,
+            pending: vec!["a".into(), "b".into(), "c".into()],
+            failed: vec![],
+        };
+
+        assert!(batch.finish(&"a".into(), None));
+        assert!(!batch.finish(&"a".into(), None));
+        assert!(batch.finish(
+            &"b".into(),
+            Some(AppError::new(AppErrorKind::Download, "failed", &[]))
+        ));
+
+        assert_eq!(batch.finished, 2);
+        assert_eq!(batch.pending, vec![Arc::<str>::from("c")]);
+        assert_eq!(batch.failed.len(), 1);
+
+        batch.fail_pending(AppError::new(AppErrorKind::Download, "failed", &[]));
+        assert!(batch.pending.is_empty());
+        assert_eq!(batch.failed.len(), 2);
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    audio_hosts::{
+        local::{check_audio_file_decodable, read_audio_metadata},
+        loudness::normalization_gain_db_or_log,
+    },
+    audio_playback::audio_item::AudioMetadata,
+    database::{
+        fetch_data::get_audio_metadata_from_db, store_data::store_audio_metadata_if_not_exists,
+    },
+    error::{AppError, AppErrorKind, IntoAppError},
+};
+
+use super::download_identifier::{
+    register_audio_folder, AudioKind, Identifier, ItemUid, AUDIO_FILE_EXTENSIONS,
+};
+
+/// Directory audio files can be imported from, importing local files is disabled if it isn't set
+pub const LOCAL_IMPORT_DIR_ENV: &str = "LOCAL_IMPORT_DIR";
+
+pub fn local_import_dir() -> Option<PathBuf> {
+    dotenv::var(LOCAL_IMPORT_DIR_ENV)
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// Audio file outside of the audio directory that is imported instead of downloaded
+#[derive(Debug, PartialEq)]
+pub struct LocalFilePath<T: AsRef<Path> + std::fmt::Debug>(pub T);
+
+impl<T: AsRef<Path> + std::fmt::Debug> Identifier for LocalFilePath<T> {
+    /// derived from the path the file is imported from, importing the same file again plays the
+    /// copy that is already stored
+    ///
+    /// The path is hashed instead of hex encoded like urls, long paths would exceed the max length
+    /// of a file name.
+    fn uid(&self) -> ItemUid<Arc<str>> {
+        let prefix = AudioKind::LocalFile.prefix();
+        let path_hash = hex::encode(path_hash(&self.0.as_ref().to_string_lossy()).to_be_bytes());
+
+        ItemUid(format!("{prefix}{path_hash}").into())
+    }
+}
+
+/// FNV-1a, unlike the hasher of the standard library it is stable across builds
+fn path_hash(path: &str) -> u64 {
+    path.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Checks that `path` is an audio file inside of `import_dir` that can be played, returns its
+/// canonical path
+///
+/// Relative paths are relative to `import_dir`. Missing files and files outside of `import_dir`
+/// are rejected with the same error, so clients can't find out which paths exist on the server.
+/// Only the header of the file is read, files with a corrupt body are still accepted.
+pub fn validate_local_audio_file(
+    path: &Path,
+    import_dir: Option<&Path>,
+) -> Result<PathBuf, AppError> {
+    let path_info = format!("PATH: {path}", path = path.display());
+
+    let Some(import_dir) = import_dir.and_then(|dir| dir.canonicalize().ok()) else {
+        return Err(AppError::new(
+            AppErrorKind::LocalData,
+            format!("importing local audio files is disabled, set '{LOCAL_IMPORT_DIR_ENV}' to enable it"),
+            &[&path_info],
+        )
+        .bad_request());
+    };
+
+    let path = match import_dir.join(path).canonicalize() {
+        Ok(path) if path.is_file() && path.starts_with(&import_dir) => path,
+        _ => {
+            return Err(AppError::new(
+                AppErrorKind::LocalData,
+                "audio file can't be imported, it has to be a file in the import directory",
+                &[&path_info],
+            )
+            .bad_request())
+        }
+    };
+
+    let is_supported = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_FILE_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+
+    if !is_supported {
+        return Err(AppError::new(
+            AppErrorKind::LocalData,
+            format!(
+                "unsupported audio file format, expected one of: {formats}",
+                formats = AUDIO_FILE_EXTENSIONS.join(", ")
+            ),
+            &[&path_info],
+        )
+        .bad_request());
+    }
+
+    check_audio_file_decodable(&path)?;
+
+    Ok(path)
+}
+
+/// Copies the audio file at `path` into `folder` of the audio directory and stores its metadata,
+/// files that were already imported are only looked up
+///
+/// The file is hard linked instead if it is on the same file system as the audio directory.
+pub async fn import_local_audio_file(
+    path: &Path,
+    folder: Option<&str>,
+) -> Result<(ItemUid<Arc<str>>, AudioMetadata), AppError> {
+    let path = validate_local_audio_file(path, local_import_dir().as_deref())?;
+    let uid = LocalFilePath(&path).uid();
+
+    if let Some(metadata) = get_audio_metadata_from_db(&uid).await? {
+        return Ok((uid, metadata));
+    }
+
+    let path_info = format!("PATH: {path}", path = path.display());
+    let stored_path = uid
+        .to_path_in_folder(folder)
+        .with_extension(path.extension().unwrap_or_default().to_ascii_lowercase());
+
+    // reading the tags and measuring the loudness decodes the whole file
+    let metadata = actix_rt::task::spawn_blocking(move || {
+        let mut metadata = read_audio_metadata(&path)?;
+        store_audio_file(&path, &stored_path)?;
+
+        metadata.gain_db = metadata
+            .gain_db
+            .or_else(|| normalization_gain_db_or_log(&stored_path));
+
+        Ok::<_, AppError>(metadata)
+    })
+    .await
+    .into_app_err(
+        "failed to import audio file",
+        AppErrorKind::LocalData,
+        &[&path_info],
+    )??;
+
+    store_audio_metadata_if_not_exists(&uid, &metadata).await?;
+
+    if let Some(folder) = folder {
+        register_audio_folder(&uid, folder);
+    }
+
+    Ok((uid, metadata))
+}
+
+fn store_audio_file(path: &Path, stored_path: &Path) -> Result<(), AppError> {
+    let path_info = format!("PATH: {path}", path = path.display());
+    let stored_path_info = format!("STORED_PATH: {path}", path = stored_path.display());
+
+    if let Some(dir) = stored_path.parent() {
+        fs::create_dir_all(dir).into_app_err(
+            "failed to create audio folder",
+            AppErrorKind::LocalData,
+            &[&format!("DIR: {dir}", dir = dir.display())],
+        )?;
+    }
+
+    // links fail across file systems or if a previous import left a file behind
+    if fs::hard_link(path, stored_path).is_err() {
+        fs::copy(path, stored_path).into_app_err(
+            "failed to copy audio file",
+            AppErrorKind::LocalData,
+            &[&path_info, &stored_path_info],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_utils::{test_dir, write_test_wav};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_validate_local_audio_file() {
+        let dir = test_dir("validate-local-audio-file");
+
+        let wav = dir.join("Song.WAV");
+        write_test_wav(&wav, 48_000, 2, 4_800, |_| 0);
+        assert_eq!(
+            validate_local_audio_file(&wav, Some(&dir)).unwrap(),
+            wav.canonicalize().unwrap()
+        );
+        assert_eq!(
+            validate_local_audio_file(Path::new("Song.WAV"), Some(&dir)).unwrap(),
+            wav.canonicalize().unwrap()
+        );
+
+        let text = dir.join("notes.txt");
+        fs::write(&text, "not audio").unwrap();
+        let fake_wav = dir.join("fake.wav");
+        fs::write(&fake_wav, "not audio").unwrap();
+
+        for path in [text, fake_wav, dir.clone(), dir.join("missing.wav")] {
+            let err = validate_local_audio_file(&path, Some(&dir)).unwrap_err();
+            assert_eq!(
+                actix_web::ResponseError::status_code(&err).as_u16(),
+                400,
+                "PATH: {path:?}"
+            );
+        }
+
+        assert!(validate_local_audio_file(&wav, None).is_err());
+    }
+
+    #[test]
+    fn test_files_outside_of_import_dir_look_missing() {
+        let import_dir = test_dir("local-import-dir");
+        let outside = test_dir("local-import-outside").join("song.wav");
+        write_test_wav(&outside, 48_000, 2, 4_800, |_| 0);
+
+        // absolute and escaping the import directory through `..`
+        let rejected = [
+            outside.clone(),
+            Path::new("..").join(outside.strip_prefix(import_dir.parent().unwrap()).unwrap()),
+        ];
+        let missing =
+            validate_local_audio_file(Path::new("missing.wav"), Some(&import_dir)).unwrap_err();
+
+        for path in rejected {
+            let err = validate_local_audio_file(&path, Some(&import_dir)).unwrap_err();
+
+            assert_eq!(
+                actix_web::ResponseError::status_code(&err),
+                actix_web::ResponseError::status_code(&missing),
+                "PATH: {path:?}"
+            );
+            assert_eq!(
+                serde_json::to_value(&err).unwrap(),
+                serde_json::to_value(&missing).unwrap(),
+                "PATH: {path:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_local_file_uid() {
+        let uid = LocalFilePath("/music/rips/song.flac").uid();
+
+        assert!(matches!(
+            AudioKind::from_uid(&uid),
+            Some(AudioKind::LocalFile)
+        ));
+        assert_eq!(uid.0, LocalFilePath("/music/rips/song.flac").uid().0);
+        assert_ne!(uid.0, LocalFilePath("/music/rips/other.flac").uid().0);
+        assert_eq!(
+            uid.0.len(),
+            AudioKind::LocalFile.prefix().len() + 16,
+            "UID: {uid:?}"
+        );
+    }
+}
@@ -2,11 +2,20 @@ use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 
-use self::download_identifier::{YoutubePlaylistUrl, YoutubeVideoUrl};
+use self::download_identifier::{SoundCloudTrackUrl, YoutubePlaylistUrl, YoutubeVideoUrl};
+
+pub use youtube::YT_DLP_COMMAND;
 
 pub mod actor;
 pub mod download_identifier;
 pub mod info;
+pub mod library;
+pub mod local_file;
+pub mod playback_activity;
+pub mod process;
+pub mod rate_limit;
+mod soundcloud;
+pub mod workers;
 mod youtube;
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -14,6 +23,7 @@ pub enum DownloadRequiredInformation {
     StoredLocally { uid: Arc<str> },
     YoutubeVideo { url: YoutubeVideoUrl<Arc<str>> },
     YoutubePlaylist(YoutubePlaylistDownloadInfo),
+    SoundCloudTrack { url: SoundCloudTrackUrl<Arc<str>> },
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
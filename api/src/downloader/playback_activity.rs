@@ -0,0 +1,53 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Whether any node is playing, used to hold back downloads while playback is active
+///
+/// Cloning the handle shares the activity, the downloader actor updates it while the download
+/// thread reads it before every download. Downloads only wait if waiting was enabled in the config.
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackActivity {
+    wait_for_idle: bool,
+    playing: Arc<AtomicBool>,
+}
+
+impl PlaybackActivity {
+    pub fn new(wait_for_idle: bool) -> Self {
+        Self {
+            wait_for_idle,
+            playing: Default::default(),
+        }
+    }
+
+    pub fn set_playing(&self, playing: bool) {
+        self.playing.store(playing, Ordering::Relaxed);
+    }
+
+    /// whether new downloads have to wait until no node is playing anymore
+    pub fn should_wait(&self) -> bool {
+        self.wait_for_idle && self.playing.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playback_activity_wait() {
+        let activity = PlaybackActivity::new(true);
+        assert!(!activity.should_wait());
+
+        activity.clone().set_playing(true);
+        assert!(activity.should_wait());
+
+        activity.set_playing(false);
+        assert!(!activity.should_wait());
+
+        let disabled = PlaybackActivity::new(false);
+        disabled.set_playing(true);
+        assert!(!disabled.should_wait());
+    }
+}
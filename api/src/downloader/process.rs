@@ -0,0 +1,164 @@
+use std::{
+    io::{self, BufRead, BufReader},
+    process::{Child, Command, ExitStatus},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::node::node_server::SourceName;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Handle to one of the download processes the downloader is currently running
+///
+/// Cloning the handle shares the underlying slot, this allows the downloader actor to cancel a
+/// download that is running on the download thread.
+#[derive(Debug, Clone, Default)]
+pub struct RunningDownload(Arc<Mutex<RunningDownloadSlot>>);
+
+#[derive(Debug, Default)]
+struct RunningDownloadSlot {
+    source_name: Option<SourceName>,
+    child: Option<Child>,
+    cancelled: bool,
+}
+
+impl RunningDownload {
+    /// marks the start of a new download request for the given source
+    pub fn start(&self, source_name: Option<SourceName>) {
+        if let Ok(mut slot) = self.0.lock() {
+            *slot = RunningDownloadSlot {
+                source_name,
+                ..Default::default()
+            };
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.lock().map(|slot| slot.cancelled).unwrap_or(false)
+    }
+
+    /// cancels the current download request if it belongs to `source_name`, killing the running
+    /// process
+    ///
+    /// Returns true if the current download was cancelled.
+    pub fn cancel(&self, source_name: &SourceName) -> bool {
+        let Ok(mut slot) = self.0.lock() else {
+            return false;
+        };
+
+        if slot.source_name.as_ref() != Some(source_name) {
+            return false;
+        }
+
+        slot.cancelled = true;
+        if let Some(mut child) = slot.child.take() {
+            if let Err(err) = child.kill().and_then(|_| child.wait()) {
+                log::error!("failed to kill download process\nERROR: {err}");
+            }
+        }
+
+        true
+    }
+
+    /// spawns the command and blocks until it exits or the download is cancelled
+    ///
+    /// Every line the command writes to a piped stdout is passed to `on_output_line` while it runs.
+    pub fn run(
+        &self,
+        command: &mut Command,
+        mut on_output_line: impl FnMut(&str),
+    ) -> io::Result<ExitStatus> {
+        let stdout = {
+            let mut slot = self
+                .0
+                .lock()
+                .map_err(|err| io::Error::other(err.to_string()))?;
+
+            if slot.cancelled {
+                return Err(cancelled_err());
+            }
+
+            let mut child = command.spawn()?;
+            let stdout = child.stdout.take();
+            slot.child = Some(child);
+
+            stdout
+        };
+
+        // ends once the process exits or is killed by a cancellation
+        if let Some(stdout) = stdout {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                on_output_line(&line);
+            }
+        }
+
+        loop {
+            {
+                let mut slot = self
+                    .0
+                    .lock()
+                    .map_err(|err| io::Error::other(err.to_string()))?;
+
+                let Some(child) = slot.child.as_mut() else {
+                    return Err(cancelled_err());
+                };
+
+                if let Some(status) = child.try_wait()? {
+                    slot.child = None;
+                    return Ok(status);
+                }
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+fn cancelled_err() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "download was cancelled")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+
+    #[test]
+    fn test_cancel_running_download() {
+        let running = RunningDownload::default();
+        running.start(Some("node".into()));
+
+        let handle = {
+            let running = running.clone();
+            std::thread::spawn(move || running.run(Command::new("sleep").arg("10"), |_| {}))
+        };
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!running.cancel(&"other node".into()));
+        assert!(running.cancel(&"node".into()));
+
+        let err = handle.join().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        assert!(running.is_cancelled());
+    }
+
+    #[test]
+    fn test_output_lines_of_running_download() {
+        let running = RunningDownload::default();
+        running.start(None);
+
+        let mut lines = vec![];
+        let status = running
+            .run(
+                Command::new("printf")
+                    .arg("first\\nsecond\\n")
+                    .stdout(Stdio::piped()),
+                |line| lines.push(line.to_owned()),
+            )
+            .unwrap();
+
+        assert!(status.success());
+        pretty_assertions::assert_eq!(lines, vec!["first", "second"]);
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::{Arc, Mutex};
+
+use crate::error::{AppError, AppErrorKind};
+
+/// Max download rate passed to `yt-dlp --limit-rate`
+///
+/// Cloning the handle shares the value, this allows the downloader actor to change the limit of
+/// downloads running on the download thread. The limit is read every time a download process is
+/// launched.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadRateLimit(Arc<Mutex<Option<Arc<str>>>>);
+
+impl DownloadRateLimit {
+    pub fn new(rate_limit: Option<Arc<str>>) -> Self {
+        Self(Arc::new(Mutex::new(rate_limit)))
+    }
+
+    pub fn get(&self) -> Option<Arc<str>> {
+        self.0.lock().ok().and_then(|rate_limit| rate_limit.clone())
+    }
+
+    pub fn set(&self, rate_limit: Option<Arc<str>>) {
+        if let Ok(mut current) = self.0.lock() {
+            *current = rate_limit;
+        }
+    }
+}
+
+/// Checks that `rate_limit` is a rate `yt-dlp` understands, bytes per second with an optional
+/// `K`, `M` or `G` suffix (e.g. `500K`, `2.5M`)
+pub fn validate_rate_limit(rate_limit: &str) -> Result<Arc<str>, AppError> {
+    let rate_limit = rate_limit.trim();
+
+    let number = rate_limit
+        .strip_suffix(['K', 'M', 'G', 'k', 'm', 'g'])
+        .unwrap_or(rate_limit);
+
+    let is_valid = !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && number.parse::<f64>().is_ok_and(|rate| rate > 0.0);
+
+    if !is_valid {
+        return Err(AppError::new(
+            AppErrorKind::Download,
+            "invalid download rate limit, expected bytes per second like '500K' or '2.5M'",
+            &[&format!("RATE_LIMIT: {rate_limit}")],
+        )
+        .bad_request());
+    }
+
+    Ok(rate_limit.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_validate_rate_limit() {
+        assert_eq!(validate_rate_limit(" 500K ").unwrap().as_ref(), "500K");
+        assert_eq!(validate_rate_limit("2.5M").unwrap().as_ref(), "2.5M");
+        assert_eq!(validate_rate_limit("100000").unwrap().as_ref(), "100000");
+
+        for invalid in ["", "K", "0", "-1M", "1.5T", "fast", "1e6", "1 M", "inf"] {
+            assert!(validate_rate_limit(invalid).is_err(), "RATE: {invalid}");
+        }
+    }
+}
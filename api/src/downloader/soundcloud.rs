@@ -0,0 +1,118 @@
+use std::{fs, sync::Arc};
+
+use actix::Recipient;
+
+use crate::{
+    audio_hosts::{loudness::normalization_gain_db_or_log, soundcloud::get_track_metadata},
+    audio_playback::audio_item::AudioMetadata,
+    database::{
+        fetch_data::get_audio_metadata_from_db, store_data::store_audio_metadata_if_not_exists,
+    },
+    error::{AppError, AppErrorKind, IntoAppError},
+};
+
+use super::{
+    actor::NotifyDownloadUpdate,
+    download_identifier::{
+        register_audio_folder, resolve_audio_file_path, Identifier, SoundCloudTrackUrl,
+    },
+    info::DownloadInfo,
+    workers::DownloadJob,
+    youtube::download_audio_with_yt_dlp,
+};
+
+pub async fn process_soundcloud_track(
+    url: &SoundCloudTrackUrl<impl AsRef<str> + std::fmt::Debug>,
+    folder: Option<&str>,
+    addr: &Recipient<NotifyDownloadUpdate>,
+    job: &DownloadJob,
+) {
+    let info = DownloadInfo::soundcloud_track(&url.0);
+
+    let metadata = match download_and_store_soundcloud_audio_with_metadata(
+        url, folder, &info, addr, job,
+    )
+    .await
+    {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            addr.do_send(NotifyDownloadUpdate::SingleFinished(Err((info, err))));
+            return;
+        }
+    };
+
+    let uid = url.uid();
+    addr.do_send(NotifyDownloadUpdate::SingleFinished(Ok((
+        info, metadata, uid,
+    ))));
+}
+
+/// Downloads the audio into `folder` if it isn't stored yet, audio that is already stored keeps its
+/// location
+///
+/// The metadata is read by yt-dlp as well, soundcloud has no public API.
+pub async fn download_and_store_soundcloud_audio_with_metadata(
+    url: &SoundCloudTrackUrl<impl AsRef<str> + std::fmt::Debug>,
+    folder: Option<&str>,
+    info: &DownloadInfo,
+    addr: &Recipient<NotifyDownloadUpdate>,
+    job: &DownloadJob,
+) -> Result<AudioMetadata, AppError> {
+    let uid = url.uid();
+    if let Some(metadata) = get_audio_metadata_from_db(&uid).await? {
+        return Ok(metadata);
+    }
+
+    let mut metadata = AudioMetadata::from(get_track_metadata(url.0.as_ref()).await?);
+
+    let path = resolve_audio_file_path(&url.to_path_in_folder(folder));
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).into_app_err(
+            "failed to create download folder",
+            AppErrorKind::LocalData,
+            &[&format!("DIR: {dir}", dir = dir.display())],
+        )?;
+    }
+
+    addr.do_send(NotifyDownloadUpdate::Started(info.clone()));
+
+    let url_info = format!("URL: {url}", url = url.0.as_ref());
+    metadata.gain_db = {
+        let url: Arc<str> = url.0.as_ref().into();
+        let running = job.running.clone();
+        let rate_limit = job.rate_limit.get();
+        let (info, addr) = (info.clone(), addr.clone());
+
+        actix_rt::task::spawn_blocking(move || {
+            download_audio_with_yt_dlp(
+                &url,
+                &path.to_string_lossy(),
+                &running,
+                rate_limit.as_deref(),
+                &info,
+                &addr,
+                "failed to download soundcloud track",
+            )?;
+
+            Ok::<_, AppError>(normalization_gain_db_or_log(&path))
+        })
+        .await
+        .into_app_err(
+            "failed to download soundcloud track",
+            AppErrorKind::Download,
+            &[&url_info],
+        )??
+    };
+
+    let _db_write = job.db_writes.lock().await;
+
+    // the same track can be downloaded for multiple nodes at the same time, only the first one is
+    // stored
+    store_audio_metadata_if_not_exists(&uid, &metadata).await?;
+
+    if let Some(folder) = folder {
+        register_audio_folder(&uid, folder);
+    }
+
+    Ok(get_audio_metadata_from_db(&uid).await?.unwrap_or(metadata))
+}
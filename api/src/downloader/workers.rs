@@ -0,0 +1,162 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use crate::node::node_server::SourceName;
+
+use super::{
+    actor::SerializableDownloadAudioRequest, process::RunningDownload,
+    rate_limit::DownloadRateLimit,
+};
+
+pub const DEFAULT_DOWNLOAD_CONCURRENCY: usize = 3;
+
+/// Downloads that were taken from the queue and are running right now
+///
+/// Cloning the handle shares the workers, this allows the downloader actor to cancel and persist
+/// downloads that are running on the download thread. At most
+/// [`concurrency`](Self::concurrency) downloads run at the same time.
+#[derive(Debug, Clone)]
+pub struct DownloadWorkers {
+    concurrency: Arc<AtomicUsize>,
+    next_id: Arc<AtomicUsize>,
+    in_flight: Arc<Mutex<Vec<InFlightDownload>>>,
+}
+
+/// What a single running download shares with the downloader
+#[derive(Debug, Clone)]
+pub struct DownloadJob {
+    pub running: RunningDownload,
+    pub rate_limit: DownloadRateLimit,
+    /// held while storing a download, downloads run at the same time but write to the database one
+    /// after another
+    pub db_writes: Arc<tokio::sync::Mutex<()>>,
+}
+
+#[derive(Debug)]
+struct InFlightDownload {
+    id: usize,
+    running: RunningDownload,
+    request: SerializableDownloadAudioRequest,
+}
+
+impl DownloadWorkers {
+    pub fn new(concurrency: usize) -> Self {
+        Self {
+            concurrency: Arc::new(AtomicUsize::new(concurrency.max(1))),
+            next_id: Default::default(),
+            in_flight: Default::default(),
+        }
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.load(Ordering::Relaxed)
+    }
+
+    /// downloads that are already running when the concurrency is lowered are finished
+    pub fn set_concurrency(&self, concurrency: usize) {
+        self.concurrency
+            .store(concurrency.max(1), Ordering::Relaxed);
+    }
+
+    pub fn has_free_worker(&self) -> bool {
+        self.in_flight
+            .lock()
+            .is_ok_and(|in_flight| in_flight.len() < self.concurrency())
+    }
+
+    /// marks `request` as running, returns the id to [`finish`](Self::finish) it with and the
+    /// handle of its download process
+    pub fn start(&self, request: SerializableDownloadAudioRequest) -> (usize, RunningDownload) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let running = RunningDownload::default();
+        running.start(request.source_name.clone());
+
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.push(InFlightDownload {
+                id,
+                running: running.clone(),
+                request,
+            });
+        }
+
+        (id, running)
+    }
+
+    pub fn finish(&self, id: usize) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.retain(|download| download.id != id);
+        }
+    }
+
+    /// cancels every running download of `source_name`, returns how many were cancelled
+    pub fn cancel(&self, source_name: &SourceName) -> usize {
+        let Ok(in_flight) = self.in_flight.lock() else {
+            return 0;
+        };
+
+        in_flight
+            .iter()
+            .filter(|download| download.running.cancel(source_name))
+            .count()
+    }
+
+    /// requests of the running downloads in the order they were started in
+    pub fn requests(&self) -> Vec<SerializableDownloadAudioRequest> {
+        self.in_flight
+            .lock()
+            .map(|in_flight| {
+                in_flight
+                    .iter()
+                    .map(|download| download.request.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloader::{download_identifier::YoutubeVideoUrl, DownloadRequiredInformation};
+    use pretty_assertions::assert_eq;
+
+    fn request(source_name: &str, url: &str) -> SerializableDownloadAudioRequest {
+        SerializableDownloadAudioRequest {
+            source_name: Some(source_name.into()),
+            required_info: DownloadRequiredInformation::YoutubeVideo {
+                url: YoutubeVideoUrl(url.into()),
+            },
+            folder: None,
+        }
+    }
+
+    #[test]
+    fn test_download_workers() {
+        let workers = DownloadWorkers::new(2);
+
+        let (first, _) = workers.start(request("kitchen", "a"));
+        assert!(workers.has_free_worker());
+        let (_, running) = workers.start(request("office", "b"));
+        assert!(!workers.has_free_worker());
+
+        assert_eq!(
+            workers.requests(),
+            vec![request("kitchen", "a"), request("office", "b")]
+        );
+
+        assert_eq!(workers.cancel(&"office".into()), 1);
+        assert!(running.is_cancelled());
+        assert_eq!(workers.cancel(&"living_room".into()), 0);
+
+        workers.finish(first);
+        assert!(workers.has_free_worker());
+        assert_eq!(workers.requests(), vec![request("office", "b")]);
+
+        workers.set_concurrency(0);
+        assert_eq!(workers.concurrency(), 1);
+        assert!(!workers.has_free_worker());
+    }
+}
@@ -1,48 +1,63 @@
-use std::process::Command;
+use std::{
+    fs,
+    process::{Command, Stdio},
+    sync::Arc,
+    time::Duration,
+};
 
 use actix::Recipient;
 use sqlx::PgPool;
 
 use crate::{
-    audio_hosts::youtube::video::get_video_metadata,
+    audio_hosts::{loudness::normalization_gain_db_or_log, youtube::video::get_video_metadata},
     audio_playback::audio_item::AudioMetadata,
     database::fetch_data::get_audio_metadata_from_db,
     error::{AppError, AppErrorKind, IntoAppError},
+    message_send_handler::{MessageLimiter, RateLimiter},
     yt_api_key,
 };
 
 use super::{
     actor::NotifyDownloadUpdate,
-    download_identifier::{Identifier, YoutubeVideoUrl},
-    info::DownloadInfo,
+    download_identifier::{
+        register_audio_folder, resolve_audio_file_path, Identifier, YoutubeVideoUrl,
+        DEFAULT_AUDIO_EXTENSION,
+    },
+    info::{DownloadInfo, DownloadProgress},
+    process::RunningDownload,
+    workers::DownloadJob,
 };
 
+/// resolved through `PATH`
+pub const YT_DLP_COMMAND: &str = "yt-dlp";
+
+/// most frequent progress updates of a single download, yt-dlp reports progress many times per
+/// second
+pub const DOWNLOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// prefix of the progress lines yt-dlp prints with [`PROGRESS_TEMPLATE`]
+const PROGRESS_LINE_PREFIX: &str = "[progress]";
+const PROGRESS_TEMPLATE: &str = "download:[progress] %(progress._percent_str)s %(progress.eta)s";
+
 pub async fn process_single_youtube_video(
     url: &YoutubeVideoUrl<impl AsRef<str> + std::fmt::Display + std::fmt::Debug>,
+    folder: Option<&str>,
     pool: &PgPool,
     addr: &Recipient<NotifyDownloadUpdate>,
+    job: &DownloadJob,
 ) {
     let info = DownloadInfo::yt_video(&url.0);
 
-    let tx = match pool.begin().await.into_app_err(
-        "failed to start transaction",
-        AppErrorKind::Database,
-        &[],
-    ) {
-        Ok(tx) => tx,
-        Err(err) => {
-            addr.do_send(NotifyDownloadUpdate::SingleFinished(Err((info, err))));
-            return;
-        }
-    };
-
-    let metadata = match download_and_store_youtube_audio_with_metadata(url, tx).await {
-        Ok(metadata) => metadata,
-        Err(err) => {
-            addr.do_send(NotifyDownloadUpdate::SingleFinished(Err((info, err))));
-            return;
-        }
-    };
+    let metadata =
+        match download_and_store_youtube_audio_with_metadata(url, folder, pool, &info, addr, job)
+            .await
+        {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                addr.do_send(NotifyDownloadUpdate::SingleFinished(Err((info, err))));
+                return;
+            }
+        };
 
     let uid = url.uid();
     addr.do_send(NotifyDownloadUpdate::SingleFinished(Ok((
@@ -50,67 +65,238 @@ pub async fn process_single_youtube_video(
     ))));
 }
 
+/// Downloads the audio into `folder` if it isn't stored yet, audio that is already stored keeps its
+/// location
 pub async fn download_and_store_youtube_audio_with_metadata(
     url: &YoutubeVideoUrl<impl AsRef<str> + std::fmt::Debug>,
-    mut tx: sqlx::Transaction<'_, sqlx::Postgres>,
+    folder: Option<&str>,
+    pool: &PgPool,
+    info: &DownloadInfo,
+    addr: &Recipient<NotifyDownloadUpdate>,
+    job: &DownloadJob,
 ) -> Result<AudioMetadata, AppError> {
     let uid = url.uid();
     if let Some(metadata) = get_audio_metadata_from_db(&uid).await? {
         return Ok(metadata);
     }
 
-    let metadata: AudioMetadata =
+    let mut metadata: AudioMetadata =
         AudioMetadata::from(get_video_metadata(url.0.as_ref(), yt_api_key()).await?);
 
+    let path = resolve_audio_file_path(&url.to_path_in_folder(folder));
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).into_app_err(
+            "failed to create download folder",
+            AppErrorKind::LocalData,
+            &[&format!("DIR: {dir}", dir = dir.display())],
+        )?;
+    }
+
+    addr.do_send(NotifyDownloadUpdate::Started(info.clone()));
+
+    let url_info = format!("URL: {url}", url = url.0.as_ref());
+    metadata.gain_db = {
+        let url: Arc<str> = url.0.as_ref().into();
+        let running = job.running.clone();
+        let rate_limit = job.rate_limit.get();
+        let (info, addr) = (info.clone(), addr.clone());
+
+        // the download process blocks until it exits, other downloads keep running on the download
+        // thread in the meantime
+        actix_rt::task::spawn_blocking(move || {
+            download_youtube_audio(
+                &url,
+                &path.to_string_lossy(),
+                &running,
+                rate_limit.as_deref(),
+                &info,
+                &addr,
+            )?;
+
+            // measured once the audio is stored
+            Ok::<_, AppError>(normalization_gain_db_or_log(&path))
+        })
+        .await
+        .into_app_err(
+            "failed to download youtube video",
+            AppErrorKind::Download,
+            &[&url_info],
+        )??
+    };
+
+    let _db_write = job.db_writes.lock().await;
+
+    // the same video can be downloaded for multiple nodes at the same time, only the first one is
+    // stored
+    if let Some(metadata) = get_audio_metadata_from_db(&uid).await? {
+        return Ok(metadata);
+    }
+
+    let mut tx = pool.begin().await.into_app_err(
+        "failed to start transaction",
+        AppErrorKind::Database,
+        &[],
+    )?;
+
     let key = uid.0.as_ref();
-    sqlx::query!("INSERT INTO audio_metadata (identifier, name, author, duration, cover_art_url) values ($1, $2, $3, $4, $5)",
+    sqlx::query!("INSERT INTO audio_metadata (identifier, name, author, duration, cover_art_url, gain_db) values ($1, $2, $3, $4, $5, $6)",
                     key,
                     metadata.name.inner_as_ref(),
                     metadata.author.inner_as_ref(),
                     metadata.duration,
-                    metadata.cover_art_url.inner_as_ref()
+                    metadata.cover_art_url.inner_as_ref(),
+                    metadata.gain_db
                 )
                 .execute(&mut *tx)
                 .await.into_app_err("failed to store audio metadata", AppErrorKind::Database, 
                                     &[&format!("UID: {key}")]
                                     )?;
 
-    let path = url.to_path_with_ext();
-    download_youtube_audio(url.0.as_ref(), &path.to_string_lossy())?;
-
     tx.commit()
         .await
         .into_app_err("failed to commit transaction", AppErrorKind::Database, &[])?;
 
+    if let Some(folder) = folder {
+        register_audio_folder(&uid, folder);
+    }
+
     Ok(metadata)
 }
 
-pub fn download_youtube_audio(url: &str, download_location: &str) -> Result<(), AppError> {
-    let out = Command::new("yt-dlp")
-        .args([
-            "-f",
-            "bestaudio",
-            "-x",
-            "--audio-format",
-            "wav",
-            "-o",
-            download_location,
-            url,
-        ])
-        .output()
-        .into_app_err(
-            "failed to download youtube video",
-            AppErrorKind::Download,
-            &[&format!("URL: {url}")],
-        )?;
+/// Downloads the audio of the video at `url`, `addr` is notified about the progress of `info`
+pub fn download_youtube_audio(
+    url: &str,
+    download_location: &str,
+    running: &RunningDownload,
+    rate_limit: Option<&str>,
+    info: &DownloadInfo,
+    addr: &Recipient<NotifyDownloadUpdate>,
+) -> Result<(), AppError> {
+    download_audio_with_yt_dlp(
+        url,
+        download_location,
+        running,
+        rate_limit,
+        info,
+        addr,
+        "failed to download youtube video",
+    )
+}
+
+/// Downloads the audio at `url` from any site yt-dlp supports, `err_msg` describes a failed
+/// download
+pub(super) fn download_audio_with_yt_dlp(
+    url: &str,
+    download_location: &str,
+    running: &RunningDownload,
+    rate_limit: Option<&str>,
+    info: &DownloadInfo,
+    addr: &Recipient<NotifyDownloadUpdate>,
+    err_msg: &str,
+) -> Result<(), AppError> {
+    let mut command = Command::new(YT_DLP_COMMAND);
+    command.args([
+        "-f",
+        "bestaudio",
+        "-x",
+        "--audio-format",
+        DEFAULT_AUDIO_EXTENSION,
+        "-o",
+        download_location,
+        "--newline",
+        "--progress-template",
+        PROGRESS_TEMPLATE,
+    ]);
+
+    // keeps background downloads from starving streaming playback on slow connections
+    if let Some(rate_limit) = rate_limit {
+        command.args(["--limit-rate", rate_limit]);
+    }
 
-    if out.status.code().unwrap_or(1) != 0 {
+    let mut limiter = RateLimiter::with_rate_limit(DOWNLOAD_PROGRESS_INTERVAL);
+    let on_output_line = |line: &str| {
+        let Some((progress_percent, eta_secs)) = parse_progress_line(line) else {
+            return;
+        };
+
+        let msg = NotifyDownloadUpdate::Progress(DownloadProgress {
+            info: info.clone(),
+            progress_percent,
+            eta_secs,
+        });
+
+        if limiter.can_send(&msg) {
+            limiter.has_sent(&msg);
+            addr.do_send(msg);
+        }
+    };
+
+    let status = running
+        .run(
+            command
+                .arg(url)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null()),
+            on_output_line,
+        )
+        .into_app_err(err_msg, AppErrorKind::Download, &[&format!("URL: {url}")])?;
+
+    if status.code().unwrap_or(1) != 0 {
         return Err(AppError::new(
             AppErrorKind::Download,
-            "failed to download youtube video",
-            &["failed to parse stderr of 'yt-dlp' command"],
+            err_msg,
+            &[&format!("URL: {url}"), &format!("EXIT STATUS: {status}")],
         ));
     }
 
     Ok(())
 }
+
+/// percentage and eta in seconds of a line printed with [`PROGRESS_TEMPLATE`], `None` for any
+/// other line
+///
+/// The eta is `None` while yt-dlp can't estimate it yet.
+fn parse_progress_line(line: &str) -> Option<(f32, Option<u32>)> {
+    let mut values = line
+        .trim()
+        .strip_prefix(PROGRESS_LINE_PREFIX)?
+        .split_whitespace();
+
+    let progress_percent = values
+        .next()?
+        .trim_end_matches('%')
+        .parse::<f32>()
+        .ok()?
+        .clamp(0.0, 100.0);
+    let eta_secs = values.next().and_then(|eta| eta.parse().ok());
+
+    Some((progress_percent, eta_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_progress_line() {
+        assert_eq!(
+            parse_progress_line("[progress]  42.5% 17"),
+            Some((42.5, Some(17)))
+        );
+        assert_eq!(
+            parse_progress_line("[progress]   0.0% NA"),
+            Some((0.0, None))
+        );
+        assert_eq!(
+            parse_progress_line("[progress] 100.0%"),
+            Some((100.0, None))
+        );
+
+        assert_eq!(
+            parse_progress_line("[download] Destination: audio.mp3"),
+            None
+        );
+        assert_eq!(parse_progress_line("[progress] N/A% NA"), None);
+    }
+}
@@ -1,6 +1,7 @@
 use std::{fmt::Display, sync::Arc};
 
 use actix::Message;
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
@@ -19,6 +20,8 @@ pub struct AppError {
     kind: AppErrorKind,
     info: Arc<str>,
     detailed_info: Arc<str>,
+    /// status of REST responses with this error, not part of the error the user receives
+    status: StatusCode,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -29,6 +32,7 @@ pub enum AppErrorKind {
     LocalData,
     Database,
     Download,
+    RateLimited,
 }
 
 #[derive(Debug, Serialize, TS)]
@@ -45,6 +49,7 @@ impl Clone for AppError {
             kind: self.kind.clone(),
             info: Arc::clone(&self.info),
             detailed_info: Arc::clone(&self.detailed_info),
+            status: self.status,
         }
     }
 }
@@ -78,6 +83,7 @@ impl Display for AppErrorKind {
             Self::Database => "DATABASE ERROR",
             Self::Download => "DOWNLOAD ERROR",
             Self::LocalData => "LOCAL DATA ERROR",
+            Self::RateLimited => "RATE LIMIT ERROR",
         };
 
         write!(f, "{str}")
@@ -95,6 +101,7 @@ impl<E: Display> IntoAppError<AppError> for E {
             kind,
             info: info.into(),
             detailed_info: AppError::format_detailed_info(self, extra_details),
+            status: StatusCode::INTERNAL_SERVER_ERROR,
         };
 
         log::error!("{app_err}");
@@ -126,18 +133,47 @@ impl Serialize for AppError {
     }
 }
 
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(self)
+    }
+}
+
 impl AppError {
     pub fn new(kind: AppErrorKind, info: impl Into<Arc<str>>, extra_details: &[&str]) -> Self {
         let app_err = Self {
             kind,
             info: info.into(),
             detailed_info: AppError::format_detailed_info("", extra_details),
+            status: StatusCode::INTERNAL_SERVER_ERROR,
         };
 
         log::error!("{app_err}");
         app_err
     }
 
+    /// marks the error as caused by something the client asked for that doesn't exist
+    pub fn not_found(mut self) -> Self {
+        self.status = StatusCode::NOT_FOUND;
+        self
+    }
+
+    /// marks the error as caused by an invalid request of the client
+    pub fn bad_request(mut self) -> Self {
+        self.status = StatusCode::BAD_REQUEST;
+        self
+    }
+
+    /// marks the error as caused by a client sending too many requests
+    pub fn too_many_requests(mut self) -> Self {
+        self.status = StatusCode::TOO_MANY_REQUESTS;
+        self
+    }
+
     fn format_detailed_info<D: Display>(err: D, extra_details: &[&str]) -> Arc<str> {
         format!(
             "DETAILS:\n{extra}{err}",
@@ -158,3 +194,43 @@ impl AppError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+    use pretty_assertions::assert_eq;
+
+    #[actix_web::test]
+    async fn test_error_response_status() {
+        let cases = [
+            (
+                AppError::new(AppErrorKind::Api, "failed", &[]),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                AppError::new(AppErrorKind::Database, "missing", &[]).not_found(),
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                AppError::new(AppErrorKind::Api, "invalid", &[]).bad_request(),
+                StatusCode::BAD_REQUEST,
+            ),
+        ];
+
+        for (err, status) in cases {
+            let res = ResponseError::error_response(&err);
+            assert_eq!(res.status(), status);
+
+            // the body is the same no matter the status
+            let body = to_bytes(res.into_body()).await.unwrap();
+            assert_eq!(body, serde_json::to_string(&err).unwrap());
+        }
+
+        let err = AppError::new(AppErrorKind::LocalData, "gone", &["PATH: /tmp"]).not_found();
+        assert_eq!(
+            serde_json::to_string(&err.clone()).unwrap(),
+            r#"{"kind":"LocalData","info":"gone"}"#
+        );
+    }
+}
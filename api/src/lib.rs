@@ -1,18 +1,26 @@
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock};
 
 use actix::Addr;
 use brain::brain_server::AudioBrain;
+use commands::rate_limit::CommandRateLimiter;
+use config::RuntimeConfig;
+use downloader::{actor::AudioDownloader, library::LibraryDownloader};
 use sqlx::PgPool;
+use state_storage::restore_state_actor::RestoreStateActor;
 
 pub mod commands;
 pub mod streams;
 
 pub mod audio_hosts;
 pub mod audio_playback;
+pub mod auth;
 pub mod brain;
+pub mod config;
 pub mod database;
 pub mod downloader;
 pub mod error;
+pub mod logging;
+pub mod maintenance;
 pub mod message_send_handler;
 pub mod node;
 pub mod opt_arc;
@@ -23,8 +31,14 @@ pub mod utils;
 
 pub static POOL: OnceLock<PgPool> = OnceLock::new(); // set on server start
 pub static YOUTUBE_API_KEY: OnceLock<String> = OnceLock::new(); // set on server start
+pub static ADMIN_TOKEN: OnceLock<String> = OnceLock::new(); // set on server start if configured
+pub static RUNTIME_CONFIG: OnceLock<RuntimeConfig> = OnceLock::new(); // set on server start
+pub static COMMAND_RATE_LIMITER: OnceLock<Mutex<CommandRateLimiter>> = OnceLock::new(); // set on server start
 
 pub static BRAIN_ADDR: OnceLock<Addr<AudioBrain>> = OnceLock::new(); // set on server start
+pub static DOWNLOADER_ADDR: OnceLock<Addr<AudioDownloader>> = OnceLock::new(); // set on server start
+pub static LIBRARY_DOWNLOADER_ADDR: OnceLock<Addr<LibraryDownloader>> = OnceLock::new(); // set on server start
+pub static RESTORE_STATE_ADDR: OnceLock<Addr<RestoreStateActor>> = OnceLock::new(); // set on server start
 
 pub fn db_pool<'a>() -> &'a PgPool {
     POOL.get().expect("pool should be set at server start")
@@ -42,5 +56,23 @@ pub fn brain_addr<'a>() -> &'a Addr<AudioBrain> {
         .expect("brain address should be set at server start")
 }
 
+pub fn downloader_addr<'a>() -> &'a Addr<AudioDownloader> {
+    DOWNLOADER_ADDR
+        .get()
+        .expect("downloader address should be set at server start")
+}
+
+pub fn library_downloader_addr<'a>() -> &'a Addr<LibraryDownloader> {
+    LIBRARY_DOWNLOADER_ADDR
+        .get()
+        .expect("library downloader address should be set at server start")
+}
+
+pub fn restore_state_addr<'a>() -> &'a Addr<RestoreStateActor> {
+    RESTORE_STATE_ADDR
+        .get()
+        .expect("restore state address should be set at server start")
+}
+
 #[cfg(test)]
 pub mod tests_utils;
@@ -0,0 +1,351 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_FILE_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+const DEFAULT_MAX_ROTATED_FILES: usize = 5;
+
+/// file the logger writes to in production builds
+pub const LOG_FILE: &str = "info.log";
+/// most lines that can be requested from [`tail_log_file`]
+pub const MAX_LOG_TAIL_LINES: usize = 1000;
+/// only the end of the log file is read, keeps a request from loading a huge file into memory
+const MAX_LOG_TAIL_BYTES: u64 = 256 * 1024;
+
+/// Logger settings read from the environment
+///
+/// - `RUST_LOG`: log level (`off`, `error`, `warn`, `info`, `debug`, `trace`), defaults to `info`
+/// - `LOG_MAX_SIZE`: max size of the log file in bytes before it is rotated
+/// - `LOG_MAX_AGE`: max age of the log file before it is rotated (e.g. `7 days`, `12h`)
+/// - `LOG_MAX_FILES`: number of rotated log files to keep
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogConfig {
+    pub level: LevelFilter,
+    pub max_file_size: u64,
+    pub max_file_age: Duration,
+    pub max_rotated_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::Info,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_file_age: DEFAULT_MAX_FILE_AGE,
+            max_rotated_files: DEFAULT_MAX_ROTATED_FILES,
+        }
+    }
+}
+
+impl LogConfig {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            level: read_env("RUST_LOG", |str| LevelFilter::from_str(str).ok())
+                .unwrap_or(default.level),
+            max_file_size: read_env("LOG_MAX_SIZE", |str| str.parse().ok())
+                .unwrap_or(default.max_file_size),
+            max_file_age: read_env("LOG_MAX_AGE", |str| parse_duration::parse(str).ok())
+                .unwrap_or(default.max_file_age),
+            max_rotated_files: read_env("LOG_MAX_FILES", |str| str.parse().ok())
+                .unwrap_or(default.max_rotated_files),
+        }
+    }
+}
+
+fn read_env<T>(key: &str, parse: impl FnOnce(&str) -> Option<T>) -> Option<T> {
+    let value = dotenv::var(key).ok()?;
+    let parsed = parse(value.trim());
+
+    if parsed.is_none() {
+        eprintln!("invalid value '{value}' for environment variable '{key}', using default");
+    }
+
+    parsed
+}
+
+/// File logger that rotates `info.log` -> `info.log.1` -> `info.log.2` ... once the current file
+/// exceeds the configured size or age. Rotated files beyond `max_rotated_files` are deleted.
+pub struct RotatingFileLogger {
+    level: LevelFilter,
+    inner: Mutex<RotatingFile>,
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    /// creation time of the current file, an existing file keeps its age across restarts
+    created_at: SystemTime,
+    start: Instant,
+    max_file_size: u64,
+    max_file_age: Duration,
+    max_rotated_files: usize,
+}
+
+impl RotatingFileLogger {
+    pub fn new(path: impl AsRef<Path>, config: &LogConfig) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        let size = metadata.len();
+        let created_at = metadata
+            .created()
+            .or_else(|_| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+
+        Ok(Self {
+            level: config.level,
+            inner: Mutex::new(RotatingFile {
+                path,
+                file,
+                size,
+                created_at,
+                start: Instant::now(),
+                max_file_size: config.max_file_size,
+                max_file_age: config.max_file_age,
+                max_rotated_files: config.max_rotated_files,
+            }),
+        })
+    }
+}
+
+impl Log for RotatingFileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        if let Ok(mut inner) = self.inner.lock() {
+            let line = inner.format(record);
+            if let Err(err) = inner.write(line.as_bytes()) {
+                eprintln!("failed to write to log file, ERROR: {err}");
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            let _ = inner.file.flush();
+        }
+    }
+}
+
+impl RotatingFile {
+    fn format(&self, record: &Record) -> String {
+        let now = self.start.elapsed();
+        let seconds = now.as_secs();
+
+        format!(
+            "[{:02}:{:02}:{:02}.{:03}] {:6} {}\n",
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60,
+            now.subsec_millis(),
+            record.level(),
+            record.args()
+        )
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let too_large = self.size + bytes.len() as u64 > self.max_file_size;
+        let too_old = self
+            .created_at
+            .elapsed()
+            .is_ok_and(|age| age > self.max_file_age);
+
+        if self.size > 0 && (too_large || too_old) {
+            self.rotate()?;
+        }
+
+        self.file.write_all(bytes)?;
+        self.size += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.max_rotated_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = rotated_path(&self.path, self.max_rotated_files);
+            if oldest.exists() {
+                fs::remove_file(oldest)?;
+            }
+
+            for i in (1..self.max_rotated_files).rev() {
+                let from = rotated_path(&self.path, i);
+                if from.exists() {
+                    fs::rename(from, rotated_path(&self.path, i + 1))?;
+                }
+            }
+
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.created_at = SystemTime::now();
+
+        Ok(())
+    }
+}
+
+pub fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+pub fn log_to_rotating_file(path: impl AsRef<Path>, config: &LogConfig) -> io::Result<()> {
+    let logger = RotatingFileLogger::new(path, config)?;
+
+    log::set_logger(Box::leak(Box::new(logger)))
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    log::set_max_level(config.level);
+
+    Ok(())
+}
+
+/// Returns the last `lines` lines of the log file, capped at [`MAX_LOG_TAIL_LINES`]
+///
+/// Control characters are removed so the lines can be safely displayed. A missing log file, e.g.
+/// in debug builds which log to stderr, results in no lines.
+pub fn tail_log_file(path: &Path, lines: usize) -> io::Result<Vec<String>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+
+    let start = file.metadata()?.len().saturating_sub(MAX_LOG_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes)?;
+    let content = String::from_utf8_lossy(&bytes);
+
+    let mut all_lines: Vec<&str> = content.lines().collect();
+    // the first line is most likely cut off if reading didn't start at the beginning of the file
+    if start > 0 && !all_lines.is_empty() {
+        all_lines.remove(0);
+    }
+
+    let skip = all_lines
+        .len()
+        .saturating_sub(lines.min(MAX_LOG_TAIL_LINES));
+    Ok(all_lines[skip..]
+        .iter()
+        .map(|line| {
+            line.chars()
+                .filter(|char| !char.is_control() || *char == '\t')
+                .collect()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_rotate_log_file() {
+        let dir = std::env::temp_dir().join(format!("audio-api-log-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("info.log");
+        let config = LogConfig {
+            max_file_size: 10,
+            max_rotated_files: 2,
+            ..Default::default()
+        };
+
+        let logger = RotatingFileLogger::new(&path, &config).unwrap();
+        {
+            let mut inner = logger.inner.lock().unwrap();
+            inner.write(b"first....\n").unwrap();
+            inner.write(b"second...\n").unwrap();
+            inner.write(b"third....\n").unwrap();
+            inner.write(b"fourth...\n").unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth...\n");
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+            "third....\n"
+        );
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 2)).unwrap(),
+            "second...\n"
+        );
+        assert!(!rotated_path(&path, 3).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotate_existing_old_log_file() {
+        let dir = crate::tests_utils::test_dir("rotate-old-log-file");
+        let path = dir.join("info.log");
+        fs::write(&path, "old\n").unwrap();
+
+        let config = LogConfig {
+            max_file_age: Duration::from_millis(50),
+            ..Default::default()
+        };
+
+        // the file is older than the max age before the logger is created
+        std::thread::sleep(Duration::from_millis(100));
+
+        let logger = RotatingFileLogger::new(&path, &config).unwrap();
+        logger.inner.lock().unwrap().write(b"new\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "old\n");
+    }
+
+    #[test]
+    fn test_tail_log_file() {
+        let dir = crate::tests_utils::test_dir("tail-log-file");
+        let path = dir.join("info.log");
+
+        fs::write(&path, "first\nsecond\n\x1b[31mthird\tline\n").unwrap();
+
+        assert_eq!(
+            tail_log_file(&path, 2).unwrap(),
+            vec!["second".to_owned(), "[31mthird\tline".to_owned()]
+        );
+        assert_eq!(tail_log_file(&path, 10).unwrap().len(), 3);
+        assert!(tail_log_file(&dir.join("missing.log"), 10)
+            .unwrap()
+            .is_empty());
+
+        let long_line = "x".repeat(100);
+        let content = format!("{long_line}\n").repeat(MAX_LOG_TAIL_BYTES as usize / 50);
+        fs::write(&path, content).unwrap();
+
+        let lines = tail_log_file(&path, usize::MAX).unwrap();
+        assert_eq!(lines.len(), MAX_LOG_TAIL_LINES);
+        assert!(lines.iter().all(|line| *line == long_line));
+    }
+}
@@ -1,17 +1,43 @@
-use std::{env, fs};
+use std::{env, sync::Mutex};
 
 use actix::Actor;
 use actix_rt::Arbiter;
 use audio_manager_api::brain::brain_server::AudioBrain;
-use audio_manager_api::commands::node_commands::receive_node_cmd;
+use audio_manager_api::commands::node_commands::{
+    add_node, receive_node_admin_cmd, receive_node_cmd, remove_node,
+};
+use audio_manager_api::commands::rate_limit::{
+    CommandRateLimiter, CommandRateLimits, DEFAULT_COMMAND_RATE_LIMIT,
+    DEFAULT_HEAVY_COMMAND_RATE_LIMIT,
+};
+use audio_manager_api::config::RuntimeConfig;
 use audio_manager_api::downloader::actor::AudioDownloader;
+use audio_manager_api::downloader::download_identifier::index_audio_folders;
+use audio_manager_api::downloader::library::LibraryDownloader;
+use audio_manager_api::downloader::workers::DEFAULT_DOWNLOAD_CONCURRENCY;
+use audio_manager_api::logging::{log_to_rotating_file, LogConfig, LOG_FILE};
+use audio_manager_api::maintenance::{
+    check_audio_dir_wipe, init_audio_dir, wipe_audio_dir, CONFIRM_WIPE_ENV,
+};
 use audio_manager_api::path::audio_data_dir;
-use audio_manager_api::rest_data_access::{get_audio, get_audio_in_playlist, get_playlists};
+use audio_manager_api::rest_data_access::{
+    add_audio_favorite, batch_delete_audio, cancel_all_node_downloads, delete_audio,
+    download_playlist_to_library, export_state, get_api_version, get_audio, get_audio_bookmarks,
+    get_audio_cover_art, get_audio_file, get_audio_in_playlist, get_audio_peaks,
+    get_audio_resume_position, get_default_volume, get_download_queue, get_download_rate_limit,
+    get_library_downloads, get_library_stats, get_logs, get_node_buffer_stats, get_node_errors,
+    get_node_output_config, get_node_processor_info, get_node_queue, get_node_sessions,
+    get_output_devices, get_play_history, get_playlists, get_runtime_config, get_storage_usage,
+    import_state, move_pending_download, purge_orphaned_audio, queue_from_search,
+    remove_audio_favorite, set_default_volume, set_download_rate_limit, TOTAL_COUNT_HEADER,
+};
 use audio_manager_api::state_storage::restore_state_actor::RestoreStateActor;
 use audio_manager_api::streams::brain_streams::get_brain_stream;
-use audio_manager_api::streams::node_streams::get_node_stream;
-use audio_manager_api::{db_pool, BRAIN_ADDR, POOL, YOUTUBE_API_KEY};
-use log::LevelFilter;
+use audio_manager_api::streams::node_streams::{get_multi_node_stream, get_node_stream};
+use audio_manager_api::{
+    db_pool, ADMIN_TOKEN, BRAIN_ADDR, COMMAND_RATE_LIMITER, DOWNLOADER_ADDR,
+    LIBRARY_DOWNLOADER_ADDR, POOL, RESTORE_STATE_ADDR, RUNTIME_CONFIG, YOUTUBE_API_KEY,
+};
 
 use actix_cors::Cors;
 use actix_web::{App, HttpServer};
@@ -22,18 +48,19 @@ async fn main() -> std::io::Result<()> {
     dotenv::dotenv().expect(".env file should exists");
     dotenv::from_filename(".env-secret").expect(".env-secret file should exists");
 
+    let log_config = LogConfig::from_env();
+
     let addr;
     if cfg!(not(debug_assertions)) {
         addr = dotenv::var("API_ADDRESS_PROD")
             .expect("environment variable 'API_ADDRESS_PROD' should exist for production builds");
 
-        simple_logging::log_to_file("info.log", LevelFilter::Info)
-            .expect("logger should not fail to initialize");
+        log_to_rotating_file(LOG_FILE, &log_config).expect("logger should not fail to initialize");
     } else {
         addr = dotenv::var("API_ADDRESS_DEV")
             .expect("environment variable 'API_ADDRESS_DEV' should exist for debug builds");
 
-        simple_logging::log_to_stderr(LevelFilter::Info);
+        simple_logging::log_to_stderr(log_config.level);
     };
 
     let pool = PgPoolOptions::new()
@@ -55,16 +82,73 @@ async fn main() -> std::io::Result<()> {
         .set(youtube_api_key)
         .expect("should never fail");
 
+    match dotenv::var("ADMIN_TOKEN") {
+        Ok(admin_token) if !admin_token.trim().is_empty() => {
+            ADMIN_TOKEN
+                .set(admin_token.trim().to_owned())
+                .expect("should never fail");
+        }
+        _ => log::warn!("'ADMIN_TOKEN' is not set, admin commands are disabled"),
+    }
+
     clear_dev_db().await;
 
+    if let Err(err) = init_audio_dir(&audio_data_dir()) {
+        log::error!("failed to initialize audio directory\nERROR: {err}");
+    }
+
+    match index_audio_folders(&audio_data_dir()) {
+        Ok(count) => log::info!("indexed {count} audio files stored in subfolders"),
+        Err(err) => log::error!("failed to index audio subfolders\nERROR: {err}"),
+    }
+
+    let pause_downloads_while_playing = pause_downloads_while_playing();
+    let download_concurrency = download_concurrency();
+    let command_rate_limits = command_rate_limits();
+
+    let runtime_config = RuntimeConfig::collect(
+        &addr,
+        &log_config,
+        pause_downloads_while_playing,
+        download_concurrency,
+        command_rate_limits,
+    );
+    if runtime_config.yt_dlp_path.is_none() {
+        log::warn!("'yt-dlp' was not found in 'PATH', downloads will fail");
+    }
+    RUNTIME_CONFIG
+        .set(runtime_config)
+        .expect("should never fail");
+
+    COMMAND_RATE_LIMITER
+        .set(Mutex::new(CommandRateLimiter::new(command_rate_limits)))
+        .expect("should never fail");
+
     let download_arbiter = Arbiter::new();
 
     let restore_state_actor = RestoreStateActor::load_or_default().await;
     let restored_state = restore_state_actor.state();
     let restore_state_addr = restore_state_actor.start();
+    RESTORE_STATE_ADDR
+        .set(restore_state_addr.clone())
+        .expect("should never fail");
 
-    let downloader = AudioDownloader::new(download_arbiter, restore_state_addr.clone());
+    let downloader = AudioDownloader::new(
+        download_arbiter,
+        restore_state_addr.clone(),
+        restored_state.settings.download_rate_limit.clone(),
+        pause_downloads_while_playing,
+        download_concurrency,
+    );
     let downloader_addr = downloader.start();
+    DOWNLOADER_ADDR
+        .set(downloader_addr.clone())
+        .expect("should never fail");
+
+    let library_downloader = LibraryDownloader::new(downloader_addr.clone());
+    LIBRARY_DOWNLOADER_ADDR
+        .set(library_downloader.start())
+        .expect("should never fail");
 
     let queue_server = AudioBrain::new(downloader_addr, restore_state_addr, restored_state);
     let brain_addr = queue_server.start();
@@ -74,26 +158,110 @@ async fn main() -> std::io::Result<()> {
         let cors = Cors::default()
             .allow_any_origin()
             .allow_any_method()
-            .allow_any_header();
+            .allow_any_header()
+            .expose_headers([TOTAL_COUNT_HEADER]);
 
         App::new()
             .wrap(cors)
             .service(get_brain_stream)
             .service(get_node_stream)
+            .service(get_multi_node_stream)
             .service(receive_node_cmd)
+            .service(receive_node_admin_cmd)
+            .service(add_node)
+            .service(remove_node)
+            .service(get_api_version)
             .service(get_audio)
+            .service(download_playlist_to_library)
+            .service(get_library_downloads)
+            .service(get_download_queue)
+            .service(move_pending_download)
+            .service(get_storage_usage)
+            .service(get_library_stats)
+            .service(purge_orphaned_audio)
+            .service(batch_delete_audio)
+            .service(delete_audio)
+            .service(get_logs)
+            .service(export_state)
+            .service(import_state)
             .service(get_playlists)
             .service(get_audio_in_playlist)
+            .service(get_audio_bookmarks)
+            .service(get_audio_resume_position)
+            .service(add_audio_favorite)
+            .service(remove_audio_favorite)
+            .service(get_audio_file)
+            .service(get_audio_cover_art)
+            .service(get_audio_peaks)
+            .service(get_play_history)
+            .service(get_default_volume)
+            .service(set_default_volume)
+            .service(get_download_rate_limit)
+            .service(set_download_rate_limit)
+            .service(cancel_all_node_downloads)
+            .service(get_node_buffer_stats)
+            .service(get_node_errors)
+            .service(get_node_output_config)
+            .service(get_node_processor_info)
+            .service(get_node_sessions)
+            .service(get_node_queue)
+            .service(queue_from_search)
+            .service(get_runtime_config)
+            .service(get_output_devices)
     })
     .bind((addr, 50051))?
     .run()
     .await
 }
 
+/// opt-in through `PAUSE_DOWNLOADS_WHILE_PLAYING=true`, prevents buffering on hardware that can't
+/// download and play at the same time
+fn pause_downloads_while_playing() -> bool {
+    dotenv::var("PAUSE_DOWNLOADS_WHILE_PLAYING")
+        .is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+/// number of downloads that run at the same time, set through `DOWNLOAD_CONCURRENCY`
+fn download_concurrency() -> usize {
+    dotenv::var("DOWNLOAD_CONCURRENCY")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|concurrency| *concurrency > 0)
+        .unwrap_or(DEFAULT_DOWNLOAD_CONCURRENCY)
+}
+
+/// commands per second a client can send to nodes, set through `NODE_COMMAND_RATE_LIMIT` and
+/// `NODE_HEAVY_COMMAND_RATE_LIMIT` for commands that edit the queue
+fn command_rate_limits() -> CommandRateLimits {
+    let read_rate = |key: &str, default: f64| {
+        dotenv::var(key)
+            .ok()
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .filter(|rate| rate.is_finite() && *rate > 0.0)
+            .unwrap_or(default)
+    };
+
+    CommandRateLimits {
+        commands_per_sec: read_rate("NODE_COMMAND_RATE_LIMIT", DEFAULT_COMMAND_RATE_LIMIT),
+        heavy_commands_per_sec: read_rate(
+            "NODE_HEAVY_COMMAND_RATE_LIMIT",
+            DEFAULT_HEAVY_COMMAND_RATE_LIMIT,
+        ),
+    }
+}
+
 async fn clear_dev_db() {
     let should_clear = env::args().any(|str| str == "-c");
 
     if should_clear && cfg!(debug_assertions) {
+        let confirmed = env::args().any(|str| str == "--yes")
+            || env::var(CONFIRM_WIPE_ENV).is_ok_and(|value| value == "1" || value == "true");
+
+        if let Err(err) = check_audio_dir_wipe(&audio_data_dir(), confirmed) {
+            log::error!("not removing dev database\nERROR: {err}");
+            return;
+        }
+
         println!(
             "
 ============================
@@ -113,7 +281,6 @@ async fn clear_dev_db() {
             .await
             .unwrap();
 
-        fs::remove_dir_all(audio_data_dir()).unwrap();
-        fs::create_dir(audio_data_dir()).unwrap();
+        wipe_audio_dir(&audio_data_dir(), confirmed).unwrap();
     }
 }
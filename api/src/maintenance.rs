@@ -0,0 +1,729 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::{
+    brain::brain_server::GetAllAudioNodes,
+    brain_addr,
+    database::{
+        fetch_data::{
+            get_all_audio_uids_from_db, get_existing_audio_uids_from_db, get_library_totals_from_db,
+        },
+        store_data::delete_audio_metadata,
+        LibraryTotals,
+    },
+    downloader::download_identifier::{unregister_audio_folder, AudioKind, Identifier, ItemUid},
+    error::{AppError, AppErrorKind, IntoAppError},
+    node::node_server::{
+        sync_actor::{GetLoadedUid, RemoveStoredItems},
+        SourceName,
+    },
+    opt_arc::OptionArcStr,
+    path::audio_data_dir,
+};
+
+/// files that were modified more recently might belong to a running download, the metadata of a
+/// download is only committed once its file has been written
+const ORPHAN_MIN_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// how long a computed storage usage is reused before the audio directory is walked again
+const STORAGE_USAGE_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// file in the root of the audio directory that marks it as managed by the server, only a marked
+/// directory is ever wiped
+pub const AUDIO_DIR_MARKER: &str = ".audiotorium-audio-dir";
+
+/// environment variable that confirms wiping the dev data instead of passing `--yes`
+pub const CONFIRM_WIPE_ENV: &str = "AUDIOTORIUM_CONFIRM_WIPE";
+
+static STORAGE_USAGE_CACHE: Mutex<Option<(Instant, StorageUsage)>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct OrphanReport {
+    /// paths relative to the audio directory of files that have no metadata
+    #[ts(type = "Array<string>")]
+    pub orphaned_files: Vec<Arc<str>>,
+    /// uids of metadata without an audio file, only checked if requested
+    #[ts(type = "Array<string>")]
+    pub missing_files: Vec<Arc<str>>,
+    /// whether the orphans were deleted or only reported
+    pub removed: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct StorageUsage {
+    #[ts(type = "number")]
+    pub total_bytes: u64,
+    pub file_count: usize,
+    /// audio downloaded from youtube, both single videos and playlist items
+    pub youtube: ProviderStorageUsage,
+    pub soundcloud: ProviderStorageUsage,
+    /// files whose uid doesn't belong to any provider
+    pub local: ProviderStorageUsage,
+    /// seconds since the usage was computed
+    #[ts(type = "number")]
+    pub age_secs: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct ProviderStorageUsage {
+    #[ts(type = "number")]
+    pub bytes: u64,
+    pub file_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct LibraryStats {
+    #[ts(type = "number")]
+    pub total_tracks: i64,
+    #[ts(type = "number")]
+    pub total_playlists: i64,
+    /// sum of the duration of all tracks whose duration is known
+    #[ts(type = "number")]
+    pub total_duration: i64,
+    /// author with the most tracks
+    pub most_common_author: OptionArcStr,
+    /// tracks with metadata but without an audio file
+    pub tracks_missing_files: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct AudioDeleteResult {
+    pub uid: Arc<str>,
+    pub status: AudioDeleteStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "kebab-case")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub enum AudioDeleteStatus {
+    Deleted,
+    NotFound,
+    /// the audio is loaded by the node with this source name, only deleted if forced
+    InUse(SourceName),
+}
+
+/// Overview of the stored library, counts are aggregated by the database while missing files are
+/// looked up in the audio directory
+pub async fn library_stats() -> Result<LibraryStats, AppError> {
+    let LibraryTotals {
+        total_tracks,
+        total_playlists,
+        total_duration,
+        most_common_author,
+    } = get_library_totals_from_db().await?;
+
+    let uids = get_all_audio_uids_from_db().await?;
+    let tracks_missing_files = actix_rt::task::spawn_blocking(move || {
+        count_missing_files(uids.iter().map(|uid| uid.to_path_with_ext()))
+    })
+    .await
+    .into_app_err(
+        "failed to look up missing audio files",
+        AppErrorKind::LocalData,
+        &[],
+    )?;
+
+    Ok(LibraryStats {
+        total_tracks,
+        total_playlists,
+        total_duration,
+        most_common_author,
+        tracks_missing_files,
+    })
+}
+
+/// number of `paths` that don't point to a file
+fn count_missing_files(paths: impl Iterator<Item = PathBuf>) -> usize {
+    paths.filter(|path| !path.is_file()).count()
+}
+
+/// Disk space used by the audio directory, the result is cached for [`STORAGE_USAGE_MAX_AGE`]
+/// unless `refresh` is set
+pub fn storage_usage(refresh: bool) -> Result<StorageUsage, AppError> {
+    let mut cache = STORAGE_USAGE_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    if let Some((computed_at, usage)) = cache.as_ref() {
+        let age = computed_at.elapsed();
+        if !refresh && age < STORAGE_USAGE_MAX_AGE {
+            return Ok(StorageUsage {
+                age_secs: age.as_secs(),
+                ..usage.clone()
+            });
+        }
+    }
+
+    let usage = compute_storage_usage(&audio_data_dir())?;
+    *cache = Some((Instant::now(), usage.clone()));
+
+    Ok(usage)
+}
+
+/// drops the cached storage usage, the next request walks the audio directory again
+pub fn invalidate_storage_usage() {
+    if let Ok(mut cache) = STORAGE_USAGE_CACHE.lock() {
+        *cache = None;
+    }
+}
+
+/// Finds audio files without metadata and optionally metadata without audio files, both are
+/// deleted if `remove` is set
+pub async fn purge_orphans(
+    remove: bool,
+    include_missing_files: bool,
+) -> Result<OrphanReport, AppError> {
+    let uids: HashSet<Arc<str>> = get_all_audio_uids_from_db()
+        .await?
+        .iter()
+        .map(|uid| Arc::clone(&uid.0))
+        .collect();
+
+    let dir = audio_data_dir();
+    let orphaned_files = find_orphaned_files(&dir, &uids, SystemTime::now())?;
+
+    let mut missing_files: Vec<Arc<str>> = if include_missing_files {
+        uids.into_iter()
+            .filter(|uid| !ItemUid(Arc::clone(uid)).to_path_with_ext().is_file())
+            .collect()
+    } else {
+        vec![]
+    };
+    missing_files.sort();
+
+    if remove {
+        invalidate_storage_usage();
+
+        for name in orphaned_files.iter() {
+            fs::remove_file(dir.join(name.as_ref())).into_app_err(
+                "failed to remove orphaned audio file",
+                AppErrorKind::LocalData,
+                &[&format!("FILE: {name}")],
+            )?;
+        }
+
+        let missing_uids: Vec<_> = missing_files
+            .iter()
+            .map(|uid| ItemUid(Arc::clone(uid)))
+            .collect();
+        delete_audio_metadata(&missing_uids).await?;
+        for uid in missing_uids.iter() {
+            unregister_audio_folder(uid);
+        }
+
+        log::info!(
+            "purged {files} orphaned audio files and {rows} audio metadata rows without a file",
+            files = orphaned_files.len(),
+            rows = missing_files.len()
+        );
+    }
+
+    Ok(OrphanReport {
+        orphaned_files,
+        missing_files,
+        removed: remove,
+    })
+}
+
+/// Deletes the metadata and audio files of stored tracks and removes them from all node queues
+///
+/// Playlists lose the tracks through the database. Tracks that a node is currently playing or has
+/// paused are skipped unless `force` is set, forcing it makes the node continue with its next
+/// track.
+pub async fn delete_stored_audio(
+    uids: Vec<Arc<str>>,
+    force: bool,
+) -> Result<Vec<AudioDeleteResult>, AppError> {
+    let mut seen = HashSet::new();
+    let uids: Vec<ItemUid<Arc<str>>> = uids
+        .into_iter()
+        .filter(|uid| seen.insert(Arc::clone(uid)))
+        .map(ItemUid)
+        .collect();
+
+    let existing: HashSet<Arc<str>> = get_existing_audio_uids_from_db(&uids)
+        .await?
+        .into_iter()
+        .map(|uid| uid.0)
+        .collect();
+
+    let nodes = brain_addr().send(GetAllAudioNodes).await.into_app_err(
+        "failed to get audio nodes",
+        AppErrorKind::Api,
+        &[],
+    )?;
+
+    let mut in_use = HashMap::new();
+    for (source_name, addr) in nodes.iter() {
+        let loaded = addr.send(GetLoadedUid).await.into_app_err(
+            "failed to get loaded audio of node",
+            AppErrorKind::Api,
+            &[&format!("NODE_NAME: {source_name}")],
+        )?;
+
+        if let Some(uid) = loaded {
+            in_use.insert(uid, source_name.clone());
+        }
+    }
+
+    let results: Vec<AudioDeleteResult> = uids
+        .into_iter()
+        .map(|uid| AudioDeleteResult {
+            status: delete_status(&uid.0, &existing, &in_use, force),
+            uid: uid.0,
+        })
+        .collect();
+
+    let deleted: HashSet<Arc<str>> = results
+        .iter()
+        .filter(|result| result.status == AudioDeleteStatus::Deleted)
+        .map(|result| Arc::clone(&result.uid))
+        .collect();
+
+    if deleted.is_empty() {
+        return Ok(results);
+    }
+
+    // nothing else is changed if the metadata can't be deleted
+    let deleted_uids: Vec<_> = deleted.iter().cloned().map(ItemUid).collect();
+    delete_audio_metadata(&deleted_uids).await?;
+
+    // the queues don't reference the files anymore once they are removed, the files are kept if a
+    // queue couldn't be updated and can be cleaned up with `purge_orphans`
+    for (source_name, addr) in nodes.iter() {
+        addr.send(RemoveStoredItems(deleted.clone()))
+            .await
+            .into_app_err(
+                "failed to remove deleted audio from queue of node",
+                AppErrorKind::Api,
+                &[&format!("NODE_NAME: {source_name}")],
+            )??;
+    }
+
+    invalidate_storage_usage();
+    for uid in deleted_uids.iter() {
+        let path = uid.to_path_with_ext();
+        unregister_audio_folder(uid);
+        remove_audio_file(&path)?;
+    }
+
+    log::info!("deleted {count} stored tracks", count = deleted_uids.len());
+
+    Ok(results)
+}
+
+/// a file that is already missing counts as removed
+fn remove_audio_file(path: &Path) -> Result<(), AppError> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err.into_app_err(
+            "failed to remove audio file",
+            AppErrorKind::LocalData,
+            &[&format!("PATH: {path}", path = path.display())],
+        )),
+    }
+}
+
+fn delete_status(
+    uid: &Arc<str>,
+    existing: &HashSet<Arc<str>>,
+    in_use: &HashMap<Arc<str>, SourceName>,
+    force: bool,
+) -> AudioDeleteStatus {
+    if !existing.contains(uid) {
+        return AudioDeleteStatus::NotFound;
+    }
+
+    match in_use.get(uid) {
+        Some(source_name) if !force => AudioDeleteStatus::InUse(source_name.clone()),
+        _ => AudioDeleteStatus::Deleted,
+    }
+}
+
+/// Creates the audio directory and marks it as managed by the server
+///
+/// An existing directory is only marked if it is empty, a directory that already contains files
+/// might be a misconfigured path to a directory that doesn't belong to the server.
+pub fn init_audio_dir(dir: &Path) -> Result<(), AppError> {
+    let dir_info = format!("DIR: {dir}", dir = dir.display());
+
+    let is_empty = match fs::read_dir(dir) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            fs::create_dir_all(dir).into_app_err(
+                "failed to create audio directory",
+                AppErrorKind::LocalData,
+                &[&dir_info],
+            )?;
+            true
+        }
+        Err(err) => {
+            return Err(err.into_app_err(
+                "failed to read audio directory",
+                AppErrorKind::LocalData,
+                &[&dir_info],
+            ))
+        }
+    };
+
+    if is_empty {
+        fs::write(dir.join(AUDIO_DIR_MARKER), []).into_app_err(
+            "failed to mark audio directory",
+            AppErrorKind::LocalData,
+            &[&dir_info],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Checks that `dir` may be wiped, the wipe has to be `confirmed` and the directory has to be
+/// marked as audio directory
+pub fn check_audio_dir_wipe(dir: &Path, confirmed: bool) -> Result<(), AppError> {
+    let dir_info = format!("DIR: {dir}", dir = dir.display());
+
+    if !confirmed {
+        return Err(AppError::new(
+            AppErrorKind::LocalData,
+            format!("refusing to wipe the audio directory without confirmation, pass '--yes' or set '{CONFIRM_WIPE_ENV}=1'"),
+            &[&dir_info],
+        ));
+    }
+
+    if dir.exists() && !dir.join(AUDIO_DIR_MARKER).is_file() {
+        return Err(AppError::new(
+            AppErrorKind::LocalData,
+            format!("refusing to wipe a directory that isn't marked as audio directory, create an empty '{AUDIO_DIR_MARKER}' file in it if it really is one"),
+            &[&dir_info],
+        ));
+    }
+
+    Ok(())
+}
+
+/// Deletes everything in the audio directory, see [`check_audio_dir_wipe`] for when this is
+/// refused
+pub fn wipe_audio_dir(dir: &Path, confirmed: bool) -> Result<(), AppError> {
+    check_audio_dir_wipe(dir, confirmed)?;
+
+    if dir.exists() {
+        fs::remove_dir_all(dir).into_app_err(
+            "failed to wipe audio directory",
+            AppErrorKind::LocalData,
+            &[&format!("DIR: {dir}", dir = dir.display())],
+        )?;
+    }
+
+    invalidate_storage_usage();
+    init_audio_dir(dir)
+}
+
+/// paths relative to `dir` of all files in `dir` and its subfolders that don't belong to any of the
+/// `uids`, files that are younger than [`ORPHAN_MIN_AGE`] are ignored
+///
+/// Everything in front of the first `.` of a file name is its uid, this also matches leftovers of
+/// downloads like `<uid>.wav.part`.
+fn find_orphaned_files(
+    root: &Path,
+    uids: &HashSet<Arc<str>>,
+    now: SystemTime,
+) -> Result<Vec<Arc<str>>, AppError> {
+    let mut orphans = vec![];
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let dir_info = format!("DIR: {dir}", dir = dir.display());
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(err.into_app_err(
+                    "failed to read audio directory",
+                    AppErrorKind::LocalData,
+                    &[&dir_info],
+                ))
+            }
+        };
+
+        for entry in entries {
+            let entry = entry.into_app_err(
+                "failed to read audio directory",
+                AppErrorKind::LocalData,
+                &[&dir_info],
+            )?;
+
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+                continue;
+            }
+
+            if !metadata.is_file() || entry.file_name() == AUDIO_DIR_MARKER {
+                continue;
+            }
+
+            let is_recent = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_none_or(|age| age < ORPHAN_MIN_AGE);
+            if is_recent {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            let uid = name.split('.').next().unwrap_or_default();
+            if !uids.contains(uid) {
+                let path = entry.path();
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                orphans.push(relative.to_string_lossy().into());
+            }
+        }
+    }
+
+    orphans.sort();
+    Ok(orphans)
+}
+
+/// sums up the size of all files in `dir` and its subfolders, the provider of a file is derived
+/// from the prefix of its uid
+fn compute_storage_usage(dir: &Path) -> Result<StorageUsage, AppError> {
+    let mut usage = StorageUsage::default();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let dir_info = format!("DIR: {dir}", dir = dir.display());
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(err.into_app_err(
+                    "failed to read audio directory",
+                    AppErrorKind::LocalData,
+                    &[&dir_info],
+                ))
+            }
+        };
+
+        for entry in entries {
+            let entry = entry.into_app_err(
+                "failed to read audio directory",
+                AppErrorKind::LocalData,
+                &[&dir_info],
+            )?;
+
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else if metadata.is_file() && entry.file_name() != AUDIO_DIR_MARKER {
+                add_file_usage(
+                    &mut usage,
+                    &entry.file_name().to_string_lossy(),
+                    metadata.len(),
+                );
+            }
+        }
+    }
+
+    Ok(usage)
+}
+
+fn add_file_usage(usage: &mut StorageUsage, name: &str, bytes: u64) {
+    let provider = match AudioKind::from_uid(&ItemUid(name)) {
+        Some(AudioKind::YoutubeVideo | AudioKind::YoutubePlaylist) => &mut usage.youtube,
+        Some(AudioKind::SoundCloud) => &mut usage.soundcloud,
+        Some(AudioKind::LocalFile) | None => &mut usage.local,
+    };
+
+    provider.bytes += bytes;
+    provider.file_count += 1;
+    usage.total_bytes += bytes;
+    usage.file_count += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_utils::test_dir;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_delete_status() {
+        let existing: HashSet<Arc<str>> = ["a".into(), "b".into()].into();
+        let in_use: HashMap<Arc<str>, SourceName> = [("b".into(), "living-room".into())].into();
+
+        assert_eq!(
+            delete_status(&"a".into(), &existing, &in_use, false),
+            AudioDeleteStatus::Deleted
+        );
+        assert_eq!(
+            delete_status(&"b".into(), &existing, &in_use, false),
+            AudioDeleteStatus::InUse("living-room".into())
+        );
+        assert_eq!(
+            delete_status(&"b".into(), &existing, &in_use, true),
+            AudioDeleteStatus::Deleted
+        );
+        assert_eq!(
+            delete_status(&"c".into(), &existing, &in_use, true),
+            AudioDeleteStatus::NotFound
+        );
+    }
+
+    #[test]
+    fn test_remove_audio_file() {
+        let dir = test_dir("remove-audio-file");
+        let path = dir.join("track.wav");
+        fs::write(&path, []).unwrap();
+
+        remove_audio_file(&path).unwrap();
+        assert!(!path.exists());
+
+        // deleting a track whose file is already gone still succeeds
+        remove_audio_file(&path).unwrap();
+        assert!(remove_audio_file(&dir).is_err());
+    }
+
+    #[test]
+    fn test_count_missing_files() {
+        let dir = test_dir("count-missing-files");
+        fs::write(dir.join("stored.wav"), []).unwrap();
+        fs::create_dir(dir.join("folder.wav")).unwrap();
+
+        let paths = ["stored.wav", "missing.wav", "folder.wav"].map(|name| dir.join(name));
+        assert_eq!(count_missing_files(paths.into_iter()), 2);
+        assert_eq!(count_missing_files(std::iter::empty()), 0);
+    }
+
+    #[test]
+    fn test_find_orphaned_files() {
+        let dir = test_dir("find-orphaned-files");
+
+        for name in [
+            "known.wav",
+            "orphan.wav",
+            "partial.wav.part",
+            AUDIO_DIR_MARKER,
+        ] {
+            fs::write(dir.join(name), []).unwrap();
+        }
+        fs::create_dir_all(dir.join("artist/album")).unwrap();
+        fs::write(dir.join("artist/album/known-nested.wav"), []).unwrap();
+        fs::write(dir.join("artist/album/nested-orphan.wav"), []).unwrap();
+
+        let uids = HashSet::from([Arc::<str>::from("known"), Arc::from("known-nested")]);
+
+        // all files are too recent to be considered orphans
+        let orphans = find_orphaned_files(&dir, &uids, SystemTime::now()).unwrap();
+        assert!(orphans.is_empty());
+
+        let later = SystemTime::now() + ORPHAN_MIN_AGE * 2;
+        let orphans = find_orphaned_files(&dir, &uids, later).unwrap();
+        assert_eq!(
+            orphans,
+            vec![
+                Arc::<str>::from("artist/album/nested-orphan.wav"),
+                Arc::<str>::from("orphan.wav"),
+                Arc::<str>::from("partial.wav.part")
+            ]
+        );
+
+        let orphans = find_orphaned_files(&dir.join("missing"), &uids, later).unwrap();
+        assert!(orphans.is_empty());
+    }
+
+    #[test]
+    fn test_compute_storage_usage() {
+        let dir = test_dir("compute-storage-usage");
+
+        fs::write(dir.join("youtube_audio_abc.wav"), [0; 10]).unwrap();
+        fs::write(dir.join("youtube_playlist_audio_def.wav"), [0; 20]).unwrap();
+        fs::write(dir.join("local.mp3"), [0; 5]).unwrap();
+        fs::write(dir.join("soundcloud_audio_jkl.wav"), [0; 7]).unwrap();
+        fs::write(dir.join(AUDIO_DIR_MARKER), [0; 1]).unwrap();
+        fs::create_dir_all(dir.join("artist/album")).unwrap();
+        fs::write(dir.join("artist/album/youtube_audio_ghi.wav"), [0; 15]).unwrap();
+
+        assert_eq!(
+            compute_storage_usage(&dir).unwrap(),
+            StorageUsage {
+                total_bytes: 57,
+                file_count: 5,
+                youtube: ProviderStorageUsage {
+                    bytes: 45,
+                    file_count: 3,
+                },
+                soundcloud: ProviderStorageUsage {
+                    bytes: 7,
+                    file_count: 1,
+                },
+                local: ProviderStorageUsage {
+                    bytes: 5,
+                    file_count: 1,
+                },
+                age_secs: 0,
+            }
+        );
+
+        assert_eq!(
+            compute_storage_usage(&dir.join("missing")).unwrap(),
+            StorageUsage::default()
+        );
+    }
+
+    #[test]
+    fn test_wipe_audio_dir_guard() {
+        let dir = test_dir("wipe-audio-dir-guard");
+        fs::write(dir.join("track.wav"), []).unwrap();
+
+        // not confirmed
+        fs::write(dir.join(AUDIO_DIR_MARKER), []).unwrap();
+        assert!(wipe_audio_dir(&dir, false).is_err());
+        assert!(dir.join("track.wav").is_file());
+
+        // not marked as audio directory
+        fs::remove_file(dir.join(AUDIO_DIR_MARKER)).unwrap();
+        assert!(wipe_audio_dir(&dir, true).is_err());
+        assert!(dir.join("track.wav").is_file());
+
+        // an existing directory with files isn't marked
+        init_audio_dir(&dir).unwrap();
+        assert!(!dir.join(AUDIO_DIR_MARKER).exists());
+
+        fs::write(dir.join(AUDIO_DIR_MARKER), []).unwrap();
+        wipe_audio_dir(&dir, true).unwrap();
+        assert!(!dir.join("track.wav").exists());
+        assert!(dir.join(AUDIO_DIR_MARKER).is_file());
+
+        let missing = dir.join("missing");
+        wipe_audio_dir(&missing, true).unwrap();
+        assert!(missing.join(AUDIO_DIR_MARKER).is_file());
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    collections::VecDeque,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::error::AppError;
+
+/// number of errors a node remembers, older errors are dropped
+pub const MAX_RECENT_ERRORS: usize = 20;
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct NodeError {
+    /// seconds since the unix epoch
+    #[ts(type = "number")]
+    pub timestamp_secs: u64,
+    #[ts(type = "UserError")]
+    pub error: AppError,
+}
+
+/// Playback errors of a node that would otherwise only show up in the logs, e.g. why the music
+/// stopped while nobody was connected
+#[derive(Debug, Default)]
+pub struct RecentErrors(VecDeque<NodeError>);
+
+impl RecentErrors {
+    pub fn push(&mut self, error: AppError, at: SystemTime) {
+        if self.0.len() == MAX_RECENT_ERRORS {
+            self.0.pop_front();
+        }
+
+        self.0.push_back(NodeError {
+            timestamp_secs: at
+                .duration_since(UNIX_EPOCH)
+                .map(|since_epoch| since_epoch.as_secs())
+                .unwrap_or_default(),
+            error,
+        });
+    }
+
+    /// all remembered errors, newest first
+    pub fn list(&self) -> Vec<NodeError> {
+        self.0.iter().rev().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::error::AppErrorKind;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_recent_errors_keep_newest() {
+        let mut errors = RecentErrors::default();
+
+        for secs in 0..MAX_RECENT_ERRORS as u64 + 5 {
+            errors.push(
+                AppError::new(AppErrorKind::Queue, "audio device is not available", &[]),
+                UNIX_EPOCH + Duration::from_secs(secs),
+            );
+        }
+
+        let timestamps: Vec<_> = errors
+            .list()
+            .iter()
+            .map(|error| error.timestamp_secs)
+            .collect();
+        let expected: Vec<_> = (5..MAX_RECENT_ERRORS as u64 + 5).rev().collect();
+        assert_eq!(timestamps, expected);
+    }
+}
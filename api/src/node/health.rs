@@ -10,6 +10,19 @@ pub enum AudioNodeHealth {
     Poor(AudioNodeHealthPoor),
 }
 
+impl AudioNodeHealth {
+    /// whether the audio device is gone, nothing can be played until it is back
+    pub fn device_unavailable(&self) -> bool {
+        matches!(
+            self,
+            Self::Poor(
+                AudioNodeHealthPoor::DeviceNotAvailable
+                    | AudioNodeHealthPoor::PermanentlyUnavailable
+            )
+        )
+    }
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq, Eq, TS)]
 #[serde(rename_all = "kebab-case")]
 #[ts(export, export_to = "../app/src/api-types/")]
@@ -24,4 +37,6 @@ pub enum AudioNodeHealthPoor {
     DeviceNotAvailable,
     AudioStreamReadFailed,
     AudioBackendError(String),
+    /// the device couldn't be recovered, it is only tried again when it is reconnected explicitly
+    PermanentlyUnavailable,
 }
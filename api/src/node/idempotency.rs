@@ -0,0 +1,79 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// how long a node remembers an idempotency key, long enough to cover retries of a client after a
+/// timed out request
+pub const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(60);
+
+/// number of keys a node remembers, the oldest key is forgotten first
+pub const MAX_IDEMPOTENCY_KEYS: usize = 256;
+
+/// Keys of recently added queue items, so a client retrying a request it didn't get a response
+/// for doesn't add the same item twice
+#[derive(Debug, Default)]
+pub struct RecentIdempotencyKeys(HashMap<Arc<str>, Instant>);
+
+impl RecentIdempotencyKeys {
+    /// remembers `key`, returns false if it was already seen within [`IDEMPOTENCY_KEY_TTL`]
+    pub fn insert(&mut self, key: Arc<str>, now: Instant) -> bool {
+        self.0
+            .retain(|_, seen| now.saturating_duration_since(*seen) < IDEMPOTENCY_KEY_TTL);
+
+        if self.0.contains_key(&key) {
+            return false;
+        }
+
+        if self.0.len() >= MAX_IDEMPOTENCY_KEYS {
+            let oldest = self
+                .0
+                .iter()
+                .min_by_key(|(_, seen)| **seen)
+                .map(|(key, _)| Arc::clone(key));
+
+            if let Some(oldest) = oldest {
+                self.0.remove(&oldest);
+            }
+        }
+
+        self.0.insert(key, now);
+        true
+    }
+
+    /// forgets `key`, e.g. when adding the item failed so the client can retry it
+    pub fn remove(&mut self, key: &str) {
+        self.0.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_key_expires() {
+        let mut keys = RecentIdempotencyKeys::default();
+        let start = Instant::now();
+
+        assert!(keys.insert("retry".into(), start));
+        assert!(!keys.insert("retry".into(), start + IDEMPOTENCY_KEY_TTL / 2));
+        assert!(keys.insert("retry".into(), start + IDEMPOTENCY_KEY_TTL));
+    }
+
+    #[test]
+    fn test_oldest_key_is_forgotten() {
+        let mut keys = RecentIdempotencyKeys::default();
+        let start = Instant::now();
+
+        for i in 0..MAX_IDEMPOTENCY_KEYS as u64 + 1 {
+            assert!(keys.insert(i.to_string().into(), start + Duration::from_millis(i)));
+        }
+
+        assert_eq!(keys.0.len(), MAX_IDEMPOTENCY_KEYS);
+        assert!(keys.insert("0".into(), start + Duration::from_secs(1)));
+        assert!(!keys.insert("2".into(), start + Duration::from_secs(1)));
+    }
+}
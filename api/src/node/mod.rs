@@ -1,8 +1,11 @@
+pub mod error_log;
 pub mod health;
+pub mod idempotency;
+pub mod multi_node_session;
 pub mod node_server;
 pub mod node_session;
+pub mod recovery;
 
 pub use processor_communication::AudioProcessorToNodeMessage;
 
 mod processor_communication;
-mod recovery;
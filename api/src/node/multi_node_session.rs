@@ -0,0 +1,388 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use actix::{
+    Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, ContextFutureSpawner, Handler,
+    Message, ResponseActFuture, Running, StreamHandler, WrapFuture,
+};
+
+use actix_web_actors::ws;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    brain::brain_server::{AudioBrain, SubscribeNodes},
+    error::{AppError, AppErrorKind, IntoAppError},
+    node::node_server::connections::NodeDisconnectMessage,
+    streams::{
+        node_streams::{
+            get_type_of_stream_data, AudioNodeInfoStreamMessage, AudioNodeInfoStreamType,
+        },
+        ClientActivity, HeartBeat, CLIENT_TIMEOUT_CHECK_INTERVAL,
+    },
+};
+
+use super::{
+    node_server::{AudioNode, SourceName},
+    node_session::NodeSessionWsResponse,
+};
+
+/// Websocket session that follows multiple nodes at once, e.g. for a dashboard of the whole home
+///
+/// Every message is tagged with the source name of the node it belongs to, see
+/// [`MultiNodeStreamMessage`].
+pub struct MultiNodeSession {
+    server_addr: Addr<AudioBrain>,
+    subscriptions: HashMap<SourceName, NodeSubscription>,
+    heartbeat_interval: Duration,
+    activity: ClientActivity,
+}
+
+struct NodeSubscription {
+    node_addr: Addr<AudioNode>,
+    id: usize,
+    wanted_info: Arc<[AudioNodeInfoStreamType]>,
+}
+
+/// stream message of a node that is forwarded to a [`MultiNodeSession`]
+#[derive(Debug, Clone)]
+pub struct TaggedNodeStreamMessage<M> {
+    pub source_name: SourceName,
+    pub msg: M,
+}
+
+impl<M> Message for TaggedNodeStreamMessage<M> {
+    type Result = ();
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct MultiNodeStreamMessage {
+    pub source_name: SourceName,
+    #[ts(type = "NodeSessionWsResponse | AudioNodeInfoStreamMessage | UserError")]
+    pub message: MultiNodeStreamPayload,
+}
+
+/// serialized exactly like the message on the stream of a single node
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum MultiNodeStreamPayload {
+    Connected(NodeSessionWsResponse),
+    Stream(AudioNodeInfoStreamMessage),
+    Error(AppError),
+}
+
+/// Commands a client can send over the multi node websocket
+///
+/// Example:
+///
+/// {"SUBSCRIBE": {"sources": [{"sourceName": "living_room", "wantedInfo": ["QUEUE", "HEALTH"]}]}}
+///
+/// {"UNSUBSCRIBE": {"sourceNames": ["living_room"]}}
+#[derive(Debug, Clone, Deserialize, TS, Message)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[ts(export, export_to = "../app/src/api-types/")]
+#[rtype(result = "()")]
+pub enum MultiNodeSessionWsCommand {
+    /// follows the nodes, a node that is already followed is followed with the new wanted info
+    /// instead
+    Subscribe(SubscribeNodesParams),
+    Unsubscribe(UnsubscribeNodesParams),
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct SubscribeNodesParams {
+    pub sources: Vec<NodeSubscriptionParams>,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct NodeSubscriptionParams {
+    pub source_name: SourceName,
+    pub wanted_info: Vec<AudioNodeInfoStreamType>,
+}
+
+#[derive(Debug, Clone, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct UnsubscribeNodesParams {
+    pub source_names: Vec<SourceName>,
+}
+
+impl MultiNodeSession {
+    pub fn new(server_addr: Addr<AudioBrain>, heartbeat_interval: Duration) -> Self {
+        Self {
+            server_addr,
+            subscriptions: HashMap::default(),
+            heartbeat_interval,
+            activity: ClientActivity::new(heartbeat_interval),
+        }
+    }
+
+    fn unsubscribe(&mut self, source_name: &SourceName) {
+        if let Some(subscription) = self.subscriptions.remove(source_name) {
+            subscription.node_addr.do_send(NodeDisconnectMessage {
+                id: subscription.id,
+            });
+        }
+    }
+
+    fn send_tagged(
+        ctx: &mut ws::WebsocketContext<Self>,
+        source_name: SourceName,
+        message: MultiNodeStreamPayload,
+    ) {
+        ctx.text(
+            serde_json::to_string(&MultiNodeStreamMessage {
+                source_name,
+                message,
+            })
+            .unwrap_or("failed to serialize on server".to_owned()),
+        );
+    }
+}
+
+impl Actor for MultiNodeSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        log::info!("stared new 'MultiNodeSession'");
+
+        ctx.notify(HeartBeat);
+
+        ctx.run_interval(CLIENT_TIMEOUT_CHECK_INTERVAL, |act, ctx| {
+            if act.activity.timed_out(Instant::now()) {
+                log::info!(
+                    "'MultiNodeSession' timed out, SOURCES: {:?}",
+                    act.subscriptions.keys()
+                );
+                ctx.stop();
+            }
+        });
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        log::info!(
+            "'MultiNodeSession' stopping, SOURCES: {:?}",
+            self.subscriptions.keys()
+        );
+
+        let source_names: Vec<_> = self.subscriptions.keys().cloned().collect();
+        for source_name in source_names {
+            self.unsubscribe(&source_name);
+        }
+
+        Running::Stop
+    }
+}
+
+impl Handler<HeartBeat> for MultiNodeSession {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, _msg: HeartBeat, ctx: &mut Self::Context) -> Self::Result {
+        ctx.ping(b"heart-beat");
+
+        let interval = self.heartbeat_interval;
+        Box::pin(
+            async move {
+                actix_rt::time::sleep(interval).await;
+            }
+            .into_actor(self)
+            .map(|_res, _act, ctx| ctx.notify(HeartBeat)),
+        )
+    }
+}
+
+impl Handler<TaggedNodeStreamMessage<AudioNodeInfoStreamMessage>> for MultiNodeSession {
+    type Result = ();
+
+    /// used to receive multicast messages from nodes
+    fn handle(
+        &mut self,
+        msg: TaggedNodeStreamMessage<AudioNodeInfoStreamMessage>,
+        ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let TaggedNodeStreamMessage { source_name, msg } = msg;
+
+        let Some(subscription) = self.subscriptions.get(&source_name) else {
+            return;
+        };
+
+        if subscription
+            .wanted_info
+            .contains(&get_type_of_stream_data(&msg))
+        {
+            Self::send_tagged(ctx, source_name, MultiNodeStreamPayload::Stream(msg));
+        }
+    }
+}
+
+impl Handler<TaggedNodeStreamMessage<AppError>> for MultiNodeSession {
+    type Result = ();
+
+    /// used to receive multicast messages from nodes
+    fn handle(
+        &mut self,
+        msg: TaggedNodeStreamMessage<AppError>,
+        ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let TaggedNodeStreamMessage { source_name, msg } = msg;
+
+        if self.subscriptions.contains_key(&source_name) {
+            Self::send_tagged(ctx, source_name, MultiNodeStreamPayload::Error(msg));
+        }
+    }
+}
+
+impl Handler<MultiNodeSessionWsCommand> for MultiNodeSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: MultiNodeSessionWsCommand, ctx: &mut Self::Context) -> Self::Result {
+        match msg {
+            MultiNodeSessionWsCommand::Subscribe(SubscribeNodesParams { sources }) => {
+                let sources: Vec<_> = sources
+                    .into_iter()
+                    .map(|source| (source.source_name, source.wanted_info.into()))
+                    .collect();
+
+                for (source_name, _) in sources.iter() {
+                    self.unsubscribe(source_name);
+                }
+
+                // messages of the nodes are only handled once all of them are connected, so no
+                // message arrives before the connection response of its node
+                self.server_addr
+                    .send(SubscribeNodes {
+                        addr: ctx.address(),
+                        sources,
+                    })
+                    .into_actor(self)
+                    .map(|res, act, ctx| {
+                        let results = match res {
+                            Ok(results) => results,
+                            Err(err) => {
+                                let err = err.into_app_err(
+                                    "failed to subscribe to nodes",
+                                    AppErrorKind::Api,
+                                    &[],
+                                );
+                                ctx.text(
+                                    serde_json::to_string(&err)
+                                        .unwrap_or("failed to serialize on server".to_owned()),
+                                );
+                                return;
+                            }
+                        };
+
+                        for (source_name, wanted_info, res) in results {
+                            match res {
+                                Ok((node_addr, res)) => {
+                                    act.subscriptions.insert(
+                                        source_name.clone(),
+                                        NodeSubscription {
+                                            node_addr,
+                                            id: res.id,
+                                            wanted_info,
+                                        },
+                                    );
+
+                                    Self::send_tagged(
+                                        ctx,
+                                        source_name,
+                                        MultiNodeStreamPayload::Connected(res.connection_response),
+                                    );
+                                }
+                                Err(err) => Self::send_tagged(
+                                    ctx,
+                                    source_name,
+                                    MultiNodeStreamPayload::Error(err),
+                                ),
+                            }
+                        }
+                    })
+                    .wait(ctx);
+            }
+            MultiNodeSessionWsCommand::Unsubscribe(UnsubscribeNodesParams { source_names }) => {
+                for source_name in source_names.iter() {
+                    self.unsubscribe(source_name);
+                }
+            }
+        }
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for MultiNodeSession {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        if msg.is_ok() {
+            self.activity.seen(Instant::now());
+        }
+
+        match &msg {
+            Ok(ws::Message::Text(text)) => match serde_json::from_str(text) {
+                Ok(cmd) => ctx.notify::<MultiNodeSessionWsCommand>(cmd),
+                Err(err) => {
+                    let err = err.into_app_err(
+                        "invalid multi node session command",
+                        AppErrorKind::Api,
+                        &[&format!("COMMAND: {text}")],
+                    );
+
+                    ctx.text(
+                        serde_json::to_string(&err)
+                            .unwrap_or("failed to serialize on server".to_owned()),
+                    );
+                }
+            },
+            Ok(ws::Message::Ping(bytes)) => ctx.pong(bytes),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason.clone());
+                ctx.stop();
+            }
+            Err(_) => ctx.stop(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_multi_node_stream_format() {
+        let cmd: MultiNodeSessionWsCommand = serde_json::from_str(
+            r#"{"SUBSCRIBE": {"sources": [{"sourceName": "living_room", "wantedInfo": ["QUEUE", "HEALTH"]}]}}"#,
+        )
+        .unwrap();
+
+        let MultiNodeSessionWsCommand::Subscribe(params) = cmd else {
+            panic!("expected subscribe command");
+        };
+        assert_eq!(&*params.sources[0].source_name, "living_room");
+        assert_eq!(
+            params.sources[0].wanted_info,
+            vec![
+                AudioNodeInfoStreamType::Queue,
+                AudioNodeInfoStreamType::Health
+            ]
+        );
+
+        // messages are serialized like on the stream of a single node, tagged with the node
+        let msg = MultiNodeStreamMessage {
+            source_name: "living_room".into(),
+            message: MultiNodeStreamPayload::Stream(AudioNodeInfoStreamMessage::TrackEnded),
+        };
+        assert_eq!(
+            serde_json::to_string(&msg).unwrap(),
+            r#"{"sourceName":"living_room","message":"TRACK_ENDED"}"#
+        );
+    }
+}
@@ -1,24 +1,37 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use actix::{
     ActorFutureExt, AsyncContext, Handler, Message, Recipient, ResponseActFuture, WrapFuture,
 };
 
 use crate::{
-    audio_hosts::youtube::{
-        playlist::get_playlist_video_urls, youtube_content_type, YoutubeContentType,
+    audio_hosts::{
+        soundcloud::{clean_soundcloud_url, soundcloud_content_type, SoundCloudContentType},
+        youtube::{playlist::get_playlist_video_urls, youtube_content_type, YoutubeContentType},
     },
     audio_playback::audio_item::{AudioMetadata, AudioPlayerQueueItem},
-    commands::node_commands::{AddQueueItemParams, AudioIdentifier},
+    commands::node_commands::{
+        AddQueueItemParams, AnnounceParams, AudioIdentifier, SeekBookmarkParams,
+    },
     database::{
-        fetch_data::{get_audio_metadata_from_db, get_playlist_items_from_db},
-        store_data::{store_playlist_if_not_exists, store_playlist_item_relation_if_not_exists},
+        fetch_data::{
+            get_audio_metadata_from_db, get_bookmark_from_db, get_gain_overrides_from_db,
+            get_many_audio_metadata_from_db, get_playlist_items_from_db,
+            get_resume_position_from_db,
+        },
+        store_data::{
+            delete_resume_position, store_bookmark, store_gain_override, store_play,
+            store_playlist_if_not_exists, store_playlist_item_relation_if_not_exists,
+            store_resume_position,
+        },
+        AudioBookmark,
     },
     downloader::{
         actor::{DownloadAudioRequest, NotifyDownloadUpdate},
         download_identifier::{
-            AudioKind, Identifier, ItemUid, YoutubePlaylistUrl, YoutubeVideoUrl,
+            AudioKind, Identifier, ItemUid, SoundCloudTrackUrl, YoutubePlaylistUrl, YoutubeVideoUrl,
         },
+        local_file::import_local_audio_file,
         DownloadRequiredInformation, YoutubePlaylistDownloadInfo,
     },
     error::{AppError, AppErrorKind, IntoAppError},
@@ -34,6 +47,60 @@ use super::{clean_url, AudioNode, AudioUrl};
 #[rtype(result = "()")]
 pub struct AsyncAddQueueItem(pub AddQueueItemParams);
 
+/// Adds several tracks with a single queue update, the metadata of all tracks is looked up at once
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct AsyncAddQueueItems(pub Vec<AddQueueItemParams>);
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct AsyncAddBookmark {
+    pub uid: ItemUid<Arc<str>>,
+    pub label: Arc<str>,
+    pub position_secs: f64,
+}
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct AsyncSeekBookmark(pub SeekBookmarkParams);
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct AsyncAnnounce(pub AnnounceParams);
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct AsyncStoreItemGain {
+    pub uid: ItemUid<Arc<str>>,
+    pub gain_db: f32,
+}
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct AsyncStoreResumePosition {
+    pub uid: ItemUid<Arc<str>>,
+    pub position_secs: f64,
+}
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct AsyncClearResumePosition(pub ItemUid<Arc<str>>);
+
+/// Records in the play history that the player has started a track
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct AsyncRecordPlay(pub ItemUid<Arc<str>>);
+
+/// Seeks to the stored resume position of the item at the head of the queue, if there is one
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct AsyncResumeCurrentItem;
+
+/// Applies the stored gain overrides to all items in the queue
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct AsyncRefreshItemGains;
+
 #[derive(Debug)]
 pub enum LocalAudioMetadata {
     Found {
@@ -64,9 +131,20 @@ impl Handler<AsyncAddQueueItem> for AudioNode {
             ManyLocal(Arc<[(ItemUid<Arc<str>>, AudioMetadata)]>),
         }
 
+        let start_paused = msg.0.start_paused;
+        let position = msg.0.position;
+        let folder = msg.0.folder.clone();
+        let import_folder = folder.clone();
+        let idempotency_key = msg.0.idempotency_key.clone();
+
         Box::pin(
             async move {
-                let identifier = match msg.0.identifier.into_required_info().await {
+                let identifier = match msg
+                    .0
+                    .identifier
+                    .into_required_info(import_folder.as_deref())
+                    .await
+                {
                     Ok(ident) => ident,
                     Err(err) => {
                         return Err(err);
@@ -79,24 +157,35 @@ impl Handler<AsyncAddQueueItem> for AudioNode {
                         let kind = AudioKind::from_uid(&uid);
 
                         match kind {
-                            Some(AudioKind::YoutubeVideo) => {
-                                match get_audio_metadata_from_db(&uid).await {
-                                    Ok(Some(metadata)) => {
-                                        Ok(MetadataQueryResult::Single(LocalAudioMetadata::Found {
-                                            metadata,
-                                            uid,
-                                        }))
-                                    }
-                                    Ok(None) => Err(AppError::new(
-                                        AppErrorKind::LocalData,
-                                        "failed to find audio data locally",
-                                        &[],
-                                    )),
-                                    Err(err) => Err(err),
+                            Some(
+                                AudioKind::YoutubeVideo
+                                | AudioKind::LocalFile
+                                | AudioKind::SoundCloud,
+                            ) => match get_audio_metadata_from_db(&uid).await {
+                                Ok(Some(metadata)) => {
+                                    Ok(MetadataQueryResult::Single(LocalAudioMetadata::Found {
+                                        metadata,
+                                        uid,
+                                    }))
                                 }
-                            }
+                                Ok(None) => Err(AppError::new(
+                                    AppErrorKind::LocalData,
+                                    "failed to find audio data locally",
+                                    &[],
+                                )
+                                .not_found()),
+                                Err(err) => Err(err),
+                            },
                             Some(AudioKind::YoutubePlaylist) => {
-                                match get_playlist_items_from_db(&uid, None, None).await {
+                                match get_playlist_items_from_db(
+                                    &uid,
+                                    None,
+                                    None,
+                                    None,
+                                    Default::default(),
+                                )
+                                .await
+                                {
                                     Ok(items) => Ok(MetadataQueryResult::ManyLocal(items)),
                                     Err(err) => Err(err),
                                 }
@@ -105,7 +194,8 @@ impl Handler<AsyncAddQueueItem> for AudioNode {
                                 AppErrorKind::LocalData,
                                 "invalid audio uid",
                                 &[&format!("UID: {uid}", uid = uid.0)],
-                            )),
+                            )
+                            .bad_request()),
                         }
                     }
                     DownloadRequiredInformation::YoutubeVideo { url } => {
@@ -119,6 +209,17 @@ impl Handler<AsyncAddQueueItem> for AudioNode {
                             )
                         })
                     }
+                    DownloadRequiredInformation::SoundCloudTrack { url } => {
+                        let uid = url.uid();
+                        get_audio_metadata_from_db(&uid).await.map(|res| {
+                            MetadataQueryResult::Single(
+                                res.map(|md| LocalAudioMetadata::Found { metadata: md, uid })
+                                    .unwrap_or(LocalAudioMetadata::NotFound {
+                                        url: AudioUrl::SoundCloud(url.0),
+                                    }),
+                            )
+                        })
+                    }
                     DownloadRequiredInformation::YoutubePlaylist(YoutubePlaylistDownloadInfo {
                         video_urls,
                         playlist_url,
@@ -126,28 +227,31 @@ impl Handler<AsyncAddQueueItem> for AudioNode {
                         let playlist_uid = playlist_url.uid();
                         store_playlist_if_not_exists(&playlist_uid).await?;
 
-                        let mut metadata_list = Vec::with_capacity(video_urls.len());
-
-                        for url in video_urls.iter() {
-                            let youtube_url = YoutubeVideoUrl(url);
-                            let audio_uid = youtube_url.uid();
+                        let video_uids: Vec<_> = video_urls
+                            .iter()
+                            .map(|url| YoutubeVideoUrl(url).uid())
+                            .collect();
+                        let stored_metadata = get_many_audio_metadata_from_db(&video_uids).await?;
 
-                            let metadata = get_audio_metadata_from_db(&audio_uid).await?;
-                            match metadata {
+                        // stored videos are queued right away, the rest is downloaded in the
+                        // background and linked to the playlist by the downloader
+                        let mut metadata_list = Vec::with_capacity(video_urls.len());
+                        for (url, audio_uid) in video_urls.iter().zip(video_uids) {
+                            match stored_metadata.get(&audio_uid.0) {
                                 Some(metadata) => {
-                                    metadata_list.push(LocalAudioMetadata::Found {
-                                        metadata,
-                                        uid: youtube_url.uid(),
-                                    });
-
                                     store_playlist_item_relation_if_not_exists(
                                         &playlist_uid,
                                         &audio_uid,
                                     )
                                     .await?;
+
+                                    metadata_list.push(LocalAudioMetadata::Found {
+                                        metadata: metadata.clone(),
+                                        uid: audio_uid,
+                                    });
                                 }
                                 None => metadata_list.push(LocalAudioMetadata::NotFound {
-                                    url: AudioUrl::Youtube(Arc::clone(youtube_url.0)),
+                                    url: AudioUrl::Youtube(Arc::clone(url)),
                                 }),
                             }
                         }
@@ -162,78 +266,593 @@ impl Handler<AsyncAddQueueItem> for AudioNode {
                 query_res
             }
             .into_actor(self)
-            .map(move |res, act, ctx| match res {
-                Ok(MetadataQueryResult::Single(data)) => {
-                    let msg = handle_add_single_queue_item(data, act, ctx.address().recipient());
+            .map(move |res, act, ctx| {
+                ctx.notify(AsyncRefreshItemGains);
+
+                match res {
+                    Ok(MetadataQueryResult::Single(data)) => {
+                        let msg = handle_add_single_queue_item(
+                            data,
+                            act,
+                            ctx.address().recipient(),
+                            start_paused,
+                            position,
+                            folder,
+                        );
 
-                    if let Some(msg) = msg {
-                        act.multicast_result(msg);
+                        if let Some(msg) = msg {
+                            if msg.is_err() {
+                                act.forget_idempotency_key(idempotency_key.as_deref());
+                            }
+
+                            act.multicast_result(msg);
+                        }
+                    }
+                    Ok(MetadataQueryResult::Many(LocalAudioMetadataList {
+                        list_url,
+                        metadata,
+                    })) => {
+                        let download_addr = act.downloader_addr.clone().recipient();
+
+                        let audio_urls: Arc<[AudioUrl]> = metadata
+                            .iter()
+                            .filter_map(|data| {
+                                if let LocalAudioMetadata::NotFound { url } = data {
+                                    Some(url.clone())
+                                } else {
+                                    None
+                                }
+                            })
+                            .collect();
+
+                        let existing_metadata = metadata
+                            .into_iter()
+                            .filter_map(|data| match data {
+                                LocalAudioMetadata::Found { metadata, uid } => {
+                                    Some((uid, metadata))
+                                }
+                                _ => None,
+                            })
+                            .collect();
+
+                        play_existing_playlist_items(
+                            act,
+                            existing_metadata,
+                            start_paused,
+                            position,
+                        );
+
+                        if start_paused {
+                            act.start_paused_downloads
+                                .extend(audio_urls.iter().map(AudioUrl::inner));
+                        }
+
+                        request_download_of_missing_items(
+                            Some(Arc::clone(&act.source_name)),
+                            download_addr,
+                            ctx.address().recipient(),
+                            list_url,
+                            audio_urls,
+                            folder,
+                        );
+                    }
+                    Ok(MetadataQueryResult::ManyLocal(items)) => {
+                        play_existing_playlist_items(act, items, start_paused, position);
+                    }
+                    Err(err_resp) => {
+                        // the client can retry the item with the same key
+                        act.forget_idempotency_key(idempotency_key.as_deref());
+                        act.multicast(err_resp);
                     }
                 }
-                Ok(MetadataQueryResult::Many(LocalAudioMetadataList { list_url, metadata })) => {
-                    let download_addr = act.downloader_addr.clone().recipient();
-
-                    let audio_urls = metadata
-                        .iter()
-                        .filter_map(|data| {
-                            if let LocalAudioMetadata::NotFound { url } = data {
-                                Some(url.clone())
-                            } else {
-                                None
+            }),
+        )
+    }
+}
+
+impl Handler<AsyncAddQueueItems> for AudioNode {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: AsyncAddQueueItems, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        struct ResolvedQueueItems {
+            items: Vec<(AddQueueItemParams, LocalAudioMetadata)>,
+            /// items that failed don't stop the rest of the batch from being added
+            errors: Vec<AppError>,
+            /// idempotency keys of the failed items, so they can be retried
+            failed_keys: Vec<Arc<str>>,
+        }
+
+        let idempotency_keys: Vec<_> = msg
+            .0
+            .iter()
+            .filter_map(|params| params.idempotency_key.clone())
+            .collect();
+
+        Box::pin(
+            async move {
+                let mut errors = Vec::new();
+                let mut failed_keys = Vec::new();
+                let mut tracks = Vec::with_capacity(msg.0.len());
+
+                for params in msg.0 {
+                    let track = params
+                        .identifier
+                        .clone()
+                        .into_required_info(params.folder.as_deref())
+                        .await
+                        .and_then(track_location);
+
+                    match track {
+                        Ok(track) => tracks.push((params, track)),
+                        Err(err) => {
+                            failed_keys.extend(params.idempotency_key);
+                            errors.push(err);
+                        }
+                    }
+                }
+
+                let uids: Vec<_> = tracks.iter().map(|(_, (uid, _))| uid.clone()).collect();
+                let metadata = get_many_audio_metadata_from_db(&uids).await?;
+
+                let mut items = Vec::with_capacity(tracks.len());
+                for (params, (uid, url)) in tracks {
+                    let data = match (metadata.get(&uid.0), url) {
+                        (Some(metadata), _) => LocalAudioMetadata::Found {
+                            metadata: metadata.clone(),
+                            uid,
+                        },
+                        (None, Some(url)) => LocalAudioMetadata::NotFound { url },
+                        (None, None) => {
+                            failed_keys.extend(params.idempotency_key);
+                            errors.push(
+                                AppError::new(
+                                    AppErrorKind::LocalData,
+                                    "failed to find audio data locally",
+                                    &[&format!("UID: {uid}", uid = uid.0)],
+                                )
+                                .not_found(),
+                            );
+                            continue;
+                        }
+                    };
+
+                    items.push((params, data));
+                }
+
+                Ok::<_, AppError>(ResolvedQueueItems {
+                    items,
+                    errors,
+                    failed_keys,
+                })
+            }
+            .into_actor(self)
+            .map(move |res, act, ctx| {
+                ctx.notify(AsyncRefreshItemGains);
+
+                let ResolvedQueueItems {
+                    items,
+                    errors,
+                    failed_keys,
+                } = match res {
+                    Ok(resolved) => resolved,
+                    Err(err) => {
+                        for key in &idempotency_keys {
+                            act.forget_idempotency_key(Some(key.as_ref()));
+                        }
+
+                        act.multicast(err);
+                        return;
+                    }
+                };
+
+                for key in &failed_keys {
+                    act.forget_idempotency_key(Some(key.as_ref()));
+                }
+
+                let mut queue_changed = false;
+                for (params, data) in items {
+                    match data {
+                        LocalAudioMetadata::Found { metadata, uid } => {
+                            let item = local_queue_item(uid, metadata);
+
+                            match act.add_to_queue(item, params.position, params.start_paused) {
+                                Ok(()) => queue_changed = true,
+                                Err(err) => {
+                                    act.forget_idempotency_key(params.idempotency_key.as_deref());
+                                    act.multicast(err.into_app_err(
+                                        "failed to add audio to queue",
+                                        AppErrorKind::Queue,
+                                        &[&format!("NODE_NAME: {name}", name = act.source_name)],
+                                    ));
+                                }
                             }
-                        })
-                        .collect();
+                        }
+                        LocalAudioMetadata::NotFound { url } => request_download_of_missing_item(
+                            act,
+                            ctx.address().recipient(),
+                            url,
+                            params.start_paused,
+                            params.position,
+                            params.folder,
+                        ),
+                    }
+                }
 
-                    let existing_metadata = metadata
-                        .into_iter()
-                        .filter_map(|data| match data {
-                            LocalAudioMetadata::Found { metadata, uid } => Some((uid, metadata)),
-                            _ => None,
-                        })
-                        .collect();
+                // one update for the whole batch instead of one per item
+                if queue_changed {
+                    act.multicast(AudioNodeInfoStreamMessage::Queue(extract_queue_metadata(
+                        act.player.queue(),
+                        act.player.queue_head(),
+                    )));
+                    act.update_up_next();
+                }
 
-                    play_existing_playlist_items(act, existing_metadata);
+                for err in errors {
+                    act.multicast(err);
+                }
+            }),
+        )
+    }
+}
 
-                    request_download_of_missing_items(
-                        Some(Arc::clone(&act.source_name)),
-                        download_addr,
-                        ctx.address().recipient(),
-                        list_url,
-                        audio_urls,
-                    );
+impl Handler<AsyncStoreItemGain> for AudioNode {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: AsyncStoreItemGain, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let AsyncStoreItemGain { uid, gain_db } = msg;
+
+        Box::pin(
+            async move { store_gain_override(&uid, gain_db).await }
+                .into_actor(self)
+                .map(|res, act, _ctx| {
+                    if let Err(err) = res {
+                        act.multicast(err);
+                    }
+                }),
+        )
+    }
+}
+
+impl Handler<AsyncRefreshItemGains> for AudioNode {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: AsyncRefreshItemGains, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let uids: Vec<_> = self
+            .player
+            .queue()
+            .iter()
+            .map(|item| item.identifier.clone())
+            .collect();
+
+        Box::pin(
+            async move { get_gain_overrides_from_db(&uids).await }
+                .into_actor(self)
+                .map(|res, act, _ctx| match res {
+                    Ok(overrides) => act.player.apply_gain_overrides(&overrides),
+                    Err(err) => act.multicast(err),
+                }),
+        )
+    }
+}
+
+impl Handler<AsyncStoreResumePosition> for AudioNode {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: AsyncStoreResumePosition, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let AsyncStoreResumePosition { uid, position_secs } = msg;
+
+        Box::pin(
+            async move { store_resume_position(&uid, position_secs).await }
+                .into_actor(self)
+                .map(|res, act, _ctx| {
+                    if let Err(err) = res {
+                        act.multicast(err);
+                    }
+                }),
+        )
+    }
+}
+
+impl Handler<AsyncClearResumePosition> for AudioNode {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: AsyncClearResumePosition, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let uid = msg.0;
+
+        Box::pin(
+            async move { delete_resume_position(&uid).await }
+                .into_actor(self)
+                .map(|res, act, _ctx| {
+                    if let Err(err) = res {
+                        act.multicast(err);
+                    }
+                }),
+        )
+    }
+}
+
+impl Handler<AsyncRecordPlay> for AudioNode {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: AsyncRecordPlay, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let uid = msg.0;
+        let source_name = Arc::clone(&self.source_name);
+
+        Box::pin(
+            async move { store_play(&uid, &source_name).await }
+                .into_actor(self)
+                // the error is already logged, a missing history entry isn't worth interrupting
+                // the listeners for
+                .map(|_res, _act, _ctx| {}),
+        )
+    }
+}
+
+impl Handler<AsyncResumeCurrentItem> for AudioNode {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: AsyncResumeCurrentItem, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let uid = self.current_uid();
+
+        Box::pin(
+            async move {
+                match uid {
+                    Some(uid) => get_resume_position_from_db(&uid)
+                        .await
+                        .map(|position_secs| {
+                            position_secs.map(|position_secs| (uid, position_secs))
+                        }),
+                    None => Ok(None),
                 }
-                Ok(MetadataQueryResult::ManyLocal(items)) => {
-                    play_existing_playlist_items(act, items);
+            }
+            .into_actor(self)
+            .map(|res, act, _ctx| match res {
+                Ok(Some((uid, position_secs))) => {
+                    // another track might have been selected while the position was loaded
+                    if act.current_uid().map(|current| current.0) != Some(Arc::clone(&uid.0)) {
+                        return;
+                    }
+
+                    if let Err(err) = act
+                        .player
+                        .set_stream_progress_secs(position_secs)
+                        .into_app_err(
+                            "failed to seek to resume position",
+                            AppErrorKind::Queue,
+                            &[
+                                &format!("NODE_NAME: {name}", name = act.source_name),
+                                &format!("UID: {uid}", uid = uid.0),
+                            ],
+                        )
+                    {
+                        act.multicast(err);
+                    }
                 }
-                Err(err_resp) => {
-                    act.multicast(err_resp);
+                Ok(None) => {}
+                Err(err) => act.multicast(err),
+            }),
+        )
+    }
+}
+
+impl Handler<AsyncAddBookmark> for AudioNode {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: AsyncAddBookmark, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let AsyncAddBookmark {
+            uid,
+            label,
+            position_secs,
+        } = msg;
+
+        Box::pin(
+            async move { store_bookmark(&uid, &label, position_secs).await }
+                .into_actor(self)
+                .map(|res, act, _ctx| {
+                    if let Err(err) = res {
+                        act.multicast(err);
+                    }
+                }),
+        )
+    }
+}
+
+impl Handler<AsyncSeekBookmark> for AudioNode {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: AsyncSeekBookmark, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let id = msg.0.id;
+
+        Box::pin(
+            async move {
+                let Some(bookmark) = get_bookmark_from_db(id).await? else {
+                    return Err(AppError::new(
+                        AppErrorKind::LocalData,
+                        "failed to find bookmark",
+                        &[&format!("ID: {id}")],
+                    )
+                    .not_found());
+                };
+
+                let uid = ItemUid(Arc::clone(&bookmark.audio_identifier));
+                let Some(metadata) = get_audio_metadata_from_db(&uid).await? else {
+                    return Err(AppError::new(
+                        AppErrorKind::LocalData,
+                        "failed to find audio data locally",
+                        &[&format!("UID: {uid}", uid = uid.0)],
+                    )
+                    .not_found());
+                };
+
+                Ok((bookmark, metadata))
+            }
+            .into_actor(self)
+            .map(|res, act, _ctx| match res {
+                Ok((bookmark, metadata)) => {
+                    if let Err(err) = seek_to_bookmark(act, bookmark, metadata) {
+                        act.multicast(err);
+                    }
+                }
+                Err(err) => act.multicast(err),
+            }),
+        )
+    }
+}
+
+impl Handler<AsyncAnnounce> for AudioNode {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: AsyncAnnounce, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let identifier = msg.0.identifier;
+
+        Box::pin(
+            async move {
+                let uid = match identifier {
+                    AudioIdentifier::Local { uid } => ItemUid(uid),
+                    AudioIdentifier::Youtube { url } => YoutubeVideoUrl(clean_url(&url)).uid(),
+                    AudioIdentifier::SoundCloud { url } => {
+                        SoundCloudTrackUrl(clean_soundcloud_url(&url)).uid()
+                    }
+                    AudioIdentifier::LocalFile { path } => {
+                        import_local_audio_file(&path, None).await?.0
+                    }
+                };
+
+                let Some(metadata) = get_audio_metadata_from_db(&uid).await? else {
+                    return Err(AppError::new(
+                        AppErrorKind::LocalData,
+                        "failed to find announcement audio locally",
+                        &[&format!("UID: {uid}", uid = uid.0)],
+                    ));
+                };
+
+                Ok((uid, metadata))
+            }
+            .into_actor(self)
+            .map(|res, act, _ctx| match res {
+                Ok((uid, metadata)) => {
+                    let item = AudioPlayerQueueItem {
+                        metadata,
+                        locator: uid.to_path_with_ext(),
+                        identifier: uid,
+                        gain_db: 0.0,
+                        fade_in_ms: None,
+                    };
+
+                    if let Err(err) = act.player.announce(item).into_app_err(
+                        "failed to play announcement",
+                        AppErrorKind::Queue,
+                        &[&format!("NODE_NAME: {name}", name = act.source_name)],
+                    ) {
+                        act.multicast(err);
+                    }
                 }
+                Err(err) => act.multicast(err),
             }),
         )
     }
 }
 
+/// plays the bookmarked track at the bookmarked position, the track is added to the end of the
+/// queue if it isn't part of the queue yet
+fn seek_to_bookmark(
+    node: &mut AudioNode,
+    bookmark: AudioBookmark,
+    metadata: AudioMetadata,
+) -> Result<(), AppError> {
+    let node_name = format!("NODE_NAME: {name}", name = node.source_name);
+    let bookmark_id = format!("BOOKMARK_ID: {id}", id = bookmark.id);
+
+    let index = match node
+        .player
+        .queue()
+        .iter()
+        .position(|item| item.identifier.0 == bookmark.audio_identifier)
+    {
+        Some(index) => index,
+        None => {
+            let uid = ItemUid(Arc::clone(&bookmark.audio_identifier));
+            node.player
+                .push_to_queue(AudioPlayerQueueItem {
+                    metadata,
+                    locator: uid.to_path_with_ext(),
+                    identifier: uid,
+                    gain_db: 0.0,
+                    fade_in_ms: None,
+                })
+                .into_app_err(
+                    "failed to add bookmarked audio to queue",
+                    AppErrorKind::Queue,
+                    &[&node_name, &bookmark_id],
+                )?;
+
+            node.multicast(AudioNodeInfoStreamMessage::Queue(extract_queue_metadata(
+                node.player.queue(),
+                node.player.queue_head(),
+            )));
+            node.update_up_next();
+
+            node.player.queue().len() - 1
+        }
+    };
+
+    node.player.play_selected(index, false).into_app_err(
+        "failed to play bookmarked audio",
+        AppErrorKind::Queue,
+        &[&node_name, &bookmark_id],
+    )?;
+
+    node.player
+        .set_stream_progress_secs(bookmark.position_secs)
+        .into_app_err(
+            "failed to seek to bookmark",
+            AppErrorKind::Queue,
+            &[&node_name, &bookmark_id],
+        )
+}
+
 fn play_existing_playlist_items(
     node: &mut AudioNode,
     metadata_list: Arc<[(ItemUid<Arc<str>>, AudioMetadata)]>,
+    start_paused: bool,
+    position: Option<usize>,
 ) {
     if metadata_list.is_empty() {
         return;
     }
 
-    for (uid, metadata) in metadata_list.iter().cloned() {
-        let audio_item = AudioPlayerQueueItem {
-            metadata,
-            locator: uid.to_path_with_ext(),
-            identifier: uid,
-        };
+    for (i, (uid, metadata)) in metadata_list.iter().cloned().enumerate() {
+        let audio_item = local_queue_item(uid, metadata);
 
-        let _ = node.player.push_to_queue(audio_item);
+        // keeps the order of the playlist
+        let _ = node.add_to_queue(audio_item, position.map(|index| index + i), start_paused);
     }
 
     node.multicast(AudioNodeInfoStreamMessage::Queue(extract_queue_metadata(
         node.player.queue(),
-    )))
+        node.player.queue_head(),
+    )));
+    node.update_up_next();
 }
 
 fn request_download_of_missing_items(
@@ -242,6 +861,7 @@ fn request_download_of_missing_items(
     receiver_addr: Recipient<NotifyDownloadUpdate>,
     list_url: AudioUrl,
     audio_urls: Arc<[AudioUrl]>,
+    folder: Option<Arc<str>>,
 ) {
     if audio_urls.is_empty() {
         return;
@@ -275,18 +895,46 @@ fn request_download_of_missing_items(
                 source_name,
                 addr: receiver_addr,
                 required_info,
+                folder,
             };
 
             downloader_addr.do_send(request); // TODO handle mailbox full
         }
+        AudioUrl::SoundCloud(url) => {
+            log::warn!("soundcloud sets can't be downloaded as a batch, URL: {url}");
+        }
     }
 }
 
 impl AudioIdentifier {
-    async fn into_required_info(self) -> Result<DownloadRequiredInformation, AppError> {
+    /// whether the identifier refers to a playlist instead of a single track
+    pub(super) fn is_playlist(&self) -> bool {
+        match self {
+            Self::Local { uid } => matches!(
+                AudioKind::from_uid(&ItemUid(&**uid)),
+                Some(AudioKind::YoutubePlaylist)
+            ),
+            Self::Youtube { url } => youtube_content_type(&**url) == YoutubeContentType::Playlist,
+            Self::SoundCloud { url } => {
+                soundcloud_content_type(&**url) == SoundCloudContentType::Set
+            }
+            Self::LocalFile { .. } => false,
+        }
+    }
+
+    /// local files are imported into `folder` right away, they are stored locally afterwards
+    async fn into_required_info(
+        self,
+        folder: Option<&str>,
+    ) -> Result<DownloadRequiredInformation, AppError> {
         let url = match self {
             Self::Local { uid } => return Ok(DownloadRequiredInformation::StoredLocally { uid }),
             Self::Youtube { url } => url,
+            Self::SoundCloud { url } => return soundcloud_required_info(&url),
+            Self::LocalFile { path } => {
+                let (uid, _) = import_local_audio_file(&path, folder).await?;
+                return Ok(DownloadRequiredInformation::StoredLocally { uid: uid.0 });
+            }
         };
 
         let content_type = youtube_content_type(&*url);
@@ -313,23 +961,45 @@ impl AudioIdentifier {
                 AppErrorKind::Download,
                 "invalid youtube video url",
                 &[&format!("URL: {url}")],
-            )),
+            )
+            .bad_request()),
         }
     }
 }
 
+fn soundcloud_required_info(url: &str) -> Result<DownloadRequiredInformation, AppError> {
+    match soundcloud_content_type(url) {
+        SoundCloudContentType::Track => Ok(DownloadRequiredInformation::SoundCloudTrack {
+            url: SoundCloudTrackUrl(clean_soundcloud_url(url).into()),
+        }),
+        SoundCloudContentType::Set => Err(AppError::new(
+            AppErrorKind::Download,
+            "soundcloud sets are not supported, add their tracks one by one instead",
+            &[&format!("URL: {url}")],
+        )
+        .bad_request()),
+        SoundCloudContentType::Invalid => Err(AppError::new(
+            AppErrorKind::Download,
+            "invalid soundcloud track url",
+            &[&format!("URL: {url}")],
+        )
+        .bad_request()),
+    }
+}
+
 fn handle_add_single_queue_item(
     data: LocalAudioMetadata,
     node: &mut AudioNode,
     node_addr: Recipient<NotifyDownloadUpdate>,
+    start_paused: bool,
+    position: Option<usize>,
+    folder: Option<Arc<str>>,
 ) -> Option<Result<AudioNodeInfoStreamMessage, AppError>> {
     match data {
         LocalAudioMetadata::Found { metadata, uid } => {
-            if let Err(err) = node.player.push_to_queue(AudioPlayerQueueItem {
-                metadata,
-                locator: uid.to_path_with_ext(),
-                identifier: uid,
-            }) {
+            let item = local_queue_item(uid, metadata);
+
+            if let Err(err) = node.add_to_queue(item, position, start_paused) {
                 return Some(Err(err.into_app_err(
                     "failed to auto play first song,",
                     AppErrorKind::Queue,
@@ -338,23 +1008,151 @@ fn handle_add_single_queue_item(
             }
         }
         LocalAudioMetadata::NotFound { url } => {
-            let download_info = match url {
-                AudioUrl::Youtube(url) => DownloadRequiredInformation::YoutubeVideo {
-                    url: YoutubeVideoUrl(url),
-                },
-            };
-
-            node.downloader_addr.do_send(DownloadAudioRequest {
-                source_name: Some(Arc::clone(&node.source_name)),
-                addr: node_addr,
-                required_info: download_info,
-            });
-
+            request_download_of_missing_item(node, node_addr, url, start_paused, position, folder);
             return None;
         }
     }
 
     Some(Ok(AudioNodeInfoStreamMessage::Queue(
-        extract_queue_metadata(node.player.queue()),
+        extract_queue_metadata(node.player.queue(), node.player.queue_head()),
     )))
 }
+
+/// the item is added to the queue once its download has finished
+fn request_download_of_missing_item(
+    node: &mut AudioNode,
+    node_addr: Recipient<NotifyDownloadUpdate>,
+    url: AudioUrl,
+    start_paused: bool,
+    position: Option<usize>,
+    folder: Option<Arc<str>>,
+) {
+    if start_paused {
+        node.start_paused_downloads.insert(url.inner());
+    }
+
+    if let Some(position) = position {
+        node.download_queue_positions.insert(url.inner(), position);
+    }
+
+    let download_info = match url {
+        AudioUrl::Youtube(url) => DownloadRequiredInformation::YoutubeVideo {
+            url: YoutubeVideoUrl(url),
+        },
+        AudioUrl::SoundCloud(url) => DownloadRequiredInformation::SoundCloudTrack {
+            url: SoundCloudTrackUrl(url),
+        },
+    };
+
+    node.downloader_addr.do_send(DownloadAudioRequest {
+        source_name: Some(Arc::clone(&node.source_name)),
+        addr: node_addr,
+        required_info: download_info,
+        folder,
+    });
+}
+
+/// uid of a single track and the url it is downloaded from if it isn't stored locally
+fn track_location(
+    info: DownloadRequiredInformation,
+) -> Result<(ItemUid<Arc<str>>, Option<AudioUrl>), AppError> {
+    match info {
+        DownloadRequiredInformation::StoredLocally { uid } => Ok((ItemUid(uid), None)),
+        DownloadRequiredInformation::YoutubeVideo { url } => {
+            Ok((url.uid(), Some(AudioUrl::Youtube(url.0))))
+        }
+        DownloadRequiredInformation::SoundCloudTrack { url } => {
+            Ok((url.uid(), Some(AudioUrl::SoundCloud(url.0))))
+        }
+        DownloadRequiredInformation::YoutubePlaylist(YoutubePlaylistDownloadInfo {
+            playlist_url,
+            ..
+        }) => Err(AppError::new(
+            AppErrorKind::Queue,
+            "expected a single track, got a playlist",
+            &[&format!("URL: {url}", url = playlist_url.0)],
+        )
+        .bad_request()),
+    }
+}
+
+fn local_queue_item(
+    uid: ItemUid<Arc<str>>,
+    metadata: AudioMetadata,
+) -> AudioPlayerQueueItem<PathBuf> {
+    AudioPlayerQueueItem {
+        metadata,
+        locator: uid.to_path_with_ext(),
+        identifier: uid,
+        gain_db: 0.0,
+        fade_in_ms: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::store_data::{delete_audio_metadata, store_audio_metadata_if_not_exists},
+        rest_data_access::get_audio_resume_position,
+        tests_utils::{test_db_pool, test_node},
+    };
+
+    use super::*;
+    use actix::Actor;
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, read_body_json, TestRequest},
+        App,
+    };
+    use pretty_assertions::assert_eq;
+
+    #[actix_web::test]
+    async fn test_resume_position_is_stored_and_cleared() {
+        test_db_pool().await;
+
+        let uid = ItemUid(Arc::from("test_resume_position"));
+        let metadata = AudioMetadata {
+            name: Some(String::from("Audiobook")).into(),
+            author: Option::<String>::None.into(),
+            duration: Some(3600),
+            cover_art_url: Option::<String>::None.into(),
+            gain_db: None,
+        };
+        store_audio_metadata_if_not_exists(&uid, &metadata)
+            .await
+            .unwrap();
+
+        let node = test_node("test-resume-position").start();
+        let app = init_service(App::new().service(get_audio_resume_position)).await;
+        let uri = "/data/audio/test_resume_position/resume-position";
+
+        // pausing again later replaces the stored position
+        for position_secs in [42.5, 1800.0] {
+            node.send(AsyncStoreResumePosition {
+                uid: uid.clone(),
+                position_secs,
+            })
+            .await
+            .unwrap();
+
+            let res = call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(res.status(), StatusCode::OK);
+
+            let body: serde_json::Value = read_body_json(res).await;
+            assert_eq!(
+                body,
+                serde_json::json!({ "uid": "test_resume_position", "positionSecs": position_secs })
+            );
+        }
+
+        // the track was played to its end
+        node.send(AsyncClearResumePosition(uid.clone()))
+            .await
+            .unwrap();
+
+        let res = call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        delete_audio_metadata(&[uid]).await.unwrap();
+    }
+}
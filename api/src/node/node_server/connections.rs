@@ -1,20 +1,87 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use actix::{Addr, Handler, Message, MessageResponse};
+use actix::{Addr, Handler, Message, MessageResponse, MessageResult};
+use serde::Serialize;
+use ts_rs::TS;
 
 use crate::{
-    audio_playback::audio_player::AudioInfo,
-    node::node_session::{AudioNodeSession, NodeSessionWsResponse},
+    node::{
+        multi_node_session::{MultiNodeSession, TaggedNodeStreamMessage},
+        node_session::{AudioNodeSession, NodeSessionWsResponse},
+    },
     streams::node_streams::{AudioNodeInfoStreamType, RunningDownloadInfo},
     utils::log_msg_received,
 };
 
-use super::{extract_queue_metadata, AudioNode};
+use super::{extract_queue_metadata, AudioNode, SourceName};
+
+/// session a node multicasts its stream messages to
+#[derive(Debug, Clone)]
+pub enum NodeSessionAddr {
+    Single(Addr<AudioNodeSession>),
+    /// receives messages of multiple nodes, tagged with the source name of the node
+    Multiplexed(Addr<MultiNodeSession>),
+}
+
+impl NodeSessionAddr {
+    pub fn do_send<M>(&self, source_name: &SourceName, msg: M)
+    where
+        M: Message<Result = ()> + Send + 'static,
+        AudioNodeSession: Handler<M>,
+        MultiNodeSession: Handler<TaggedNodeStreamMessage<M>>,
+    {
+        match self {
+            Self::Single(addr) => addr.do_send(msg),
+            Self::Multiplexed(addr) => addr.do_send(TaggedNodeStreamMessage {
+                source_name: source_name.clone(),
+                msg,
+            }),
+        }
+    }
+}
+
+/// session that receives the multicasts of a node
+#[derive(Debug, Clone)]
+pub struct ConnectedSession {
+    pub addr: NodeSessionAddr,
+    pub wanted_info: Arc<[AudioNodeInfoStreamType]>,
+    pub connected_at: SystemTime,
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct NodeSessionInfo {
+    pub id: usize,
+    pub kind: NodeSessionKind,
+    /// seconds since the unix epoch
+    #[ts(type = "number")]
+    pub connected_at_secs: u64,
+    pub wanted_info: Vec<AudioNodeInfoStreamType>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[serde(rename_all = "kebab-case")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub enum NodeSessionKind {
+    /// stream of only this node
+    Single,
+    /// stream that follows multiple nodes
+    Multiplexed,
+}
+
+/// sessions that are connected to the node, ordered by when they connected
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Vec<NodeSessionInfo>")]
+pub struct GetConnectedSessions;
 
 #[derive(Debug, Clone, Message)]
 #[rtype(result = "NodeConnectResponse")]
 pub struct NodeConnectMessage {
-    pub addr: Addr<AudioNodeSession>,
+    pub addr: NodeSessionAddr,
     pub wanted_info: Arc<[AudioNodeInfoStreamType]>,
 }
 
@@ -37,13 +104,23 @@ impl Handler<NodeConnectMessage> for AudioNode {
         log_msg_received(&self, &msg);
 
         let id = self.sessions.keys().max().unwrap_or(&0) + 1;
-        self.sessions.insert(id, msg.addr);
+        self.sessions.insert(
+            id,
+            ConnectedSession {
+                addr: msg.addr,
+                wanted_info: Arc::clone(&msg.wanted_info),
+                connected_at: SystemTime::now(),
+            },
+        );
 
         let connection_response = NodeSessionWsResponse::SessionConnectedResponse {
             queue: msg
                 .wanted_info
                 .contains(&AudioNodeInfoStreamType::Queue)
-                .then_some(extract_queue_metadata(self.player.queue())),
+                .then_some(extract_queue_metadata(
+                    self.player.queue(),
+                    self.player.queue_head(),
+                )),
             health: msg
                 .wanted_info
                 .contains(&AudioNodeInfoStreamType::Health)
@@ -58,12 +135,12 @@ impl Handler<NodeConnectMessage> for AudioNode {
             audio_state_info: msg
                 .wanted_info
                 .contains(&AudioNodeInfoStreamType::AudioStateInfo)
-                .then_some(AudioInfo {
-                    current_queue_index: self.player.queue_head(),
-                    audio_volume: self.current_processor_info.audio_volume,
-                    audio_progress: self.current_processor_info.audio_progress,
-                    playback_state: self.current_processor_info.playback_state.clone(),
-                }),
+                .then(|| self.audio_info()),
+            up_next: msg
+                .wanted_info
+                .contains(&AudioNodeInfoStreamType::Queue)
+                .then(|| self.up_next().map(|item| item.metadata.clone()))
+                .flatten(),
         };
 
         NodeConnectResponse {
@@ -82,3 +159,32 @@ impl Handler<NodeDisconnectMessage> for AudioNode {
         self.sessions.remove(&msg.id);
     }
 }
+
+impl Handler<GetConnectedSessions> for AudioNode {
+    type Result = MessageResult<GetConnectedSessions>;
+
+    fn handle(&mut self, msg: GetConnectedSessions, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let mut sessions: Vec<_> = self
+            .sessions
+            .iter()
+            .map(|(id, session)| NodeSessionInfo {
+                id: *id,
+                kind: match session.addr {
+                    NodeSessionAddr::Single(_) => NodeSessionKind::Single,
+                    NodeSessionAddr::Multiplexed(_) => NodeSessionKind::Multiplexed,
+                },
+                connected_at_secs: session
+                    .connected_at
+                    .duration_since(UNIX_EPOCH)
+                    .map(|since_epoch| since_epoch.as_secs())
+                    .unwrap_or_default(),
+                wanted_info: session.wanted_info.to_vec(),
+            })
+            .collect();
+        sessions.sort_by_key(|session| (session.connected_at_secs, session.id));
+
+        MessageResult(sessions)
+    }
+}
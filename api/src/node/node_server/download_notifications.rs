@@ -1,20 +1,30 @@
+use std::collections::HashSet;
+
 use crate::{
     audio_playback::audio_item::AudioPlayerQueueItem,
     downloader::{
-        actor::NotifyDownloadUpdate, download_identifier::Identifier, info::DownloadInfo,
+        actor::{CancelDownloads, NotifyDownloadUpdate},
+        download_identifier::Identifier,
+        info::DownloadInfo,
     },
     error::{AppErrorKind, IntoAppError},
     streams::node_streams::{AudioNodeInfoStreamMessage, RunningDownloadInfo},
+    utils::log_msg_received,
 };
 
-use actix::Handler;
+use actix::{AsyncContext, Handler, Message};
+
+/// Cancels every active and queued download that was requested by this node
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct CancelAllDownloads;
 
-use super::{extract_queue_metadata, AudioNode};
+use super::{async_actor::AsyncRefreshItemGains, extract_queue_metadata, AudioNode};
 
 impl Handler<NotifyDownloadUpdate> for AudioNode {
     type Result = ();
 
-    fn handle(&mut self, msg: NotifyDownloadUpdate, _ctx: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, msg: NotifyDownloadUpdate, ctx: &mut Self::Context) -> Self::Result {
         match msg {
             NotifyDownloadUpdate::Queued(info) => {
                 self.active_downloads.insert(info);
@@ -26,6 +36,17 @@ impl Handler<NotifyDownloadUpdate> for AudioNode {
 
                 self.multicast(msg);
             }
+            NotifyDownloadUpdate::Started(info) => {
+                self.multicast(AudioNodeInfoStreamMessage::DownloadStarted(info));
+            }
+            NotifyDownloadUpdate::Progress(progress) => {
+                if is_download_expected(&self.active_downloads, &progress.info) {
+                    self.multicast(AudioNodeInfoStreamMessage::DownloadProgress(progress));
+                }
+            }
+            NotifyDownloadUpdate::WaitingForIdle(info) => {
+                self.multicast(AudioNodeInfoStreamMessage::DownloadWaitingForIdle(info));
+            }
             NotifyDownloadUpdate::FailedToQueue((info, err_resp)) => {
                 self.failed_downloads.insert(info, err_resp);
 
@@ -36,6 +57,17 @@ impl Handler<NotifyDownloadUpdate> for AudioNode {
 
                 self.multicast(msg);
             }
+            NotifyDownloadUpdate::SingleFinished(Ok((info, _, _)))
+            | NotifyDownloadUpdate::SingleFinished(Err((info, _)))
+                if !is_download_expected(&self.active_downloads, &info) =>
+            {
+                self.take_start_paused(&info);
+                self.take_queue_position(&info);
+                log::warn!(
+                    "dropping finished download that is no longer expected by node '{name}', INFO: {info:?}",
+                    name = self.source_name
+                );
+            }
             NotifyDownloadUpdate::SingleFinished(Ok((info, metadata, uid))) => {
                 self.active_downloads.remove(&info);
                 self.failed_downloads.remove(&info);
@@ -44,9 +76,15 @@ impl Handler<NotifyDownloadUpdate> for AudioNode {
                     metadata,
                     locator: uid.to_path_with_ext(),
                     identifier: uid,
+                    gain_db: 0.0,
+                    fade_in_ms: None,
                 };
 
-                let has_errored = if let Err(err) = self.player.push_to_queue(item) {
+                let start_paused = self.take_start_paused(&info);
+                let position = self.take_queue_position(&info);
+                let res = self.add_to_queue(item, position, start_paused);
+
+                let has_errored = if let Err(err) = res {
                     self.failed_downloads.insert(
                         info,
                         err.into_app_err(
@@ -67,14 +105,19 @@ impl Handler<NotifyDownloadUpdate> for AudioNode {
                 self.multicast(download_fin_msg);
 
                 if !has_errored {
+                    ctx.notify(AsyncRefreshItemGains);
+
                     let updated_queue_msg = AudioNodeInfoStreamMessage::Queue(
-                        extract_queue_metadata(self.player.queue()),
+                        extract_queue_metadata(self.player.queue(), self.player.queue_head()),
                     );
 
                     self.multicast(updated_queue_msg);
+                    self.update_up_next();
                 }
             }
             NotifyDownloadUpdate::SingleFinished(Err((info, err_resp))) => {
+                self.take_start_paused(&info);
+                self.take_queue_position(&info);
                 self.active_downloads.remove(&info);
                 self.failed_downloads.insert(info, err_resp);
 
@@ -118,3 +161,141 @@ impl Handler<NotifyDownloadUpdate> for AudioNode {
         }
     }
 }
+
+impl AudioNode {
+    /// whether the downloaded video was added with `start_paused`, forgets about the video
+    fn take_start_paused(&mut self, info: &DownloadInfo) -> bool {
+        match info {
+            DownloadInfo::YoutubeVideo { url } | DownloadInfo::SoundCloudTrack { url } => {
+                self.start_paused_downloads.remove(url)
+            }
+            DownloadInfo::YoutubePlaylist { .. } => false,
+        }
+    }
+
+    /// queue position the downloaded track was added at, forgets about the track
+    fn take_queue_position(&mut self, info: &DownloadInfo) -> Option<usize> {
+        match info {
+            DownloadInfo::YoutubeVideo { url } | DownloadInfo::SoundCloudTrack { url } => {
+                self.download_queue_positions.remove(url)
+            }
+            DownloadInfo::YoutubePlaylist { .. } => None,
+        }
+    }
+}
+
+/// whether a finished download is still wanted by the node, this is not the case if the download
+/// was cancelled in the meantime
+///
+/// Videos of a playlist are expected as long as the playlist download is active.
+fn is_download_expected(active_downloads: &HashSet<DownloadInfo>, info: &DownloadInfo) -> bool {
+    if active_downloads.contains(info) {
+        return true;
+    }
+
+    match info {
+        DownloadInfo::YoutubeVideo { url } => active_downloads.iter().any(|active| {
+            matches!(
+                active,
+                DownloadInfo::YoutubePlaylist { video_urls, .. } if video_urls.contains(url)
+            )
+        }),
+        DownloadInfo::YoutubePlaylist { .. } | DownloadInfo::SoundCloudTrack { .. } => false,
+    }
+}
+
+impl Handler<CancelAllDownloads> for AudioNode {
+    type Result = ();
+
+    fn handle(&mut self, msg: CancelAllDownloads, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.downloader_addr.do_send(CancelDownloads {
+            source_name: self.source_name.clone(),
+        });
+        self.active_downloads.clear();
+        self.start_paused_downloads.clear();
+        self.download_queue_positions.clear();
+
+        let msg = AudioNodeInfoStreamMessage::Download(RunningDownloadInfo {
+            active: self.active_downloads.clone().into_iter().collect(),
+            failed: self.failed_downloads.clone().into_iter().collect(),
+        });
+
+        self.multicast(msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        audio_playback::audio_item::AudioMetadata,
+        downloader::download_identifier::ItemUid,
+        error::{AppError, AppErrorKind},
+        tests_utils::test_node,
+    };
+
+    use super::*;
+    use actix::Context;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_failed_first_download_does_not_occupy_queue_slot() {
+        let mut node = test_node("failed-download");
+        let mut ctx = Context::new();
+
+        let failed = DownloadInfo::yt_video("https://www.youtube.com/watch?v=a");
+        let finished = DownloadInfo::yt_video("https://www.youtube.com/watch?v=b");
+        for info in [&failed, &finished] {
+            node.handle(NotifyDownloadUpdate::Queued(info.clone()), &mut ctx);
+        }
+
+        let err = AppError::new(AppErrorKind::Download, "failed to download audio", &[]);
+        node.handle(
+            NotifyDownloadUpdate::SingleFinished(Err((failed.clone(), err))),
+            &mut ctx,
+        );
+        assert!(node.player.queue().is_empty());
+        assert!(node.failed_downloads.contains_key(&failed));
+
+        let uid = ItemUid(Arc::from("youtube_audio_b"));
+        let metadata = AudioMetadata {
+            name: Some(String::from("b")).into(),
+            author: Option::<String>::None.into(),
+            duration: Some(60),
+            cover_art_url: Option::<String>::None.into(),
+            gain_db: None,
+        };
+        node.handle(
+            NotifyDownloadUpdate::SingleFinished(Ok((finished, metadata, uid.clone()))),
+            &mut ctx,
+        );
+
+        // the next download takes the first slot and starts playing
+        assert_eq!(node.player.queue().len(), 1);
+        assert_eq!(node.player.queue_head(), 0);
+        assert_eq!(node.current_uid().map(|uid| uid.0), Some(uid.0));
+        assert!(!node.player.is_stopped());
+    }
+
+    #[test]
+    fn test_download_not_expected_after_cancel() {
+        let video = DownloadInfo::yt_video("https://www.youtube.com/watch?v=a");
+        let playlist_video = DownloadInfo::yt_video("https://www.youtube.com/watch?v=b");
+        let playlist = DownloadInfo::yt_playlist(
+            "https://www.youtube.com/playlist?list=c",
+            &["https://www.youtube.com/watch?v=b"],
+        );
+
+        let mut active_downloads = HashSet::from([video.clone(), playlist]);
+        assert!(is_download_expected(&active_downloads, &video));
+        assert!(is_download_expected(&active_downloads, &playlist_video));
+
+        // downloads of the node were cancelled while the downloads were running
+        active_downloads.clear();
+        assert!(!is_download_expected(&active_downloads, &video));
+        assert!(!is_download_expected(&active_downloads, &playlist_video));
+    }
+}
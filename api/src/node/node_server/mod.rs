@@ -11,15 +11,28 @@ use ts_rs::TS;
 use crate::{
     audio_playback::{
         audio_item::{AudioDataLocator, AudioPlayerQueueItem},
-        audio_player::{AudioPlayer, ProcessorInfo, SerializableQueue},
+        audio_player::{
+            next_head_after_finish, AudioInfo, AudioPlayer, PlaybackState, ProcessorInfo,
+            RepeatMode, SerializableQueue, SerializableQueueItem, TrackInfo,
+        },
     },
-    brain::brain_server::AudioBrain,
-    downloader::{actor::AudioDownloader, info::DownloadInfo},
-    error::AppError,
-    state_storage::restore_state_actor::RestoreStateActor,
+    brain::brain_server::{AudioBrain, AudioNodeToBrainMessage},
+    downloader::{actor::AudioDownloader, download_identifier::ItemUid, info::DownloadInfo},
+    error::{AppError, AppErrorKind},
+    state_storage::{restore_state_actor::RestoreStateActor, AudioStateInfo},
+    streams::node_streams::AudioNodeInfoStreamMessage,
 };
 
-use super::{health::AudioNodeHealth, node_session::AudioNodeSession};
+use self::{async_actor::AsyncRefreshItemGains, connections::ConnectedSession};
+
+use super::{
+    error_log::RecentErrors,
+    health::AudioNodeHealth,
+    idempotency::RecentIdempotencyKeys,
+    multi_node_session::{MultiNodeSession, TaggedNodeStreamMessage},
+    node_session::AudioNodeSession,
+    recovery::{RecoveryAttempts, DEFAULT_MAX_RECOVERY_ATTEMPTS},
+};
 
 pub mod async_actor;
 pub mod connections;
@@ -36,9 +49,67 @@ pub struct AudioNode {
     pub(super) restore_state_addr: Addr<RestoreStateActor>,
     pub(super) active_downloads: HashSet<DownloadInfo>,
     pub(super) failed_downloads: HashMap<DownloadInfo, AppError>,
+    /// urls of downloading videos that should start paused if they are the first item of the queue
+    pub(super) start_paused_downloads: HashSet<Arc<str>>,
+    /// queue positions of downloading tracks that were added at a position instead of appended
+    pub(super) download_queue_positions: HashMap<Arc<str>, usize>,
     pub(super) server_addr: Addr<AudioBrain>,
-    pub(super) sessions: HashMap<usize, Addr<AudioNodeSession>>,
+    pub(super) sessions: HashMap<usize, ConnectedSession>,
     pub(super) health: AudioNodeHealth,
+    pub(super) recent_errors: RecentErrors,
+    /// plays the next track once the current one has ended, otherwise playback stops until the
+    /// next track is requested
+    pub(super) auto_advance: bool,
+    /// stores the position of a track when it is paused or skipped and continues from there the
+    /// next time it is played
+    pub(super) resume_playback: bool,
+    /// pauses playback while the audio device is lost, so playback continues where it stopped
+    /// once the device is back
+    pub(super) pause_on_device_loss: bool,
+    /// whether the current pause was caused by losing the audio device and should end once the
+    /// device has been recovered
+    pub(super) paused_by_device_loss: bool,
+    /// last playback activity that was reported to the brain
+    pub(super) playback_active: bool,
+    /// track that was last played to its end, used to replay it
+    pub(super) last_finished: Option<FinishedTrack>,
+    /// uid of the up next track that was last sent to clients, `None` until it was sent once
+    pub(super) last_up_next: Option<Option<Arc<str>>>,
+    pub(super) recovery_attempts: RecoveryAttempts,
+    /// idempotency keys of recently added queue items
+    pub(super) idempotency_keys: RecentIdempotencyKeys,
+    /// label of the current queue for display, independent of stored playlists
+    pub(super) queue_label: Option<Arc<str>>,
+}
+
+/// longest label a queue can have
+pub const MAX_QUEUE_LABEL_LEN: usize = 100;
+
+/// options of a node that are stored with its state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NodeOptions {
+    pub auto_advance: bool,
+    pub resume_playback: bool,
+    pub pause_on_device_loss: bool,
+    /// applied to the player, see [`AudioPlayer::set_fade_in_ms`]
+    pub fade_in_ms: u32,
+    /// applied to the player, see [`AudioPlayer::set_normalization_enabled`]
+    pub normalization_enabled: bool,
+    /// configured in the sources file instead of being stored with the state
+    pub max_recovery_attempts: u32,
+}
+
+impl Default for NodeOptions {
+    fn default() -> Self {
+        Self {
+            auto_advance: true,
+            resume_playback: false,
+            pause_on_device_loss: false,
+            fade_in_ms: 0,
+            normalization_enabled: true,
+            max_recovery_attempts: DEFAULT_MAX_RECOVERY_ATTEMPTS,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, TS)]
@@ -47,16 +118,29 @@ pub struct AudioNodeInfo {
     pub source_name: SourceName,
     pub human_readable_name: String,
     pub health: AudioNodeHealth,
+    pub max_volume: f32,
+    pub auto_advance: bool,
+    pub resume_playback: bool,
+    pub pause_on_device_loss: bool,
+    /// gain of every equalizer band in dB, empty if the equalizer is flat
+    pub equalizer_bands: Vec<f32>,
+    /// fade in at the start of every track, items can override it
+    pub fade_in_ms: u32,
+    /// whether the measured loudness of tracks is normalized
+    pub normalization_enabled: bool,
+    pub queue_label: Option<Arc<str>>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum UrlKindByProvider {
     Youtube,
+    SoundCloud,
 }
 
 #[derive(Debug)]
 pub enum AudioUrl {
     Youtube(Arc<str>),
+    SoundCloud(Arc<str>),
 }
 
 impl Actor for AudioNode {
@@ -65,7 +149,10 @@ impl Actor for AudioNode {
     fn started(&mut self, ctx: &mut Self::Context) {
         log::info!("stared new 'AudioNode', CONTEXT: {ctx:?}");
 
-        self.player.set_addr(Some(ctx.address()))
+        self.player.set_addr(Some(ctx.address()));
+
+        // gain overrides of the restored queue
+        ctx.notify(AsyncRefreshItemGains);
     }
 }
 
@@ -73,6 +160,7 @@ impl Clone for AudioUrl {
     fn clone(&self) -> Self {
         match self {
             Self::Youtube(url) => Self::Youtube(Arc::clone(url)),
+            Self::SoundCloud(url) => Self::SoundCloud(Arc::clone(url)),
         }
     }
 }
@@ -80,13 +168,14 @@ impl Clone for AudioUrl {
 impl AudioUrl {
     fn inner(&self) -> Arc<str> {
         match self {
-            Self::Youtube(url) => Arc::clone(url),
+            Self::Youtube(url) | Self::SoundCloud(url) => Arc::clone(url),
         }
     }
 
     fn kind(&self) -> UrlKindByProvider {
         match self {
             Self::Youtube(_) => UrlKindByProvider::Youtube,
+            Self::SoundCloud(_) => UrlKindByProvider::SoundCloud,
         }
     }
 }
@@ -98,8 +187,10 @@ impl AudioNode {
         server_addr: Addr<AudioBrain>,
         downloader_addr: Addr<AudioDownloader>,
         restore_state_addr: Addr<RestoreStateActor>,
+        options: NodeOptions,
+        queue_label: Option<Arc<str>>,
     ) -> Self {
-        Self {
+        let mut node = Self {
             source_name,
             current_processor_info: ProcessorInfo::new(1.0),
             player,
@@ -108,42 +199,204 @@ impl AudioNode {
             server_addr,
             active_downloads: HashSet::default(),
             failed_downloads: HashMap::default(),
+            start_paused_downloads: HashSet::default(),
+            download_queue_positions: HashMap::default(),
             sessions: HashMap::default(),
             health: AudioNodeHealth::Good,
+            recent_errors: RecentErrors::default(),
+            auto_advance: options.auto_advance,
+            resume_playback: options.resume_playback,
+            pause_on_device_loss: options.pause_on_device_loss,
+            paused_by_device_loss: false,
+            playback_active: false,
+            last_finished: None,
+            last_up_next: None,
+            recovery_attempts: RecoveryAttempts::new(options.max_recovery_attempts),
+            idempotency_keys: RecentIdempotencyKeys::default(),
+            queue_label,
+        };
+
+        node.sync_stopped_state();
+        node
+    }
+
+    /// there is no processor reporting the state of a stopped player
+    pub(super) fn sync_stopped_state(&mut self) {
+        if self.player.is_stopped() {
+            self.current_processor_info.playback_state = PlaybackState::Stopped;
+            self.current_processor_info.audio_progress = 0.0;
+            self.current_processor_info.elapsed_secs = 0.0;
+            self.current_processor_info.duration_secs = None;
+            self.current_processor_info.track_loaded = false;
+        }
+    }
+
+    /// latest playback state reported by the audio processor
+    pub(super) fn audio_info(&self) -> AudioInfo {
+        AudioInfo {
+            current_queue_index: self.player.queue_head(),
+            audio_volume: self.current_processor_info.audio_volume,
+            audio_progress: self.current_processor_info.audio_progress,
+            playback_state: self.current_processor_info.playback_state.clone(),
+            duration_secs: current_duration_secs(
+                self.current_processor_info.duration_secs,
+                self.player.current_track_info(),
+            ),
+            elapsed_secs: self
+                .player
+                .current_track_info()
+                .map(|_| self.current_processor_info.elapsed_secs),
+            repeat_mode: self.player.repeat_mode(),
+            crossfade_ms: self.player.crossfade_ms(),
+        }
+    }
+
+    /// whether a track is loaded and playing, a paused node or a node whose track has ended is
+    /// idle
+    fn is_playing(&self) -> bool {
+        self.player.has_stream()
+            && self.current_processor_info.track_loaded
+            && self.current_processor_info.playback_state == PlaybackState::Playing
+    }
+
+    /// track that plays once the current one has ended
+    pub(super) fn up_next(&self) -> Option<&AudioPlayerQueueItem<PathBuf>> {
+        up_next_index(
+            self.player.queue_head(),
+            self.player.queue().len(),
+            self.auto_advance,
+            self.player.repeat_mode(),
+        )
+        .and_then(|index| self.player.queue().get(index))
+    }
+
+    /// informs clients about the track that follows the current one if it has changed, it is also
+    /// prepared to be crossfaded into
+    pub(crate) fn update_up_next(&mut self) {
+        self.player.prepare_crossfade(self.auto_advance);
+
+        let next = self
+            .up_next()
+            .map(|item| (Arc::clone(&item.identifier.0), item.metadata.clone()));
+
+        let uid = next.as_ref().map(|(uid, _)| Arc::clone(uid));
+        if self.last_up_next.as_ref() == Some(&uid) {
+            return;
+        }
+
+        self.last_up_next = Some(uid);
+        self.multicast(AudioNodeInfoStreamMessage::UpNext {
+            metadata: next.map(|(_, metadata)| metadata),
+        });
+    }
+
+    /// informs the brain if the node started or stopped playing
+    pub(crate) fn update_playback_activity(&mut self) {
+        let is_playing = self.is_playing();
+        if is_playing == self.playback_active {
+            return;
+        }
+
+        self.playback_active = is_playing;
+        self.server_addr
+            .do_send(AudioNodeToBrainMessage::NodePlaybackActivityUpdate((
+                self.source_name.clone(),
+                is_playing,
+            )));
+    }
+
+    /// state of the node that should survive a restart
+    pub(super) fn audio_state_info(&self) -> AudioStateInfo {
+        AudioStateInfo {
+            current_queue_index: self.player.queue_head(),
+            audio_volume: self.current_processor_info.audio_volume,
+            max_volume: self.player.max_volume(),
+            auto_advance: self.auto_advance,
+            resume_playback: self.resume_playback,
+            pause_on_device_loss: self.pause_on_device_loss,
+            queue_label: self.queue_label.clone(),
+            equalizer_bands: self.player.equalizer_bands().to_vec(),
+            fade_in_ms: self.player.fade_in_ms(),
+            normalization_enabled: self.player.normalization_enabled(),
+            repeat_mode: self.player.repeat_mode(),
+            crossfade_ms: self.player.crossfade_ms(),
+            audio_progress: self.current_processor_info.audio_progress,
+            playback_state: self.current_processor_info.playback_state.clone(),
+            restored_queue: vec![],
+            queue: self
+                .player
+                .queue()
+                .iter()
+                .map(|item| item.identifier.clone())
+                .collect(),
+        }
+    }
+
+    /// inserts `item` at `position` or appends it if there is no position
+    pub(super) fn add_to_queue(
+        &mut self,
+        item: AudioPlayerQueueItem<PathBuf>,
+        position: Option<usize>,
+        start_paused: bool,
+    ) -> anyhow::Result<()> {
+        match position {
+            Some(index) if !self.player.queue().is_empty() => {
+                self.player.insert_into_queue(index, item)
+            }
+            _ if start_paused => self.player.push_to_queue_paused(item),
+            _ => self.player.push_to_queue(item),
         }
     }
 
+    /// forgets the idempotency key of an item that failed to be added, so a retry isn't dropped
+    pub(super) fn forget_idempotency_key(&mut self, key: Option<&str>) {
+        if let Some(key) = key {
+            self.idempotency_keys.remove(key);
+        }
+    }
+
+    /// remembers the current item as played to its end and returns its uid
+    pub(super) fn finish_current_item(&mut self) -> Option<ItemUid<Arc<str>>> {
+        let uid = self.current_uid()?;
+        self.last_finished = Some(FinishedTrack {
+            uid: uid.clone(),
+            index: self.player.queue_head(),
+        });
+
+        Some(uid)
+    }
+
+    /// uid of the item at the head of the queue
+    pub(super) fn current_uid(&self) -> Option<ItemUid<Arc<str>>> {
+        self.player
+            .queue()
+            .get(self.player.queue_head())
+            .map(|item| item.identifier.clone())
+    }
+
     pub(super) fn multicast<M>(&self, msg: M)
     where
-        M: Message + Send + Clone + 'static,
-        M::Result: Send,
+        M: Message<Result = ()> + Send + Clone + 'static,
         AudioNodeSession: Handler<M>,
+        MultiNodeSession: Handler<TaggedNodeStreamMessage<M>>,
     {
-        for addr in self.sessions.values() {
-            addr.do_send(msg.clone());
+        for session in self.sessions.values() {
+            session.addr.do_send(&self.source_name, msg.clone());
         }
     }
 
     pub(super) fn multicast_result<MOk, MErr>(&self, msg: Result<MOk, MErr>)
     where
-        MOk: Message + Send + Clone + 'static,
-        MOk::Result: Send,
+        MOk: Message<Result = ()> + Send + Clone + 'static,
         AudioNodeSession: Handler<MOk>,
-        MErr: Message + Send + Clone + 'static,
-        MErr::Result: Send,
+        MultiNodeSession: Handler<TaggedNodeStreamMessage<MOk>>,
+        MErr: Message<Result = ()> + Send + Clone + 'static,
         AudioNodeSession: Handler<MErr>,
+        MultiNodeSession: Handler<TaggedNodeStreamMessage<MErr>>,
     {
         match msg {
-            Ok(msg) => {
-                for addr in self.sessions.values() {
-                    addr.do_send(msg.clone());
-                }
-            }
-            Err(msg) => {
-                for addr in self.sessions.values() {
-                    addr.do_send(msg.clone());
-                }
-            }
+            Ok(msg) => self.multicast(msg),
+            Err(msg) => self.multicast(msg),
         }
     }
 }
@@ -157,6 +410,201 @@ pub fn clean_url(url: &str) -> &str {
 
 pub fn extract_queue_metadata<ADL: AudioDataLocator>(
     queue: &[AudioPlayerQueueItem<ADL>],
+    queue_head: usize,
 ) -> SerializableQueue {
-    queue.iter().map(|item| item.metadata.clone()).collect()
+    SerializableQueue {
+        items: queue
+            .iter()
+            .map(|item| SerializableQueueItem {
+                uid: Arc::clone(&item.identifier.0),
+                metadata: item.metadata.clone(),
+            })
+            .collect(),
+        current_queue_index: queue_head,
+    }
+}
+
+/// Track that was played to its end and the queue index it was played at, the index tells apart
+/// tracks that are queued more than once
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct FinishedTrack {
+    pub uid: ItemUid<Arc<str>>,
+    pub index: usize,
+}
+
+/// index of `finished` in a queue with the items `uids`, `None` if it was removed
+///
+/// The occurrence closest to the index the track finished at is used, so the same entry is found
+/// if the track is queued more than once or other items were added or removed since.
+fn finished_track_index<'a>(
+    uids: impl Iterator<Item = &'a ItemUid<Arc<str>>>,
+    finished: &FinishedTrack,
+) -> Option<usize> {
+    uids.enumerate()
+        .filter(|(_, uid)| uid.0 == finished.uid.0)
+        .map(|(index, _)| index)
+        .min_by_key(|index| index.abs_diff(finished.index))
+}
+
+/// index of the track that plays after `head` once it has ended, depending on the repeat mode,
+/// nothing follows if the node doesn't advance on its own
+fn up_next_index(
+    head: usize,
+    len: usize,
+    auto_advance: bool,
+    repeat_mode: RepeatMode,
+) -> Option<usize> {
+    if !auto_advance {
+        return None;
+    }
+
+    next_head_after_finish(repeat_mode, head, len)
+}
+
+/// length of the current track, the decoded length of a track is known as soon as it is loaded,
+/// before the processor reports it for the first time
+fn current_duration_secs(
+    processor_duration_secs: Option<f64>,
+    track_info: Option<TrackInfo>,
+) -> Option<f64> {
+    processor_duration_secs.or_else(|| track_info.map(|track_info| track_info.duration_secs()))
+}
+
+/// trims the label of a queue, an empty label clears it
+pub fn validate_queue_label(label: Option<&str>) -> Result<Option<Arc<str>>, AppError> {
+    let label = label.map(str::trim).unwrap_or_default();
+
+    if label.chars().count() > MAX_QUEUE_LABEL_LEN {
+        return Err(AppError::new(
+            AppErrorKind::Queue,
+            format!(
+                "queue label is too long, at most {MAX_QUEUE_LABEL_LEN} characters are allowed"
+            ),
+            &[&format!("LABEL: {label}")],
+        ));
+    }
+
+    Ok((!label.is_empty()).then(|| label.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio_playback::audio_item::AudioMetadata;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_queue_items_keep_their_uid() {
+        let item = |uid: &str| AudioPlayerQueueItem {
+            metadata: AudioMetadata {
+                name: Some(String::from("Intro")).into(),
+                author: Option::<String>::None.into(),
+                duration: Some(60),
+                cover_art_url: Option::<String>::None.into(),
+                gain_db: None,
+            },
+            locator: PathBuf::from(uid),
+            identifier: ItemUid(Arc::from(uid)),
+            gain_db: 0.0,
+            fade_in_ms: None,
+        };
+
+        // same metadata, different tracks
+        let queue = extract_queue_metadata(&[item("youtube_audio_a"), item("youtube_audio_b")], 1);
+
+        let uids: Vec<_> = queue
+            .items
+            .iter()
+            .map(|item| item.uid.to_string())
+            .collect();
+        assert_eq!(uids, vec!["youtube_audio_a", "youtube_audio_b"]);
+        assert_eq!(queue.current_queue_index, 1);
+    }
+
+    #[test]
+    fn test_validate_queue_label() {
+        assert_eq!(
+            validate_queue_label(Some("  Sunday Jazz ")).unwrap(),
+            Some("Sunday Jazz".into())
+        );
+        assert_eq!(validate_queue_label(Some("   ")).unwrap(), None);
+        assert_eq!(validate_queue_label(None).unwrap(), None);
+
+        let long = "a".repeat(MAX_QUEUE_LABEL_LEN + 1);
+        assert!(validate_queue_label(Some(&long)).is_err());
+        assert!(validate_queue_label(Some(&long[1..])).is_ok());
+    }
+
+    #[test]
+    fn test_finished_track_index() {
+        let uids: Vec<_> = ["a", "b", "a", "c"]
+            .into_iter()
+            .map(|uid| ItemUid(Arc::from(uid)))
+            .collect();
+        let finished = |uid: &str, index| FinishedTrack {
+            uid: ItemUid(Arc::from(uid)),
+            index,
+        };
+
+        // the same track queued twice
+        assert_eq!(
+            finished_track_index(uids.iter(), &finished("a", 0)),
+            Some(0)
+        );
+        assert_eq!(
+            finished_track_index(uids.iter(), &finished("a", 2)),
+            Some(2)
+        );
+        assert_eq!(
+            finished_track_index(uids.iter(), &finished("c", 3)),
+            Some(3)
+        );
+
+        // an item before the finished track was removed
+        assert_eq!(
+            finished_track_index(uids.iter(), &finished("c", 4)),
+            Some(3)
+        );
+        assert_eq!(
+            finished_track_index(uids.iter(), &finished("a", 3)),
+            Some(2)
+        );
+
+        // the finished track was removed
+        assert_eq!(finished_track_index(uids.iter(), &finished("d", 1)), None);
+    }
+
+    #[test]
+    fn test_up_next_index() {
+        assert_eq!(up_next_index(0, 3, true, RepeatMode::All), Some(1));
+        assert_eq!(up_next_index(2, 3, true, RepeatMode::All), Some(0));
+        assert_eq!(up_next_index(0, 1, true, RepeatMode::All), Some(0));
+        assert_eq!(up_next_index(0, 0, true, RepeatMode::All), None);
+        assert_eq!(up_next_index(0, 3, false, RepeatMode::All), None);
+
+        assert_eq!(up_next_index(1, 3, true, RepeatMode::One), Some(1));
+        assert_eq!(up_next_index(2, 3, true, RepeatMode::Off), None);
+        assert_eq!(up_next_index(1, 3, false, RepeatMode::One), None);
+    }
+
+    #[test]
+    fn test_current_duration_secs() {
+        // 2.5 seconds, independent of the duration stored in the metadata
+        let track_info = TrackInfo {
+            num_frames: 44100 * 5 / 2,
+            sample_rate: 44100,
+        };
+
+        assert_eq!(current_duration_secs(None, Some(track_info)), Some(2.5));
+        // the processor reports the length of the track it is actually playing
+        assert_eq!(
+            current_duration_secs(Some(2.0), Some(track_info)),
+            Some(2.0)
+        );
+        assert_eq!(current_duration_secs(None, None), None);
+
+        // reported by the processor before the player knows the track, e.g. after a restore
+        assert_eq!(current_duration_secs(Some(3.0), None), Some(3.0));
+    }
 }
@@ -1,15 +1,45 @@
+use std::{collections::HashSet, path::PathBuf, sync::Arc, time::Instant};
+
+use serde::Serialize;
+use ts_rs::TS;
+
 use crate::{
-    audio_playback::audio_player::{PlaybackState, SerializableQueue},
-    commands::node_commands::{AudioNodeCommand, MoveQueueItemParams, RemoveQueueItemParams},
+    audio_playback::{
+        audio_item::{AudioMetadata, AudioPlayerQueueItem},
+        audio_player::{AudioInfo, OutputConfig, PlaybackState, SerializableQueue},
+        buffer_stats::BufferStats,
+    },
+    brain::brain_server::AudioNodeToBrainMessage,
+    commands::node_commands::{
+        AddBookmarkParams, AddQueueItemParams, AudioIdentifier, AudioNodeAdminCommand,
+        AudioNodeCommand, MoveQueueItemParams, RemoveQueueItemParams, SetItemFadeInParams,
+        SetItemGainParams, MAX_QUEUE_ITEMS_PER_BATCH,
+    },
+    downloader::{
+        download_identifier::{validate_audio_folder, Identifier, ItemUid},
+        local_file::{local_import_dir, validate_local_audio_file},
+    },
     error::{AppError, AppErrorKind, IntoAppError},
-    node::node_server::async_actor::AsyncAddQueueItem,
+    node::{
+        error_log::NodeError,
+        health::AudioNodeHealth,
+        idempotency::RecentIdempotencyKeys,
+        node_server::async_actor::{
+            AsyncAddBookmark, AsyncAddQueueItem, AsyncAddQueueItems, AsyncAnnounce,
+            AsyncRefreshItemGains, AsyncResumeCurrentItem, AsyncSeekBookmark, AsyncStoreItemGain,
+            AsyncStoreResumePosition,
+        },
+        processor_communication::AudioProcessorToNodeMessage,
+    },
+    state_storage::{restore_state_actor::AudioInfoStateUpdateMessage, AudioStateInfo},
     streams::node_streams::AudioNodeInfoStreamMessage,
     utils::log_msg_received,
 };
 
-use actix::{AsyncContext, Handler};
+use actix::{ActorContext, AsyncContext, Context, Handler, Message, MessageResult};
+use rand::{thread_rng, Rng};
 
-use super::{extract_queue_metadata, AudioNode};
+use super::{extract_queue_metadata, finished_track_index, validate_queue_label, AudioNode};
 
 impl Handler<AudioNodeCommand> for AudioNode {
     type Result = Result<(), AppError>;
@@ -17,11 +47,64 @@ impl Handler<AudioNodeCommand> for AudioNode {
     fn handle(&mut self, msg: AudioNodeCommand, ctx: &mut Self::Context) -> Self::Result {
         log_msg_received(&self, &msg);
 
+        msg.ensure_device_available(&self.health, &self.source_name)?;
+
+        let uid_before = self.current_uid().map(|uid| uid.0);
+
+        let result = self.handle_command(msg, ctx);
+        // commands can start or stop playback without the processor reporting it, e.g. when the
+        // last item is removed from the queue
+        self.update_playback_activity();
+        self.update_up_next();
+
+        // the duration of a new track is known once it is loaded, clients don't have to wait for
+        // the first report of the processor
+        if self.current_uid().map(|uid| uid.0) != uid_before
+            && self.player.current_track_info().is_some()
+        {
+            // the last report of the processor still describes the previous track
+            self.current_processor_info.duration_secs = None;
+            self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+                self.audio_info(),
+            ));
+        }
+
+        result
+    }
+}
+
+impl AudioNode {
+    fn handle_command(
+        &mut self,
+        msg: AudioNodeCommand,
+        ctx: &mut Context<AudioNode>,
+    ) -> Result<(), AppError> {
         match &msg {
             AudioNodeCommand::AddQueueItem(params) => {
                 log::info!("'AddQueueItem' handler received a message, MESSAGE: {msg:?}");
 
-                ctx.notify(AsyncAddQueueItem(params.clone()));
+                let params = validate_queue_item(params)?;
+                if is_repeated_queue_item(&mut self.idempotency_keys, &params, Instant::now()) {
+                    return Ok(());
+                }
+
+                ctx.notify(AsyncAddQueueItem(params));
+                Ok(())
+            }
+            AudioNodeCommand::AddQueueItems(items) => {
+                log::info!("'AddQueueItems' handler received a message, MESSAGE: {msg:?}");
+
+                let items: Vec<_> = validate_queue_items(items)?
+                    .into_iter()
+                    .filter(|params| {
+                        !is_repeated_queue_item(&mut self.idempotency_keys, params, Instant::now())
+                    })
+                    .collect();
+
+                if !items.is_empty() {
+                    ctx.notify(AsyncAddQueueItems(items));
+                }
+
                 Ok(())
             }
             AudioNodeCommand::RemoveQueueItem(params) => {
@@ -38,8 +121,10 @@ impl Handler<AudioNodeCommand> for AudioNode {
             AudioNodeCommand::MoveQueueItem(params) => {
                 log::info!("'MoveQueueItem' handler received a message, MESSAGE: {msg:?}");
 
-                let msg =
-                    AudioNodeInfoStreamMessage::Queue(handle_move_queue_item(self, params.clone()));
+                let msg = AudioNodeInfoStreamMessage::Queue(handle_move_queue_item(
+                    self,
+                    params.clone(),
+                )?);
 
                 self.multicast(msg);
 
@@ -48,11 +133,48 @@ impl Handler<AudioNodeCommand> for AudioNode {
             AudioNodeCommand::ShuffleQueue => {
                 log::info!("'ShuffleQueue ' handler received a message, MESSAGE: {msg:?}");
 
-                let msg = AudioNodeInfoStreamMessage::Queue(handle_shuffle_queue(self)?);
-                self.multicast(msg);
+                self.player.shuffle_queue();
+                self.multicast(AudioNodeInfoStreamMessage::Queue(extract_queue_metadata(
+                    self.player.queue(),
+                    self.player.queue_head(),
+                )));
 
                 Ok(())
             }
+            AudioNodeCommand::ShuffleQueueSmart => {
+                log::info!("'ShuffleQueueSmart' handler received a message, MESSAGE: {msg:?}");
+
+                self.player.smart_shuffle_queue(thread_rng().gen());
+                self.multicast(AudioNodeInfoStreamMessage::Queue(extract_queue_metadata(
+                    self.player.queue(),
+                    self.player.queue_head(),
+                )));
+
+                Ok(())
+            }
+            AudioNodeCommand::ClearQueue => {
+                log::info!("'ClearQueue' handler received a message, MESSAGE: {msg:?}");
+
+                self.player.clear_queue();
+                self.paused_by_device_loss = false;
+                self.sync_stopped_state();
+
+                self.multicast(AudioNodeInfoStreamMessage::Queue(extract_queue_metadata(
+                    self.player.queue(),
+                    self.player.queue_head(),
+                )));
+                self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+                    self.audio_info(),
+                ));
+
+                // a restart must not bring back the old queue
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+                Ok(())
+            }
             AudioNodeCommand::SetAudioVolume(params) => {
                 log::info!("'SetAudioVolume' handler received a message, MESSAGE: {msg:?}");
 
@@ -65,42 +187,109 @@ impl Handler<AudioNodeCommand> for AudioNode {
                 self.player.set_stream_progress(params.progress);
                 Ok(())
             }
+            AudioNodeCommand::SeekPercentDelta(params) => {
+                log::info!("'SeekPercentDelta' handler received a message, MESSAGE: {msg:?}");
+
+                if !self.player.seek_stream_progress_delta(params.delta) {
+                    log::warn!(
+                        "ignoring relative seek, the length of the current track is unknown, NODE_NAME: {name}",
+                        name = self.source_name
+                    );
+                }
+
+                Ok(())
+            }
+            AudioNodeCommand::SeekToSeconds(params) => {
+                log::info!("'SeekToSeconds' handler received a message, MESSAGE: {msg:?}");
+
+                self.player
+                    .set_stream_progress_secs(params.seconds)
+                    .into_app_err(
+                        "failed to seek",
+                        AppErrorKind::Queue,
+                        &[
+                            &format!("NODE_NAME: {name}", name = self.source_name),
+                            &format!("SECONDS: {seconds}", seconds = params.seconds),
+                        ],
+                    )?;
+
+                Ok(())
+            }
             AudioNodeCommand::PauseQueue => {
                 log::info!("'PauseQueue' handler received a message, MESSAGE: {msg:?}");
 
+                store_current_resume_position(self, ctx);
                 self.player.set_stream_playback_state(PlaybackState::Paused);
                 Ok(())
             }
             AudioNodeCommand::UnPauseQueue => {
                 log::info!("'UnPauseQueue' handler received a message, MESSAGE: {msg:?}");
 
+                if self.player.is_stopped() {
+                    self.player
+                        .play_selected(self.player.queue_head(), true)
+                        .into_app_err(
+                            "failed to restart stopped audio",
+                            AppErrorKind::Queue,
+                            &[&format!("NODE_NAME: {name}", name = self.source_name)],
+                        )?;
+                    return Ok(());
+                }
+
                 self.player
                     .set_stream_playback_state(PlaybackState::Playing);
                 Ok(())
             }
+            AudioNodeCommand::Stop => {
+                log::info!("'Stop' handler received a message, MESSAGE: {msg:?}");
+
+                self.player.stop();
+                self.paused_by_device_loss = false;
+                self.sync_stopped_state();
+
+                self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+                    self.audio_info(),
+                ));
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+                Ok(())
+            }
             AudioNodeCommand::PlayNext => {
                 log::info!("'PlayNext' handler received a message, MESSAGE: {msg:?}");
 
+                store_current_resume_position(self, ctx);
                 self.player.play_next().into_app_err(
                     "failed to play next audio",
                     AppErrorKind::Queue,
                     &[&format!("NODE_NAME: {name}", name = self.source_name)],
                 )?;
+                resume_current_item(self, ctx);
                 Ok(())
             }
             AudioNodeCommand::PlayPrevious => {
                 log::info!("'PlayPrevious' handler received a message, MESSAGE: {msg:?}");
 
+                store_current_resume_position(self, ctx);
                 self.player.play_prev().into_app_err(
                     "failed to play previous audio",
                     AppErrorKind::Queue,
                     &[&format!("NODE_NAME: {name}", name = self.source_name)],
                 )?;
+                resume_current_item(self, ctx);
                 Ok(())
             }
             AudioNodeCommand::PlaySelected(params) => {
                 log::info!("'PlaySelected' handler received a message, MESSAGE: {msg:?}");
 
+                // selecting the current item doesn't restart it
+                let changes_item = params.index != self.player.queue_head();
+                if changes_item {
+                    store_current_resume_position(self, ctx);
+                }
+
                 self.player
                     .play_selected(params.index, false)
                     .into_app_err(
@@ -111,12 +300,924 @@ impl Handler<AudioNodeCommand> for AudioNode {
                             &format!("INDEX: {index}", index = params.index),
                         ],
                     )?;
+
+                if changes_item {
+                    resume_current_item(self, ctx);
+                }
+                Ok(())
+            }
+            AudioNodeCommand::ReplayCurrent => {
+                log::info!("'ReplayCurrent' handler received a message, MESSAGE: {msg:?}");
+
+                // the processor drops the stream of a finished track, it has to be loaded again
+                if self.current_processor_info.track_loaded && !self.player.is_stopped() {
+                    self.player.set_stream_progress(0.0);
+                    self.player
+                        .set_stream_playback_state(PlaybackState::Playing);
+                } else {
+                    self.player
+                        .play_selected(self.player.queue_head(), true)
+                        .into_app_err(
+                            "failed to replay current audio",
+                            AppErrorKind::Queue,
+                            &[&format!("NODE_NAME: {name}", name = self.source_name)],
+                        )?;
+                }
+
+                // the processor only reports the new position with its next update
+                self.current_processor_info.audio_progress = 0.0;
+                self.current_processor_info.elapsed_secs = 0.0;
+                self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+                    self.audio_info(),
+                ));
+                Ok(())
+            }
+            AudioNodeCommand::ReplayPrevious => {
+                log::info!("'ReplayPrevious' handler received a message, MESSAGE: {msg:?}");
+
+                let index = self.last_finished.as_ref().and_then(|finished| {
+                    finished_track_index(
+                        self.player.queue().iter().map(|item| &item.identifier),
+                        finished,
+                    )
+                });
+
+                let Some(index) = index else {
+                    return Err(AppError::new(
+                        AppErrorKind::Queue,
+                        "no finished track to replay, it hasn't been played or was removed from the queue",
+                        &[&format!("NODE_NAME: {name}", name = self.source_name)],
+                    ).not_found());
+                };
+
+                if index != self.player.queue_head() {
+                    store_current_resume_position(self, ctx);
+                }
+
+                self.player.play_selected(index, true).into_app_err(
+                    "failed to replay previous audio",
+                    AppErrorKind::Queue,
+                    &[
+                        &format!("NODE_NAME: {name}", name = self.source_name),
+                        &format!("INDEX: {index}"),
+                    ],
+                )?;
+
+                self.current_processor_info.audio_progress = 0.0;
+                self.current_processor_info.elapsed_secs = 0.0;
+                self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+                    self.audio_info(),
+                ));
+                Ok(())
+            }
+            AudioNodeCommand::AddBookmark(params) => {
+                log::info!("'AddBookmark' handler received a message, MESSAGE: {msg:?}");
+
+                let bookmark = handle_add_bookmark(self, params.clone())?;
+                ctx.notify(bookmark);
+
+                Ok(())
+            }
+            AudioNodeCommand::SeekBookmark(params) => {
+                log::info!("'SeekBookmark' handler received a message, MESSAGE: {msg:?}");
+
+                ctx.notify(AsyncSeekBookmark(params.clone()));
+                Ok(())
+            }
+            AudioNodeCommand::SetItemGain(params) => {
+                log::info!("'SetItemGain' handler received a message, MESSAGE: {msg:?}");
+
+                let store_gain = handle_set_item_gain(self, params.clone())?;
+                ctx.notify(store_gain);
+
+                Ok(())
+            }
+            AudioNodeCommand::ResetBufferStats => {
+                log::info!("'ResetBufferStats' handler received a message, MESSAGE: {msg:?}");
+
+                self.player.reset_buffer_stats();
+                Ok(())
+            }
+            AudioNodeCommand::ReconnectDevice => {
+                log::info!("'ReconnectDevice' handler received a message, MESSAGE: {msg:?}");
+
+                self.recovery_attempts.reset();
+
+                self.player
+                    .try_recover_device(self.current_processor_info.audio_progress)
+                    .into_app_err(
+                        "failed to reconnect audio device",
+                        AppErrorKind::Queue,
+                        &[&format!("NODE_NAME: {name}", name = self.source_name)],
+                    )?;
+
+                ctx.notify(AudioProcessorToNodeMessage::Health(AudioNodeHealth::Good));
+                Ok(())
+            }
+            AudioNodeCommand::SetAutoAdvance(params) => {
+                log::info!("'SetAutoAdvance' handler received a message, MESSAGE: {msg:?}");
+
+                self.auto_advance = params.auto_advance;
+
+                self.server_addr
+                    .do_send(AudioNodeToBrainMessage::NodeAutoAdvanceUpdate((
+                        self.source_name.clone(),
+                        self.auto_advance,
+                    )));
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+
+                Ok(())
+            }
+            AudioNodeCommand::PlayTestTone(params) => {
+                log::info!("'PlayTestTone' handler received a message, MESSAGE: {msg:?}");
+
+                self.player
+                    .play_test_tone(params.channel, params.seconds)
+                    .into_app_err(
+                        "failed to play test tone",
+                        AppErrorKind::Queue,
+                        &[&format!("NODE_NAME: {name}", name = self.source_name)],
+                    )
+            }
+            AudioNodeCommand::SetResumePlayback(params) => {
+                log::info!("'SetResumePlayback' handler received a message, MESSAGE: {msg:?}");
+
+                self.resume_playback = params.resume_playback;
+
+                self.server_addr
+                    .do_send(AudioNodeToBrainMessage::NodeResumePlaybackUpdate((
+                        self.source_name.clone(),
+                        self.resume_playback,
+                    )));
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+
+                Ok(())
+            }
+            AudioNodeCommand::SetEqualizer(params) => {
+                log::info!("'SetEqualizer' handler received a message, MESSAGE: {msg:?}");
+
+                let equalizer_bands = self.player.set_equalizer(&params.bands).to_vec();
+
+                self.server_addr
+                    .do_send(AudioNodeToBrainMessage::NodeEqualizerUpdate((
+                        self.source_name.clone(),
+                        equalizer_bands,
+                    )));
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+
+                Ok(())
+            }
+            AudioNodeCommand::SetPauseOnDeviceLoss(params) => {
+                log::info!("'SetPauseOnDeviceLoss' handler received a message, MESSAGE: {msg:?}");
+
+                self.pause_on_device_loss = params.pause_on_device_loss;
+
+                self.server_addr
+                    .do_send(AudioNodeToBrainMessage::NodePauseOnDeviceLossUpdate((
+                        self.source_name.clone(),
+                        self.pause_on_device_loss,
+                    )));
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+
+                Ok(())
+            }
+            AudioNodeCommand::SetQueueLabel(params) => {
+                log::info!("'SetQueueLabel' handler received a message, MESSAGE: {msg:?}");
+
+                self.queue_label = validate_queue_label(params.label.as_deref())?;
+
+                self.server_addr
+                    .do_send(AudioNodeToBrainMessage::NodeQueueLabelUpdate((
+                        self.source_name.clone(),
+                        self.queue_label.clone(),
+                    )));
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+
+                Ok(())
+            }
+            AudioNodeCommand::SetFadeIn(params) => {
+                log::info!("'SetFadeIn' handler received a message, MESSAGE: {msg:?}");
+
+                let fade_in_ms = self.player.set_fade_in_ms(params.fade_in_ms);
+
+                self.server_addr
+                    .do_send(AudioNodeToBrainMessage::NodeFadeInUpdate((
+                        self.source_name.clone(),
+                        fade_in_ms,
+                    )));
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+
+                Ok(())
+            }
+            AudioNodeCommand::SetNormalizationEnabled(params) => {
+                log::info!(
+                    "'SetNormalizationEnabled' handler received a message, MESSAGE: {msg:?}"
+                );
+
+                self.player
+                    .set_normalization_enabled(params.normalization_enabled);
+
+                self.server_addr
+                    .do_send(AudioNodeToBrainMessage::NodeNormalizationUpdate((
+                        self.source_name.clone(),
+                        params.normalization_enabled,
+                    )));
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+
+                Ok(())
+            }
+            AudioNodeCommand::SetItemFadeIn(params) => {
+                log::info!("'SetItemFadeIn' handler received a message, MESSAGE: {msg:?}");
+
+                let SetItemFadeInParams { index, fade_in_ms } = params.clone();
+
+                self.player
+                    .set_item_fade_in_ms(index, fade_in_ms)
+                    .into_app_err(
+                        "failed to set fade in of queue item",
+                        AppErrorKind::Queue,
+                        &[
+                            &format!("NODE_NAME: {name}", name = self.source_name),
+                            &format!("INDEX: {index}"),
+                        ],
+                    )?;
+
+                Ok(())
+            }
+            AudioNodeCommand::SetRepeatMode(params) => {
+                log::info!("'SetRepeatMode' handler received a message, MESSAGE: {msg:?}");
+
+                self.player.set_repeat_mode(params.repeat_mode);
+
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+                self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+                    self.audio_info(),
+                ));
+
+                Ok(())
+            }
+            AudioNodeCommand::SetCrossfade(params) => {
+                log::info!("'SetCrossfade' handler received a message, MESSAGE: {msg:?}");
+
+                self.player.set_crossfade_ms(params.crossfade_ms);
+
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+                self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+                    self.audio_info(),
+                ));
+
+                Ok(())
+            }
+            AudioNodeCommand::Announce(params) => {
+                log::info!("'Announce' handler received a message, MESSAGE: {msg:?}");
+
+                ctx.notify(AsyncAnnounce(params.clone()));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// whether an item with the same idempotency key was added recently or is still being added, the
+/// key is remembered otherwise and has to be forgotten if adding the item fails
+fn is_repeated_queue_item(
+    keys: &mut RecentIdempotencyKeys,
+    params: &AddQueueItemParams,
+    now: Instant,
+) -> bool {
+    let Some(key) = params.idempotency_key.as_ref() else {
+        return false;
+    };
+
+    if keys.insert(Arc::clone(key), now) {
+        return false;
+    }
+
+    log::info!("queue item with this idempotency key was already added, KEY: {key}");
+    true
+}
+
+/// one invalid item rejects the whole batch, nothing is added
+fn validate_queue_items(items: &[AddQueueItemParams]) -> Result<Vec<AddQueueItemParams>, AppError> {
+    if items.len() > MAX_QUEUE_ITEMS_PER_BATCH {
+        return Err(AppError::new(
+            AppErrorKind::Queue,
+            "too many items in a batch, split them into several batches",
+            &[
+                &format!("ITEMS: {len}", len = items.len()),
+                &format!("MAX_ITEMS: {MAX_QUEUE_ITEMS_PER_BATCH}"),
+            ],
+        )
+        .bad_request());
+    }
+
+    items
+        .iter()
+        .map(|params| {
+            if params.identifier.is_playlist() {
+                return Err(AppError::new(
+                    AppErrorKind::Queue,
+                    "playlists can't be added in a batch, add them one by one instead",
+                    &[&format!("IDENTIFIER: {:?}", params.identifier)],
+                )
+                .bad_request());
+            }
+
+            validate_queue_item(params)
+        })
+        .collect()
+}
+
+fn validate_queue_item(params: &AddQueueItemParams) -> Result<AddQueueItemParams, AppError> {
+    let mut params = params.clone();
+    params.folder = params
+        .folder
+        .as_deref()
+        .map(validate_audio_folder)
+        .transpose()?;
+
+    // rejected right away, the file is only imported once the command was answered
+    if let AudioIdentifier::LocalFile { path } = &params.identifier {
+        validate_local_audio_file(path, local_import_dir().as_deref())?;
+    }
+
+    Ok(params)
+}
+
+/// remembers where the current track was left off, only if the node resumes playback
+fn store_current_resume_position(node: &AudioNode, ctx: &mut Context<AudioNode>) {
+    if !node.resume_playback {
+        return;
+    }
+
+    let (Some(uid), Some(track_info)) = (node.current_uid(), node.player.current_track_info())
+    else {
+        return;
+    };
+
+    ctx.notify(AsyncStoreResumePosition {
+        uid,
+        position_secs: track_info.progress_to_secs(node.current_processor_info.audio_progress),
+    });
+}
+
+pub(crate) fn resume_current_item(node: &AudioNode, ctx: &mut Context<AudioNode>) {
+    if node.resume_playback {
+        ctx.notify(AsyncResumeCurrentItem);
+    }
+}
+
+/// Gets the items of the queue the node is currently playing from together with the queue head
+/// and label, this is independent of the stored audio library
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "(Arc<[(ItemUid<Arc<str>>, AudioMetadata)]>, usize, Option<Arc<str>>)")]
+pub struct GetQueue;
+
+impl Handler<GetQueue> for AudioNode {
+    type Result = MessageResult<GetQueue>;
+
+    fn handle(&mut self, msg: GetQueue, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        MessageResult((
+            self.player
+                .queue()
+                .iter()
+                .map(|item| (item.identifier.clone(), item.metadata.clone()))
+                .collect(),
+            self.player.queue_head(),
+            self.queue_label.clone(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "BufferStats")]
+pub struct GetBufferStats;
+
+impl Handler<GetBufferStats> for AudioNode {
+    type Result = MessageResult<GetBufferStats>;
+
+    fn handle(&mut self, msg: GetBufferStats, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        MessageResult(self.player.buffer_stats())
+    }
+}
+
+/// Recent playback errors of the node, newest first
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Vec<NodeError>")]
+pub struct GetRecentErrors;
+
+impl Handler<GetRecentErrors> for AudioNode {
+    type Result = MessageResult<GetRecentErrors>;
+
+    fn handle(&mut self, msg: GetRecentErrors, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        MessageResult(self.recent_errors.list())
+    }
+}
+
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "OutputConfig")]
+pub struct GetOutputConfig;
+
+impl Handler<GetOutputConfig> for AudioNode {
+    type Result = MessageResult<GetOutputConfig>;
+
+    fn handle(&mut self, msg: GetOutputConfig, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        MessageResult(self.player.output_config())
+    }
+}
+
+/// Stops the node once it has been removed from the brain, dropping its player releases the audio
+/// device
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct StopAudioNode;
+
+impl Handler<StopAudioNode> for AudioNode {
+    type Result = ();
+
+    fn handle(&mut self, msg: StopAudioNode, ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.player.stop();
+        self.multicast(AppError::new(
+            AppErrorKind::Api,
+            "audio node was removed",
+            &[&format!("NODE_NAME: {name}", name = self.source_name)],
+        ));
+
+        ctx.stop();
+    }
+}
+
+/// Latest playback state of the node, also available while paused when the processor doesn't report
+/// any updates
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "AudioInfo")]
+pub struct GetProcessorInfo;
+
+impl Handler<GetProcessorInfo> for AudioNode {
+    type Result = MessageResult<GetProcessorInfo>;
+
+    fn handle(&mut self, msg: GetProcessorInfo, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        MessageResult(self.audio_info())
+    }
+}
+
+/// Appends already stored tracks to the end of the queue, tracks whose audio file is missing are
+/// skipped
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Result<EnqueuedItems, AppError>")]
+pub struct EnqueueStoredItems(pub Arc<[(ItemUid<Arc<str>>, AudioMetadata)]>);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct EnqueuedItems {
+    pub added: usize,
+    /// uids of the tracks that were skipped because their audio file is missing
+    pub skipped: Vec<Arc<str>>,
+}
+
+impl Handler<EnqueueStoredItems> for AudioNode {
+    type Result = Result<EnqueuedItems, AppError>;
+
+    fn handle(&mut self, msg: EnqueueStoredItems, ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let mut enqueued = EnqueuedItems::default();
+
+        for (uid, metadata) in msg.0.iter().cloned() {
+            let locator = uid.to_path_with_ext();
+            if !locator.is_file() {
+                enqueued.skipped.push(uid.0);
+                continue;
+            }
+
+            self.player
+                .push_to_queue(AudioPlayerQueueItem {
+                    metadata,
+                    locator,
+                    identifier: uid,
+                    gain_db: 0.0,
+                    fade_in_ms: None,
+                })
+                .into_app_err(
+                    "failed to add stored audio to queue",
+                    AppErrorKind::Queue,
+                    &[&format!("NODE_NAME: {name}", name = self.source_name)],
+                )?;
+
+            enqueued.added += 1;
+        }
+
+        if enqueued.added > 0 {
+            self.multicast(AudioNodeInfoStreamMessage::Queue(extract_queue_metadata(
+                self.player.queue(),
+                self.player.queue_head(),
+            )));
+            self.update_up_next();
+
+            ctx.notify(AsyncRefreshItemGains);
+        }
+
+        Ok(enqueued)
+    }
+}
+
+/// Replaces the queue, playback state and settings of the node with imported state, as if the
+/// node had been started with it
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct RestoreNodeState(pub AudioStateInfo);
+
+impl Handler<RestoreNodeState> for AudioNode {
+    type Result = ();
+
+    fn handle(&mut self, msg: RestoreNodeState, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let AudioStateInfo {
+            playback_state,
+            current_queue_index,
+            audio_progress,
+            audio_volume,
+            max_volume,
+            auto_advance,
+            resume_playback,
+            pause_on_device_loss,
+            queue_label,
+            equalizer_bands,
+            fade_in_ms,
+            normalization_enabled,
+            repeat_mode,
+            crossfade_ms,
+            restored_queue,
+            ..
+        } = msg.0;
+
+        self.player.set_max_volume(max_volume);
+        let equalizer_bands = self.player.set_equalizer(&equalizer_bands).to_vec();
+        let fade_in_ms = self.player.set_fade_in_ms(fade_in_ms);
+        self.player.set_normalization_enabled(normalization_enabled);
+        self.player.restore(
+            restored_queue,
+            AudioInfo {
+                playback_state,
+                current_queue_index,
+                audio_progress,
+                audio_volume,
+                duration_secs: None,
+                elapsed_secs: None,
+                repeat_mode,
+                crossfade_ms,
+            },
+        );
+        self.auto_advance = auto_advance;
+        self.resume_playback = resume_playback;
+        self.pause_on_device_loss = pause_on_device_loss;
+        self.queue_label = queue_label.clone();
+        self.sync_stopped_state();
+
+        let source_name = self.source_name.clone();
+        self.server_addr
+            .do_send(AudioNodeToBrainMessage::NodeMaxVolumeUpdate((
+                source_name.clone(),
+                self.player.max_volume(),
+            )));
+        self.server_addr
+            .do_send(AudioNodeToBrainMessage::NodeAutoAdvanceUpdate((
+                source_name.clone(),
+                auto_advance,
+            )));
+        self.server_addr
+            .do_send(AudioNodeToBrainMessage::NodeResumePlaybackUpdate((
+                source_name.clone(),
+                resume_playback,
+            )));
+        self.server_addr
+            .do_send(AudioNodeToBrainMessage::NodePauseOnDeviceLossUpdate((
+                source_name.clone(),
+                pause_on_device_loss,
+            )));
+        self.server_addr
+            .do_send(AudioNodeToBrainMessage::NodeQueueLabelUpdate((
+                source_name.clone(),
+                queue_label,
+            )));
+        self.server_addr
+            .do_send(AudioNodeToBrainMessage::NodeEqualizerUpdate((
+                source_name.clone(),
+                equalizer_bands,
+            )));
+        self.server_addr
+            .do_send(AudioNodeToBrainMessage::NodeFadeInUpdate((
+                source_name.clone(),
+                fade_in_ms,
+            )));
+        self.server_addr
+            .do_send(AudioNodeToBrainMessage::NodeNormalizationUpdate((
+                source_name.clone(),
+                normalization_enabled,
+            )));
+
+        self.multicast(AudioNodeInfoStreamMessage::Queue(extract_queue_metadata(
+            self.player.queue(),
+            self.player.queue_head(),
+        )));
+        self.restore_state_addr
+            .do_send(AudioInfoStateUpdateMessage((
+                source_name,
+                self.audio_state_info(),
+            )));
+        self.update_playback_activity();
+        self.update_up_next();
+    }
+}
+
+/// Sent by the player whenever the index of the current item has changed, clients are informed
+/// right away instead of with the next report of the processor
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct QueueHeadChanged(pub usize);
+
+impl Handler<QueueHeadChanged> for AudioNode {
+    type Result = ();
+
+    fn handle(&mut self, msg: QueueHeadChanged, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.multicast(AudioNodeInfoStreamMessage::QueueHead(msg.0));
+    }
+}
+
+/// Gets the queue items of the node together with its playback state, used to copy the queue to
+/// another node
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "(Vec<AudioPlayerQueueItem<PathBuf>>, AudioInfo)")]
+pub struct GetQueueItems;
+
+impl Handler<GetQueueItems> for AudioNode {
+    type Result = MessageResult<GetQueueItems>;
+
+    fn handle(&mut self, msg: GetQueueItems, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        MessageResult((self.player.queue().to_vec(), self.audio_info()))
+    }
+}
+
+/// Uid of the item whose stream is currently loaded, playing or paused
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Option<Arc<str>>")]
+pub struct GetLoadedUid;
+
+impl Handler<GetLoadedUid> for AudioNode {
+    type Result = Option<Arc<str>>;
+
+    fn handle(&mut self, msg: GetLoadedUid, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        if !self.player.has_stream() {
+            return None;
+        }
+
+        self.current_uid().map(|uid| uid.0)
+    }
+}
+
+/// Removes every queue item with one of the uids, used when their stored audio is deleted
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "Result<(), AppError>")]
+pub struct RemoveStoredItems(pub HashSet<Arc<str>>);
+
+impl Handler<RemoveStoredItems> for AudioNode {
+    type Result = Result<(), AppError>;
+
+    fn handle(&mut self, msg: RemoveStoredItems, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let indices: Vec<usize> = self
+            .player
+            .queue()
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| msg.0.contains(&item.identifier.0))
+            .map(|(index, _)| index)
+            .collect();
+
+        if indices.is_empty() {
+            return Ok(());
+        }
+
+        // from the back so the remaining indices stay valid
+        let result = indices.into_iter().rev().try_for_each(|index| {
+            self.player.remove_from_queue(index).into_app_err(
+                "failed to play correct audio after removing item",
+                AppErrorKind::Queue,
+                &[
+                    &format!("NODE_NAME: {name}", name = self.source_name),
+                    &format!("INDEX: {index}"),
+                ],
+            )
+        });
+
+        self.multicast(AudioNodeInfoStreamMessage::Queue(extract_queue_metadata(
+            self.player.queue(),
+            self.player.queue_head(),
+        )));
+        self.update_playback_activity();
+        self.update_up_next();
+
+        result
+    }
+}
+
+/// Replaces the queue of the node, the node keeps its volume and settings
+///
+/// Playback starts at the beginning of the queue if no position is given.
+#[derive(Debug, Clone, Message)]
+#[rtype(result = "()")]
+pub struct ReplaceQueue {
+    pub queue: Vec<AudioPlayerQueueItem<PathBuf>>,
+    pub position: Option<QueuePosition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuePosition {
+    pub current_queue_index: usize,
+    pub audio_progress: f64,
+    pub playback_state: PlaybackState,
+}
+
+impl Handler<ReplaceQueue> for AudioNode {
+    type Result = ();
+
+    fn handle(&mut self, msg: ReplaceQueue, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        let ReplaceQueue { queue, position } = msg;
+        let QueuePosition {
+            current_queue_index,
+            audio_progress,
+            playback_state,
+        } = position.unwrap_or(QueuePosition {
+            current_queue_index: 0,
+            audio_progress: 0.0,
+            playback_state: PlaybackState::Playing,
+        });
+
+        self.player.restore(
+            queue,
+            AudioInfo {
+                playback_state: playback_state.clone(),
+                current_queue_index,
+                audio_progress,
+                audio_volume: self.current_processor_info.audio_volume,
+                duration_secs: None,
+                elapsed_secs: None,
+                repeat_mode: self.player.repeat_mode(),
+                crossfade_ms: self.player.crossfade_ms(),
+            },
+        );
+        self.current_processor_info.playback_state = playback_state;
+        self.current_processor_info.audio_progress = audio_progress;
+        self.sync_stopped_state();
+
+        self.multicast(AudioNodeInfoStreamMessage::Queue(extract_queue_metadata(
+            self.player.queue(),
+            self.player.queue_head(),
+        )));
+        self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+            self.audio_info(),
+        ));
+        self.restore_state_addr
+            .do_send(AudioInfoStateUpdateMessage((
+                self.source_name.clone(),
+                self.audio_state_info(),
+            )));
+        self.update_playback_activity();
+        self.update_up_next();
+    }
+}
+
+impl Handler<AudioNodeAdminCommand> for AudioNode {
+    type Result = Result<(), AppError>;
+
+    fn handle(&mut self, msg: AudioNodeAdminCommand, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        match &msg {
+            AudioNodeAdminCommand::SetMaxVolume(params) => {
+                log::info!("'SetMaxVolume' handler received a message, MESSAGE: {msg:?}");
+
+                self.player.set_max_volume(params.max_volume);
+
+                self.server_addr
+                    .do_send(AudioNodeToBrainMessage::NodeMaxVolumeUpdate((
+                        self.source_name.clone(),
+                        self.player.max_volume(),
+                    )));
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+
                 Ok(())
             }
         }
     }
 }
 
+fn handle_set_item_gain(
+    node: &mut AudioNode,
+    params: SetItemGainParams,
+) -> Result<AsyncStoreItemGain, AppError> {
+    let SetItemGainParams { index, gain_db } = params;
+
+    let gain_db = node.player.set_item_gain(index, gain_db).into_app_err(
+        "failed to set gain of queue item",
+        AppErrorKind::Queue,
+        &[
+            &format!("NODE_NAME: {name}", name = node.source_name),
+            &format!("INDEX: {index}"),
+        ],
+    )?;
+
+    // index was checked by the player
+    let uid = node.player.queue()[index].identifier.clone();
+
+    Ok(AsyncStoreItemGain { uid, gain_db })
+}
+
+fn handle_add_bookmark(
+    node: &AudioNode,
+    params: AddBookmarkParams,
+) -> Result<AsyncAddBookmark, AppError> {
+    let (Some(item), Some(track_info)) = (
+        node.player.queue().get(node.player.queue_head()),
+        node.player.current_track_info(),
+    ) else {
+        return Err(AppError::new(
+            AppErrorKind::Queue,
+            "failed to add bookmark, nothing is playing",
+            &[&format!("NODE_NAME: {name}", name = node.source_name)],
+        ));
+    };
+
+    Ok(AsyncAddBookmark {
+        uid: item.identifier.clone(),
+        label: params.label,
+        position_secs: track_info.progress_to_secs(node.current_processor_info.audio_progress),
+    })
+}
+
 fn handle_remove_queue_item(
     node: &mut AudioNode,
     params: RemoveQueueItemParams,
@@ -131,24 +1232,130 @@ fn handle_remove_queue_item(
         ));
     }
 
-    Ok(extract_queue_metadata(node.player.queue()))
+    Ok(extract_queue_metadata(
+        node.player.queue(),
+        node.player.queue_head(),
+    ))
 }
 
-fn handle_move_queue_item(node: &mut AudioNode, params: MoveQueueItemParams) -> SerializableQueue {
+fn handle_move_queue_item(
+    node: &mut AudioNode,
+    params: MoveQueueItemParams,
+) -> Result<SerializableQueue, AppError> {
     let MoveQueueItemParams { old_pos, new_pos } = params;
-    node.player.move_queue_item(old_pos, new_pos);
 
-    extract_queue_metadata(node.player.queue())
+    if node.player.move_queue_item(old_pos, new_pos).is_none() {
+        return Err(AppError::new(
+            AppErrorKind::Queue,
+            "position out of bounds, can not move queue item",
+            &[
+                &format!("NODE_NAME: {name}", name = node.source_name),
+                &format!("OLD_POS: {old_pos}"),
+                &format!("NEW_POS: {new_pos}"),
+                &format!("QUEUE_LEN: {len}", len = node.player.queue().len()),
+            ],
+        )
+        .bad_request());
+    }
+
+    Ok(extract_queue_metadata(
+        node.player.queue(),
+        node.player.queue_head(),
+    ))
 }
 
-fn handle_shuffle_queue(node: &mut AudioNode) -> Result<SerializableQueue, AppError> {
-    if let Err(err) = node.player.shuffle_queue() {
-        return Err(err.into_app_err(
-            "failed to play audio after shuffeling queue",
-            AppErrorKind::Queue,
-            &[&format!("NODE_NAME: {name}", name = node.source_name)],
-        ));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_utils::test_node;
+    use actix_web::{http::StatusCode, ResponseError};
+    use pretty_assertions::assert_eq;
+
+    fn params_with_key(key: Option<&str>) -> AddQueueItemParams {
+        AddQueueItemParams {
+            identifier: AudioIdentifier::Local { uid: "uid".into() },
+            start_paused: false,
+            folder: None,
+            idempotency_key: key.map(Arc::from),
+            position: None,
+        }
+    }
+
+    #[test]
+    fn test_retry_after_failed_add_is_not_dropped() {
+        let mut keys = RecentIdempotencyKeys::default();
+        let params = params_with_key(Some("retry"));
+        let now = Instant::now();
+
+        assert!(!is_repeated_queue_item(&mut keys, &params, now));
+        // retried while the first request is still being added
+        assert!(is_repeated_queue_item(&mut keys, &params, now));
+
+        // adding the item failed, e.g. the download info couldn't be fetched
+        keys.remove("retry");
+        assert!(!is_repeated_queue_item(&mut keys, &params, now));
+        assert!(is_repeated_queue_item(&mut keys, &params, now));
     }
 
-    Ok(extract_queue_metadata(node.player.queue()))
+    #[test]
+    fn test_batch_size_is_limited() {
+        let items = vec![params_with_key(None); MAX_QUEUE_ITEMS_PER_BATCH];
+        assert_eq!(validate_queue_items(&items).unwrap().len(), items.len());
+
+        let items = vec![params_with_key(None); MAX_QUEUE_ITEMS_PER_BATCH + 1];
+        let err = validate_queue_items(&items).unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_items_without_key_are_never_repeated() {
+        let mut keys = RecentIdempotencyKeys::default();
+        let params = params_with_key(None);
+        let now = Instant::now();
+
+        let added = (0..3)
+            .filter(|_| !is_repeated_queue_item(&mut keys, &params, now))
+            .count();
+        assert_eq!(added, 3);
+    }
+
+    #[actix_web::test]
+    async fn test_move_queue_item_out_of_bounds() {
+        let mut node = test_node("test-move-queue-item");
+        for uid in ["a", "b"] {
+            node.player
+                .push_to_queue(AudioPlayerQueueItem {
+                    identifier: ItemUid(uid.into()),
+                    metadata: AudioMetadata {
+                        name: Some(uid.to_owned()).into(),
+                        author: Option::<String>::None.into(),
+                        duration: Some(60),
+                        cover_art_url: Option::<String>::None.into(),
+                        gain_db: None,
+                    },
+                    locator: PathBuf::from(uid),
+                    gain_db: 0.0,
+                    fade_in_ms: None,
+                })
+                .unwrap();
+        }
+
+        let moved = |node: &mut AudioNode, old_pos, new_pos| {
+            handle_move_queue_item(node, MoveQueueItemParams { old_pos, new_pos })
+        };
+
+        let err = moved(&mut node, 0, 2).unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        let err = moved(&mut node, 2, 0).unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+
+        let queue = moved(&mut node, 0, 1).unwrap();
+        let uids: Vec<_> = queue
+            .items
+            .iter()
+            .map(|item| item.uid.to_string())
+            .collect();
+        assert_eq!(uids, vec!["b", "a"]);
+        assert_eq!(node.player.queue_head(), 1);
+    }
 }
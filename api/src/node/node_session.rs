@@ -1,8 +1,11 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use actix::{
     Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, ContextFutureSpawner, Handler,
-    ResponseActFuture, Running, StreamHandler, WrapFuture,
+    ResponseActFuture, Running, SpawnHandle, StreamHandler, WrapFuture,
 };
 
 use actix_web_actors::ws;
@@ -11,15 +14,18 @@ use serde::Serialize;
 use ts_rs::TS;
 
 use crate::{
-    audio_playback::{audio_item::AudioMetadata, audio_player::AudioInfo},
+    audio_playback::{
+        audio_item::AudioMetadata,
+        audio_player::{AudioInfo, SerializableQueue},
+    },
     error::AppError,
-    node::node_server::connections::{NodeConnectMessage, NodeDisconnectMessage},
+    node::node_server::connections::{NodeConnectMessage, NodeDisconnectMessage, NodeSessionAddr},
     streams::{
         node_streams::{
-            get_type_of_stream_data, AudioNodeInfoStreamMessage, AudioNodeInfoStreamType,
-            RunningDownloadInfo,
+            get_type_of_stream_data, is_batchable, AudioNodeInfoStreamMessage,
+            AudioNodeInfoStreamType, RunningDownloadInfo,
         },
-        HeartBeat,
+        ClientActivity, HeartBeat, StreamBatch, CLIENT_TIMEOUT_CHECK_INTERVAL,
     },
 };
 
@@ -29,6 +35,11 @@ pub struct AudioNodeSession {
     id: usize,
     node_addr: Addr<AudioNode>,
     wanted_info: Arc<[AudioNodeInfoStreamType]>,
+    heartbeat_interval: Duration,
+    batch_window: Option<Duration>,
+    batch: StreamBatch,
+    batch_flush: Option<SpawnHandle>,
+    activity: ClientActivity,
 }
 
 #[derive(Debug, Clone, Serialize, TS)]
@@ -37,22 +48,42 @@ pub struct AudioNodeSession {
 pub enum NodeSessionWsResponse {
     #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
     SessionConnectedResponse {
-        // can't use SerializableQueue due to issue discussed
-        // here: https://github.com/Aleph-Alpha/ts-rs/issues/70
-        #[ts(type = "Array<AudioMetadata>")]
-        queue: Option<Arc<[AudioMetadata]>>,
+        queue: Option<SerializableQueue>,
         health: Option<AudioNodeHealth>,
         downloads: Option<RunningDownloadInfo>,
         audio_state_info: Option<AudioInfo>,
+        /// only set if the queue is wanted and a track follows the current one
+        up_next: Option<AudioMetadata>,
     },
 }
 
 impl AudioNodeSession {
-    pub fn new(node_addr: Addr<AudioNode>, wanted_info: Arc<[AudioNodeInfoStreamType]>) -> Self {
+    pub fn new(
+        node_addr: Addr<AudioNode>,
+        wanted_info: Arc<[AudioNodeInfoStreamType]>,
+        heartbeat_interval: Duration,
+        batch_window: Option<Duration>,
+    ) -> Self {
         Self {
             id: usize::MAX,
             node_addr,
             wanted_info,
+            heartbeat_interval,
+            batch_window,
+            batch: StreamBatch::default(),
+            batch_flush: None,
+            activity: ClientActivity::new(heartbeat_interval),
+        }
+    }
+
+    /// sends all batched messages as a single frame
+    fn flush_batch(&mut self, ctx: &mut ws::WebsocketContext<Self>) {
+        if let Some(handle) = self.batch_flush.take() {
+            ctx.cancel_future(handle);
+        }
+
+        if let Some(frame) = self.batch.take_frame() {
+            ctx.text(frame);
         }
     }
 }
@@ -66,7 +97,7 @@ impl Actor for AudioNodeSession {
         let addr = ctx.address();
         self.node_addr
             .send(NodeConnectMessage {
-                addr,
+                addr: NodeSessionAddr::Single(addr),
                 wanted_info: Arc::clone(&self.wanted_info),
             })
             .into_actor(self)
@@ -93,6 +124,13 @@ impl Actor for AudioNodeSession {
                 actix::fut::ready(())
             })
             .wait(ctx);
+
+        ctx.run_interval(CLIENT_TIMEOUT_CHECK_INTERVAL, |act, ctx| {
+            if act.activity.timed_out(Instant::now()) {
+                info!("'AudioNodeSession' timed out, ID: {}", act.id);
+                ctx.stop();
+            }
+        });
     }
 
     fn stopping(&mut self, _: &mut Self::Context) -> Running {
@@ -110,9 +148,11 @@ impl Handler<HeartBeat> for AudioNodeSession {
 
     fn handle(&mut self, _msg: HeartBeat, ctx: &mut Self::Context) -> Self::Result {
         ctx.ping(b"heart-beat");
+
+        let interval = self.heartbeat_interval;
         Box::pin(
-            async {
-                actix_rt::time::sleep(std::time::Duration::from_millis(333)).await;
+            async move {
+                actix_rt::time::sleep(interval).await;
             }
             .into_actor(self)
             .map(|_res, _act, ctx| ctx.notify(HeartBeat)),
@@ -127,11 +167,28 @@ impl Handler<AudioNodeInfoStreamMessage> for AudioNodeSession {
     fn handle(&mut self, msg: AudioNodeInfoStreamMessage, ctx: &mut Self::Context) -> Self::Result {
         let msg_type = get_type_of_stream_data(&msg);
 
-        if self.wanted_info.contains(&msg_type) {
-            ctx.text(
-                serde_json::to_string(&msg)
-                    .unwrap_or(String::from("failed to serialize on server")),
-            )
+        if !self.wanted_info.contains(&msg_type) {
+            return;
+        }
+
+        let text =
+            serde_json::to_string(&msg).unwrap_or(String::from("failed to serialize on server"));
+
+        match self.batch_window {
+            Some(window) if is_batchable(&msg) => {
+                if self.batch.push(text) {
+                    self.batch_flush = Some(ctx.run_later(window, |act, ctx| {
+                        act.batch_flush = None;
+                        act.flush_batch(ctx);
+                    }));
+                }
+            }
+            _ => {
+                // batched updates happened before this message, sending them first keeps the
+                // order intact
+                self.flush_batch(ctx);
+                ctx.text(text);
+            }
         }
     }
 }
@@ -141,6 +198,7 @@ impl Handler<AppError> for AudioNodeSession {
 
     /// used to receive multicast messages from nodes
     fn handle(&mut self, msg: AppError, ctx: &mut Self::Context) -> Self::Result {
+        self.flush_batch(ctx);
         ctx.text(
             serde_json::to_string(&msg).unwrap_or(String::from("failed to serialize on server")),
         )
@@ -149,9 +207,17 @@ impl Handler<AppError> for AudioNodeSession {
 
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for AudioNodeSession {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
-        if let Ok(ws::Message::Close(reason)) = msg {
-            ctx.close(reason.clone());
-            ctx.stop();
+        match msg {
+            Ok(ws::Message::Ping(bytes)) => {
+                self.activity.seen(Instant::now());
+                ctx.pong(&bytes);
+            }
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Ok(_) => self.activity.seen(Instant::now()),
+            Err(_) => ctx.stop(),
         }
     }
 }
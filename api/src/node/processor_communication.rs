@@ -1,14 +1,23 @@
+use std::time::SystemTime;
+
 use actix::{AsyncContext, Handler, Message};
 
 use crate::{
-    audio_playback::audio_player::{AudioInfo, ProcessorInfo},
+    audio_playback::audio_player::{PlaybackState, ProcessorInfo},
     brain::brain_server::AudioNodeToBrainMessage,
-    state_storage::{restore_state_actor::AudioInfoStateUpdateMessage, AudioStateInfo},
+    error::{AppError, AppErrorKind, IntoAppError},
+    state_storage::restore_state_actor::AudioInfoStateUpdateMessage,
     streams::node_streams::AudioNodeInfoStreamMessage,
     utils::log_msg_received,
 };
 
-use super::{health::AudioNodeHealth, node_server::AudioNode, recovery::TryRecoverDevice};
+use super::{
+    health::{AudioNodeHealth, AudioNodeHealthPoor},
+    node_server::{
+        async_actor::AsyncClearResumePosition, sync_actor::resume_current_item, AudioNode,
+    },
+    recovery::TryRecoverDevice,
+};
 
 /// Used to communicate between the audio player and the audio node.
 #[derive(Debug, Clone, Message, PartialEq)]
@@ -16,6 +25,13 @@ use super::{health::AudioNodeHealth, node_server::AudioNode, recovery::TryRecove
 pub enum AudioProcessorToNodeMessage {
     AudioStateInfo(ProcessorInfo),
     Health(AudioNodeHealth),
+    /// the current track has been played to its end
+    TrackEnded,
+    /// the current track has ended during a crossfade, the processor continues with the track it
+    /// crossfaded into
+    TrackCrossfaded,
+    /// the current announcement has been played to its end
+    AnnouncementEnded,
 }
 
 impl Handler<AudioProcessorToNodeMessage> for AudioNode {
@@ -33,8 +49,26 @@ impl Handler<AudioProcessorToNodeMessage> for AudioNode {
             }
         }
         match msg {
+            // late reports of the processor that was dropped when playback was stopped
+            AudioProcessorToNodeMessage::AudioStateInfo(_)
+            | AudioProcessorToNodeMessage::TrackEnded
+            | AudioProcessorToNodeMessage::TrackCrossfaded
+                if self.player.is_stopped() => {}
+            // errors of the old stream don't restart the recovery of a device that was given up on
+            AudioProcessorToNodeMessage::Health(AudioNodeHealth::Poor(_))
+                if self.health
+                    == AudioNodeHealth::Poor(AudioNodeHealthPoor::PermanentlyUnavailable) => {}
             AudioProcessorToNodeMessage::Health(health) => {
+                if health != self.health {
+                    if let Some(err) = health_error(&health, &self.source_name) {
+                        self.recent_errors.push(err, SystemTime::now());
+                    }
+                }
+
                 self.health = health.clone();
+                self.player
+                    .set_device_available(!self.health.device_unavailable());
+                self.update_device_loss_pause();
 
                 self.server_addr
                     .do_send(AudioNodeToBrainMessage::NodeHealthUpdate((
@@ -45,7 +79,8 @@ impl Handler<AudioProcessorToNodeMessage> for AudioNode {
                 self.multicast(AudioNodeInfoStreamMessage::Health(health));
 
                 match self.health {
-                    AudioNodeHealth::Good => {}
+                    AudioNodeHealth::Good
+                    | AudioNodeHealth::Poor(AudioNodeHealthPoor::PermanentlyUnavailable) => {}
                     _ => {
                         if let Err(err) = ctx.address().try_send(TryRecoverDevice) {
                             log::error!(
@@ -55,35 +90,161 @@ impl Handler<AudioProcessorToNodeMessage> for AudioNode {
                     }
                 };
             }
+            AudioProcessorToNodeMessage::TrackEnded => {
+                if let Some(uid) = self.finish_current_item() {
+                    ctx.notify(AsyncClearResumePosition(uid));
+                }
+
+                if self.auto_advance {
+                    // not sent as a `PlayNext` command, that would store the end of the finished
+                    // track as its resume position
+                    match self.player.play_after_finish().into_app_err(
+                        "failed to play next audio",
+                        AppErrorKind::Queue,
+                        &[&format!("NODE_NAME: {name}", name = self.source_name)],
+                    ) {
+                        Ok(true) => resume_current_item(self, ctx),
+                        Ok(false) => self.multicast(AudioNodeInfoStreamMessage::TrackEnded),
+                        Err(err) => {
+                            self.recent_errors.push(err.clone(), SystemTime::now());
+                            self.multicast(err);
+                        }
+                    }
+                } else {
+                    self.multicast(AudioNodeInfoStreamMessage::TrackEnded);
+                }
+            }
+            AudioProcessorToNodeMessage::TrackCrossfaded => {
+                if let Some(uid) = self.finish_current_item() {
+                    ctx.notify(AsyncClearResumePosition(uid));
+                }
+
+                if let Err(err) = self.player.finish_crossfade().into_app_err(
+                    "failed to continue with crossfaded audio",
+                    AppErrorKind::Queue,
+                    &[&format!("NODE_NAME: {name}", name = self.source_name)],
+                ) {
+                    self.recent_errors.push(err.clone(), SystemTime::now());
+                    self.multicast(err);
+                }
+
+                // the last report of the processor still describes the previous track
+                self.current_processor_info.duration_secs = None;
+                self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+                    self.audio_info(),
+                ));
+            }
+            AudioProcessorToNodeMessage::AnnouncementEnded => {
+                if let Err(err) = self.player.finish_announcement().into_app_err(
+                    "failed to play next announcement",
+                    AppErrorKind::Queue,
+                    &[&format!("NODE_NAME: {name}", name = self.source_name)],
+                ) {
+                    self.recent_errors.push(err.clone(), SystemTime::now());
+                    self.multicast(err);
+                }
+            }
             AudioProcessorToNodeMessage::AudioStateInfo(processor_info) => {
-                self.current_processor_info = processor_info.clone();
+                self.current_processor_info = processor_info;
+
+                self.restore_state_addr
+                    .do_send(AudioInfoStateUpdateMessage((
+                        self.source_name.clone(),
+                        self.audio_state_info(),
+                    )));
+
+                self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+                    self.audio_info(),
+                ));
+            }
+        }
+
+        self.update_playback_activity();
+        self.update_up_next();
+    }
+}
+
+impl AudioNode {
+    /// pauses playback when the device is lost and resumes it once the device has been recovered,
+    /// only if the node pauses on device loss
+    ///
+    /// The paused state is stored so the progress at the time the device was lost is kept while
+    /// the node is recovering.
+    fn update_device_loss_pause(&mut self) {
+        match self.health {
+            AudioNodeHealth::Poor(AudioNodeHealthPoor::DeviceNotAvailable) => {
+                if !self.pause_on_device_loss
+                    || self.current_processor_info.playback_state != PlaybackState::Playing
+                {
+                    return;
+                }
+
+                log::info!(
+                    "pausing playback of node with source name {} until its device is recovered",
+                    self.source_name
+                );
+
+                self.player.set_stream_playback_state(PlaybackState::Paused);
+                self.current_processor_info.playback_state = PlaybackState::Paused;
+                self.paused_by_device_loss = true;
 
                 self.restore_state_addr
                     .do_send(AudioInfoStateUpdateMessage((
                         self.source_name.clone(),
-                        AudioStateInfo {
-                            current_queue_index: self.player.queue_head(),
-                            audio_volume: processor_info.audio_volume,
-                            audio_progress: processor_info.audio_progress,
-                            playback_state: processor_info.playback_state.clone(),
-                            restored_queue: vec![],
-                            queue: self
-                                .player
-                                .queue()
-                                .iter()
-                                .map(|item| item.identifier.clone())
-                                .collect(),
-                        },
+                        self.audio_state_info(),
                     )));
+                self.multicast(AudioNodeInfoStreamMessage::AudioStateInfo(
+                    self.audio_info(),
+                ));
+            }
+            AudioNodeHealth::Good => {
+                if !self.paused_by_device_loss {
+                    return;
+                }
+
+                log::info!(
+                    "resuming playback of node with source name {} after its device was recovered",
+                    self.source_name
+                );
 
-                let msg = AudioNodeInfoStreamMessage::AudioStateInfo(AudioInfo {
-                    current_queue_index: self.player.queue_head(),
-                    audio_volume: processor_info.audio_volume,
-                    audio_progress: processor_info.audio_progress,
-                    playback_state: processor_info.playback_state,
-                });
-                self.multicast(msg);
+                self.paused_by_device_loss = false;
+                self.player
+                    .set_stream_playback_state(PlaybackState::Playing);
             }
+            _ => {}
         }
     }
 }
+
+/// error that is remembered when a node enters `health`, only poor health is an error
+fn health_error(health: &AudioNodeHealth, source_name: &str) -> Option<AppError> {
+    let AudioNodeHealth::Poor(poor) = health else {
+        return None;
+    };
+
+    let node_name = format!("NODE_NAME: {source_name}");
+    let err = match poor {
+        AudioNodeHealthPoor::DeviceNotAvailable => AppError::new(
+            AppErrorKind::Queue,
+            "audio device is not available",
+            &[&node_name],
+        ),
+        AudioNodeHealthPoor::AudioStreamReadFailed => AppError::new(
+            AppErrorKind::Queue,
+            "failed to read audio stream",
+            &[&node_name],
+        ),
+        AudioNodeHealthPoor::AudioBackendError(description) => AppError::new(
+            AppErrorKind::Queue,
+            format!("audio backend error: {description}"),
+            &[&node_name],
+        ),
+        AudioNodeHealthPoor::PermanentlyUnavailable => AppError::new(
+            AppErrorKind::Queue,
+            "audio device is permanently unavailable, it has to be reconnected",
+            &[&node_name],
+        ),
+    };
+
+    Some(err)
+}
@@ -3,12 +3,51 @@ use std::{thread, time::Duration};
 use actix::{AsyncContext, Handler, Message};
 
 use super::{
-    health::AudioNodeHealth, node_server::AudioNode,
+    health::{AudioNodeHealth, AudioNodeHealthPoor},
+    node_server::AudioNode,
     processor_communication::AudioProcessorToNodeMessage,
 };
 
 const DEVICE_RECOVERY_ATTEMPT_INTERVAL: Duration = Duration::from_secs(5);
 
+/// failed recovery attempts after which a device is considered permanently unavailable, about 5
+/// minutes with the attempt interval
+pub const DEFAULT_MAX_RECOVERY_ATTEMPTS: u32 = 60;
+
+/// Counts failed attempts to recover a device, so a device that was physically removed isn't
+/// retried forever
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecoveryAttempts {
+    failed: u32,
+    max: u32,
+}
+
+impl RecoveryAttempts {
+    pub fn new(max: u32) -> Self {
+        Self {
+            failed: 0,
+            max: max.max(1),
+        }
+    }
+
+    /// returns whether the device should be tried again
+    pub fn record_failure(&mut self) -> bool {
+        self.failed = self.failed.saturating_add(1);
+        self.failed < self.max
+    }
+
+    /// health of the node after a failed attempt, `None` while the device should be tried again
+    pub fn health_after_failure(&mut self) -> Option<AudioNodeHealth> {
+        (!self.record_failure()).then_some(AudioNodeHealth::Poor(
+            AudioNodeHealthPoor::PermanentlyUnavailable,
+        ))
+    }
+
+    pub fn reset(&mut self) {
+        self.failed = 0;
+    }
+}
+
 /// Used to try and recover connections with devices. Mainly intended to be used with bluetooth
 /// devices.
 ///
@@ -25,7 +64,9 @@ impl Handler<TryRecoverDevice> for AudioNode {
     #[allow(clippy::collapsible_else_if)]
     fn handle(&mut self, _msg: TryRecoverDevice, ctx: &mut Self::Context) -> Self::Result {
         match self.health {
-            AudioNodeHealth::Good => {}
+            // only an explicit `ReconnectDevice` command tries again
+            AudioNodeHealth::Good
+            | AudioNodeHealth::Poor(AudioNodeHealthPoor::PermanentlyUnavailable) => {}
             _ => {
                 let device_health_restored = if let Err(err) = self
                     .player
@@ -41,12 +82,24 @@ impl Handler<TryRecoverDevice> for AudioNode {
                 };
 
                 if !device_health_restored {
+                    if let Some(health) = self.recovery_attempts.health_after_failure() {
+                        log::error!(
+                            "giving up on recovering device for node with source name {}, it has to be reconnected manually",
+                            self.source_name
+                        );
+
+                        ctx.notify(AudioProcessorToNodeMessage::Health(health));
+                        return;
+                    }
+
                     thread::sleep(DEVICE_RECOVERY_ATTEMPT_INTERVAL);
 
                     if let Err(err) = ctx.address().try_send(TryRecoverDevice) {
                         log::error!("failed to resend 'try device revocer' message\nERROR: {err}");
                     };
                 } else {
+                    self.recovery_attempts.reset();
+
                     if let Err(err) = ctx
                         .address()
                         .try_send(AudioProcessorToNodeMessage::Health(AudioNodeHealth::Good))
@@ -61,3 +114,38 @@ impl Handler<TryRecoverDevice> for AudioNode {
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_recovery_attempts() {
+        // the device never comes back
+        let mut attempts = RecoveryAttempts::new(3);
+        let retries: Vec<bool> = (0..4).map(|_| attempts.record_failure()).collect();
+        assert_eq!(retries, vec![true, true, false, false]);
+
+        attempts.reset();
+        assert!(attempts.record_failure());
+
+        // at least one attempt is always made
+        assert!(!RecoveryAttempts::new(0).record_failure());
+    }
+
+    #[test]
+    fn test_device_is_given_up_after_last_attempt() {
+        let given_up = AudioNodeHealth::Poor(AudioNodeHealthPoor::PermanentlyUnavailable);
+        let mut attempts = RecoveryAttempts::new(3);
+
+        assert_eq!(attempts.health_after_failure(), None);
+        assert_eq!(attempts.health_after_failure(), None);
+        assert_eq!(attempts.health_after_failure(), Some(given_up.clone()));
+        assert!(given_up.device_unavailable());
+
+        // reconnecting the device manually starts counting again
+        attempts.reset();
+        assert_eq!(attempts.health_after_failure(), None);
+    }
+}
@@ -1,5 +1,7 @@
 use std::path::{Path, PathBuf};
 
+use crate::state_storage::format::StateFormat;
+
 const DEV_DIR: &str = "dev";
 const PROD_DIR: &str = "prod";
 
@@ -7,8 +9,15 @@ pub fn audio_data_dir() -> PathBuf {
     parent_dir().join("audio")
 }
 
+/// computed waveforms of stored tracks, kept outside of the audio directory so they never show up
+/// as audio files
+pub fn waveform_cache_dir() -> PathBuf {
+    parent_dir().join("waveforms")
+}
+
+/// ends with `.json` if `STATE_RECOVERY_FORMAT` is set to `json`
 pub fn state_recovery_file_path() -> PathBuf {
-    parent_dir().join("state-recovery-info")
+    StateFormat::from_env().file_path(&parent_dir().join("state-recovery-info"))
 }
 
 fn parent_dir<'a>() -> &'a Path {
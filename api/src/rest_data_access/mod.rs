@@ -1,24 +1,132 @@
-use std::sync::Arc;
+use std::{future::Future, path::Path, sync::Arc};
 
-use actix_web::{get, web, HttpResponse};
+use actix_files::NamedFile;
+use actix_web::{
+    delete, get,
+    http::{
+        header::{ContentType, HeaderName, HeaderValue, LOCATION},
+        StatusCode,
+    },
+    patch, post, route, web, HttpRequest, HttpResponse, ResponseError,
+};
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
 use crate::{
+    audio_hosts::{
+        local::read_cover_art,
+        waveform::{waveform_peaks, Peak, DEFAULT_PEAK_BUCKETS},
+    },
     audio_playback::audio_item::AudioMetadata,
+    auth::is_admin_request,
+    brain::brain_server::{
+        GetBrainSessionCount, GetDefaultVolume, GetDownloadRateLimit, SetDefaultVolume,
+        SetDownloadRateLimit,
+    },
+    brain_addr,
     database::{
         fetch_data::{
-            get_all_audio_metadata_from_db, get_all_playlist_metadata_from_db,
-            get_playlist_items_from_db,
+            count_audio_metadata_in_db, count_play_history_in_db, count_playlist_items_in_db,
+            count_playlists_in_db, get_all_audio_metadata_from_db,
+            get_all_playlist_metadata_from_db, get_audio_metadata_from_db, get_bookmarks_from_db,
+            get_play_history_from_db, get_playlist_items_from_db, get_resume_position_from_db,
+            playlist_exists_in_db, search_audio_metadata_from_db,
+        },
+        store_data::set_audio_favorite,
+        PlaylistMetadata, SortBy, SortOrder,
+    },
+    downloader::{
+        actor::{GetDownloadQueue, MoveDownloadQueueItem},
+        download_identifier::{Identifier, ItemUid},
+        library::{DownloadPlaylistToLibrary, GetLibraryDownloads},
+    },
+    downloader_addr,
+    error::{AppError, AppErrorKind, IntoAppError},
+    library_downloader_addr,
+    logging::{tail_log_file, LOG_FILE},
+    maintenance::{
+        delete_stored_audio, library_stats, purge_orphans, storage_usage, AudioDeleteResult,
+        AudioDeleteStatus,
+    },
+    node::node_server::{
+        connections::{GetConnectedSessions, NodeSessionInfo},
+        download_notifications::CancelAllDownloads,
+        sync_actor::{
+            EnqueueStoredItems, GetBufferStats, GetOutputConfig, GetProcessorInfo, GetQueue,
+            GetRecentErrors,
         },
-        PlaylistMetadata,
+        SourceName,
     },
-    downloader::download_identifier::ItemUid,
+    restore_state_addr,
+    state_storage::{
+        import::restore_imported_state, restore_state_actor::GetRecoveryState, AppStateRecoveryInfo,
+    },
+    utils::{get_node_by_source_name, get_node_by_source_name_any_health, list_output_devices},
+    RUNTIME_CONFIG,
 };
 
+/// Builds a JSON response, falls back to an internal server error with a JSON error body if the
+/// value can't be serialized
+pub fn json_response<T: Serialize>(status: StatusCode, value: &T) -> HttpResponse {
+    match serde_json::to_string(value) {
+        Ok(body) => HttpResponse::build(status)
+            .content_type(ContentType::json())
+            .body(body),
+        Err(err) => {
+            // `into_app_err` logs the error, this should never happen for any of the response types
+            let err = err.into_app_err("failed to serialize response", AppErrorKind::Api, &[]);
+            error_response(&err)
+        }
+    }
+}
+
+/// header with the total number of items of a paginated list, only set if the total was requested
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+fn with_total_count(mut response: HttpResponse, total: Option<i64>) -> HttpResponse {
+    if let Some(total) = total {
+        response.headers_mut().insert(
+            HeaderName::from_static(TOTAL_COUNT_HEADER),
+            HeaderValue::from(total),
+        );
+    }
+
+    response
+}
+
+/// only counts if requested, counting all items is more expensive than fetching a single page
+async fn requested_total(
+    with_count: bool,
+    count: impl Future<Output = Result<i64, AppError>>,
+) -> Result<Option<i64>, AppError> {
+    if !with_count {
+        return Ok(None);
+    }
+
+    count.await.map(Some)
+}
+
+/// Builds a response with the status of the error and the user facing part of the error as JSON
+/// body
+pub fn error_response(err: &AppError) -> HttpResponse {
+    ResponseError::error_response(err)
+}
+
 #[derive(Debug, Serialize)]
 struct StoredAudioData {
     uid: Arc<str>,
     metadata: AudioMetadata,
+    /// only included in the list of stored audio, see [`get_audio`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    favorite: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeQueue {
+    items: Vec<StoredAudioData>,
+    current_queue_index: usize,
+    label: Option<Arc<str>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -27,17 +135,197 @@ struct StoredPlaylistData {
     metadata: PlaylistMetadata,
 }
 
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct DefaultVolumeSetting {
+    pub default_volume: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct DownloadRateLimitSetting {
+    /// bytes per second with an optional `K`, `M` or `G` suffix, `null` removes the limit
+    #[ts(type = "string | null")]
+    pub download_rate_limit: Option<Arc<str>>,
+}
+
+/// Identifies the API types the server was built with, clients should compare `types_hash` to
+/// the hash of their own types to detect incompatible versions
+#[derive(Debug, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct ApiVersion {
+    pub version: &'static str,
+    pub types_hash: &'static str,
+}
+
+/// Sessions that receive the updates of a node, used to check why a client isn't updating
+#[derive(Debug, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct NodeSessions {
+    pub session_count: usize,
+    pub sessions: Vec<NodeSessionInfo>,
+    /// sessions connected to the brain, they receive updates of all nodes
+    pub brain_session_count: usize,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct AudioFavorite {
+    pub uid: Arc<str>,
+    pub favorite: bool,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct ResumePosition {
+    pub uid: Arc<str>,
+    pub position_secs: f64,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct DownloadPlaylistParams {
+    pub playlist_url: Arc<str>,
+}
+
+#[derive(Debug, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct WaveformPeaks {
+    pub uid: Arc<str>,
+    /// lowest and highest sample of every bucket, between -1 and 1
+    #[ts(type = "Array<[number, number]>")]
+    pub peaks: Vec<Peak>,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct WaveformPeaksParams {
+    /// number of peaks, 512 by default and limited to 4096
+    pub buckets: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct StorageUsageParams {
+    /// walk the audio directory again even if a cached result is available
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct BatchDeleteAudioParams {
+    #[ts(type = "Array<string>")]
+    pub uids: Vec<Arc<str>>,
+    /// also delete tracks that a node is currently playing
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct PurgeOrphansParams {
+    /// only reports what was found if not set
+    #[serde(default)]
+    pub confirm: bool,
+    /// also look for audio metadata whose audio file is missing
+    #[serde(default)]
+    pub include_missing_files: bool,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct MovePendingDownloadParams {
+    pub old_pos: usize,
+    pub new_pos: usize,
+}
+
+#[derive(Deserialize)]
+struct LogTailParams {
+    lines: Option<usize>,
+}
+
 #[derive(Deserialize)]
 struct OffsetLimitParams {
     limit: Option<i64>,
     offset: Option<i64>,
+    /// defaults to the name, except for the items of a playlist which are in playlist order
+    sort_by: Option<SortBy>,
+    #[serde(default)]
+    order: SortOrder,
+    /// also responds with the total number of items in the [`TOTAL_COUNT_HEADER`]
+    #[serde(default)]
+    with_count: bool,
+}
+
+#[derive(Deserialize)]
+struct FavoritesFilterParams {
+    /// only lists the tracks that were marked as favorite
+    #[serde(default)]
+    favorites_only: bool,
+}
+
+#[derive(Deserialize)]
+struct PlayHistoryParams {
+    /// capped at [`MAX_PLAY_HISTORY_LIMIT`](crate::database::fetch_data::MAX_PLAY_HISTORY_LIMIT)
+    limit: Option<i64>,
+    offset: Option<i64>,
+    /// only the plays of this node
+    source_name: Option<SourceName>,
+    /// also responds with the total number of plays in the [`TOTAL_COUNT_HEADER`]
+    #[serde(default)]
+    with_count: bool,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct QueueFromSearchParams {
+    pub query: Arc<str>,
+    pub limit: Option<i64>,
+}
+
+#[get("/data/api-version")]
+pub async fn get_api_version() -> HttpResponse {
+    json_response(
+        StatusCode::OK,
+        &ApiVersion {
+            version: env!("CARGO_PKG_VERSION"),
+            types_hash: env!("API_TYPES_HASH"),
+        },
+    )
 }
 
 #[get("/data/playlists")]
 pub async fn get_playlists(
-    web::Query(OffsetLimitParams { limit, offset }): web::Query<OffsetLimitParams>,
+    web::Query(OffsetLimitParams {
+        limit,
+        offset,
+        sort_by,
+        order,
+        with_count,
+    }): web::Query<OffsetLimitParams>,
 ) -> HttpResponse {
-    match get_all_playlist_metadata_from_db(limit, offset).await {
+    let total = match requested_total(with_count, count_playlists_in_db()).await {
+        Ok(total) => total,
+        Err(err) => return error_response(&err),
+    };
+
+    match get_all_playlist_metadata_from_db(limit, offset, sort_by.unwrap_or_default(), order).await
+    {
         Ok(items) => {
             let result: Vec<StoredPlaylistData> = items
                 .iter()
@@ -47,59 +335,952 @@ pub async fn get_playlists(
                 })
                 .collect();
 
-            HttpResponse::Ok().body(
-                serde_json::to_string(&result).unwrap_or("oops something went wrong".to_owned()),
-            )
+            with_total_count(json_response(StatusCode::OK, &result), total)
         }
-        Err(err) => HttpResponse::InternalServerError()
-            .body(serde_json::to_string(&err).unwrap_or("oops something went wrong".to_owned())),
+        Err(err) => error_response(&err),
     }
 }
 
+/// Gets the stored audio library, use [`get_node_queue`] for the queue a node is playing from
 #[get("/data/audio")]
 pub async fn get_audio(
-    web::Query(OffsetLimitParams { limit, offset }): web::Query<OffsetLimitParams>,
+    web::Query(OffsetLimitParams {
+        limit,
+        offset,
+        sort_by,
+        order,
+        with_count,
+    }): web::Query<OffsetLimitParams>,
+    web::Query(FavoritesFilterParams { favorites_only }): web::Query<FavoritesFilterParams>,
 ) -> HttpResponse {
-    match get_all_audio_metadata_from_db(limit, offset).await {
+    let total = match requested_total(with_count, count_audio_metadata_in_db(favorites_only)).await
+    {
+        Ok(total) => total,
+        Err(err) => return error_response(&err),
+    };
+
+    match get_all_audio_metadata_from_db(
+        limit,
+        offset,
+        sort_by.unwrap_or_default(),
+        order,
+        favorites_only,
+    )
+    .await
+    {
         Ok(items) => {
             let result: Vec<StoredAudioData> = items
                 .iter()
-                .map(|(uid, metadata)| StoredAudioData {
+                .map(|(uid, metadata, favorite)| StoredAudioData {
                     uid: Arc::clone(&uid.0),
                     metadata: metadata.clone(),
+                    favorite: Some(*favorite),
                 })
                 .collect();
 
-            HttpResponse::Ok().body(
-                serde_json::to_string(&result).unwrap_or("oops something went wrong".to_owned()),
-            )
+            with_total_count(json_response(StatusCode::OK, &result), total)
         }
-        Err(err) => HttpResponse::InternalServerError()
-            .body(serde_json::to_string(&err).unwrap_or("oops something went wrong".to_owned())),
+        Err(err) => error_response(&err),
+    }
+}
+
+/// Tracks that were started on any node, most recent first
+#[get("/data/history")]
+pub async fn get_play_history(
+    web::Query(PlayHistoryParams {
+        limit,
+        offset,
+        source_name,
+        with_count,
+    }): web::Query<PlayHistoryParams>,
+) -> HttpResponse {
+    let source_name = source_name.as_deref();
+
+    let total = match requested_total(with_count, count_play_history_in_db(source_name)).await {
+        Ok(total) => total,
+        Err(err) => return error_response(&err),
+    };
+
+    match get_play_history_from_db(limit, offset, source_name).await {
+        Ok(entries) => with_total_count(json_response(StatusCode::OK, &entries), total),
+        Err(err) => error_response(&err),
     }
 }
 
 #[get("/data/playlists/{playlist_uid}")]
 pub async fn get_audio_in_playlist(
     playlist_uid: web::Path<Arc<str>>,
-    web::Query(OffsetLimitParams { limit, offset }): web::Query<OffsetLimitParams>,
+    web::Query(OffsetLimitParams {
+        limit,
+        offset,
+        sort_by,
+        order,
+        with_count,
+    }): web::Query<OffsetLimitParams>,
 ) -> HttpResponse {
     let uid = ItemUid(playlist_uid.into_inner());
-    match get_playlist_items_from_db(&uid, limit, offset).await {
+    match playlist_exists_in_db(&uid.0).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return error_response(
+                &AppError::new(
+                    AppErrorKind::LocalData,
+                    "playlist not found",
+                    &[&format!("PLAYLIST_UID: {uid}", uid = uid.0)],
+                )
+                .not_found(),
+            )
+        }
+        Err(err) => return error_response(&err),
+    }
+
+    let total = match requested_total(with_count, count_playlist_items_in_db(&uid.0)).await {
+        Ok(total) => total,
+        Err(err) => return error_response(&err),
+    };
+
+    match get_playlist_items_from_db(&uid, limit, offset, sort_by, order).await {
         Ok(items) => {
             let result: Vec<StoredAudioData> = items
                 .iter()
                 .map(|(uid, metadata)| StoredAudioData {
                     uid: Arc::clone(&uid.0),
                     metadata: metadata.clone(),
+                    favorite: None,
                 })
                 .collect();
 
-            HttpResponse::Ok().body(
-                serde_json::to_string(&result).unwrap_or("oops something went wrong".to_owned()),
-            )
+            with_total_count(json_response(StatusCode::OK, &result), total)
+        }
+        Err(err) => error_response(&err),
+    }
+}
+
+/// Downloads all videos of a youtube playlist into the library without queueing them on any node,
+/// responds with the batch that can be polled via `/data/downloads`
+#[post("/data/youtube/playlist/download")]
+pub async fn download_playlist_to_library(
+    params: web::Json<DownloadPlaylistParams>,
+) -> HttpResponse {
+    let msg = DownloadPlaylistToLibrary {
+        playlist_url: Arc::clone(&params.playlist_url),
+    };
+
+    match library_downloader_addr().send(msg).await {
+        Ok(Ok(batch)) => json_response(StatusCode::OK, &batch),
+        Ok(Err(err)) => error_response(&err),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to start playlist download",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+/// Progress of all library downloads started since the server started
+#[get("/data/downloads")]
+pub async fn get_library_downloads() -> HttpResponse {
+    match library_downloader_addr().send(GetLibraryDownloads).await {
+        Ok(batches) => json_response(StatusCode::OK, &batches),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to get library downloads",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+/// Downloads of all nodes and the library that haven't been started yet, in the order they will be
+/// started in
+#[get("/data/downloads/queue")]
+pub async fn get_download_queue() -> HttpResponse {
+    match downloader_addr().send(GetDownloadQueue).await {
+        Ok(queue) => json_response(StatusCode::OK, &queue),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to get download queue",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+/// Moves a pending download, e.g. to download a single track before a long playlist
+#[patch("/data/downloads/queue")]
+pub async fn move_pending_download(params: web::Json<MovePendingDownloadParams>) -> HttpResponse {
+    let msg = MoveDownloadQueueItem {
+        old_pos: params.old_pos,
+        new_pos: params.new_pos,
+    };
+
+    match downloader_addr().send(msg).await {
+        Ok(Ok(queue)) => json_response(StatusCode::OK, &queue),
+        Ok(Err(err)) => error_response(&err),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to move pending download",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+#[get("/data/audio/{uid}/bookmarks")]
+pub async fn get_audio_bookmarks(uid: web::Path<Arc<str>>) -> HttpResponse {
+    let uid = ItemUid(uid.into_inner());
+    match get_bookmarks_from_db(&uid).await {
+        Ok(bookmarks) => json_response(StatusCode::OK, &bookmarks),
+        Err(err) => error_response(&err),
+    }
+}
+
+/// Marks a stored track as favorite, favoriting it again changes nothing
+#[post("/data/audio/{uid}/favorite")]
+pub async fn add_audio_favorite(uid: web::Path<Arc<str>>) -> HttpResponse {
+    update_audio_favorite(uid.into_inner(), true).await
+}
+
+/// Removes the favorite mark of a stored track, removing it again changes nothing
+#[delete("/data/audio/{uid}/favorite")]
+pub async fn remove_audio_favorite(uid: web::Path<Arc<str>>) -> HttpResponse {
+    update_audio_favorite(uid.into_inner(), false).await
+}
+
+async fn update_audio_favorite(uid: Arc<str>, favorite: bool) -> HttpResponse {
+    match set_audio_favorite(&ItemUid(&uid), favorite).await {
+        Ok(true) => json_response(StatusCode::OK, &AudioFavorite { uid, favorite }),
+        Ok(false) => HttpResponse::new(StatusCode::NOT_FOUND),
+        Err(err) => error_response(&err),
+    }
+}
+
+/// Position a node with resume playback enabled continues the track at, not found if the track
+/// has no stored position
+#[get("/data/audio/{uid}/resume-position")]
+pub async fn get_audio_resume_position(uid: web::Path<Arc<str>>) -> HttpResponse {
+    let uid = ItemUid(uid.into_inner());
+    match get_resume_position_from_db(&uid).await {
+        Ok(Some(position_secs)) => json_response(
+            StatusCode::OK,
+            &ResumePosition {
+                uid: uid.0,
+                position_secs,
+            },
+        ),
+        Ok(None) => HttpResponse::new(StatusCode::NOT_FOUND),
+        Err(err) => error_response(&err),
+    }
+}
+
+/// Serves the stored audio file, supports range requests so external players can seek
+#[route("/data/audio/{uid}/file", method = "GET", method = "HEAD")]
+pub async fn get_audio_file(req: HttpRequest, uid: web::Path<Arc<str>>) -> HttpResponse {
+    let uid = ItemUid(uid.into_inner());
+    match get_audio_metadata_from_db(&uid).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::new(StatusCode::NOT_FOUND),
+        Err(err) => return error_response(&err),
+    };
+
+    match NamedFile::open_async(uid.to_path_with_ext()).await {
+        Ok(file) => file.into_response(&req),
+        Err(_) => HttpResponse::new(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Serves the cover art embedded in the stored audio file, so it is available without requests to
+/// the audio host
+///
+/// Redirects to the cover art url of the metadata if the file has no embedded cover art.
+#[get("/data/audio/{uid}/cover-art")]
+pub async fn get_audio_cover_art(uid: web::Path<Arc<str>>) -> HttpResponse {
+    let uid = ItemUid(uid.into_inner());
+    let metadata = match get_audio_metadata_from_db(&uid).await {
+        Ok(Some(metadata)) => metadata,
+        Ok(None) => return HttpResponse::new(StatusCode::NOT_FOUND),
+        Err(err) => return error_response(&err),
+    };
+
+    let path = uid.to_path_with_ext();
+    if path.is_file() {
+        match web::block(move || read_cover_art(&path)).await {
+            Ok(Ok(Some(cover))) => {
+                return HttpResponse::Ok()
+                    .content_type(cover.media_type.as_ref())
+                    .body(cover.data)
+            }
+            // errors are logged when they are created
+            Ok(Ok(None) | Err(_)) => {}
+            Err(err) => log::error!("failed to run blocking task\nERROR: {err}"),
+        }
+    }
+
+    // imported files have no cover art url, their art can only be embedded in the file
+    match metadata.cover_art_url.inner_as_ref() {
+        Some(url) if url.starts_with("http") => HttpResponse::Found()
+            .insert_header((LOCATION, url))
+            .finish(),
+        _ => HttpResponse::new(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Waveform of the stored audio file for scrubbing, the file is only decoded on the first request
+/// and the waveform is cached afterwards
+#[get("/data/audio/{uid}/peaks")]
+pub async fn get_audio_peaks(
+    uid: web::Path<Arc<str>>,
+    web::Query(WaveformPeaksParams { buckets }): web::Query<WaveformPeaksParams>,
+) -> HttpResponse {
+    let uid = ItemUid(uid.into_inner());
+    match get_audio_metadata_from_db(&uid).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::new(StatusCode::NOT_FOUND),
+        Err(err) => return error_response(&err),
+    };
+
+    let path = uid.to_path_with_ext();
+    if !path.is_file() {
+        return HttpResponse::new(StatusCode::NOT_FOUND);
+    }
+
+    let task_uid = uid.clone();
+    let buckets = buckets.unwrap_or(DEFAULT_PEAK_BUCKETS);
+    let peaks = web::block(move || waveform_peaks(&task_uid, &path, buckets)).await;
+
+    match peaks {
+        Ok(Ok(peaks)) => json_response(StatusCode::OK, &WaveformPeaks { uid: uid.0, peaks }),
+        Ok(Err(err)) => error_response(&err),
+        Err(err) => {
+            error_response(&err.into_app_err("failed to run blocking task", AppErrorKind::Api, &[]))
+        }
+    }
+}
+
+/// Deletes stored tracks by uid, the response contains whether every uid was deleted, not found
+/// or skipped because a node is playing it
+#[post("/data/audio/batch-delete")]
+pub async fn batch_delete_audio(
+    req: HttpRequest,
+    params: web::Json<BatchDeleteAudioParams>,
+) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
+    let BatchDeleteAudioParams { uids, force } = params.into_inner();
+
+    match delete_stored_audio(uids, force).await {
+        Ok(results) => json_response(StatusCode::OK, &results),
+        Err(err) => error_response(&err),
+    }
+}
+
+/// Deletes a stored track, responds with a conflict if a node is playing it
+#[delete("/data/audio/{uid}")]
+pub async fn delete_audio(req: HttpRequest, uid: web::Path<Arc<str>>) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
+    match delete_stored_audio(vec![uid.into_inner()], false).await {
+        Ok(mut results) => delete_audio_response(&results.remove(0)),
+        Err(err) => error_response(&err),
+    }
+}
+
+fn delete_audio_response(result: &AudioDeleteResult) -> HttpResponse {
+    match result.status {
+        AudioDeleteStatus::Deleted => json_response(StatusCode::OK, result),
+        AudioDeleteStatus::NotFound => HttpResponse::new(StatusCode::NOT_FOUND),
+        AudioDeleteStatus::InUse(_) => json_response(StatusCode::CONFLICT, result),
+    }
+}
+
+#[get("/data/settings/default-volume")]
+pub async fn get_default_volume() -> HttpResponse {
+    match brain_addr().send(GetDefaultVolume).await {
+        Ok(default_volume) => {
+            json_response(StatusCode::OK, &DefaultVolumeSetting { default_volume })
+        }
+        Err(err) => error_response(&err.into_app_err(
+            "failed to get default volume",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+/// Sets the volume newly created nodes start with, existing nodes keep their volume
+#[patch("/data/settings/default-volume")]
+pub async fn set_default_volume(setting: web::Json<DefaultVolumeSetting>) -> HttpResponse {
+    match brain_addr()
+        .send(SetDefaultVolume(setting.default_volume))
+        .await
+    {
+        Ok(default_volume) => {
+            json_response(StatusCode::OK, &DefaultVolumeSetting { default_volume })
+        }
+        Err(err) => error_response(&err.into_app_err(
+            "failed to set default volume",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+#[get("/data/settings/download-rate-limit")]
+pub async fn get_download_rate_limit() -> HttpResponse {
+    match brain_addr().send(GetDownloadRateLimit).await {
+        Ok(download_rate_limit) => json_response(
+            StatusCode::OK,
+            &DownloadRateLimitSetting {
+                download_rate_limit,
+            },
+        ),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to get download rate limit",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+/// Sets the max download rate, downloads that are already running keep their limit
+#[patch("/data/settings/download-rate-limit")]
+pub async fn set_download_rate_limit(setting: web::Json<DownloadRateLimitSetting>) -> HttpResponse {
+    match brain_addr()
+        .send(SetDownloadRateLimit(
+            setting.into_inner().download_rate_limit,
+        ))
+        .await
+    {
+        Ok(Ok(download_rate_limit)) => json_response(
+            StatusCode::OK,
+            &DownloadRateLimitSetting {
+                download_rate_limit,
+            },
+        ),
+        Ok(Err(err)) => error_response(&err),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to set download rate limit",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+/// Cancels all active and queued downloads of a node, downloads of other nodes are not affected
+#[post("/data/node/{source_name}/downloads/cancel-all")]
+pub async fn cancel_all_node_downloads(source_name: web::Path<SourceName>) -> HttpResponse {
+    let node_addr = match get_node_by_source_name(source_name.into_inner(), brain_addr()).await {
+        Some(addr) => addr,
+        None => {
+            return HttpResponse::new(StatusCode::NOT_FOUND);
+        }
+    };
+
+    match node_addr.send(CancelAllDownloads).await {
+        Ok(()) => HttpResponse::new(StatusCode::OK),
+        Err(err) => {
+            error_response(&err.into_app_err("failed to cancel downloads", AppErrorKind::Api, &[]))
+        }
+    }
+}
+
+/// Gets the queue a node is currently playing from, this contains the same items and queue head as
+/// the `QUEUE` node stream but with their uids
+#[get("/data/node/{source_name}/queue")]
+pub async fn get_node_queue(source_name: web::Path<SourceName>) -> HttpResponse {
+    let node_addr = match get_node_by_source_name(source_name.into_inner(), brain_addr()).await {
+        Some(addr) => addr,
+        None => {
+            return HttpResponse::new(StatusCode::NOT_FOUND);
+        }
+    };
+
+    match node_addr.send(GetQueue).await {
+        Ok((items, current_queue_index, label)) => {
+            let result = NodeQueue {
+                items: items
+                    .iter()
+                    .map(|(uid, metadata)| StoredAudioData {
+                        uid: Arc::clone(&uid.0),
+                        metadata: metadata.clone(),
+                        favorite: None,
+                    })
+                    .collect(),
+                current_queue_index,
+                label,
+            };
+
+            json_response(StatusCode::OK, &result)
+        }
+        Err(err) => {
+            error_response(&err.into_app_err("failed to get queue", AppErrorKind::Api, &[]))
+        }
+    }
+}
+
+#[get("/data/node/{source_name}/buffer-stats")]
+pub async fn get_node_buffer_stats(source_name: web::Path<SourceName>) -> HttpResponse {
+    let node_addr = match get_node_by_source_name(source_name.into_inner(), brain_addr()).await {
+        Some(addr) => addr,
+        None => {
+            return HttpResponse::new(StatusCode::NOT_FOUND);
+        }
+    };
+
+    match node_addr.send(GetBufferStats).await {
+        Ok(stats) => json_response(StatusCode::OK, &stats),
+        Err(err) => {
+            error_response(&err.into_app_err("failed to get buffer stats", AppErrorKind::Api, &[]))
+        }
+    }
+}
+
+#[get("/data/node/{source_name}/errors")]
+pub async fn get_node_errors(source_name: web::Path<SourceName>) -> HttpResponse {
+    let node_addr = match get_node_by_source_name(source_name.into_inner(), brain_addr()).await {
+        Some(addr) => addr,
+        None => {
+            return HttpResponse::new(StatusCode::NOT_FOUND);
+        }
+    };
+
+    match node_addr.send(GetRecentErrors).await {
+        Ok(errors) => json_response(StatusCode::OK, &errors),
+        Err(err) => {
+            error_response(&err.into_app_err("failed to get node errors", AppErrorKind::Api, &[]))
+        }
+    }
+}
+
+#[get("/data/node/{source_name}/sessions")]
+pub async fn get_node_sessions(
+    req: HttpRequest,
+    source_name: web::Path<SourceName>,
+) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
+    // sessions of a node with an unavailable device still receive its health updates
+    let node_addr =
+        match get_node_by_source_name_any_health(source_name.into_inner(), brain_addr()).await {
+            Some(addr) => addr,
+            None => {
+                return HttpResponse::new(StatusCode::NOT_FOUND);
+            }
+        };
+
+    let sessions = match node_addr.send(GetConnectedSessions).await {
+        Ok(sessions) => sessions,
+        Err(err) => {
+            return error_response(&err.into_app_err(
+                "failed to get node sessions",
+                AppErrorKind::Api,
+                &[],
+            ))
+        }
+    };
+
+    match brain_addr().send(GetBrainSessionCount).await {
+        Ok(brain_session_count) => json_response(
+            StatusCode::OK,
+            &NodeSessions {
+                session_count: sessions.len(),
+                sessions,
+                brain_session_count,
+            },
+        ),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to get brain session count",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+#[get("/data/node/{source_name}/output-config")]
+pub async fn get_node_output_config(source_name: web::Path<SourceName>) -> HttpResponse {
+    let node_addr = match get_node_by_source_name(source_name.into_inner(), brain_addr()).await {
+        Some(addr) => addr,
+        None => {
+            return HttpResponse::new(StatusCode::NOT_FOUND);
+        }
+    };
+
+    match node_addr.send(GetOutputConfig).await {
+        Ok(config) => json_response(StatusCode::OK, &config),
+        Err(err) => {
+            error_response(&err.into_app_err("failed to get output config", AppErrorKind::Api, &[]))
+        }
+    }
+}
+
+#[get("/data/node/{source_name}/processor-info")]
+pub async fn get_node_processor_info(source_name: web::Path<SourceName>) -> HttpResponse {
+    let node_addr = match get_node_by_source_name(source_name.into_inner(), brain_addr()).await {
+        Some(addr) => addr,
+        None => {
+            return HttpResponse::new(StatusCode::NOT_FOUND);
+        }
+    };
+
+    match node_addr.send(GetProcessorInfo).await {
+        Ok(info) => json_response(StatusCode::OK, &info),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to get processor info",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+/// Searches the stored audio and appends up to `limit` matches to the queue of a node in relevance
+/// order
+#[post("/data/node/{source_name}/queue/from-search")]
+pub async fn queue_from_search(
+    source_name: web::Path<SourceName>,
+    params: web::Json<QueueFromSearchParams>,
+) -> HttpResponse {
+    let node_addr = match get_node_by_source_name(source_name.into_inner(), brain_addr()).await {
+        Some(addr) => addr,
+        None => {
+            return HttpResponse::new(StatusCode::NOT_FOUND);
+        }
+    };
+
+    let items = match search_audio_metadata_from_db(&params.query, params.limit).await {
+        Ok(items) => items,
+        Err(err) => return error_response(&err),
+    };
+
+    match node_addr.send(EnqueueStoredItems(items)).await {
+        Ok(Ok(enqueued)) => json_response(StatusCode::OK, &enqueued),
+        Ok(Err(err)) => error_response(&err),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to queue search results",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+#[get("/data/logs")]
+pub async fn get_logs(
+    req: HttpRequest,
+    web::Query(LogTailParams { lines }): web::Query<LogTailParams>,
+) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
+    match tail_log_file(Path::new(LOG_FILE), lines.unwrap_or(100)).into_app_err(
+        "failed to read log file",
+        AppErrorKind::LocalData,
+        &[&format!("FILE: {LOG_FILE}")],
+    ) {
+        Ok(lines) => json_response(StatusCode::OK, &lines),
+        Err(err) => error_response(&err),
+    }
+}
+
+/// Output devices of the host a node can be added for, empty if the host has no output devices
+#[get("/data/devices")]
+pub async fn get_output_devices() -> HttpResponse {
+    json_response(StatusCode::OK, &list_output_devices())
+}
+
+/// Settings the server was started with, secrets are redacted
+#[get("/data/config")]
+pub async fn get_runtime_config(req: HttpRequest) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
+    match RUNTIME_CONFIG.get() {
+        Some(config) => json_response(StatusCode::OK, config),
+        None => error_response(&AppError::new(
+            AppErrorKind::Api,
+            "runtime config was not collected",
+            &[],
+        )),
+    }
+}
+
+/// Current content of the state recovery file as JSON
+#[get("/data/state/export")]
+pub async fn export_state(req: HttpRequest) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
+    match restore_state_addr().send(GetRecoveryState).await {
+        Ok(state) => json_response(StatusCode::OK, &state),
+        Err(err) => error_response(&err.into_app_err(
+            "failed to get recovery state",
+            AppErrorKind::Api,
+            &[],
+        )),
+    }
+}
+
+/// Replaces node queues, the download queue and settings with a previously exported state,
+/// responds with the parts of the state that couldn't be restored
+#[post("/data/state/import")]
+pub async fn import_state(
+    req: HttpRequest,
+    state: web::Json<AppStateRecoveryInfo>,
+) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
+    match restore_imported_state(state.into_inner()).await {
+        Ok(report) => json_response(StatusCode::OK, &report),
+        Err(err) => error_response(&err),
+    }
+}
+
+/// Totals of the stored library and the number of tracks whose audio file is missing
+#[get("/data/stats")]
+pub async fn get_library_stats() -> HttpResponse {
+    match library_stats().await {
+        Ok(stats) => json_response(StatusCode::OK, &stats),
+        Err(err) => error_response(&err),
+    }
+}
+
+#[get("/data/storage")]
+pub async fn get_storage_usage(params: web::Query<StorageUsageParams>) -> HttpResponse {
+    // walking the audio directory blocks, the cached result is only read behind a lock
+    let refresh = params.refresh;
+    match web::block(move || storage_usage(refresh)).await {
+        Ok(Ok(usage)) => json_response(StatusCode::OK, &usage),
+        Ok(Err(err)) => error_response(&err),
+        Err(err) => {
+            error_response(&err.into_app_err("failed to run blocking task", AppErrorKind::Api, &[]))
+        }
+    }
+}
+
+/// Finds audio files without metadata and, if requested, metadata without audio files
+///
+/// Nothing is deleted unless `confirm` is set, the response lists what was found either way.
+#[post("/data/maintenance/purge-orphans")]
+pub async fn purge_orphaned_audio(
+    req: HttpRequest,
+    params: web::Json<PurgeOrphansParams>,
+) -> HttpResponse {
+    if !is_admin_request(&req) {
+        return HttpResponse::new(StatusCode::UNAUTHORIZED);
+    }
+
+    match purge_orphans(params.confirm, params.include_missing_files).await {
+        Ok(report) => json_response(StatusCode::OK, &report),
+        Err(err) => error_response(&err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        database::store_data::{
+            delete_audio_metadata, store_audio_metadata_if_not_exists, store_playlist_if_not_exists,
+        },
+        tests_utils::test_db_pool,
+    };
+
+    use super::*;
+    use actix_web::{
+        test::{call_service, init_service, read_body_json, TestRequest},
+        App,
+    };
+    use pretty_assertions::assert_eq;
+
+    #[actix_web::test]
+    async fn test_total_count_header() {
+        let total = requested_total(true, async { Ok(42) }).await.unwrap();
+        let response = with_total_count(json_response(StatusCode::OK, &[1, 2]), total);
+        assert_eq!(
+            response.headers().get(TOTAL_COUNT_HEADER).unwrap(),
+            &HeaderValue::from(42)
+        );
+
+        // the total isn't counted unless it was requested
+        let total = requested_total(false, async { panic!("counted") })
+            .await
+            .unwrap();
+        let response = with_total_count(json_response(StatusCode::OK, &[1, 2]), total);
+        assert!(response.headers().get(TOTAL_COUNT_HEADER).is_none());
+    }
+
+    #[test]
+    fn test_delete_audio_status() {
+        let response = |status| {
+            delete_audio_response(&AudioDeleteResult {
+                uid: "a".into(),
+                status,
+            })
+            .status()
+        };
+
+        assert_eq!(response(AudioDeleteStatus::Deleted), StatusCode::OK);
+        assert_eq!(response(AudioDeleteStatus::NotFound), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response(AudioDeleteStatus::InUse("living-room".into())),
+            StatusCode::CONFLICT
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_batch_delete_requires_admin() {
+        let app = init_service(App::new().service(batch_delete_audio)).await;
+
+        let req = TestRequest::post()
+            .uri("/data/audio/batch-delete")
+            .set_json(serde_json::json!({ "uids": ["a"], "force": true }))
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_delete_audio_requires_admin() {
+        let app = init_service(App::new().service(delete_audio)).await;
+
+        let req = TestRequest::delete().uri("/data/audio/a").to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // a wrong token is rejected the same way
+        let req = TestRequest::delete()
+            .uri("/data/audio/a")
+            .insert_header(("Authorization", "Bearer wrong"))
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_purge_orphans_requires_admin() {
+        let app = init_service(App::new().service(purge_orphaned_audio)).await;
+
+        let req = TestRequest::post()
+            .uri("/data/maintenance/purge-orphans")
+            .set_json(serde_json::json!({ "confirm": true }))
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_playlist_is_not_found() {
+        test_db_pool().await;
+        store_playlist_if_not_exists(&ItemUid("test_playlist_known"))
+            .await
+            .unwrap();
+
+        let app = init_service(App::new().service(get_audio_in_playlist)).await;
+
+        let req = TestRequest::get()
+            .uri("/data/playlists/test_playlist_unknown")
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+
+        // an empty playlist is still found
+        let req = TestRequest::get()
+            .uri("/data/playlists/test_playlist_known")
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body: serde_json::Value = read_body_json(res).await;
+        assert_eq!(body, serde_json::json!([]));
+    }
+
+    #[actix_web::test]
+    async fn test_favorite_toggle_is_idempotent() {
+        test_db_pool().await;
+
+        let uid = ItemUid(Arc::from("test_favorite_toggle"));
+        let metadata = AudioMetadata {
+            name: Some(String::from("Favorite")).into(),
+            author: Option::<String>::None.into(),
+            duration: Some(60),
+            cover_art_url: Option::<String>::None.into(),
+            gain_db: None,
+        };
+        store_audio_metadata_if_not_exists(&uid, &metadata)
+            .await
+            .unwrap();
+
+        let app = init_service(
+            App::new()
+                .service(add_audio_favorite)
+                .service(remove_audio_favorite),
+        )
+        .await;
+        let uri = "/data/audio/test_favorite_toggle/favorite";
+
+        for (req, favorite) in [
+            (TestRequest::post(), true),
+            (TestRequest::post(), true),
+            (TestRequest::delete(), false),
+            (TestRequest::delete(), false),
+        ] {
+            let res = call_service(&app, req.uri(uri).to_request()).await;
+            assert_eq!(res.status(), StatusCode::OK);
+
+            let body: serde_json::Value = read_body_json(res).await;
+            assert_eq!(
+                body,
+                serde_json::json!({ "uid": "test_favorite_toggle", "favorite": favorite })
+            );
+        }
+
+        delete_audio_metadata(&[uid]).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_favorite_of_unknown_audio_is_not_found() {
+        test_db_pool().await;
+
+        let app = init_service(
+            App::new()
+                .service(add_audio_favorite)
+                .service(remove_audio_favorite),
+        )
+        .await;
+        let uri = "/data/audio/test_favorite_unknown/favorite";
+
+        for req in [TestRequest::post(), TestRequest::delete()] {
+            let res = call_service(&app, req.uri(uri).to_request()).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_storage_usage_is_served() {
+        let app = init_service(App::new().service(get_storage_usage)).await;
+
+        for uri in ["/data/storage?refresh=true", "/data/storage"] {
+            let res = call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(res.status(), StatusCode::OK);
+
+            let body: serde_json::Value = read_body_json(res).await;
+            assert!(body["totalBytes"].is_u64());
+            assert!(body["fileCount"].is_u64());
         }
-        Err(err) => HttpResponse::InternalServerError()
-            .body(serde_json::to_string(&err).unwrap_or("oops something went wrong".to_owned())),
     }
 }
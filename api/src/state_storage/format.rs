@@ -0,0 +1,250 @@
+use std::path::{Path, PathBuf};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::error::{AppError, AppErrorKind, IntoAppError};
+
+use super::{
+    migration::{migrate, STATE_VERSION},
+    AppStateRecoveryInfo,
+};
+
+/// Selects the format of the state recovery file, either `bincode` or `json`
+pub const STATE_FORMAT_ENV: &str = "STATE_RECOVERY_FORMAT";
+
+/// Encoding of the state recovery file
+///
+/// Bincode keeps the file small, JSON files can be read and edited by hand. JSON files always end
+/// with `.json`, so the format of an existing file is known from its extension.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StateFormat {
+    #[default]
+    Bincode,
+    Json,
+}
+
+impl StateFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "bincode" | "bin" => Some(Self::Bincode),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// format set through `STATE_RECOVERY_FORMAT`, bincode if it isn't set
+    pub fn from_env() -> Self {
+        let Ok(value) = dotenv::var(STATE_FORMAT_ENV) else {
+            return Self::default();
+        };
+
+        Self::from_name(&value).unwrap_or_else(|| {
+            log::warn!("invalid value '{value}' for '{STATE_FORMAT_ENV}', using bincode");
+            Self::default()
+        })
+    }
+
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Bincode,
+        }
+    }
+
+    /// `path` with the extension of this format, bincode files have none
+    pub fn file_path(self, path: &Path) -> PathBuf {
+        match self {
+            Self::Bincode => path.with_extension(""),
+            Self::Json => path.with_extension("json"),
+        }
+    }
+
+    pub fn other(self) -> Self {
+        match self {
+            Self::Bincode => Self::Json,
+            Self::Json => Self::Bincode,
+        }
+    }
+
+    /// Writes the state behind the current [`STATE_VERSION`]
+    pub fn encode(self, state: &AppStateRecoveryInfo) -> Result<Vec<u8>, AppError> {
+        let versioned = VersionedState {
+            version: STATE_VERSION,
+            state,
+        };
+
+        let bytes = match self {
+            Self::Bincode => bincode::serialize(&versioned).map_err(|err| err.to_string()),
+            Self::Json => serde_json::to_vec_pretty(&versioned).map_err(|err| err.to_string()),
+        };
+
+        bytes.into_app_err(
+            "failed to encode state",
+            AppErrorKind::LocalData,
+            &[&format!("FORMAT: {self:?}")],
+        )
+    }
+
+    /// Reads state of the current version, state written by older versions is migrated
+    pub fn decode(self, bytes: &[u8]) -> Result<AppStateRecoveryInfo, AppError> {
+        match self.deserialize::<VersionedState<AppStateRecoveryInfo>>(bytes) {
+            Some(VersionedState {
+                version: STATE_VERSION,
+                state,
+            }) => return Ok(state),
+            Some(VersionedState { version, .. }) => {
+                log::warn!("state has unknown version {version}, trying to migrate it")
+            }
+            None => {}
+        }
+
+        migrate(self, bytes).ok_or_else(|| {
+            AppError::new(
+                AppErrorKind::LocalData,
+                "failed to decode state",
+                &[
+                    &format!("FORMAT: {self:?}"),
+                    &format!("VERSION: {STATE_VERSION}"),
+                ],
+            )
+        })
+    }
+
+    pub(super) fn deserialize<T: DeserializeOwned>(self, bytes: &[u8]) -> Option<T> {
+        match self {
+            Self::Bincode => bincode::deserialize(bytes).ok(),
+            Self::Json => serde_json::from_slice(bytes).ok(),
+        }
+    }
+}
+
+/// Layout of the state recovery file, the version comes first so it can be read without knowing the
+/// layout of the state
+#[derive(Debug, Deserialize, Serialize)]
+struct VersionedState<S> {
+    version: u32,
+    state: S,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        audio_playback::audio_player::{PlaybackState, RepeatMode},
+        downloader::download_identifier::ItemUid,
+        state_storage::{AudioStateInfo, DownloadStateInfo, GlobalSettings},
+    };
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn test_state() -> AppStateRecoveryInfo {
+        AppStateRecoveryInfo {
+            audio_info: HashMap::from([(
+                "test".into(),
+                AudioStateInfo {
+                    playback_state: PlaybackState::Playing,
+                    current_queue_index: 1,
+                    audio_progress: 0.5,
+                    audio_volume: 0.8,
+                    max_volume: 1.0,
+                    auto_advance: true,
+                    resume_playback: false,
+                    pause_on_device_loss: false,
+                    queue_label: None,
+                    equalizer_bands: vec![1.5, -1.0],
+                    fade_in_ms: 0,
+                    normalization_enabled: true,
+                    repeat_mode: RepeatMode::All,
+                    crossfade_ms: 0,
+                    queue: vec![ItemUid("a".into()), ItemUid("b".into())],
+                    restored_queue: vec![],
+                },
+            )]),
+            download_info: DownloadStateInfo {
+                queue: vec![],
+                restored: false,
+            },
+            settings: GlobalSettings {
+                default_volume: 0.4,
+                download_rate_limit: Some("500K".into()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let state = test_state();
+
+        for format in [StateFormat::Bincode, StateFormat::Json] {
+            let bytes = format.encode(&state).unwrap();
+            let decoded = format.decode(&bytes).unwrap();
+
+            let (info, decoded_info) = (
+                state.audio_info.get("test").unwrap(),
+                decoded.audio_info.get("test").unwrap(),
+            );
+            assert_eq!(info.current_queue_index, decoded_info.current_queue_index);
+            assert_eq!(info.audio_progress, decoded_info.audio_progress);
+            assert_eq!(info.audio_volume, decoded_info.audio_volume);
+            assert_eq!(info.equalizer_bands, decoded_info.equalizer_bands);
+            assert_eq!(info.repeat_mode, decoded_info.repeat_mode);
+            assert_eq!(
+                info.queue
+                    .iter()
+                    .map(|uid| uid.0.clone())
+                    .collect::<Vec<_>>(),
+                decoded_info
+                    .queue
+                    .iter()
+                    .map(|uid| uid.0.clone())
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(state.settings, decoded.settings);
+        }
+    }
+
+    #[test]
+    fn test_formats_are_not_interchangeable() {
+        let state = test_state();
+
+        let json = StateFormat::Json.encode(&state).unwrap();
+        assert!(StateFormat::Bincode.decode(&json).is_err());
+
+        let bin = StateFormat::Bincode.encode(&state).unwrap();
+        assert!(StateFormat::Json.decode(&bin).is_err());
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            StateFormat::from_path(Path::new("dev/state-recovery-info")),
+            StateFormat::Bincode
+        );
+        assert_eq!(
+            StateFormat::from_path(Path::new("dev/state-recovery-info.json")),
+            StateFormat::Json
+        );
+
+        let path = Path::new("dev/state-recovery-info");
+        assert_eq!(
+            StateFormat::Json.file_path(path),
+            PathBuf::from("dev/state-recovery-info.json")
+        );
+        assert_eq!(
+            StateFormat::Bincode.file_path(&StateFormat::Json.file_path(path)),
+            PathBuf::from(path)
+        );
+    }
+
+    #[test]
+    fn test_format_from_name() {
+        assert_eq!(StateFormat::from_name("JSON"), Some(StateFormat::Json));
+        assert_eq!(
+            StateFormat::from_name(" bincode "),
+            Some(StateFormat::Bincode)
+        );
+        assert_eq!(StateFormat::from_name("yaml"), None);
+    }
+}
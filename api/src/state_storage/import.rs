@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use actix::Addr;
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::{
+    brain::{
+        brain_server::{GetAudioNodeMessage, SetDefaultVolume, SetDownloadRateLimit},
+        queue_clone::retain_existing,
+    },
+    brain_addr,
+    downloader::{actor::RestoreQueue, rate_limit::validate_rate_limit},
+    downloader_addr,
+    error::{AppError, AppErrorKind, IntoAppError},
+    node::node_server::{sync_actor::RestoreNodeState, AudioNode, SourceName},
+};
+
+use super::{AppStateRecoveryInfo, AudioStateInfo, DownloadStateInfo};
+
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct StateImportReport {
+    /// sources whose queue and playback state were replaced
+    #[ts(type = "Array<string>")]
+    pub restored_sources: Vec<SourceName>,
+    /// sources of the imported state that don't exist or are unavailable on this server
+    #[ts(type = "Array<string>")]
+    pub unknown_sources: Vec<SourceName>,
+    /// uids of queue items that were dropped because their audio isn't stored on this server
+    #[ts(type = "Array<string>")]
+    pub missing_audio: Vec<Arc<str>>,
+    /// number of queued downloads that were dropped because their source doesn't exist
+    pub skipped_downloads: usize,
+}
+
+/// Applies an exported state to the running server, as if the server had been restarted with it
+///
+/// Everything is validated before anything is applied. Node queues, the download queue and the
+/// global settings are replaced, nodes that aren't part of the imported state keep their state.
+pub async fn restore_imported_state(
+    mut state: AppStateRecoveryInfo,
+) -> Result<StateImportReport, AppError> {
+    let download_rate_limit = state
+        .settings
+        .download_rate_limit
+        .as_deref()
+        .map(validate_rate_limit)
+        .transpose()?;
+
+    let mut report = StateImportReport::default();
+    let mut nodes: Vec<(Addr<AudioNode>, AudioStateInfo)> = vec![];
+
+    for (source_name, mut audio_state) in state.audio_info.drain() {
+        let node_addr = brain_addr()
+            .send(GetAudioNodeMessage {
+                source_name: source_name.clone(),
+            })
+            .await
+            .into_app_err("failed to get audio node", AppErrorKind::Api, &[])?;
+
+        let Some(node_addr) = node_addr else {
+            report.unknown_sources.push(source_name);
+            continue;
+        };
+
+        let missing_metadata = audio_state.restore_queue().await;
+        let (_, _, head, head_has_metadata) = retain_existing(
+            audio_state.queue.iter().collect::<Vec<_>>(),
+            audio_state.current_queue_index,
+            |uid| !missing_metadata.contains(&uid.0),
+        );
+
+        // the metadata might have been imported without the audio files
+        let (queue, missing_files, head, head_has_file) = retain_existing(
+            std::mem::take(&mut audio_state.restored_queue),
+            head,
+            |item| item.locator.is_file(),
+        );
+        report.missing_audio.extend(missing_metadata);
+        report.missing_audio.extend(
+            missing_files
+                .into_iter()
+                .map(|item| Arc::clone(&item.identifier.0)),
+        );
+
+        // the head stays on the same item, or the next one if it was dropped
+        audio_state.restored_queue = queue;
+        audio_state.current_queue_index = head;
+        if !(head_has_metadata && head_has_file) {
+            audio_state.audio_progress = 0.0;
+        }
+
+        report.restored_sources.push(source_name);
+        nodes.push((node_addr, audio_state));
+    }
+
+    let download_queue = DownloadStateInfo::restore_queue(
+        &state.download_info.queue,
+        brain_addr().clone().recipient(),
+    )
+    .await;
+    report.skipped_downloads = state.download_info.queue.len() - download_queue.len();
+
+    for (node_addr, audio_state) in nodes {
+        node_addr.do_send(RestoreNodeState(audio_state));
+    }
+
+    downloader_addr().do_send(RestoreQueue(download_queue));
+
+    brain_addr()
+        .send(SetDefaultVolume(state.settings.default_volume))
+        .await
+        .into_app_err("failed to set default volume", AppErrorKind::Api, &[])?;
+    brain_addr()
+        .send(SetDownloadRateLimit(download_rate_limit))
+        .await
+        .into_app_err("failed to set download rate limit", AppErrorKind::Api, &[])??;
+
+    report.restored_sources.sort();
+    report.unknown_sources.sort();
+    report.missing_audio.sort();
+
+    log::info!("imported state, REPORT: {report:?}");
+
+    Ok(report)
+}
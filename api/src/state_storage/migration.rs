@@ -0,0 +1,190 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio_playback::audio_player::PlaybackState,
+    downloader::{
+        actor::SerializableDownloadAudioRequest, download_identifier::ItemUid,
+        DownloadRequiredInformation,
+    },
+    node::node_server::SourceName,
+};
+
+use super::{
+    format::StateFormat, AppStateRecoveryInfo, AudioStateInfo, DownloadStateInfo, GlobalSettings,
+};
+
+/// Written in front of the state recovery file
+///
+/// Increase it whenever the layout of [`AppStateRecoveryInfo`] changes, keep a copy of the old
+/// layout and add a migration from it to [`migrate`].
+pub const STATE_VERSION: u32 = 1;
+
+/// Files written before the version was added
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct StateV0 {
+    download_info: DownloadStateInfoV0,
+    audio_info: HashMap<SourceName, AudioStateInfoV0>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct DownloadStateInfoV0 {
+    queue: Vec<SerializableDownloadAudioRequestV0>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct SerializableDownloadAudioRequestV0 {
+    source_name: Option<SourceName>,
+    required_info: DownloadRequiredInformation,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct AudioStateInfoV0 {
+    playback_state: PlaybackState,
+    current_queue_index: usize,
+    audio_progress: f64,
+    audio_volume: f32,
+    queue: Vec<ItemUid<Arc<str>>>,
+}
+
+/// fields that were added later get their defaults, downloads are stored in the audio directory
+fn migrate_v0(state: StateV0) -> AppStateRecoveryInfo {
+    let download_queue = state
+        .download_info
+        .queue
+        .into_iter()
+        .map(|request| SerializableDownloadAudioRequest {
+            source_name: request.source_name,
+            required_info: request.required_info,
+            folder: None,
+        })
+        .collect();
+
+    let audio_info = state
+        .audio_info
+        .into_iter()
+        .map(|(source_name, info)| {
+            let info = AudioStateInfo {
+                playback_state: info.playback_state,
+                current_queue_index: info.current_queue_index,
+                audio_progress: info.audio_progress,
+                audio_volume: info.audio_volume,
+                queue: info.queue,
+                ..Default::default()
+            };
+
+            (source_name, info)
+        })
+        .collect();
+
+    AppStateRecoveryInfo {
+        download_info: DownloadStateInfo {
+            queue: download_queue,
+            restored: false,
+        },
+        audio_info,
+        settings: GlobalSettings::default(),
+    }
+}
+
+/// Tries to read `bytes` as one of the older layouts, newest first
+pub fn migrate(format: StateFormat, bytes: &[u8]) -> Option<AppStateRecoveryInfo> {
+    let state = format.deserialize::<StateV0>(bytes).map(migrate_v0)?;
+    log::info!("migrated state from version 0 to version {STATE_VERSION}");
+
+    Some(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio_playback::audio_player::RepeatMode;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn v0_state() -> StateV0 {
+        StateV0 {
+            audio_info: HashMap::from([(
+                "living_room".into(),
+                AudioStateInfoV0 {
+                    playback_state: PlaybackState::Paused,
+                    current_queue_index: 2,
+                    audio_progress: 0.25,
+                    audio_volume: 0.6,
+                    queue: vec![
+                        ItemUid("first".into()),
+                        ItemUid("second".into()),
+                        ItemUid("third".into()),
+                    ],
+                },
+            )]),
+            download_info: DownloadStateInfoV0 {
+                queue: vec![SerializableDownloadAudioRequestV0 {
+                    source_name: Some("living_room".into()),
+                    required_info: DownloadRequiredInformation::StoredLocally {
+                        uid: "fourth".into(),
+                    },
+                }],
+            },
+        }
+    }
+
+    #[test]
+    fn test_v0_state_is_migrated() {
+        // version 0 files contain only the state, without a version in front
+        let bytes = bincode::serialize(&v0_state()).unwrap();
+        let decoded = StateFormat::Bincode.decode(&bytes).unwrap();
+
+        let info = &decoded.audio_info["living_room"];
+        let queue: Vec<_> = info.queue.iter().map(|uid| uid.0.to_string()).collect();
+        assert_eq!(queue, vec!["first", "second", "third"]);
+        assert_eq!(info.playback_state, PlaybackState::Paused);
+        assert_eq!(info.current_queue_index, 2);
+        assert_eq!(info.audio_progress, 0.25);
+        assert_eq!(info.audio_volume, 0.6);
+
+        // fields that were added later
+        assert_eq!(info.max_volume, 1.0);
+        assert!(info.auto_advance);
+        assert!(!info.resume_playback);
+        assert!(!info.pause_on_device_loss);
+        assert_eq!(info.queue_label, None);
+        assert!(info.equalizer_bands.is_empty());
+        assert_eq!(info.fade_in_ms, 0);
+        assert!(info.normalization_enabled);
+        assert_eq!(info.repeat_mode, RepeatMode::Off);
+        assert_eq!(info.crossfade_ms, 0);
+        assert!(info.restored_queue.is_empty());
+        assert_eq!(decoded.settings, GlobalSettings::default());
+
+        assert_eq!(
+            decoded.download_info.queue,
+            vec![SerializableDownloadAudioRequest {
+                source_name: Some("living_room".into()),
+                required_info: DownloadRequiredInformation::StoredLocally {
+                    uid: "fourth".into()
+                },
+                folder: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_version_is_written_first() {
+        let state = migrate_v0(v0_state());
+
+        let bytes = StateFormat::Bincode.encode(&state).unwrap();
+        assert_eq!(&bytes[..4], STATE_VERSION.to_le_bytes());
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&StateFormat::Json.encode(&state).unwrap()).unwrap();
+        assert_eq!(json["version"], STATE_VERSION);
+    }
+
+    #[test]
+    fn test_invalid_state_is_not_decoded() {
+        assert!(StateFormat::Bincode.decode(&[7, 0, 0]).is_err());
+        assert!(StateFormat::Json.decode(br#"{"version": 1}"#).is_err());
+    }
+}
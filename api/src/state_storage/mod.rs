@@ -1,10 +1,13 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
 
 use actix::Recipient;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    audio_playback::{audio_item::AudioPlayerQueueItem, audio_player::PlaybackState},
+    audio_playback::{
+        audio_item::AudioPlayerQueueItem,
+        audio_player::{PlaybackState, RepeatMode},
+    },
     brain::brain_server::GetAudioNodeMessage,
     database::fetch_data::get_audio_metadata_from_db,
     downloader::{
@@ -14,12 +17,48 @@ use crate::{
     node::node_server::SourceName,
 };
 
+pub mod format;
+pub mod import;
+pub mod migration;
 pub mod restore_state_actor;
 
+/// Sets how often the state recovery file is written, e.g. `3s` or `500ms`
+pub const CHECKPOINT_INTERVAL_ENV: &str = "STATE_CHECKPOINT_INTERVAL";
+pub const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Interval in which the state is written to the state recovery file if it has changed, a crash
+/// loses at most one interval of playback progress
+pub fn checkpoint_interval() -> Duration {
+    dotenv::var(CHECKPOINT_INTERVAL_ENV)
+        .ok()
+        .and_then(|value| parse_duration::parse(value.trim()).ok())
+        .filter(|interval| !interval.is_zero())
+        .unwrap_or(DEFAULT_CHECKPOINT_INTERVAL)
+}
+
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct AppStateRecoveryInfo {
     pub download_info: DownloadStateInfo,
     pub audio_info: HashMap<SourceName, AudioStateInfo>,
+    pub settings: GlobalSettings,
+}
+
+/// Settings that apply to the whole app instead of a single node
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GlobalSettings {
+    /// volume of nodes that don't have any restored state
+    pub default_volume: f32,
+    /// max download rate in bytes per second (e.g. `2M`), downloads are not limited if not set
+    pub download_rate_limit: Option<Arc<str>>,
+}
+
+impl Default for GlobalSettings {
+    fn default() -> Self {
+        Self {
+            default_volume: 1.0,
+            download_rate_limit: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -28,6 +67,24 @@ pub struct AudioStateInfo {
     pub current_queue_index: usize,
     pub audio_progress: f64,
     pub audio_volume: f32,
+    pub max_volume: f32,
+    /// whether the next track starts playing once the current one has ended
+    pub auto_advance: bool,
+    /// whether tracks continue at their stored resume position
+    pub resume_playback: bool,
+    /// whether playback is paused while the audio device is lost
+    pub pause_on_device_loss: bool,
+    /// label of the queue for display
+    pub queue_label: Option<Arc<str>>,
+    /// gain of every equalizer band in dB, empty if the equalizer is flat
+    pub equalizer_bands: Vec<f32>,
+    /// fade in at the start of every track
+    pub fade_in_ms: u32,
+    /// whether the measured loudness of tracks is normalized
+    pub normalization_enabled: bool,
+    pub repeat_mode: RepeatMode,
+    /// overlap of the end of a track with the start of the next one
+    pub crossfade_ms: u32,
     pub queue: Vec<ItemUid<Arc<str>>>,
 
     #[serde(skip_serializing, skip_deserializing)]
@@ -38,6 +95,16 @@ impl Default for AudioStateInfo {
     fn default() -> Self {
         Self {
             audio_volume: 1.0,
+            max_volume: 1.0,
+            auto_advance: true,
+            resume_playback: false,
+            pause_on_device_loss: false,
+            queue_label: None,
+            equalizer_bands: Default::default(),
+            fade_in_ms: 0,
+            normalization_enabled: true,
+            repeat_mode: Default::default(),
+            crossfade_ms: 0,
             playback_state: Default::default(),
             current_queue_index: Default::default(),
             audio_progress: Default::default(),
@@ -74,6 +141,7 @@ impl DownloadStateInfo {
                         addr: addr.into(),
                         source_name: Some(source_name.clone()),
                         required_info: request.required_info.clone(),
+                        folder: request.folder.clone(),
                     }),
                     Ok(None) => {
                         log::warn!(
@@ -94,8 +162,11 @@ impl DownloadStateInfo {
 }
 
 impl AudioStateInfo {
-    async fn restore_queue(&mut self) {
+    /// loads the metadata of all queue items, returns the uids of items that were skipped because
+    /// they have no metadata
+    async fn restore_queue(&mut self) -> Vec<Arc<str>> {
         let mut queue = Vec::with_capacity(self.queue.len());
+        let mut skipped = vec![];
 
         for uid in self.queue.iter() {
             match get_audio_metadata_from_db(uid).await {
@@ -106,20 +177,25 @@ impl AudioStateInfo {
                         identifier: uid.clone(),
                         locator: path,
                         metadata,
+                        gain_db: 0.0,
+                        fade_in_ms: None,
                     })
                 }
                 Ok(None) => {
                     log::warn!(
                         "failed to audio queue item with {uid:?}\nno metadata found for uid"
                     );
+                    skipped.push(Arc::clone(&uid.0));
                 }
                 Err(err) => {
                     log::warn!("failed to audio queue item with {uid:?}\nERROR: {err}");
+                    skipped.push(Arc::clone(&uid.0));
                 }
             };
         }
 
         self.restored_queue = queue;
+        skipped
     }
 }
 
@@ -140,13 +216,27 @@ mod tests {
                     current_queue_index: 3,
                     audio_progress: 0.43,
                     audio_volume: 0.23,
+                    max_volume: 0.5,
+                    auto_advance: false,
+                    resume_playback: true,
+                    pause_on_device_loss: true,
+                    queue_label: Some("Sunday Jazz".into()),
+                    equalizer_bands: vec![3.0, 0.0, -2.5],
+                    fade_in_ms: 250,
+                    normalization_enabled: false,
+                    repeat_mode: RepeatMode::One,
+                    crossfade_ms: 4000,
                     queue: vec![ItemUid("uid".into())],
                     restored_queue: vec![],
                 },
             )]),
             download_info: DownloadStateInfo {
                 queue: vec![],
-                restored_queue: vec![],
+                restored: false,
+            },
+            settings: GlobalSettings {
+                default_volume: 0.7,
+                download_rate_limit: Some("2M".into()),
             },
         };
 
@@ -161,6 +251,50 @@ mod tests {
             state.audio_info.get("test").unwrap().audio_volume,
             decoded.audio_info.get("test").unwrap().audio_volume
         );
+        assert_eq!(
+            state.audio_info.get("test").unwrap().max_volume,
+            decoded.audio_info.get("test").unwrap().max_volume
+        );
+        assert_eq!(
+            state.audio_info.get("test").unwrap().auto_advance,
+            decoded.audio_info.get("test").unwrap().auto_advance
+        );
+        assert_eq!(
+            state.audio_info.get("test").unwrap().resume_playback,
+            decoded.audio_info.get("test").unwrap().resume_playback
+        );
+        assert_eq!(
+            state.audio_info.get("test").unwrap().pause_on_device_loss,
+            decoded.audio_info.get("test").unwrap().pause_on_device_loss
+        );
+        assert_eq!(
+            state.audio_info.get("test").unwrap().queue_label,
+            decoded.audio_info.get("test").unwrap().queue_label
+        );
+        assert_eq!(
+            state.audio_info.get("test").unwrap().equalizer_bands,
+            decoded.audio_info.get("test").unwrap().equalizer_bands
+        );
+        assert_eq!(
+            state.audio_info.get("test").unwrap().fade_in_ms,
+            decoded.audio_info.get("test").unwrap().fade_in_ms
+        );
+        assert_eq!(
+            state.audio_info.get("test").unwrap().normalization_enabled,
+            decoded
+                .audio_info
+                .get("test")
+                .unwrap()
+                .normalization_enabled
+        );
+        assert_eq!(
+            state.audio_info.get("test").unwrap().repeat_mode,
+            decoded.audio_info.get("test").unwrap().repeat_mode
+        );
+        assert_eq!(
+            state.audio_info.get("test").unwrap().crossfade_ms,
+            decoded.audio_info.get("test").unwrap().crossfade_ms
+        );
         assert_eq!(
             state.audio_info.get("test").unwrap().audio_progress,
             decoded.audio_info.get("test").unwrap().audio_progress
@@ -169,5 +303,6 @@ mod tests {
             state.download_info.queue.len(),
             decoded.download_info.queue.len()
         );
+        assert_eq!(state.settings, decoded.settings);
     }
 }
@@ -1,33 +1,42 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
 use actix::{
-    Actor, ActorFutureExt, AsyncContext, Context, Handler, Message, Recipient, ResponseActFuture,
-    WrapFuture,
+    Actor, ActorFutureExt, AsyncContext, Context, Handler, Message, MessageResult, Recipient,
+    ResponseActFuture, WrapFuture,
 };
 
 use crate::{
     brain::brain_server::GetAudioNodeMessage,
     downloader::{self, actor::SerializableDownloadAudioRequest},
-    error::AppError,
+    error::{AppError, AppErrorKind, IntoAppError},
     node::node_server::SourceName,
     path::state_recovery_file_path,
     utils::log_msg_received,
 };
 
-use super::{AppStateRecoveryInfo, AudioStateInfo, DownloadStateInfo};
-
-const STORE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(3000);
+use super::{
+    checkpoint_interval, format::StateFormat, AppStateRecoveryInfo, AudioStateInfo,
+    DownloadStateInfo, GlobalSettings,
+};
 
 #[derive(Debug, Default)]
 pub struct RestoreStateActor {
     current_state: AppStateRecoveryInfo,
     has_changed: bool,
+    file_path: PathBuf,
 }
 
 impl RestoreStateActor {
     pub async fn load_or_default() -> Self {
-        let mut state: AppStateRecoveryInfo = match std::fs::read(state_recovery_file_path()) {
-            Ok(bytes) => bincode::deserialize(&bytes).unwrap_or_default(),
-            Err(_) => Default::default(),
-        };
+        let file_path = state_recovery_file_path();
+        let mut state = load_state_file(&file_path).unwrap_or_else(|| {
+            log::warn!("no state could be restored, starting with the default state");
+            Default::default()
+        });
 
         for audio_state in state.audio_info.values_mut() {
             audio_state.restore_queue().await;
@@ -35,6 +44,7 @@ impl RestoreStateActor {
 
         Self {
             current_state: state,
+            file_path,
             ..Default::default()
         }
     }
@@ -44,11 +54,87 @@ impl RestoreStateActor {
     }
 
     fn store_state(&self) -> Result<(), AppError> {
-        let bin = bincode::serialize(&self.current_state).unwrap();
-        std::fs::write(state_recovery_file_path(), bin).unwrap();
+        let bytes = StateFormat::from_path(&self.file_path).encode(&self.current_state)?;
+        write_state_file(&self.file_path, &bytes).into_app_err(
+            "failed to write state recovery file",
+            AppErrorKind::LocalData,
+            &[&format!("PATH: {path}", path = self.file_path.display())],
+        )?;
 
         Ok(())
     }
+
+    /// writes the state only if it has changed since it was last stored
+    fn store_state_if_changed(&mut self) {
+        if self.has_changed {
+            let _ = self.store_state();
+            self.has_changed = false;
+        }
+    }
+}
+
+/// Replaces the file at `path` with `bytes` without leaving a partially written file behind if the
+/// process dies while writing
+///
+/// The bytes are written to a `.tmp` file first which is renamed to `path` once it is complete, the
+/// previous file is kept as `.bak`.
+fn write_state_file(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let tmp_path = with_suffix(path, "tmp");
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+
+    if path.exists() {
+        std::fs::rename(path, with_suffix(path, "bak"))?;
+    }
+
+    std::fs::rename(tmp_path, path)
+}
+
+/// Reads the state stored at `path` or its backup, falls back to the files of the other format so
+/// the state is kept when `STATE_RECOVERY_FORMAT` changes
+fn load_state_file(path: &Path) -> Option<AppStateRecoveryInfo> {
+    let format = StateFormat::from_path(path);
+
+    [format, format.other()].into_iter().find_map(|format| {
+        let path = format.file_path(path);
+        read_state_file(&path, format)
+            .or_else(|| read_state_file(&with_suffix(&path, "bak"), format))
+    })
+}
+
+/// State that can't be read or migrated is copied next to the file before it gets overwritten
+fn read_state_file(path: &Path, format: StateFormat) -> Option<AppStateRecoveryInfo> {
+    let bytes = std::fs::read(path).ok()?;
+
+    log::info!("restoring state from '{path}'", path = path.display());
+    match format.decode(&bytes) {
+        Ok(state) => Some(state),
+        Err(_) => {
+            let unreadable_path = with_suffix(path, "unreadable");
+            log::error!(
+                "STATE COULD NOT BE RESTORED from '{path}', the file is kept at '{unreadable}'",
+                path = path.display(),
+                unreadable = unreadable_path.display()
+            );
+
+            if let Err(err) = std::fs::copy(path, &unreadable_path) {
+                log::error!("failed to keep unreadable state\nERROR: {err}");
+            }
+
+            None
+        }
+    }
+}
+
+/// `path` with `.{suffix}` appended to its file name, e.g. `state-recovery-info.json.bak`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+
+    path.with_file_name(name)
 }
 
 impl Actor for RestoreStateActor {
@@ -78,14 +164,11 @@ impl Handler<StoreState> for RestoreStateActor {
     fn handle(&mut self, msg: StoreState, _ctx: &mut Self::Context) -> Self::Result {
         log_msg_received(&self, &msg);
 
-        if self.has_changed {
-            let _ = self.store_state();
-            self.has_changed = false;
-        }
+        self.store_state_if_changed();
 
         Box::pin(
             async {
-                actix_rt::time::sleep(STORE_INTERVAL).await;
+                actix_rt::time::sleep(checkpoint_interval()).await;
             }
             .into_actor(self)
             .map(|_, _, ctx| {
@@ -160,3 +243,130 @@ impl Handler<AudioInfoStateUpdateMessage> for RestoreStateActor {
         self.has_changed = true;
     }
 }
+
+#[derive(Debug, Message)]
+#[rtype(result = "()")]
+pub struct GlobalSettingsUpdateMessage(pub GlobalSettings);
+
+impl Handler<GlobalSettingsUpdateMessage> for RestoreStateActor {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        msg: GlobalSettingsUpdateMessage,
+        _ctx: &mut Self::Context,
+    ) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        self.current_state.settings = msg.0;
+        self.has_changed = true;
+    }
+}
+
+/// Gets the state that is stored in the state recovery file, including changes that haven't been
+/// written to the file yet
+#[derive(Debug, Message)]
+#[rtype(result = "AppStateRecoveryInfo")]
+pub struct GetRecoveryState;
+
+impl Handler<GetRecoveryState> for RestoreStateActor {
+    type Result = MessageResult<GetRecoveryState>;
+
+    fn handle(&mut self, msg: GetRecoveryState, _ctx: &mut Self::Context) -> Self::Result {
+        log_msg_received(&self, &msg);
+
+        MessageResult(self.state())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests_utils::test_dir;
+
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn state_with_volume(default_volume: f32) -> AppStateRecoveryInfo {
+        AppStateRecoveryInfo {
+            settings: GlobalSettings {
+                default_volume,
+                download_rate_limit: None,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn write(path: &Path, state: &AppStateRecoveryInfo) {
+        let bytes = StateFormat::from_path(path).encode(state).unwrap();
+        write_state_file(path, &bytes).unwrap();
+    }
+
+    #[test]
+    fn test_write_keeps_backup() {
+        let dir = test_dir("state-backup");
+        let path = dir.join("state-recovery-info");
+
+        write(&path, &state_with_volume(0.2));
+        write(&path, &state_with_volume(0.4));
+
+        assert!(!with_suffix(&path, "tmp").exists());
+        assert_eq!(load_state_file(&path).unwrap().settings.default_volume, 0.4);
+
+        let backup = std::fs::read(with_suffix(&path, "bak")).unwrap();
+        assert_eq!(
+            StateFormat::Bincode
+                .decode(&backup)
+                .unwrap()
+                .settings
+                .default_volume,
+            0.2
+        );
+    }
+
+    #[test]
+    fn test_truncated_file_falls_back_to_backup() {
+        let dir = test_dir("state-truncated");
+
+        for name in ["state-recovery-info", "state-recovery-info.json"] {
+            let path = dir.join(name);
+
+            write(&path, &state_with_volume(0.2));
+            write(&path, &state_with_volume(0.4));
+
+            // process died while writing the file in place
+            let bytes = std::fs::read(&path).unwrap();
+            std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+
+            assert_eq!(load_state_file(&path).unwrap().settings.default_volume, 0.2);
+            assert!(with_suffix(&path, "unreadable").exists());
+        }
+    }
+
+    #[test]
+    fn test_unchanged_state_is_not_written() {
+        let dir = test_dir("state-unchanged");
+        let mut actor = RestoreStateActor {
+            current_state: state_with_volume(0.2),
+            has_changed: false,
+            file_path: dir.join("state-recovery-info"),
+        };
+
+        actor.store_state_if_changed();
+        assert!(!actor.file_path.exists());
+
+        actor.has_changed = true;
+        actor.store_state_if_changed();
+        assert!(actor.file_path.exists());
+        assert!(!actor.has_changed);
+
+        std::fs::remove_file(&actor.file_path).unwrap();
+        actor.store_state_if_changed();
+        assert!(!actor.file_path.exists());
+    }
+
+    #[test]
+    fn test_missing_file_is_not_restored() {
+        let dir = test_dir("state-missing");
+        assert!(load_state_file(&dir.join("state-recovery-info")).is_none());
+    }
+}
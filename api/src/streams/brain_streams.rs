@@ -5,16 +5,20 @@ use actix_web::{get, http::StatusCode, web, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
 use crate::{
-    brain::brain_session::AudioBrainSession, brain_addr, node::node_server::AudioNodeInfo,
-    streams::deserialize_stringified_list,
+    brain::brain_session::AudioBrainSession,
+    brain_addr,
+    node::node_server::{AudioNodeInfo, SourceName},
+    streams::{deserialize_stringified_list, heartbeat_interval},
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum AudioBrainInfoStreamType {
     NodeInfo,
+    ActiveSource,
 }
 
 #[derive(Debug, Clone, Serialize, Message)]
@@ -22,17 +26,33 @@ pub enum AudioBrainInfoStreamType {
 #[rtype(result = "()")]
 pub enum AudioBrainInfoStreamMessage {
     NodeInfo(Arc<[AudioNodeInfo]>),
+    ActiveSourceChanged(ActiveSourceChange),
+}
+
+/// Sent to all brain sessions when a session changes its active source, allows clients with
+/// multiple open tabs to keep them in sync
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct ActiveSourceChange {
+    pub source_name: SourceName,
+    pub previous_source_name: Option<SourceName>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 struct StreamWantedInfoParams {
     #[serde(deserialize_with = "deserialize_stringified_list")]
     wanted_info: Arc<[AudioBrainInfoStreamType]>,
+    /// interval in which the server sends heart beat pings
+    heartbeat_interval_ms: Option<u64>,
 }
 
 pub fn get_type_of_stream_data(msg: &AudioBrainInfoStreamMessage) -> AudioBrainInfoStreamType {
     match msg {
         AudioBrainInfoStreamMessage::NodeInfo(_) => AudioBrainInfoStreamType::NodeInfo,
+        AudioBrainInfoStreamMessage::ActiveSourceChanged(_) => {
+            AudioBrainInfoStreamType::ActiveSource
+        }
     }
 }
 
@@ -42,8 +62,17 @@ async fn get_brain_stream(
     req: HttpRequest,
     stream: web::Payload,
 ) -> HttpResponse {
+    let StreamWantedInfoParams {
+        wanted_info,
+        heartbeat_interval_ms,
+    } = query.into_inner();
+
     match ws::start(
-        AudioBrainSession::new(brain_addr().clone(), query.into_inner().wanted_info),
+        AudioBrainSession::new(
+            brain_addr().clone(),
+            wanted_info,
+            heartbeat_interval(heartbeat_interval_ms),
+        ),
         &req,
         stream,
     ) {
@@ -1,5 +1,8 @@
 use core::fmt;
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use actix::Message;
 use serde::de::{self, IntoDeserializer};
@@ -7,10 +10,96 @@ use serde::de::{self, IntoDeserializer};
 pub mod brain_streams;
 pub mod node_streams;
 
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(333);
+const MIN_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_BATCH_WINDOW: Duration = Duration::from_secs(1);
+
+/// sessions whose client hasn't answered for this long are closed, raised for sessions with long
+/// heart beat intervals so a client always has a few pings to answer
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+/// interval in which sessions check if their client timed out
+pub const CLIENT_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Message)]
 #[rtype(result = "()")]
 pub struct HeartBeat;
 
+/// Interval in which heart beat pings are sent to a session, clients can request an interval when
+/// connecting which is limited to the range the server supports
+pub fn heartbeat_interval(requested_ms: Option<u64>) -> Duration {
+    requested_ms
+        .map(Duration::from_millis)
+        .map(|interval| interval.clamp(MIN_HEARTBEAT_INTERVAL, MAX_HEARTBEAT_INTERVAL))
+        .unwrap_or(DEFAULT_HEARTBEAT_INTERVAL)
+}
+
+/// Last time the client of a session was heard from, any message including pongs counts
+///
+/// Clients that vanish without a close frame never answer the heart beat pings, their session is
+/// closed once [`ClientActivity::timed_out`] so it doesn't leak.
+#[derive(Debug, Clone)]
+pub struct ClientActivity {
+    last_seen: Instant,
+    timeout: Duration,
+}
+
+impl ClientActivity {
+    pub fn new(heartbeat_interval: Duration) -> Self {
+        Self {
+            last_seen: Instant::now(),
+            timeout: CLIENT_TIMEOUT.max(heartbeat_interval * 3),
+        }
+    }
+
+    pub fn seen(&mut self, now: Instant) {
+        self.last_seen = now;
+    }
+
+    pub fn timed_out(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_seen) > self.timeout
+    }
+}
+
+/// Window in which high frequency updates are collected into a single frame, clients can request
+/// a window when connecting, batching is disabled if no window or a window of 0 is requested
+pub fn batch_window(requested_ms: Option<u64>) -> Option<Duration> {
+    requested_ms
+        .filter(|ms| *ms > 0)
+        .map(Duration::from_millis)
+        .map(|window| window.min(MAX_BATCH_WINDOW))
+}
+
+/// Serialized stream messages that are sent as a single JSON array frame once the batch window
+/// has passed
+#[derive(Debug, Default)]
+pub struct StreamBatch {
+    pending: Vec<String>,
+}
+
+impl StreamBatch {
+    /// adds a serialized message to the batch
+    ///
+    /// Returns true if the message opened a new batch, the batch has to be flushed with
+    /// [`StreamBatch::take_frame`] once the batch window has passed.
+    pub fn push(&mut self, msg: String) -> bool {
+        self.pending.push(msg);
+        self.pending.len() == 1
+    }
+
+    /// takes all pending messages as a JSON array, `None` if there are no pending messages
+    pub fn take_frame(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let frame = format!("[{}]", self.pending.join(","));
+        self.pending.clear();
+
+        Some(frame)
+    }
+}
+
 pub fn deserialize_stringified_list<'de, D, I>(
     deserializer: D,
 ) -> std::result::Result<Arc<[I]>, D::Error>
@@ -47,3 +136,79 @@ where
         .deserialize_any(StringVecVisitor(std::marker::PhantomData::<I>))
         .map(|vec| vec.into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_heartbeat_interval() {
+        assert_eq!(heartbeat_interval(None), DEFAULT_HEARTBEAT_INTERVAL);
+        assert_eq!(heartbeat_interval(Some(5000)), Duration::from_secs(5));
+        assert_eq!(heartbeat_interval(Some(1)), MIN_HEARTBEAT_INTERVAL);
+        assert_eq!(heartbeat_interval(Some(u64::MAX)), MAX_HEARTBEAT_INTERVAL);
+    }
+
+    #[test]
+    fn test_client_activity_timeout() {
+        let mut activity = ClientActivity::new(DEFAULT_HEARTBEAT_INTERVAL);
+        let start = activity.last_seen;
+
+        assert!(!activity.timed_out(start + CLIENT_TIMEOUT));
+        assert!(activity.timed_out(start + CLIENT_TIMEOUT + Duration::from_millis(1)));
+
+        activity.seen(start + CLIENT_TIMEOUT);
+        assert!(!activity.timed_out(start + CLIENT_TIMEOUT * 2));
+
+        // clients get at least three pings to answer
+        let activity = ClientActivity::new(MAX_HEARTBEAT_INTERVAL);
+        assert!(!activity.timed_out(start + MAX_HEARTBEAT_INTERVAL * 2));
+    }
+
+    #[test]
+    fn test_batch_window() {
+        assert_eq!(batch_window(None), None);
+        assert_eq!(batch_window(Some(0)), None);
+        assert_eq!(batch_window(Some(50)), Some(Duration::from_millis(50)));
+        assert_eq!(batch_window(Some(u64::MAX)), Some(MAX_BATCH_WINDOW));
+    }
+
+    #[test]
+    fn test_stream_batch_reduces_frames() {
+        let window = 50;
+        let mut batch = StreamBatch::default();
+        let mut flush_at = None;
+        let mut frames = vec![];
+
+        // one update every 10ms for one second, flushing the batch like a session would
+        for now in (0..1000).step_by(10) {
+            if flush_at.is_some_and(|flush_at| flush_at <= now) {
+                frames.extend(batch.take_frame());
+                flush_at = None;
+            }
+
+            if batch.push(format!("{{\"AUDIO_STATE_INFO\":{now}}}")) {
+                flush_at = Some(now + window);
+            }
+        }
+        frames.extend(batch.take_frame());
+
+        assert_eq!(frames.len(), 20);
+        assert_eq!(
+            frames[0],
+            r#"[{"AUDIO_STATE_INFO":0},{"AUDIO_STATE_INFO":10},{"AUDIO_STATE_INFO":20},{"AUDIO_STATE_INFO":30},{"AUDIO_STATE_INFO":40}]"#
+        );
+
+        let updates: usize = frames
+            .iter()
+            .map(|frame| {
+                serde_json::from_str::<Vec<serde_json::Value>>(frame)
+                    .unwrap()
+                    .len()
+            })
+            .sum();
+        assert_eq!(updates, 100);
+        assert_eq!(batch.take_frame(), None);
+    }
+}
@@ -8,17 +8,24 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
 use crate::{
-    audio_playback::{audio_item::AudioMetadata, audio_player::AudioInfo},
+    audio_playback::{
+        audio_item::AudioMetadata,
+        audio_player::{AudioInfo, SerializableQueue},
+    },
     brain_addr,
-    downloader::info::DownloadInfo,
+    downloader::info::{DownloadInfo, DownloadProgress},
     error::AppError,
-    node::{health::AudioNodeHealth, node_server::SourceName, node_session::AudioNodeSession},
-    streams::deserialize_stringified_list,
+    node::{
+        health::AudioNodeHealth, multi_node_session::MultiNodeSession, node_server::SourceName,
+        node_session::AudioNodeSession,
+    },
+    streams::{batch_window, deserialize_stringified_list, heartbeat_interval},
     utils::get_node_by_source_name,
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum, TS)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[ts(export, export_to = "../app/src/api-types/")]
 pub enum AudioNodeInfoStreamType {
     Queue,
     Health,
@@ -31,12 +38,22 @@ pub enum AudioNodeInfoStreamType {
 #[rtype(result = "()")]
 #[ts(export, export_to = "../app/src/api-types/")]
 pub enum AudioNodeInfoStreamMessage {
-    // can't use SerializableQueue due to issue discussed
-    // here: https://github.com/Aleph-Alpha/ts-rs/issues/70
-    Queue(#[ts(type = "Array<AudioMetadata>")] Arc<[AudioMetadata]>),
+    Queue(SerializableQueue),
     Health(AudioNodeHealth),
     Download(RunningDownloadInfo),
+    DownloadStarted(DownloadInfo),
+    DownloadProgress(DownloadProgress),
+    /// the download is held back until no node is playing anymore
+    DownloadWaitingForIdle(DownloadInfo),
     AudioStateInfo(AudioInfo),
+    /// sent instead of playing the next track when auto advance is disabled
+    TrackEnded,
+    /// track that plays once the current one has ended, `None` if nothing follows
+    UpNext {
+        metadata: Option<AudioMetadata>,
+    },
+    /// index of the current item in the queue, sent as soon as it changes
+    QueueHead(usize),
 }
 
 #[derive(Debug, Clone, Serialize, TS)]
@@ -54,6 +71,11 @@ pub struct RunningDownloadInfo {
 struct StreamWantedInfoParams {
     #[serde(deserialize_with = "deserialize_stringified_list")]
     wanted_info: Arc<[AudioNodeInfoStreamType]>,
+    /// interval in which the server sends heart beat pings
+    heartbeat_interval_ms: Option<u64>,
+    /// window in which audio state updates are collected and sent as a single JSON array frame,
+    /// every update is sent as its own frame if not set
+    batch_window_ms: Option<u64>,
 }
 
 pub fn get_type_of_stream_data(msg: &AudioNodeInfoStreamMessage) -> AudioNodeInfoStreamType {
@@ -61,10 +83,22 @@ pub fn get_type_of_stream_data(msg: &AudioNodeInfoStreamMessage) -> AudioNodeInf
         AudioNodeInfoStreamMessage::Queue(_) => AudioNodeInfoStreamType::Queue,
         AudioNodeInfoStreamMessage::Health(_) => AudioNodeInfoStreamType::Health,
         AudioNodeInfoStreamMessage::Download { .. } => AudioNodeInfoStreamType::Download,
+        AudioNodeInfoStreamMessage::DownloadStarted(_) => AudioNodeInfoStreamType::Download,
+        AudioNodeInfoStreamMessage::DownloadProgress(_) => AudioNodeInfoStreamType::Download,
+        AudioNodeInfoStreamMessage::DownloadWaitingForIdle(_) => AudioNodeInfoStreamType::Download,
         AudioNodeInfoStreamMessage::AudioStateInfo(_) => AudioNodeInfoStreamType::AudioStateInfo,
+        AudioNodeInfoStreamMessage::TrackEnded => AudioNodeInfoStreamType::AudioStateInfo,
+        AudioNodeInfoStreamMessage::UpNext { .. } => AudioNodeInfoStreamType::Queue,
+        AudioNodeInfoStreamMessage::QueueHead(_) => AudioNodeInfoStreamType::Queue,
     }
 }
 
+/// whether a message is a high frequency update that can be batched, discrete events are always
+/// sent immediately
+pub fn is_batchable(msg: &AudioNodeInfoStreamMessage) -> bool {
+    matches!(msg, AudioNodeInfoStreamMessage::AudioStateInfo(_))
+}
+
 #[get("/streams/node/{source_name}")]
 async fn get_node_stream(
     source_name: web::Path<SourceName>,
@@ -79,8 +113,46 @@ async fn get_node_stream(
         }
     };
 
+    let StreamWantedInfoParams {
+        wanted_info,
+        heartbeat_interval_ms,
+        batch_window_ms,
+    } = query.into_inner();
+
+    match ws::start(
+        AudioNodeSession::new(
+            node_addr,
+            wanted_info,
+            heartbeat_interval(heartbeat_interval_ms),
+            batch_window(batch_window_ms),
+        ),
+        &req,
+        stream,
+    ) {
+        Ok(res) => res,
+        Err(_) => HttpResponse::new(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct MultiNodeStreamParams {
+    /// interval in which the server sends heart beat pings
+    heartbeat_interval_ms: Option<u64>,
+}
+
+/// Stream of multiple nodes in one connection, nodes are subscribed to with a
+/// [`MultiNodeSessionWsCommand`](crate::node::multi_node_session::MultiNodeSessionWsCommand)
+#[get("/streams/nodes")]
+async fn get_multi_node_stream(
+    query: web::Query<MultiNodeStreamParams>,
+    req: HttpRequest,
+    stream: web::Payload,
+) -> HttpResponse {
     match ws::start(
-        AudioNodeSession::new(node_addr, query.into_inner().wanted_info),
+        MultiNodeSession::new(
+            brain_addr().clone(),
+            heartbeat_interval(query.heartbeat_interval_ms),
+        ),
         &req,
         stream,
     ) {
@@ -1,4 +1,14 @@
+use std::path::{Path, PathBuf};
+
 use actix::{Actor, Context, Handler, Message};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+
+use crate::{
+    audio_playback::audio_player::AudioPlayer,
+    db_pool,
+    node::node_server::{AudioNode, NodeOptions},
+    POOL,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Message)]
 #[rtype(result = "()")]
@@ -60,3 +70,85 @@ impl Handler<GetReceivedMessageCount> for TestMessageHandler {
         self.msgs_received
     }
 }
+
+/// writes a 16 bit PCM wav file where every sample of every channel is produced by `sample_fn`
+pub fn write_test_wav(
+    path: &Path,
+    sample_rate: u32,
+    num_channels: u16,
+    num_frames: usize,
+    sample_fn: impl Fn(usize) -> i16,
+) {
+    let data_len = (num_frames * num_channels as usize * 2) as u32;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&num_channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * num_channels as u32 * 2).to_le_bytes());
+    bytes.extend_from_slice(&(num_channels * 2).to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+
+    for frame in 0..num_frames {
+        for _ in 0..num_channels {
+            bytes.extend_from_slice(&sample_fn(frame).to_le_bytes());
+        }
+    }
+
+    std::fs::write(path, bytes).unwrap();
+}
+
+/// creates an empty temporary directory unique to the calling test
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "audio-api-test-{name}-{pid}",
+        pid = std::process::id()
+    ));
+
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+/// connects to the development database and runs the migrations, the pool is shared by all tests
+/// so tests have to use their own uids and node names
+pub async fn test_db_pool() -> &'static PgPool {
+    if let Some(pool) = POOL.get() {
+        return pool;
+    }
+
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(env!("DATABASE_URL"))
+        .await
+        .expect("should be able to connect to database");
+
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("all migrations should be valid");
+
+    // another test may have connected first
+    let _ = POOL.set(pool);
+    db_pool()
+}
+
+/// node with a player without an output device, the actors it sends messages to are never started
+pub fn test_node(source_name: &str) -> AudioNode {
+    AudioNode::new(
+        source_name.into(),
+        AudioPlayer::without_output(source_name.into()),
+        Context::new().address(),
+        Context::new().address(),
+        Context::new().address(),
+        NodeOptions::default(),
+        None,
+    )
+}
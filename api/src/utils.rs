@@ -1,27 +1,47 @@
 use std::{collections::HashMap, fmt::Debug, fs};
 
-use actix::Addr;
+use actix::{Actor, Addr, Context, Handler};
 use anyhow::anyhow;
 use cpal::{
     traits::{DeviceTrait, HostTrait},
     Device, SampleRate, StreamConfig,
 };
 use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
 use crate::{
-    brain::brain_server::{AudioBrain, GetAudioNodeMessage},
+    brain::brain_server::{GetAllAudioNodes, GetAudioNodeMessage},
     node::node_server::{AudioNode, SourceName},
 };
 
 const DEFAULT_SAMPLE_RATE: u32 = 48000;
 
-pub async fn get_node_by_source_name(
+pub async fn get_node_by_source_name<B>(
     source_name: SourceName,
-    addr: &Addr<AudioBrain>,
-) -> Option<Addr<AudioNode>> {
+    addr: &Addr<B>,
+) -> Option<Addr<AudioNode>>
+where
+    B: Actor<Context = Context<B>> + Handler<GetAudioNodeMessage>,
+{
     addr.send(GetAudioNodeMessage { source_name }).await.ok()?
 }
 
+/// like [`get_node_by_source_name`] but also finds nodes whose device is unavailable
+pub async fn get_node_by_source_name_any_health<B>(
+    source_name: SourceName,
+    addr: &Addr<B>,
+) -> Option<Addr<AudioNode>>
+where
+    B: Actor<Context = Context<B>> + Handler<GetAllAudioNodes>,
+{
+    addr.send(GetAllAudioNodes)
+        .await
+        .ok()?
+        .into_iter()
+        .find(|(name, _)| *name == source_name)
+        .map(|(_, addr)| addr)
+}
+
 pub fn setup_device(source_name: &str) -> anyhow::Result<(Device, StreamConfig)> {
     let host = cpal::default_host();
     let device = host
@@ -38,13 +58,77 @@ pub fn setup_device(source_name: &str) -> anyhow::Result<(Device, StreamConfig)>
     let channel_count = 2; // I choose to make this assumption not because it is good
                            // but because it is easy
 
-    let config = supported_config
+    let config: StreamConfig = supported_config
         .with_sample_rate(SampleRate(DEFAULT_SAMPLE_RATE * channel_count))
         .into();
 
+    // tracks are always played as stereo, see `write_stereo_frame`
+    match config.channels {
+        0 => return Err(anyhow!("output device {source_name} has no channels")),
+        1 => log::warn!("output device {source_name} is mono, tracks are mixed down to one channel"),
+        2 => {}
+        channels => log::warn!(
+            "output device {source_name} has {channels} channels, tracks are only played on the first two channels"
+        ),
+    }
+
     Ok((device, config))
 }
 
+/// Output device a node can be added for
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(export, export_to = "../app/src/api-types/")]
+pub struct OutputDeviceInfo {
+    /// used as the source name of a node playing on the device
+    pub name: String,
+    pub max_sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Output devices of the default host, devices without a name or without a supported config are
+/// skipped
+pub fn list_output_devices() -> Vec<OutputDeviceInfo> {
+    let devices = match cpal::default_host().output_devices() {
+        Ok(devices) => devices,
+        Err(err) => {
+            log::warn!("failed to list output devices, ERROR: {err}");
+            return vec![];
+        }
+    };
+
+    devices
+        .filter_map(|device| {
+            let name = device.name().ok()?;
+            let configs = match device.supported_output_configs() {
+                Ok(configs) => configs,
+                Err(err) => {
+                    log::warn!("failed to get configs of output device {name}, ERROR: {err}");
+                    return None;
+                }
+            };
+
+            configs.fold(None, |info: Option<OutputDeviceInfo>, config| {
+                let max_sample_rate = config.max_sample_rate().0;
+                let channels = config.channels();
+
+                Some(match info {
+                    Some(info) => OutputDeviceInfo {
+                        max_sample_rate: info.max_sample_rate.max(max_sample_rate),
+                        channels: info.channels.max(channels),
+                        ..info
+                    },
+                    None => OutputDeviceInfo {
+                        name: name.clone(),
+                        max_sample_rate,
+                        channels,
+                    },
+                })
+            })
+        })
+        .collect()
+}
+
 pub fn log_msg_received<T, M: Debug>(handler: &T, msg: &M) {
     log::info!(
         "{} received by {}\nCONTENT: {msg:?}",
@@ -61,6 +145,9 @@ fn type_as_str<'a, T: Sized>(_v: &T) -> &'a str {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioSourceInfo {
     pub human_readable_name: String,
+    /// failed attempts to recover the device before it has to be reconnected manually
+    #[serde(default)]
+    pub max_recovery_attempts: Option<u32>,
 }
 
 pub type Sources = HashMap<SourceName, AudioSourceInfo>;
@@ -88,9 +175,6 @@ mod tests {
 
         struct TestStruct;
         let input = type_as_str(&TestStruct);
-        pretty_assertions::assert_eq!(
-            input,
-            "audio_manager_api::utils::tests::test_type_as_str::TestStruct"
-        )
+        pretty_assertions::assert_eq!(input, "TestStruct")
     }
 }
@@ -1,4 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { AudioIdentifier } from "./AudioIdentifier";
 
-export interface AddQueueItemParams { identifier: AudioIdentifier, }
\ No newline at end of file
+export interface AddQueueItemParams { identifier: AudioIdentifier, startPaused: boolean, folder: string | null, idempotencyKey: string | null, position: number | null, }
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type AppErrorKind = "Queue" | "LocalData" | "Database" | "Download";
\ No newline at end of file
+export type AppErrorKind = "Queue" | "Api" | "LocalData" | "Database" | "Download" | "RateLimited";
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AudioDeleteStatus } from "./AudioDeleteStatus";
+
+export interface AudioDeleteResult { uid: string, status: AudioDeleteStatus, }
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type AudioIdentifier = { "local": { uid: string, } } | { "youtube": { url: string, } };
\ No newline at end of file
+export type AudioIdentifier = { "local": { uid: string, } } | { "youtube": { url: string, } } | { "soundcloud": { url: string, } } | { "local-file": { path: string, } };
\ No newline at end of file
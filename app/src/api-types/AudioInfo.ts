@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PlaybackState } from "./PlaybackState";
+import type { RepeatMode } from "./RepeatMode";
+
+export interface AudioInfo { playbackState: PlaybackState, currentQueueIndex: number, audioProgress: number, audioVolume: number, durationSecs: number | null, elapsedSecs: number | null, repeatMode: RepeatMode, crossfadeMs: number, }
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export interface AudioMetadata { name: string | null, author: string | null, duration: bigint | null, cover_art_url: string | null, }
\ No newline at end of file
+export interface AudioMetadata { name: string | null, author: string | null, duration: bigint | null, cover_art_url: string | null, gain_db: number | null, }
\ No newline at end of file
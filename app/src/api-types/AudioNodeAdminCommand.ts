@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SetMaxVolumeParams } from "./SetMaxVolumeParams";
+
+export type AudioNodeAdminCommand = { "SET_MAX_VOLUME": SetMaxVolumeParams };
\ No newline at end of file
@@ -1,10 +1,26 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AddBookmarkParams } from "./AddBookmarkParams";
 import type { AddQueueItemParams } from "./AddQueueItemParams";
-import type { LoopQueueParams } from "./LoopQueueParams";
+import type { AnnounceParams } from "./AnnounceParams";
 import type { MoveQueueItemParams } from "./MoveQueueItemParams";
 import type { PlaySelectedParams } from "./PlaySelectedParams";
+import type { PlayTestToneParams } from "./PlayTestToneParams";
 import type { RemoveQueueItemParams } from "./RemoveQueueItemParams";
+import type { SeekBookmarkParams } from "./SeekBookmarkParams";
+import type { SeekPercentDeltaParams } from "./SeekPercentDeltaParams";
+import type { SeekSecondsParams } from "./SeekSecondsParams";
 import type { SetAudioProgressParams } from "./SetAudioProgressParams";
 import type { SetAudioVolumeParams } from "./SetAudioVolumeParams";
+import type { SetAutoAdvanceParams } from "./SetAutoAdvanceParams";
+import type { SetCrossfadeParams } from "./SetCrossfadeParams";
+import type { SetEqualizerParams } from "./SetEqualizerParams";
+import type { SetFadeInParams } from "./SetFadeInParams";
+import type { SetItemFadeInParams } from "./SetItemFadeInParams";
+import type { SetItemGainParams } from "./SetItemGainParams";
+import type { SetNormalizationEnabledParams } from "./SetNormalizationEnabledParams";
+import type { SetPauseOnDeviceLossParams } from "./SetPauseOnDeviceLossParams";
+import type { SetQueueLabelParams } from "./SetQueueLabelParams";
+import type { SetRepeatModeParams } from "./SetRepeatModeParams";
+import type { SetResumePlaybackParams } from "./SetResumePlaybackParams";
 
-export type AudioNodeCommand = { "ADD_QUEUE_ITEM": AddQueueItemParams } | { "REMOVE_QUEUE_ITEM": RemoveQueueItemParams } | { "MOVE_QUEUE_ITEM": MoveQueueItemParams } | { "SET_AUDIO_VOLUME": SetAudioVolumeParams } | { "SET_AUDIO_PROGRESS": SetAudioProgressParams } | "PAUSE_QUEUE" | "UN_PAUSE_QUEUE" | "PLAY_NEXT" | "PLAY_PREVIOUS" | { "PLAY_SELECTED": PlaySelectedParams } | { "LOOP_QUEUE": LoopQueueParams };
\ No newline at end of file
+export type AudioNodeCommand = { "ADD_QUEUE_ITEM": AddQueueItemParams } | { "ADD_QUEUE_ITEMS": Array<AddQueueItemParams> } | { "REMOVE_QUEUE_ITEM": RemoveQueueItemParams } | { "MOVE_QUEUE_ITEM": MoveQueueItemParams } | "SHUFFLE_QUEUE" | "SHUFFLE_QUEUE_SMART" | "CLEAR_QUEUE" | { "SET_AUDIO_VOLUME": SetAudioVolumeParams } | { "SET_AUDIO_PROGRESS": SetAudioProgressParams } | { "SEEK_PERCENT_DELTA": SeekPercentDeltaParams } | { "SEEK_TO_SECONDS": SeekSecondsParams } | "PAUSE_QUEUE" | "UN_PAUSE_QUEUE" | "STOP" | "PLAY_NEXT" | "PLAY_PREVIOUS" | { "PLAY_SELECTED": PlaySelectedParams } | "REPLAY_CURRENT" | "REPLAY_PREVIOUS" | { "ADD_BOOKMARK": AddBookmarkParams } | { "SEEK_BOOKMARK": SeekBookmarkParams } | { "SET_ITEM_GAIN": SetItemGainParams } | "RESET_BUFFER_STATS" | "RECONNECT_DEVICE" | { "SET_AUTO_ADVANCE": SetAutoAdvanceParams } | { "SET_RESUME_PLAYBACK": SetResumePlaybackParams } | { "PLAY_TEST_TONE": PlayTestToneParams } | { "SET_EQUALIZER": SetEqualizerParams } | { "SET_PAUSE_ON_DEVICE_LOSS": SetPauseOnDeviceLossParams } | { "SET_QUEUE_LABEL": SetQueueLabelParams } | { "SET_FADE_IN": SetFadeInParams } | { "SET_ITEM_FADE_IN": SetItemFadeInParams } | { "ANNOUNCE": AnnounceParams } | { "SET_REPEAT_MODE": SetRepeatModeParams } | { "SET_CROSSFADE": SetCrossfadeParams } | { "SET_NORMALIZATION_ENABLED": SetNormalizationEnabledParams };
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type AudioNodeHealthPoor = "device-not-available" | "audio-stream-read-failed" | { "audio-backend-error": string };
\ No newline at end of file
+export type AudioNodeHealthPoor = "device-not-available" | "audio-stream-read-failed" | { "audio-backend-error": string } | "permanently-unavailable";
\ No newline at end of file
@@ -1,4 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { AudioNodeHealth } from "./AudioNodeHealth";
 
-export interface AudioNodeInfo { source_name: string, human_readable_name: string, health: AudioNodeHealth, }
\ No newline at end of file
+export interface AudioNodeInfo { source_name: string, human_readable_name: string, health: AudioNodeHealth, max_volume: number, auto_advance: boolean, resume_playback: boolean, pause_on_device_loss: boolean, equalizer_bands: Array<number>, fade_in_ms: number, normalization_enabled: boolean, queue_label: string | null, }
\ No newline at end of file
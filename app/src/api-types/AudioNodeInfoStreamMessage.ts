@@ -1,6 +1,10 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AudioInfo } from "./AudioInfo";
+import type { AudioMetadata } from "./AudioMetadata";
 import type { AudioNodeHealth } from "./AudioNodeHealth";
-import type { AudioStateInfo } from "./AudioStateInfo";
+import type { DownloadInfo } from "./DownloadInfo";
+import type { DownloadProgress } from "./DownloadProgress";
 import type { RunningDownloadInfo } from "./RunningDownloadInfo";
+import type { SerializableQueue } from "./SerializableQueue";
 
-export type AudioNodeInfoStreamMessage = { "QUEUE": Array<AudioMetadata> } | { "HEALTH": AudioNodeHealth } | { "DOWNLOAD": RunningDownloadInfo } | { "AUDIO_STATE_INFO": AudioStateInfo };
\ No newline at end of file
+export type AudioNodeInfoStreamMessage = { "QUEUE": SerializableQueue } | { "HEALTH": AudioNodeHealth } | { "DOWNLOAD": RunningDownloadInfo } | { "DOWNLOAD_STARTED": DownloadInfo } | { "DOWNLOAD_PROGRESS": DownloadProgress } | { "DOWNLOAD_WAITING_FOR_IDLE": DownloadInfo } | { "AUDIO_STATE_INFO": AudioInfo } | "TRACK_ENDED" | { "UP_NEXT": { metadata: AudioMetadata | null, } } | { "QUEUE_HEAD": number };
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CloneQueueParams } from "./CloneQueueParams";
+import type { SetActiveSourceParams } from "./SetActiveSourceParams";
+
+export type BrainSessionWsCommand = { "SET_ACTIVE_SOURCE": SetActiveSourceParams } | "GET_ACTIVE_SOURCE" | { "CLONE_QUEUE": CloneQueueParams };
\ No newline at end of file
@@ -1,3 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ClonedQueue } from "./ClonedQueue";
 
-export type BrainSessionWsResponse = { "SESSION_CONNECTED_RESPONSE": { node_info: Array<AudioNodeInfo>, } };
\ No newline at end of file
+export type BrainSessionWsResponse = { "SESSION_CONNECTED_RESPONSE": { node_info: Array<AudioNodeInfo>, } } | { "SET_ACTIVE_SOURCE_RESPONSE": { sourceName: string, previousSourceName: string | null, } } | { "GET_ACTIVE_SOURCE_RESPONSE": { sourceName: string | null, } } | { "CLONE_QUEUE_RESPONSE": ClonedQueue };
\ No newline at end of file
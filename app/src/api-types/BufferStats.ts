@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TrackBufferStats } from "./TrackBufferStats";
+
+export interface BufferStats { tracks: Array<TrackBufferStats>, totalCacheMisses: number, totalBufferingSecs: number, }
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type DownloadInfo = { "youtube-video": { url: string, } } | { "youtube-playlist": { playlist_url: string, video_urls: Array<string>, } };
\ No newline at end of file
+export type DownloadInfo = { "youtube-video": { url: string, } } | { "youtube-playlist": { playlist_url: string, video_urls: Array<string>, } } | { "soundcloud-track": { url: string, } };
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DownloadInfo } from "./DownloadInfo";
+
+export interface DownloadProgress { info: DownloadInfo, progressPercent: number, etaSecs: number | null, }
\ No newline at end of file
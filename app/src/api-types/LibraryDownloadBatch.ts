@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface LibraryDownloadBatch { batchId: string, playlistUrl: string, total: number, finished: number, pending: Array<string>, failed: Array<[string, AppError]>, }
\ No newline at end of file
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SubscribeNodesParams } from "./SubscribeNodesParams";
+import type { UnsubscribeNodesParams } from "./UnsubscribeNodesParams";
+
+export type MultiNodeSessionWsCommand = { "SUBSCRIBE": SubscribeNodesParams } | { "UNSUBSCRIBE": UnsubscribeNodesParams };
\ No newline at end of file
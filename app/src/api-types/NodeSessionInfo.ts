@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AudioNodeInfoStreamType } from "./AudioNodeInfoStreamType";
+import type { NodeSessionKind } from "./NodeSessionKind";
+
+export interface NodeSessionInfo { id: number, kind: NodeSessionKind, connectedAtSecs: number, wantedInfo: Array<AudioNodeInfoStreamType>, }
\ No newline at end of file
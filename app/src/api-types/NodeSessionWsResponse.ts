@@ -1,6 +1,8 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AudioInfo } from "./AudioInfo";
+import type { AudioMetadata } from "./AudioMetadata";
 import type { AudioNodeHealth } from "./AudioNodeHealth";
-import type { AudioStateInfo } from "./AudioStateInfo";
 import type { RunningDownloadInfo } from "./RunningDownloadInfo";
+import type { SerializableQueue } from "./SerializableQueue";
 
-export type NodeSessionWsResponse = { "SESSION_CONNECTED_RESPONSE": { QUEUE: Array<AudioMetadata>, HEALTH: AudioNodeHealth | null, DOWNLOADS: RunningDownloadInfo | null, AUDIO_STATE_INFO: AudioStateInfo | null, } };
\ No newline at end of file
+export type NodeSessionWsResponse = { "SESSION_CONNECTED_RESPONSE": { QUEUE: SerializableQueue | null, HEALTH: AudioNodeHealth | null, DOWNLOADS: RunningDownloadInfo | null, AUDIO_STATE_INFO: AudioInfo | null, UP_NEXT: AudioMetadata | null, } };
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NodeSessionInfo } from "./NodeSessionInfo";
+
+export interface NodeSessions { sessionCount: number, sessions: Array<NodeSessionInfo>, brainSessionCount: number, }
\ No newline at end of file
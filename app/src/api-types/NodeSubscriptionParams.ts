@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AudioNodeInfoStreamType } from "./AudioNodeInfoStreamType";
+
+export interface NodeSubscriptionParams { sourceName: string, wantedInfo: Array<AudioNodeInfoStreamType>, }
\ No newline at end of file
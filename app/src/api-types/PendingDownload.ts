@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DownloadInfo } from "./DownloadInfo";
+
+export interface PendingDownload { sourceName: string | null, info: DownloadInfo | null, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ConfiguredSource } from "./ConfiguredSource";
+
+export interface RuntimeConfig { production: boolean, apiAddress: string, databaseUrl: string, audioDataDir: string, stateRecoveryFile: string, sources: Array<ConfiguredSource>, downloadConcurrency: number, ytDlpPath: string | null, corsAllowedOrigins: Array<string>, authEnabled: boolean, youtubeApiKeySet: boolean, pauseDownloadsWhilePlaying: boolean, logLevel: string, logMaxFileSize: number, logMaxFileAgeSecs: number, logMaxRotatedFiles: number, stateCheckpointIntervalSecs: number, nodeCommandRateLimit: number, nodeHeavyCommandRateLimit: number, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SerializableQueueItem } from "./SerializableQueueItem";
+
+export interface SerializableQueue { items: Array<SerializableQueueItem>, currentQueueIndex: number, }
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RepeatMode } from "./RepeatMode";
+
+export interface SetRepeatModeParams { repeatMode: RepeatMode, }
\ No newline at end of file
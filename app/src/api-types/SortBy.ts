@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SortBy = "name" | "author" | "duration" | "created_at";
\ No newline at end of file
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ProviderStorageUsage } from "./ProviderStorageUsage";
+
+export interface StorageUsage { totalBytes: number, fileCount: number, youtube: ProviderStorageUsage, soundcloud: ProviderStorageUsage, local: ProviderStorageUsage, ageSecs: number, }
\ No newline at end of file